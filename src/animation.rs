@@ -0,0 +1,115 @@
+use cgmath::{Deg, Matrix4, Vector3};
+use serde::{Deserialize, Serialize};
+
+// how a value moves from one keyframe to the next
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    #[default]
+    Linear,
+    // starts slow, the first quarter of a sine wave
+    EaseIn,
+    // ends slow
+    EaseOut,
+    EaseInOut,
+    // jumps to the next value when its time comes
+    Step,
+}
+
+impl Easing {
+    fn apply(self, t: f32) -> f32 {
+        use std::f32::consts::{FRAC_PI_2, PI};
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => 1.0 - (t * FRAC_PI_2).cos(),
+            Easing::EaseOut => (t * FRAC_PI_2).sin(),
+            Easing::EaseInOut => (1.0 - (t * PI).cos()) / 2.0,
+            Easing::Step => 0.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Keyframe<T> {
+    // seconds from the start of the clip
+    pub time: f32,
+    pub value: T,
+    // towards the next keyframe
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+impl<T> Keyframe<T> {
+    pub fn new(time: f32, value: T, easing: Easing) -> Self {
+        Keyframe { time, value, easing }
+    }
+}
+
+// position, rotation and scale tracks that together make up an object's model matrix, each
+// with its keyframes in order of time. a track with no keyframes leaves that part alone
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct AnimationClip {
+    pub position: Vec<Keyframe<[f32; 3]>>,
+    // degrees around x, y and z, applied in that order. blended per axis, so a full turn is
+    // just 0 to 360 instead of taking the short way around
+    pub rotation: Vec<Keyframe<[f32; 3]>>,
+    // uniform, the shaders don't handle normals under anything else
+    pub scale: Vec<Keyframe<f32>>,
+    // starts over after the last keyframe instead of holding it
+    pub looping: bool,
+}
+
+impl Default for AnimationClip {
+    fn default() -> Self {
+        AnimationClip {
+            position: Vec::new(),
+            rotation: Vec::new(),
+            scale: Vec::new(),
+            looping: true,
+        }
+    }
+}
+
+impl AnimationClip {
+    // the time of the last keyframe in any track
+    pub fn duration(&self) -> f32 {
+        let position = self.position.iter().map(|k| k.time);
+        let rotation = self.rotation.iter().map(|k| k.time);
+        let scale = self.scale.iter().map(|k| k.time);
+        position.chain(rotation).chain(scale).fold(0.0, f32::max)
+    }
+
+    // the model matrix time seconds into the clip
+    pub fn sample(&self, time: f32) -> Matrix4<f32> {
+        let duration = self.duration();
+        let time = if self.looping && duration > 0.0 { time.rem_euclid(duration) } else { time };
+
+        let [x, y, z] = sample_track(&self.position, time, lerp3).unwrap_or([0.0; 3]);
+        let [rx, ry, rz] = sample_track(&self.rotation, time, lerp3).unwrap_or([0.0; 3]);
+        let scale = sample_track(&self.scale, time, |a, b, t| a + (b - a) * t).unwrap_or(1.0);
+        Matrix4::from_translation(Vector3::new(x, y, z))
+            * Matrix4::from_angle_x(Deg(rx))
+            * Matrix4::from_angle_y(Deg(ry))
+            * Matrix4::from_angle_z(Deg(rz))
+            * Matrix4::from_scale(scale)
+    }
+}
+
+// None for an empty track. before the first keyframe and after the last the value is held
+fn sample_track<T: Copy>(keyframes: &[Keyframe<T>], time: f32, lerp: impl Fn(T, T, f32) -> T) -> Option<T> {
+    let next = keyframes.partition_point(|k| k.time <= time);
+    if next == 0 {
+        return keyframes.first().map(|k| k.value);
+    }
+    let from = &keyframes[next - 1];
+    let Some(to) = keyframes.get(next) else {
+        return Some(from.value);
+    };
+    let t = (time - from.time) / (to.time - from.time).max(f32::EPSILON);
+    Some(lerp(from.value, to.value, from.easing.apply(t)))
+}
+
+fn lerp3(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t, a[2] + (b[2] - a[2]) * t]
+}
@@ -1,55 +1,491 @@
+use crate::animation::{AnimationClip, Easing, Keyframe};
+#[cfg(feature = "audio")]
+use crate::audio::{AmbientSound, Audio, Sound, SpatialSound};
+use crate::balls::BouncingBalls;
+use crate::batch::{BatchDraw, MeshBatch};
+use crate::billboard::{BillboardRenderer, Sprite};
 use crate::camera::Camera;
+use crate::capture::{CaptureOutput, CaptureTarget, FrameCapture};
+use crate::collision::Aabb;
+use crate::console::{Command, Console, Spawnable};
+use crate::debug_draw::DebugDraw;
+use crate::decals::{Decal, Decals};
+use crate::flare::LensFlare;
+use crate::flythrough::Flythrough;
+use crate::focus::FocusState;
+use crate::godrays::GodRays;
+use crate::emissive::{self, Emission};
+use crate::error::AppError;
+use crate::gpu_particles::GpuParticles;
+use crate::grading::ColorGrading;
+use crate::grass::Grass;
+use crate::scene::{CameraPose, Material, Scene, SphereMesh};
+#[cfg(feature = "scripting")]
+use crate::scripting::Scripts;
+use crate::spatial::{Frustum, InstanceGrid};
+use crate::settings::Settings;
+use crate::grid::GridRenderer;
+use crate::isosurface::Metaballs;
+use crate::primitives::{self, Mesh};
+use crate::lights::{self, Light, LightBuffer};
+use crate::logging;
+use crate::map_window::MapWindow;
+use crate::minimap::Minimap;
+use crate::morph::{self, MorphMesh};
+use crate::reflection::{self, PlanarReflection};
+use crate::particles::{Emitter, ParticleSystem};
+#[cfg(feature = "physics")]
+use crate::physics::Physics;
+use crate::skinning::{self, SkinnedModel};
+use crate::sky::{self, SkyRenderer, Sun};
+use crate::sprites::{Icon, SpriteBatch};
+use crate::text::TextRenderer;
+use crate::terrain::{Heightmap, NoiseConfig, Terrain, TerrainConfig};
+use crate::tv::{self, Tv};
+use crate::uploads::Uploads;
+use crate::post::{self, PostProcess};
+use crate::voxel::VoxelWorld;
+use crate::weather::{Rain, WeatherState};
 use crate::graphics;
+use crate::graphics::ContextOptions;
 use crate::graphics::Instance;
-use crate::graphics::RawMatrix;
+use crate::graphics::{CameraUniform, RawMatrix};
+use crate::graphics::RenderTarget;
+use crate::graphics::WgpuContext;
 use crate::graphics::Vertex;
+use crate::graphics::ViewMode;
+use crate::graphics::{PipelineKind, WireframeMode};
 use crate::input;
-use cgmath::InnerSpace;
-use cgmath::{Matrix4, Rotation3, SquareMatrix, Vector3};
-use log::debug;
-use wgpu::util::DeviceExt;
+use crate::input::Action;
+use crate::instance_wave::InstanceWave;
+use crate::picking::{self, DepthReadback};
+use crate::pool::{MeshPools, PoolSlice};
+use crate::portal::Portal;
+use crate::render_queue::{DrawItem, DrawPipeline, RenderQueue};
+use cgmath::{EuclideanSpace, InnerSpace, Transform};
+use cgmath::{Matrix4, Point3, Rotation3, SquareMatrix, Vector3, VectorSpace};
+use log::{debug, info, warn};
+#[cfg(feature = "physics")]
+use rapier3d::prelude::RigidBodyHandle;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use winit::dpi::PhysicalPosition;
 use winit::event::DeviceEvent;
+use winit::event::ElementState;
+use winit::event::KeyboardInput;
+use winit::event::MouseButton;
+use winit::event::MouseScrollDelta;
+use winit::event::ModifiersState;
+use winit::event::VirtualKeyCode;
 use winit::event::WindowEvent;
 use winit::window::Window;
 
 pub struct App {
-    surface: wgpu::Surface,
+    target: RenderTarget,
+    // for the surfaces of windows opened after the main one
+    instance: wgpu::Instance,
+    adapter: wgpu::Adapter,
+    // a top-down view in a window of its own
+    map: Option<MapWindow>,
+    // and one over the instanced objects in the corner of the screen
+    minimap: Minimap,
+    show_minimap: bool,
     device: wgpu::Device,
     queue: wgpu::Queue,
+    // set by wgpu's error handler once the device is gone, see graphics::watch_errors
+    device_lost: Arc<AtomicBool>,
+    // the per frame uniform writes, submitted with the next frame
+    uploads: Uploads,
+    // the meshes and transforms of the objects below
+    pools: MeshPools,
+    // the order the objects are drawn in this frame
+    render_queue: RenderQueue<ObjectRef>,
     config: wgpu::SurfaceConfiguration,
     pub size: winit::dpi::PhysicalSize<u32>,
     clear_color: wgpu::Color,
     render_pipeline: wgpu::RenderPipeline,
+    // used instead of render_pipeline in the overdraw view
+    overdraw_pipeline: wgpu::RenderPipeline,
+    view_mode: ViewMode,
+    // both None when the adapter can't draw lines, see PipelineKind::Wireframe
+    wireframe_pipeline: Option<wgpu::RenderPipeline>,
+    wireframe_overlay_pipeline: Option<wgpu::RenderPipeline>,
+    wireframe_mode: WireframeMode,
+    // kept for objects created after startup, like a scene's terrain
+    bind_group_layout: wgpu::BindGroupLayout,
+    // for skinned meshes, which also bind their bones with bones_bind_group_layout
+    skinned_pipeline: wgpu::RenderPipeline,
+    bones_bind_group_layout: wgpu::BindGroupLayout,
+    // for meshes with morph targets, which also bind them with morph_bind_group_layout
+    morph_pipeline: wgpu::RenderPipeline,
+    morph_bind_group_layout: wgpu::BindGroupLayout,
+    // for the floor when it mirrors the scene, which also binds the reflection with reflection_bind_group_layout
+    reflective_pipeline: wgpu::RenderPipeline,
+    reflection_bind_group_layout: wgpu::BindGroupLayout,
+    // for chrome objects, which also bind the sky's cubemap with environment_bind_group_layout
+    env_mapped_pipeline: wgpu::RenderPipeline,
+    environment_bind_group_layout: wgpu::BindGroupLayout,
+    // for emissive objects, which also bind their glow with emissive_bind_group_layout
+    emissive_pipeline: wgpu::RenderPipeline,
+    emissive_bind_group_layout: wgpu::BindGroupLayout,
+    // for the scene seen through the portal, only drawn where the portal left its stencil value
+    stenciled_pipeline: wgpu::RenderPipeline,
+    // the main pass only shades the nearest surface when depth_prepass is on
+    depth_prepass_pipeline: wgpu::RenderPipeline,
+    prepassed_pipeline: wgpu::RenderPipeline,
+    depth_prepass: bool,
+    // turns each of the cubes about the vertical a little every frame
+    spin_instances: bool,
+    // moves the cubes in a wave on the gpu instead, made the first time it's turned on
+    wave_instances: bool,
+    instance_wave: Option<InstanceWave>,
+    // the cube last picked, tinted HIGHLIGHT_TINT
+    highlighted_cube: Option<usize>,
+    // objects without instances push their model matrix instead of writing it to their transforms
+    push_constants: bool,
+    // for the tv, which also binds its picture with screen_bind_group_layout
+    screen_pipeline: wgpu::RenderPipeline,
+    screen_bind_group_layout: wgpu::BindGroupLayout,
 
     obj1: (RenderObject, wgpu::BindGroup),
     obj2: (RenderObject, wgpu::BindGroup),
     pythagoras_sphere: (RenderObject, wgpu::BindGroup),
+    sphere_mesh: SphereMesh,
     floor: (RenderObject, wgpu::BindGroup),
+    show_floor: bool,
+    // the scene mirrored in the floor
+    reflection: Option<PlanarReflection>,
+    // an opening showing the scene from somewhere else
+    portal: Option<Portal>,
+    // a screen showing the scene from a camera of its own
+    tv: Option<(Tv, wgpu::BindGroup)>,
+    terrain: Option<(Terrain, wgpu::BindGroup)>,
+    // what the terrain was built from, changed at runtime for noise terrain
+    terrain_config: Option<TerrainConfig>,
+    voxels: Option<(VoxelWorld, wgpu::BindGroup)>,
+    // remeshed every frame as the balls move
+    metaballs: Option<(Metaballs, wgpu::BindGroup)>,
+    // one of each generated primitive, lined up along the cube grid
+    primitives: Vec<(RenderObject, wgpu::BindGroup)>,
+    // crates left wherever the left mouse button was clicked, instances of one object
+    placed: (RenderObject, wgpu::BindGroup),
+    // held with the middle mouse button
+    drag: Option<Drag>,
+    // and the primitives each of its bodies moves
+    #[cfg(feature = "physics")]
+    physics: Option<(Physics, Vec<(usize, RigidBodyHandle)>)>,
+    // moving the instanced spheres while the scene has them bouncing
+    balls: Option<BouncingBalls>,
+    // the scene's scripts, None when it has none
+    #[cfg(feature = "scripting")]
+    scripts: Option<Scripts>,
+    // None when there's nothing to play sounds on
+    #[cfg(feature = "audio")]
+    audio: Option<Audio>,
+    // looping from the middle of the objects the scene has humming
+    #[cfg(feature = "audio")]
+    hums: Vec<(ObjectRef, SpatialSound)>,
+    // playing while there's any rain
+    #[cfg(feature = "audio")]
+    rain_sound: Option<AmbientSound>,
+    particles: ParticleSystem,
+    gpu_particles: Option<GpuParticles>,
+    weather: WeatherState,
+    // only kept while it's raining
+    rain: Option<Rain>,
+    god_rays: GodRays,
+    flare: LensFlare,
+    // target markers left where the right mouse button was clicked
+    decals: Decals,
+    grass: Option<Grass>,
+    // an animated character walking along the edge of the floor
+    character: Option<(SkinnedModel, wgpu::BindGroup)>,
+    // a ball blending between a few shapes beside the cube grid
+    morph_blob: Option<(MorphMesh, wgpu::BindGroup)>,
+    text: TextRenderer,
+    show_hud: bool,
+    // where the camera is, which way it's looking and how fast it's going, in the bottom left corner
+    show_telemetry: bool,
+    billboards: BillboardRenderer,
+    // crosshair, icons and menu backgrounds, drawn under the text
+    sprites: SpriteBatch,
+    // a menu listing every action and the key bound to it
+    show_help: bool,
+    // whether the mouse is captured, shown as the crosshair or a hint to click. None without a
+    // window to capture it, like when rendering headlessly
+    focused: Option<bool>,
+    // where the system cursor would be, None while it's outside the window
+    cursor_position: Option<PhysicalPosition<f64>>,
+    // takes the keyboard while it's open
+    console: Console,
+    // how much faster than real time everything moves
+    time_scale: f64,
+    // a marker and the distance to the camera above each of the instanced spheres
+    show_markers: bool,
+    // frames in the last second, None until the first second is over
+    fps: Option<u32>,
+    light_uniform_buffer: wgpu::Buffer,
+    light_buffer: LightBuffer,
+    pub lights: Vec<Light>,
+    // lights circling the sphere grid, regenerated every frame
+    light_demo: bool,
+    sky: SkyRenderer,
+    // lines queued by anything during update, drawn over the scene that frame
+    pub debug_draw: DebugDraw,
+    show_light_gizmos: bool,
+    // collider boxes, and the view frustum as it was when this was turned on
+    debug_shapes: Option<Matrix4<f32>>,
+    // the camera as it was when the view was detached from it. the scene still streams in and
+    // wraps around it while the input flies self.camera around freely to look at it
+    scene_camera: Option<Camera>,
+    grid: GridRenderer,
+    show_grid: bool,
+    // seconds per day/night cycle, 0 when the scene is unlit
+    day_length: f32,
+    // 0..1 from midnight to midnight
+    time_of_day: f32,
+    // what the camera bumps into in walk mode, rebuilt each frame since the cubes move
+    colliders: Vec<Aabb>,
 
     pub input_state: input::InputState,
 
     camera: Camera,
     camera_uniform: RawMatrix,
     camera_uniform_buffer: wgpu::Buffer,
+    // one view/projection matrix per seam copy of the scene, picked with a dynamic offset. the
+    // main pass uses the first MAX_CAMERA_TILES and the reflection pass the ones after, then
+    // there's one for each level of the view through the portal, then the two map views and the
+    // tv's camera last
+    camera_uniform_stride: u32,
+    camera_offsets: Vec<u32>,
+    reflection_offsets: Vec<u32>,
+    portal_offsets: Vec<u32>,
 
     selected_obj: u32,
-    cooldowns: (f64, f64),
     pub delta_time: f64,
 
     depth_texture: (wgpu::TextureView, wgpu::Sampler, wgpu::Texture),
-    intial_instant: std::time::Instant,
+    // what the particles, decals and picking read the depth buffer through
+    depth_sample_view: wgpu::TextureView,
+    msaa_target: Option<wgpu::TextureView>,
+    // the scene's resolution relative to the window's. the depth and msaa targets above are the
+    // scene's size
+    render_scale: f32,
+    color_grading: ColorGrading,
+    post_effects: post::Effects,
+    // only there when the scene is scaled or graded, or has effects on
+    post: Option<PostProcess>,
+    depth_readback: DepthReadback,
+    flythrough: Flythrough,
+    bookmarks: [Option<CameraPose>; BOOKMARK_SLOTS],
+    // short flight from wherever the camera is to a recalled bookmark
+    bookmark_flight: Flythrough,
+    modifiers: ModifiersState,
+    elapsed: f64,
+    capture: Option<FrameCapture>,
+    // kept between frames while recording on a surface that can't be copied from, built again at
+    // the next size
+    capture_target: Option<CaptureTarget>,
+    // the next frame goes to the clipboard, alongside any recording
+    #[cfg(feature = "clipboard")]
+    copy_requested: bool,
+    // the last one applied, built again when the device is recreated
+    scene: Scene,
+}
+
+// one of the objects drawn with the main shader, see App::object
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ObjectRef {
+    Obj1,
+    Obj2,
+    Sphere,
+    Floor,
+    Primitive(usize),
+    Placed,
+}
+
+// what the middle mouse button can pick up
+#[derive(Clone, Copy, Debug)]
+enum DragTarget {
+    // moved by its model matrix
+    Object(ObjectRef),
+    // one of an instanced object's instances
+    Instance(ObjectRef, usize),
+}
+
+// something held with the middle mouse button, following the crosshair across a plane
+struct Drag {
+    target: DragTarget,
+    // from where the crosshair meets the plane to the target's origin
+    offset: Vector3<f32>,
+    // through the grabbed point, facing the camera as it was then
+    plane_point: Point3<f32>,
+    plane_normal: Vector3<f32>,
+}
+
+// the passes of a frame, each recorded into a command buffer of its own so they can be recorded
+// at the same time
+#[derive(Clone, Copy, Debug)]
+enum FramePass {
+    Tv,
+    Reflection,
+    DepthPrepass,
+    Main,
+    Decals,
+    Particles,
+    GodRays,
+    Flare,
+    Post,
+    Minimap,
+    Overlay,
+    Capture,
+}
+
+// what the frame's passes draw into
+struct FrameTargets<'a> {
+    view: &'a wgpu::TextureView,
+    // what the scene's passes resolve into, the frame itself unless it's rendered at another scale
+    scene_view: &'a wgpu::TextureView,
+    // the overlay's msaa target, the frame's size
+    overlay_msaa: Option<&'a wgpu::TextureView>,
+    texture: &'a wgpu::Texture,
+    // the surface's frame while the passes draw into the capture target, which goes onto it last
+    surface_view: Option<&'a wgpu::TextureView>,
+    prepass: bool,
+    // the one frame copied to the clipboard
+    copy: Option<&'a FrameCapture>,
+}
+
+// the bind group layouts of the main shader's pipelines. every one binds scene first, the others
+// are the second group of the pipelines that need one
+struct MainLayouts<'a> {
+    scene: &'a wgpu::BindGroupLayout,
+    bones: &'a wgpu::BindGroupLayout,
+    morph: &'a wgpu::BindGroupLayout,
+    reflection: &'a wgpu::BindGroupLayout,
+    environment: &'a wgpu::BindGroupLayout,
+    emissive: &'a wgpu::BindGroupLayout,
+    screen: &'a wgpu::BindGroupLayout,
+}
+
+// every pipeline built from the main shader, see the App fields of the same names
+struct MainPipelines {
+    render_pipeline: wgpu::RenderPipeline,
+    overdraw_pipeline: wgpu::RenderPipeline,
+    wireframe_pipeline: Option<wgpu::RenderPipeline>,
+    wireframe_overlay_pipeline: Option<wgpu::RenderPipeline>,
+    skinned_pipeline: wgpu::RenderPipeline,
+    morph_pipeline: wgpu::RenderPipeline,
+    reflective_pipeline: wgpu::RenderPipeline,
+    env_mapped_pipeline: wgpu::RenderPipeline,
+    emissive_pipeline: wgpu::RenderPipeline,
+    stenciled_pipeline: wgpu::RenderPipeline,
+    depth_prepass_pipeline: wgpu::RenderPipeline,
+    prepassed_pipeline: wgpu::RenderPipeline,
+    screen_pipeline: wgpu::RenderPipeline,
+}
+
+impl MainPipelines {
+    fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, shader: &wgpu::ShaderModule, layouts: &MainLayouts) -> Self {
+        let build_pipeline = |second: Option<&wgpu::BindGroupLayout>, kind| {
+            let bind_group_layouts = [Some(layouts.scene), second].into_iter().flatten().collect::<Vec<_>>();
+            graphics::build_pipeline(&bind_group_layouts, device, shader, config, kind)
+        };
+        let (wireframe_pipeline, wireframe_overlay_pipeline) =
+            if device.features().contains(wgpu::Features::POLYGON_MODE_LINE) {
+                (
+                    Some(build_pipeline(None, PipelineKind::Wireframe)),
+                    Some(build_pipeline(None, PipelineKind::WireframeOverlay)),
+                )
+            } else {
+                (None, None)
+            };
+        MainPipelines {
+            render_pipeline: build_pipeline(None, PipelineKind::Fill),
+            overdraw_pipeline: build_pipeline(None, PipelineKind::Overdraw),
+            wireframe_pipeline,
+            wireframe_overlay_pipeline,
+            skinned_pipeline: build_pipeline(Some(layouts.bones), PipelineKind::Skinned),
+            morph_pipeline: build_pipeline(Some(layouts.morph), PipelineKind::Morph),
+            reflective_pipeline: build_pipeline(Some(layouts.reflection), PipelineKind::Reflective),
+            env_mapped_pipeline: build_pipeline(Some(layouts.environment), PipelineKind::EnvMapped),
+            emissive_pipeline: build_pipeline(Some(layouts.emissive), PipelineKind::Emissive),
+            stenciled_pipeline: build_pipeline(None, PipelineKind::Stenciled),
+            depth_prepass_pipeline: build_pipeline(None, PipelineKind::DepthPrepass),
+            prepassed_pipeline: build_pipeline(None, PipelineKind::Prepassed),
+            screen_pipeline: build_pipeline(Some(layouts.screen), PipelineKind::Screen),
+        }
+    }
 }
 
 struct RenderObject {
-    vertices: wgpu::Buffer,
-    indices: wgpu::Buffer,
-    model_buf: wgpu::Buffer,
-    is_instanced_buf: wgpu::Buffer,
+    // what scenes refer to the object by
+    name: &'static str,
+    // replaces the model matrix every frame
+    animation: Option<AnimationClip>,
+    material: Material,
+    // the glow of an emissive material
+    emission: Option<Emission>,
+    vertices: PoolSlice,
+    indices: PoolSlice,
+    // the model matrix and then the instances', see graphics::transforms
+    transforms: PoolSlice,
+    // what's in transforms after the model matrix, empty when not instanced
+    instances: Vec<Instance>,
+    // where the instances are, for culling and picking them. None when not instanced
+    grid: Option<InstanceGrid>,
+    // the runs of instances each main view's camera sees, by its offset. a view without an entry
+    // draws all that are shown
+    visible: Vec<(u32, Vec<Range<u32>>)>,
     num_indices: u32,
-    instances_buffer: Option<wgpu::Buffer>,
     num_instances: Option<u32>,
     shown_instances: Option<u32>,
+    // where the object is, for sorting its draws. center is the middle of its mesh, or of its
+    // instances when it has them
+    model: Matrix4<f32>,
+    center: Point3<f32>,
+    // the meshes of a static batch, drawn separately from the shared buffers. empty for everything
+    // else, which is drawn whole
+    draws: Vec<BatchDraw>,
+}
+
+impl RenderObject {
+    // writes the instances in range to transforms again, after they've been changed
+    fn update_instances(&mut self, device: &wgpu::Device, uploads: &mut Uploads, range: Range<usize>) {
+        if let Some(grid) = &mut self.grid {
+            grid.refresh(&self.instances, range.clone());
+        }
+        let raw = self.instances[range.clone()].iter().map(Instance::as_raw).collect::<Vec<_>>();
+        // they start after the model matrix
+        let offset = (1 + range.start) * std::mem::size_of::<graphics::InstanceRaw>();
+        uploads.write(device, self.transforms.buffer(), self.transforms.offset() + offset as wgpu::BufferAddress, &raw);
+    }
+
+    // adds an instance after the others, moving the transforms to a slice twice the size once
+    // they're full. returns whether they moved, the object's bind group has to be rebuilt then
+    fn add_instance(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, pools: &mut MeshPools, uploads: &mut Uploads, instance: Instance) -> bool {
+        if let Some(grid) = &mut self.grid {
+            grid.push(&instance);
+        }
+        self.instances.push(instance);
+        let count = self.instances.len();
+        self.shown_instances = Some(count as u32);
+        self.center = bounds_center(self.instances.iter().map(|instance| Point3::from_vec(instance.trans)));
+        let capacity = self.num_instances.unwrap_or(0) as usize;
+        if count <= capacity {
+            self.update_instances(device, uploads, count - 1..count);
+            return false;
+        }
+        let capacity = (capacity * 2).max(count);
+        let mut raw = self.instances.iter().map(Instance::as_raw).collect::<Vec<_>>();
+        raw.resize(capacity, bytemuck::Zeroable::zeroed());
+        // the old slice goes back to the pool when it's replaced
+        self.transforms = pools.transforms.alloc(device, queue, &graphics::transforms(self.model, &raw));
+        self.num_instances = Some(capacity as u32);
+        true
+    }
 }
 
 pub const INSTANCED_ROWS: usize = 50;
@@ -58,13 +494,219 @@ pub const INSTANCE_SPACING: f32 = 3.0;
 const SPHERE_INSTANCED_ROWS: usize = 10;
 const SPHERE_INSTANCED_COLS: usize = 10;
 const SPHERE_INSTANCE_SPACING: f32 = 15.0;
+const SPHERE_RADIUS: f32 = 5.0;
+// from the middle of the unit cube to a corner, the pyramids fit inside it too
+const CUBE_MESH_RADIUS: f32 = 0.87;
 const FLOOR_Y: f32 = -25.0;
+// how high above the floor the bouncing balls can start
+const BALLS_DROP_HEIGHT: f32 = 40.0;
+const BALLS_SEED: u32 = 7;
+// beside the cube grid, clear of everything else
+const METABALLS_CENTER: [f32; 3] = [-40.0, FLOOR_Y + 25.0, 75.0];
+// pixels from the screen edge and screen pixels per font pixel
+const HUD_MARGIN: f32 = 10.0;
+const HUD_SCALE: f32 = 2.0;
+const HUD_ICON_SIZE: f32 = 24.0;
+const CROSSHAIR_SIZE: f32 = 16.0;
+const CURSOR_SIZE: f32 = 24.0;
+// how far ahead of the camera the console spawns things when the crosshair isn't on anything
+const SPAWN_DISTANCE: f32 = 10.0;
+// how many of the biggest gpu allocations are listed
+const HUD_LARGEST_RESOURCES: usize = 3;
+// half the width of the billboards drawn at each light when the gizmos are shown
+const LIGHT_ICON_SIZE: f32 = 2.0;
+const EMITTER_ICON_SIZE: f32 = 1.0;
+// how far above the top of each instanced sphere its marker floats
+const MARKER_HEIGHT: f32 = 3.0;
+const MARKER_SIZE: f32 = 1.0;
+// high above the middle of the cube grid
+const GPU_PARTICLES_CENTER: [f32; 3] = [
+    (INSTANCED_ROWS - 1) as f32 * INSTANCE_SPACING / 2.0,
+    100.0,
+    (INSTANCED_COLS - 1) as f32 * INSTANCE_SPACING / 2.0,
+];
+// above the middle of the sphere grid
+const PARTICLES_EMITTER: [f32; 3] = [
+    (SPHERE_INSTANCED_ROWS - 1) as f32 * SPHERE_INSTANCE_SPACING / 2.0,
+    SPHERE_RADIUS * 2.0,
+    (SPHERE_INSTANCED_COLS - 1) as f32 * SPHERE_INSTANCE_SPACING / 2.0,
+];
+const PRIMITIVE_SPACING: f32 = 15.0;
+// where the physics demo's crates start falling from, under the cube grid
+#[cfg(feature = "physics")]
+const CRATE_DROP: [f32; 3] = [20.0, FLOOR_Y + 4.0, 20.0];
+#[cfg(feature = "physics")]
+const CRATE_COUNT: usize = 12;
+#[cfg(feature = "physics")]
+const CRATE_SIZE: f32 = 2.0;
+// how far from the camera cubes are collided with, more than it moves in a frame
+const COLLIDER_RANGE: f32 = 10.0;
+// the picked cube's color, and how far from its middle a picked point can be, a little past a corner
+const HIGHLIGHT_TINT: [f32; 4] = [1.0, 0.5, 0.2, 1.0];
+const CUBE_PICK_RADIUS: f32 = 0.9;
+// the crates clicked into the scene, and how many there's room for before their transforms grow
+const PLACED_SIZE: f32 = 2.0;
+const PLACED_CAPACITY: usize = 16;
+// how far from a primitive's origin it can be grabbed, about the size of the biggest
+const GRAB_REACH: f32 = 8.0;
+// radians per second the cubes turn when spinning
+const INSTANCE_SPIN_SPEED: f32 = 1.5;
+// factor the terrain height changes by per key press
+const TERRAIN_HEIGHT_STEP: f32 = 1.25;
+// beside the cube grid, on the other side of the metaballs
+const MORPH_BLOB_CENTER: [f32; 3] = [-40.0, FLOOR_Y + 15.0, 30.0];
+const MORPH_BLOB_RADIUS: f32 = 8.0;
+// the character walks back and forth between these, along the far edge of the floor
+const CHARACTER_PATH: [[f32; 3]; 2] = [[5.0, FLOOR_Y, 142.0], [140.0, FLOOR_Y, 142.0]];
+// the model is about as tall as a person in meters
+const CHARACTER_SCALE: f32 = 6.0;
+// roughly how far one loop of the walk animation carries it
+const CHARACTER_SPEED: f32 = 9.0;
+// thick enough that a fall at full speed can't pass through it in one frame
+const FLOOR_THICKNESS: f32 = 10.0;
+// the scene plus up to three copies across wrapping world edges
+const MAX_CAMERA_TILES: u32 = 4;
+// the map views in the camera buffer, after the main, reflected and portal views
+const MAP_CAMERA_SLOT: u32 = MAX_CAMERA_TILES * 2 + Portal::MAX_LEVELS;
+const MINIMAP_CAMERA_SLOT: u32 = MAP_CAMERA_SLOT + 1;
+const TV_CAMERA_SLOT: u32 = MINIMAP_CAMERA_SLOT + 1;
+// world units shown around the instanced objects on the minimap
+const MINIMAP_BORDER: f32 = 30.0;
+// pixels per side of the camera's marker on it
+const MINIMAP_MARKER_SIZE: f32 = 12.0;
+// touchpads report scrolling in pixels, this turns it into wheel notches
+const PIXELS_PER_SCROLL_LINE: f64 = 40.0;
+// holding up/down keeps changing the instance count after this many seconds, this many times a second
+const INSTANCE_REPEAT_DELAY: f64 = 0.3;
+const INSTANCE_REPEAT_RATE: f64 = 20.0;
+// seconds between flythrough keyframes
+const FLYTHROUGH_SEGMENT_DURATION: f32 = 2.0;
+// number keys 1 to 9
+const BOOKMARK_SLOTS: usize = 9;
+const BOOKMARK_FLIGHT_DURATION: f32 = 0.75;
 
 impl App {
-    pub fn new(window: &winit::window::Window) -> Self {
-        let (surface, device, queue, config, shader) = graphics::create_wgpu_context(window);
+    pub async fn new(window: &winit::window::Window, options: &ContextOptions) -> Result<Self, AppError> {
+        Self::from_context(graphics::create_wgpu_context(window, options).await?)
+    }
+
+    // renders into an offscreen texture instead of a window
+    pub async fn new_headless(width: u32, height: u32, options: &ContextOptions) -> Result<Self, AppError> {
+        Self::from_context(graphics::create_headless_context(width, height, options).await?)
+    }
+
+    // everything on the gpu built again on a new device, after the old one was lost. the scene
+    // and what was changed since it was loaded are kept, a recording is stopped. when no new
+    // device can be had the app is left without one and can only be dropped
+    pub async fn recreate(
+        &mut self,
+        window: &Window,
+        options: &ContextOptions,
+        settings: &Settings,
+    ) -> Result<(), AppError> {
+        // a window can only have one configured surface, the old ones go before the new ones are made
+        self.target = RenderTarget::Offscreen(graphics::create_offscreen_target(&self.device, &self.config));
+        let map = self.map.take().map(MapWindow::into_window);
+        let old = std::mem::replace(self, Self::new(window, options).await?);
+        if let Some(capture) = old.capture {
+            warn!("Stopped recording after {} frames, the device was lost", capture.frames_captured());
+        }
+        if let Some(map) = map {
+            self.open_map(map);
+        }
+        // what isn't carried over from the old app below, like the volume, comes from the settings
+        self.apply_settings(settings);
+        self.apply_scene(&old.scene);
+        // noise terrain can have been changed since the scene was loaded
+        if old.terrain_config.is_some() {
+            self.terrain_config = old.terrain_config;
+            self.terrain = self.terrain_config.as_ref().and_then(|config| self.load_terrain(config));
+        }
+        for (object, old_object) in [
+            (&mut self.obj1, &old.obj1),
+            (&mut self.obj2, &old.obj2),
+            (&mut self.pythagoras_sphere, &old.pythagoras_sphere),
+        ] {
+            object.0.shown_instances = old_object.0.shown_instances;
+        }
+        self.camera = old.camera;
+        self.scene_camera = old.scene_camera;
+        self.input_state = old.input_state;
+        self.modifiers = old.modifiers;
+        self.bookmarks = old.bookmarks;
+        self.flythrough = old.flythrough;
+        self.bookmark_flight = old.bookmark_flight;
+        self.selected_obj = old.selected_obj;
+        self.view_mode = old.view_mode;
+        self.wireframe_mode = old.wireframe_mode;
+        self.show_floor = old.show_floor;
+        self.show_hud = old.show_hud;
+        self.show_help = old.show_help;
+        self.console = old.console;
+        self.time_scale = old.time_scale;
+        self.color_grading.set_index(old.color_grading.index());
+        self.post_effects = old.post_effects;
+        self.sky.set_cloud_quality(old.sky.cloud_quality());
+        self.weather = old.weather;
+        self.set_render_scale(old.render_scale);
+        self.update_post();
+        self.show_markers = old.show_markers;
+        self.show_minimap = old.show_minimap;
+        self.show_grid = old.show_grid;
+        self.depth_prepass = old.depth_prepass;
+        self.spin_instances = old.spin_instances;
+        self.wave_instances = old.wave_instances;
+        self.show_light_gizmos = old.show_light_gizmos;
+        self.debug_shapes = old.debug_shapes;
+        self.lights = old.lights;
+        self.time_of_day = old.time_of_day;
+        self.elapsed = old.elapsed;
+        self.fps = old.fps;
+        info!("Recreated the device");
+        Ok(())
+    }
+
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Relaxed)
+    }
+
+    fn from_context((target, (instance, adapter), device, queue, config, shader): WgpuContext) -> Result<Self, AppError> {
         let bind_group_layout = build_bind_group_layout(&device);
-        let render_pipeline = graphics::build_pipeline(&[&bind_group_layout], &device, &shader, &config);
+        let bones_bind_group_layout = skinning::build_bones_bind_group_layout(&device);
+        let morph_bind_group_layout = morph::build_morph_bind_group_layout(&device);
+        let reflection_bind_group_layout = reflection::build_reflection_bind_group_layout(&device);
+        let environment_bind_group_layout = sky::build_environment_bind_group_layout(&device);
+        let emissive_bind_group_layout = emissive::build_emissive_bind_group_layout(&device);
+        let screen_bind_group_layout = tv::build_screen_bind_group_layout(&device);
+        let MainPipelines {
+            render_pipeline,
+            overdraw_pipeline,
+            wireframe_pipeline,
+            wireframe_overlay_pipeline,
+            skinned_pipeline,
+            morph_pipeline,
+            reflective_pipeline,
+            env_mapped_pipeline,
+            emissive_pipeline,
+            stenciled_pipeline,
+            depth_prepass_pipeline,
+            prepassed_pipeline,
+            screen_pipeline,
+        } = MainPipelines::new(
+            &device,
+            &config,
+            &shader,
+            &MainLayouts {
+                scene: &bind_group_layout,
+                bones: &bones_bind_group_layout,
+                morph: &morph_bind_group_layout,
+                reflection: &reflection_bind_group_layout,
+                environment: &environment_bind_group_layout,
+                emissive: &emissive_bind_group_layout,
+                screen: &screen_bind_group_layout,
+            },
+        );
+        let push_constants = graphics::has_push_constants(&device);
         let camera = Camera::new(
             (0.0, 0.0, 0.0).into(),
             45.0,
@@ -75,11 +717,19 @@ impl App {
         let mut camera_uniform = RawMatrix::new();
         camera_uniform.update_view_proj(&camera);
 
-        let camera_uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        let align = device.limits().min_uniform_buffer_offset_alignment;
+        let camera_uniform_stride = (std::mem::size_of::<CameraUniform>() as u32).div_ceil(align) * align;
+        let camera_uniform_buffer = graphics::create_buffer(&device, &wgpu::BufferDescriptor {
             label: Some("camera_buffer"),
-            contents: bytemuck::cast_slice(&[camera_uniform]),
+            size: (camera_uniform_stride * (TV_CAMERA_SLOT + 1)) as wgpu::BufferAddress,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
         });
+        queue.write_buffer(
+            &camera_uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[CameraUniform { view_proj: camera_uniform.mat, clip_plane: CameraUniform::NO_CLIP }]),
+        );
 
         let rot_instances = (0..INSTANCED_ROWS)
             .flat_map(|x| {
@@ -93,53 +743,87 @@ impl App {
                         cgmath::Vector3::unit_z(),
                         cgmath::Deg((x * 10) as f32 + (z * 10) as f32),
                     ),
+                    scale: Vector3::new(instance_size(x), instance_size(x + z), instance_size(z)),
+                    tint: graphics::NO_TINT,
                 })
             })
             .collect::<Vec<_>>();
 
-        let sphere_instances = (0..SPHERE_INSTANCED_ROWS)
-            .flat_map(|x| {
-                (0..SPHERE_INSTANCED_COLS).map(move |z| Instance {
-                    trans: Vector3::new(
-                        x as f32 * SPHERE_INSTANCE_SPACING,
-                        0.0,
-                        z as f32 * SPHERE_INSTANCE_SPACING,
-                    ),
-                    rot: cgmath::Quaternion::from_axis_angle(
-                        cgmath::Vector3::unit_z(),
-                        cgmath::Deg(0.0),
-                    ),
-                })
-            })
-            .collect::<Vec<_>>();
+        let sphere_instances = sphere_grid();
 
-        let obj1 = build_obj1(&device, &rot_instances);
-        let obj2 = build_obj2(&device, &rot_instances);
-        let floor = build_floor(&device);
-        let pythagoras_sphere = build_sphere(&device, &sphere_instances);
+        let mut pools = MeshPools::new(&device);
+        let obj1 = build_obj1(&device, &queue, &mut pools, &rot_instances);
+        let obj2 = build_obj2(&device, &queue, &mut pools, &rot_instances);
+        let floor = build_floor(&device, &queue, &mut pools);
+        let pythagoras_sphere = build_sphere(&device, &queue, &mut pools, &sphere_instances, SphereMesh::default());
+        let placed = build_placed(&device, &queue, &mut pools);
 
-        let create_bind_group = |model_buf, is_instanced_buf, tex_path, tex_name| graphics::build_bind_group(
+        let light_uniform_buffer = graphics::create_buffer_init(&device, &wgpu::util::BufferInitDescriptor {
+            label: Some("light_uniform_buffer"),
+            contents: bytemuck::cast_slice(&[Sun::unlit().light_uniform(camera.loc, 0, ViewMode::Lit, [0.0, FLOOR_Y])]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let light_buffer = LightBuffer::new(&device);
+        let sky = SkyRenderer::new(&device, &queue, config.format, &environment_bind_group_layout)?;
+        let debug_draw = DebugDraw::new(&device, config.format);
+        let grid = GridRenderer::new(&device, config.format);
+        let text = TextRenderer::new(&device, &queue, config.format);
+        let billboards = BillboardRenderer::new(&device, config.format);
+        let sprites = SpriteBatch::new(&device, &queue, config.format);
+
+        // textures are embedded so the same binary works on the web, where there's no filesystem
+        let create_bind_group = |transforms, tex_bytes: &[u8], tex_name| graphics::build_bind_group(
             &bind_group_layout,
-            &std::fs::read(tex_path).expect("Failed to load texture"),
+            tex_bytes,
             tex_name,
             &device,
             &queue,
-            vec![&camera_uniform_buffer, model_buf, is_instanced_buf],
+            vec![
+                wgpu::BufferBinding {
+                    buffer: &camera_uniform_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<CameraUniform>() as u64),
+                },
+                transforms,
+                wgpu::Buffer::as_entire_buffer_binding(&light_uniform_buffer),
+                wgpu::Buffer::as_entire_buffer_binding(light_buffer.buffer()),
+            ],
         );
 
-        let obj1_bind_group = create_bind_group(&obj1.model_buf, &obj1.is_instanced_buf, "res/tex/tex4.jpg", "texture_obj1");
-        let obj2_bind_group = create_bind_group(&obj2.model_buf, &obj2.is_instanced_buf,"res/tex/tex6.png", "texture_obj2");
-        let floor_bind_group = create_bind_group(&floor.model_buf, &floor.is_instanced_buf,"res/tex/floor.png", "texture_floor");
-        let pythagoras_sphere_bind_group = create_bind_group(&pythagoras_sphere.model_buf, &pythagoras_sphere.is_instanced_buf,"res/tex/bricks.jpg", "texture_sphere");
+        let obj1_bind_group = create_bind_group(obj1.transforms.binding(), include_bytes!("../res/tex/tex4.jpg"), "texture_obj1")?;
+        let obj2_bind_group = create_bind_group(obj2.transforms.binding(), include_bytes!("../res/tex/tex6.png"), "texture_obj2")?;
+        let floor_bind_group = create_bind_group(floor.transforms.binding(), include_bytes!("../res/tex/floor.png"), "texture_floor")?;
+        let pythagoras_sphere_bind_group = create_bind_group(pythagoras_sphere.transforms.binding(), include_bytes!("../res/tex/bricks.jpg"), "texture_sphere")?;
+        let placed_bind_group = create_bind_group(placed.transforms.binding(), include_bytes!("../res/tex/tex4.jpg"), "texture_placed")?;
 
         let depth_texture = graphics::create_depth_texture(&device, &config, "global_depth_texture");
+        let msaa_target = graphics::create_msaa_target(&device, &config, "global_msaa_target");
+        let depth_sample_view = graphics::create_depth_sample_view(&depth_texture.2);
+        let particles = ParticleSystem::new(&device, config.format, &depth_sample_view);
+        let god_rays = GodRays::new(&device, config.format, &depth_sample_view);
+        let flare = LensFlare::new(&device, config.format, &depth_sample_view);
+        let decals = Decals::new(&device, &queue, config.format, &depth_sample_view);
+        let depth_readback = DepthReadback::new(&device);
+        let color_grading = ColorGrading::new(&device, &queue);
+        let field_extent = (INSTANCED_ROWS.max(INSTANCED_COLS) - 1) as f32 * INSTANCE_SPACING;
+        let field_center = Point3::new(field_extent / 2.0, FLOOR_Y, field_extent / 2.0);
+        let minimap = Minimap::new(&device, config.format, field_center, field_extent + MINIMAP_BORDER);
 
-        Self {
-            surface,
+        Ok(Self {
+            size: winit::dpi::PhysicalSize::new(config.width, config.height),
+            target,
+            instance,
+            adapter,
+            map: None,
+            minimap,
+            show_minimap: false,
+            device_lost: graphics::watch_errors(&device),
+            uploads: Uploads::new(),
+            pools,
+            render_queue: RenderQueue::new(),
             device,
             queue,
             config,
-            size: window.inner_size(),
             clear_color: wgpu::Color {
                 r: 0.0,
                 g: 0.25,
@@ -147,20 +831,125 @@ impl App {
                 a: 1.0,
             },
             render_pipeline,
+            overdraw_pipeline,
+            view_mode: ViewMode::Lit,
+            wireframe_pipeline,
+            wireframe_overlay_pipeline,
+            wireframe_mode: WireframeMode::Off,
+            bind_group_layout,
+            skinned_pipeline,
+            bones_bind_group_layout,
+            morph_pipeline,
+            morph_bind_group_layout,
+            reflective_pipeline,
+            reflection_bind_group_layout,
+            env_mapped_pipeline,
+            environment_bind_group_layout,
+            emissive_pipeline,
+            emissive_bind_group_layout,
+            stenciled_pipeline,
+            depth_prepass_pipeline,
+            prepassed_pipeline,
+            depth_prepass: false,
+            spin_instances: false,
+            wave_instances: false,
+            instance_wave: None,
+            highlighted_cube: None,
+            push_constants,
+            screen_pipeline,
+            screen_bind_group_layout,
             obj1: (obj1, obj1_bind_group),
             obj2: (obj2, obj2_bind_group),
             floor: (floor, floor_bind_group),
+            placed: (placed, placed_bind_group),
+            drag: None,
+            show_floor: true,
+            reflection: None,
+            portal: None,
+            tv: None,
+            terrain: None,
+            terrain_config: None,
+            voxels: None,
+            metaballs: None,
+            primitives: Vec::new(),
+            #[cfg(feature = "physics")]
+            physics: None,
+            balls: None,
+            #[cfg(feature = "scripting")]
+            scripts: None,
+            #[cfg(feature = "audio")]
+            audio: Audio::new(),
+            #[cfg(feature = "audio")]
+            hums: Vec::new(),
+            #[cfg(feature = "audio")]
+            rain_sound: None,
+            particles,
+            god_rays,
+            flare,
+            gpu_particles: None,
+            weather: WeatherState::new(),
+            rain: None,
+            decals,
+            grass: None,
+            character: None,
+            morph_blob: None,
+            text,
+            show_hud: true,
+            show_telemetry: false,
+            billboards,
+            show_markers: false,
+            sprites,
+            show_help: false,
+            focused: None,
+            cursor_position: None,
+            console: Console::new(),
+            time_scale: 1.0,
+            fps: None,
+            light_uniform_buffer,
+            light_buffer,
+            lights: Vec::new(),
+            light_demo: false,
+            sky,
+            debug_draw,
+            show_light_gizmos: false,
+            debug_shapes: None,
+            scene_camera: None,
+            grid,
+            show_grid: false,
+            day_length: 0.0,
+            time_of_day: 0.0,
+            colliders: Vec::new(),
             pythagoras_sphere: (pythagoras_sphere, pythagoras_sphere_bind_group),
-            input_state: input::InputState::new(),
+            sphere_mesh: SphereMesh::default(),
+            input_state: input::InputState::new(input::KeyBindings::default()),
             camera,
             camera_uniform,
             camera_uniform_buffer,
+            camera_uniform_stride,
+            camera_offsets: vec![0],
+            reflection_offsets: Vec::new(),
+            portal_offsets: Vec::new(),
             selected_obj: 1,
-            cooldowns: (0.0, 0.0),
             delta_time: 0.0,
             depth_texture,
-            intial_instant: std::time::Instant::now(),
-        }
+            depth_sample_view,
+            msaa_target,
+            render_scale: 1.0,
+            color_grading,
+            post_effects: post::Effects::default(),
+            post: None,
+            depth_readback,
+            flythrough: Flythrough::new(FLYTHROUGH_SEGMENT_DURATION),
+            bookmarks: Default::default(),
+            bookmark_flight: Flythrough::new(BOOKMARK_FLIGHT_DURATION),
+            modifiers: ModifiersState::empty(),
+            elapsed: 0.0,
+            capture: None,
+            capture_target: None,
+            #[cfg(feature = "clipboard")]
+            copy_requested: false,
+            scene: Scene::default(),
+        })
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
@@ -168,12 +957,645 @@ impl App {
             self.size = new_size;
             self.config.width = new_size.width;
             self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+            match &mut self.target {
+                RenderTarget::Surface(surface) => surface.configure(&self.device, &self.config),
+                RenderTarget::Offscreen(texture) => {
+                    *texture = graphics::create_offscreen_target(&self.device, &self.config)
+                }
+            }
+            let scene_config = self.scene_config();
             self.depth_texture =
-                graphics::create_depth_texture(&self.device, &self.config, "global_depth_texture");
+                graphics::create_depth_texture(&self.device, &scene_config, "global_depth_texture");
+            self.msaa_target = graphics::create_msaa_target(&self.device, &scene_config, "global_msaa_target");
+            if let Some(post) = &mut self.post {
+                post.resize(&self.device, &self.config, &scene_config);
+            }
+            if let Some(reflection) = &mut self.reflection {
+                reflection.resize(&self.device, &self.reflection_bind_group_layout, &scene_config);
+            }
+            self.depth_sample_view = graphics::create_depth_sample_view(&self.depth_texture.2);
+            self.particles.set_depth_view(&self.device, &self.depth_sample_view);
+            self.god_rays.set_depth_view(&self.device, &self.depth_sample_view);
+            self.flare.set_depth_view(&self.device, &self.depth_sample_view);
+            self.decals.set_depth_view(&self.device, &self.depth_sample_view);
             self.camera
                 .set_aspect(self.config.width as f32 / self.config.height as f32);
+            if let Some(capture) = &mut self.capture {
+                capture.resize(&self.device, &self.config);
+            }
+            self.capture_target = None;
+        }
+    }
+
+    // the targets are all built again at the new size
+    pub fn set_render_scale(&mut self, scale: f32) {
+        let scale = scale.clamp(PostProcess::MIN_SCALE, PostProcess::MAX_SCALE);
+        if scale != self.render_scale {
+            self.render_scale = scale;
+            self.update_post();
+            self.resize(self.size);
+            let scene_config = self.scene_config();
+            info!("Rendering the scene at {}x{}", scene_config.width, scene_config.height);
+        }
+    }
+
+    // the window's size and format with the size scaled to the scene's
+    fn scene_config(&self) -> wgpu::SurfaceConfiguration {
+        post::scaled_config(&self.config, self.render_scale)
+    }
+
+    // the post pass is dropped when it would only copy the scene to the frame unchanged
+    fn update_post(&mut self) {
+        if self.render_scale == 1.0 && self.color_grading.current().is_none() && self.post_effects.is_off() {
+            self.post = None;
+            return;
+        }
+        let scene_config = self.scene_config();
+        let post = self.post.get_or_insert_with(|| {
+            PostProcess::new(&self.device, &self.config, &scene_config, self.color_grading.bind_group_layout())
+        });
+        post.set_effects(&self.queue, &self.post_effects);
+    }
+
+    pub fn open_map(&mut self, window: Window) {
+        self.map = Some(MapWindow::new(window, &self.instance, &self.adapter, &self.device, self.config.format));
+    }
+
+    pub fn close_map(&mut self) {
+        self.map = None;
+    }
+
+    pub fn map_window(&self) -> Option<&Window> {
+        self.map.as_ref().map(MapWindow::window)
+    }
+
+    pub fn resize_map(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        if let Some(map) = &mut self.map {
+            map.resize(&self.device, new_size);
+        }
+    }
+
+    // the scene's plain textured objects from above, drawn with the views update() left in the
+    // camera buffer
+    pub fn render_map(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let Some(map) = &self.map else {
+            return Ok(());
+        };
+        let output = map.current_texture()?;
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("map_encoder"),
+            });
+        {
+            let mut render_pass = map.begin_pass(&mut encoder, &view, self.output_clear_color());
+            render_pass.set_pipeline(&self.render_pipeline);
+            self.draw_scene(&mut render_pass, &[MAP_CAMERA_SLOT * self.camera_uniform_stride], self.show_floor, false);
+        }
+        // in case the main window's frame didn't get as far as submitting them
+        self.queue.submit(self.uploads.finish().into_iter().chain(std::iter::once(encoder.finish())));
+        self.uploads.recall();
+        output.present();
+        Ok(())
+    }
+
+    pub fn toggle_recording(&mut self) {
+        if let Some(capture) = self.capture.take() {
+            info!("Stopped recording after {} frames", capture.frames_captured());
+        } else {
+            let secs = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            if let Err(e) = self.start_recording(std::path::PathBuf::from(format!("captures/{}", secs))) {
+                warn!("{}", e);
+            }
+        }
+    }
+
+    #[cfg(feature = "clipboard")]
+    pub fn copy_frame_to_clipboard(&mut self) {
+        self.copy_requested = true;
+    }
+
+    pub fn start_recording(&mut self, dir: std::path::PathBuf) -> Result<(), AppError> {
+        self.capture = Some(FrameCapture::new(&self.device, &self.config, CaptureOutput::Sequence(dir))?);
+        Ok(())
+    }
+
+    // renders a single frame straight into an image file. false when it couldn't be written, which
+    // is logged
+    pub fn render_to_file(&mut self, path: std::path::PathBuf) -> Result<bool, AppError> {
+        let capture = FrameCapture::new(&self.device, &self.config, CaptureOutput::File(path))?;
+        let previous = self.capture.replace(capture);
+        let result = self.render();
+        // render drops a capture whose frame failed to be written
+        let written = std::mem::replace(&mut self.capture, previous).is_some();
+        result?;
+        Ok(written)
+    }
+
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.camera.set_fovy(settings.fov);
+        self.camera.set_ortho_extent(settings.ortho_extent);
+        self.camera.set_sensitivity(settings.mouse_sensitivity);
+        self.camera.set_smoothing(settings.look_smoothing, settings.movement_smoothing);
+        self.camera.set_sprint_effects(settings.sprint_effects);
+        self.camera.set_head_bob(settings.head_bob);
+        #[cfg(feature = "audio")]
+        if let Some(audio) = &mut self.audio {
+            audio.set_volume(settings.volume);
+            audio.set_music_volume(settings.music_volume);
+        }
+        self.set_render_scale(settings.render_scale);
+        self.sky.set_cloud_quality(settings.cloud_quality);
+        self.input_state.set_bindings(settings.keybindings);
+        for (slot, bookmark) in self.bookmarks.iter_mut().enumerate() {
+            *bookmark = settings.bookmarks.get(&(slot + 1).to_string()).cloned();
+        }
+    }
+
+    // the bookmarks in the form they're stored in the settings file
+    pub fn bookmarks(&self) -> BTreeMap<String, CameraPose> {
+        self.bookmarks
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, bookmark)| Some(((slot + 1).to_string(), bookmark.clone()?)))
+            .collect()
+    }
+
+    // ctrl + number saves the camera pose, the number alone flies back to it
+    fn use_bookmark(&mut self, slot: usize) {
+        if self.modifiers.ctrl() {
+            self.bookmarks[slot] = Some(self.camera.pose());
+            info!("Saved bookmark {}", slot + 1);
+        } else if let Some(pose) = self.bookmarks[slot].clone() {
+            self.flythrough.stop();
+            self.bookmark_flight.set_keyframes(vec![self.camera.pose(), pose]);
+            self.bookmark_flight.play();
+        } else {
+            info!("Bookmark {} is empty", slot + 1);
+        }
+    }
+
+    pub fn apply_scene(&mut self, scene: &Scene) {
+        self.scene_camera = None;
+        self.camera.set_pose(&scene.camera);
+        // what's held can be about to go away
+        self.drag = None;
+        // crates placed in the last scene could be floating or buried in this one
+        if !self.placed.0.instances.is_empty() {
+            self.placed.0 = build_placed(&self.device, &self.queue, &mut self.pools);
+            if let Some(bind_group) = self.create_bind_group(self.placed.0.transforms.binding(), include_bytes!("../res/tex/tex4.jpg"), "texture_placed") {
+                self.placed.1 = bind_group;
+            }
+        }
+        self.apply_scene_fields(scene, &|_| true);
+    }
+
+    // for the scene file being edited while it's shown. only what differs from the scene that's
+    // showing is rebuilt, and the camera stays wherever it's been moved to
+    pub fn reload_scene(&mut self, scene: &Scene) {
+        let mut changed = self.scene.changed_fields(scene);
+        changed.remove("camera");
+        if changed.is_empty() {
+            return;
+        }
+        info!("Reloading {} from the scene", changed.iter().copied().collect::<Vec<_>>().join(", "));
+        self.apply_scene_fields(scene, &|field| changed.contains(field));
+    }
+
+    // builds the parts of the scene whose fields changed says have, the camera excepted
+    fn apply_scene_fields(&mut self, scene: &Scene, changed: &dyn Fn(&str) -> bool) {
+        self.scene = scene.clone();
+        if changed("clear_color") {
+            let [r, g, b] = scene.clear_color;
+            self.clear_color = wgpu::Color { r, g, b, a: 1.0 };
+        }
+        if changed("selected_obj") {
+            self.selected_obj = scene.selected_obj;
+        }
+        if changed("bounds") {
+            self.camera.set_bounds(scene.bounds);
+        }
+        if changed("flythrough") {
+            self.flythrough.set_keyframes(scene.flythrough.clone());
+        }
+        if changed("day_length") {
+            self.day_length = scene.day_length;
+        }
+        if changed("time_of_day") {
+            self.time_of_day = scene.time_of_day;
+        }
+        if changed("light_demo") {
+            self.light_demo = scene.light_demo;
+            self.lights.clear();
+        }
+        if changed("terrain") {
+            self.terrain_config = scene.terrain.clone();
+            self.terrain = self.terrain_config.as_ref().and_then(|config| self.load_terrain(config));
+        }
+        if changed("voxels") {
+            self.voxels = scene.voxels.as_ref().and_then(|config| {
+                let world = VoxelWorld::new(&self.device, config);
+                let bind_group = self.create_bind_group(
+                    world.transforms_buf.as_entire_buffer_binding(),
+                    include_bytes!("../res/tex/floor.png"),
+                    "texture_voxels",
+                )?;
+                Some((world, bind_group))
+            });
+        }
+        if changed("metaballs") {
+            self.metaballs = scene.metaballs.then(|| {
+                let metaballs = Metaballs::new(&self.device, METABALLS_CENTER.into());
+                let bind_group = self.create_bind_group(
+                    metaballs.transforms_buf.as_entire_buffer_binding(),
+                    include_bytes!("../res/tex/tex6.png"),
+                    "texture_metaballs",
+                )?;
+                Some((metaballs, bind_group))
+            }).flatten();
+        }
+        let sphere_mesh_changed = scene.sphere_mesh != self.sphere_mesh;
+        if sphere_mesh_changed {
+            self.set_sphere_mesh(scene.sphere_mesh);
+        }
+        // which primitives are batched depends on their animations and materials
+        let primitives_changed = ["primitives_demo", "animations", "materials"].into_iter().any(changed);
+        if primitives_changed {
+            self.primitives = if scene.primitives_demo { self.build_primitives(scene) } else { Vec::new() };
+        }
+        #[cfg(feature = "physics")]
+        if changed("physics_demo") {
+            self.physics = scene.physics_demo.then(|| self.drop_crates());
+        }
+        #[cfg(not(feature = "physics"))]
+        if changed("physics_demo") && scene.physics_demo {
+            warn!("The physics demo needs the physics feature");
+        }
+        #[cfg(feature = "scripting")]
+        if changed("scripts") {
+            self.scripts = (!scene.scripts.is_empty()).then(|| Scripts::new(&scene.scripts));
+        }
+        #[cfg(not(feature = "scripting"))]
+        if changed("scripts") && !scene.scripts.is_empty() {
+            warn!("Scripts need the scripting feature");
+        }
+        let objects_changed = primitives_changed || sphere_mesh_changed;
+        if objects_changed {
+            let objects = [&mut self.obj1, &mut self.obj2, &mut self.floor, &mut self.pythagoras_sphere, &mut self.placed];
+            for (object, _) in objects.into_iter().chain(&mut self.primitives) {
+                object.animation = scene.animations.get(object.name).cloned().or_else(|| default_animation(object.name));
+                object.material = scene.materials.get(object.name).copied().unwrap_or_default();
+                object.emission = match object.material {
+                    Material::Emissive { color, pulse } => {
+                        Some(Emission::new(&self.device, &self.emissive_bind_group_layout, color, pulse))
+                    }
+                    _ => None,
+                };
+            }
+        }
+        // the balls take the sphere grid's animation away
+        if changed("bouncing_balls") || objects_changed {
+            self.set_bouncing_balls(scene.bouncing_balls);
+        }
+        // the primitives humming were just replaced
+        if changed("hums") || primitives_changed {
+            self.set_hums(&scene.hums);
+        }
+        if changed("particles_demo") {
+            self.particles.clear();
+            if scene.particles_demo {
+                self.particles.emitters.push(Emitter::fountain(PARTICLES_EMITTER.into()));
+            }
+        }
+        if changed("gpu_particles") {
+            self.gpu_particles = scene
+                .gpu_particles
+                .then(|| GpuParticles::new(&self.device, self.config.format, GPU_PARTICLES_CENTER.into()));
+        }
+        if changed("grass") {
+            self.grass = scene.grass.as_ref().map(|config| {
+                let extent = |count: usize| (count - 1) as f32 * INSTANCE_SPACING;
+                let grass = Grass::new(
+                    &self.device,
+                    self.config.format,
+                    config,
+                    [0.0, FLOOR_Y, 0.0],
+                    [extent(INSTANCED_ROWS), FLOOR_Y, extent(INSTANCED_COLS)],
+                );
+                info!("Scattered {} grass blades", grass.count());
+                grass
+            });
+        }
+        if changed("character") {
+            self.character = if scene.character { self.load_character() } else { None };
+        }
+        if changed("morph_demo") {
+            self.morph_blob = scene.morph_demo.then(|| {
+                let (mesh, targets) = morph::blob(MORPH_BLOB_RADIUS);
+                let model = Matrix4::from_translation(Vector3::from(MORPH_BLOB_CENTER));
+                let blob = MorphMesh::new(&self.device, &self.morph_bind_group_layout, &mesh, &targets, model);
+                let bind_group = self.create_bind_group(
+                    blob.transforms_buf.as_entire_buffer_binding(),
+                    include_bytes!("../res/tex/tex6.png"),
+                    "texture_morph_blob",
+                )?;
+                Some((blob, bind_group))
+            }).flatten();
+        }
+        if changed("terrain") || changed("voxels") {
+            // keeps the floor when the heightmap failed to load, so there's still ground
+            let terrain_replaces_floor =
+                self.terrain.is_some() && scene.terrain.as_ref().is_some_and(|config| config.replace_floor);
+            let voxels_replace_floor = scene.voxels.as_ref().is_some_and(|config| config.replace_floor);
+            self.show_floor = !(terrain_replaces_floor || voxels_replace_floor);
+        }
+        if changed("reflective_floor") {
+            self.reflection = scene.reflective_floor.then(|| {
+                PlanarReflection::new(&self.device, &self.reflection_bind_group_layout, &self.scene_config(), FLOOR_Y)
+            });
+        }
+        if changed("portal") {
+            self.portal = scene.portal.as_ref().map(|config| Portal::new(&self.device, self.config.format, config));
+        }
+        if changed("tv") {
+            self.tv = scene.tv.as_ref().and_then(|config| {
+                let tv = Tv::new(&self.device, &self.screen_bind_group_layout, self.config.format, config);
+                let bind_group = self.create_bind_group(
+                    tv.transforms_buf.as_entire_buffer_binding(),
+                    include_bytes!("../res/tex/tex.png"),
+                    "texture_tv",
+                )?;
+                Some((tv, bind_group))
+            });
+        }
+    }
+
+    #[cfg(feature = "audio")]
+    fn set_hums(&mut self, names: &[String]) {
+        self.hums.clear();
+        let Some(audio) = &self.audio else {
+            return;
+        };
+        let mut hums = Vec::new();
+        let objects = [ObjectRef::Obj1, ObjectRef::Obj2, ObjectRef::Sphere, ObjectRef::Floor, ObjectRef::Placed]
+            .into_iter()
+            .chain((0..self.primitives.len()).map(ObjectRef::Primitive));
+        let objects = objects.map(|key| (key, &self.object(key).0)).collect::<Vec<_>>();
+        for name in names {
+            match objects.iter().find(|(_, object)| object.name == name) {
+                Some(&(key, object)) => {
+                    let hum = audio.emitter(Sound::Hum, object.model.transform_point(object.center));
+                    hums.extend(hum.map(|hum| (key, hum)));
+                }
+                None => warn!("No object named {} to hum", name),
+            }
+        }
+        self.hums = hums;
+    }
+
+    #[cfg(not(feature = "audio"))]
+    fn set_hums(&mut self, names: &[String]) {
+        if !names.is_empty() {
+            warn!("Humming objects need the audio feature");
+        }
+    }
+
+    fn load_character(&self) -> Option<(SkinnedModel, wgpu::BindGroup)> {
+        let bytes = include_bytes!("../res/models/walker.glb");
+        match SkinnedModel::from_glb(&self.device, &self.bones_bind_group_layout, bytes) {
+            Ok(character) => {
+                let bind_group = self.create_bind_group(
+                    character.transforms_buf.as_entire_buffer_binding(),
+                    include_bytes!("../res/tex/tex.png"),
+                    "texture_character",
+                )?;
+                Some((character, bind_group))
+            }
+            Err(e) => {
+                warn!("Failed to load the character: {}", e);
+                None
+            }
+        }
+    }
+
+    fn load_terrain(&self, config: &TerrainConfig) -> Option<(Terrain, wgpu::BindGroup)> {
+        let heightmap = terrain_heightmap(config)?;
+        let terrain = Terrain::new(&self.device, config, &heightmap);
+        let bind_group = self.create_bind_group(
+            terrain.transforms_buf.as_entire_buffer_binding(),
+            include_bytes!("../res/tex/floor.png"),
+            "texture_terrain",
+        )?;
+        Some((terrain, bind_group))
+    }
+
+    // regenerates noise terrain when its settings are changed
+    fn update_terrain_input(&mut self) {
+        let (Some(config), Some((terrain, _))) = (&mut self.terrain_config, &mut self.terrain) else {
+            return;
+        };
+        let Some(noise) = &mut config.noise else {
+            return;
+        };
+
+        let input = &self.input_state;
+        let mut changed = true;
+        if input.just_pressed(Action::MoreTerrainOctaves) && noise.octaves < NoiseConfig::MAX_OCTAVES {
+            noise.octaves += 1;
+        } else if input.just_pressed(Action::FewerTerrainOctaves) && noise.octaves > 1 {
+            noise.octaves -= 1;
+        } else if input.just_pressed(Action::RaiseTerrain) {
+            config.height_scale *= TERRAIN_HEIGHT_STEP;
+        } else if input.just_pressed(Action::LowerTerrain) {
+            config.height_scale /= TERRAIN_HEIGHT_STEP;
+        } else if input.just_pressed(Action::ReseedTerrain) {
+            noise.seed = noise.seed.wrapping_add(1);
+        } else {
+            changed = false;
+        }
+
+        if changed {
+            info!(
+                "Terrain seed {}, {} octaves, height {:.1}",
+                noise.seed, noise.octaves, config.height_scale
+            );
+            let heightmap = Heightmap::from_noise(noise);
+            terrain.rebuild(&self.device, config, &heightmap);
+        }
+    }
+
+    // swaps the geometry of the instanced spheres, their instances and bind group stay
+    fn set_sphere_mesh(&mut self, kind: SphereMesh) {
+        let mesh = sphere_mesh(kind);
+        let sphere = &mut self.pythagoras_sphere.0;
+        sphere.vertices = self.pools.vertices.alloc(&self.device, &self.queue, &mesh.vertices);
+        sphere.indices = self.pools.indices.alloc(&self.device, &self.queue, &mesh.indices);
+        sphere.num_indices = mesh.indices.len() as u32;
+        self.sphere_mesh = kind;
+    }
+
+    // the ones that stay put and use the default material are batched together
+    fn build_primitives(&mut self, scene: &Scene) -> Vec<(RenderObject, wgpu::BindGroup)> {
+        let meshes = [
+            ("torus", primitives::torus(4.0, 1.5, 48, 24)),
+            ("cylinder", primitives::cylinder(3.0, 8.0, 32)),
+            ("cone", primitives::cone(3.5, 8.0, 32)),
+            ("capsule", primitives::capsule(2.5, 5.0, 32, 12)),
+            ("icosphere", primitives::icosphere(4.0, 3)),
+            ("plane", primitives::plane(9.0, 8)),
+            ("cube", primitives::cube(6.0)),
+        ];
+        let mut batch = MeshBatch::new();
+        let mut objects = Vec::new();
+        for (i, (name, mesh)) in meshes.into_iter().enumerate() {
+            let position = Vector3::new(i as f32 * PRIMITIVE_SPACING, FLOOR_Y + 6.0, -PRIMITIVE_SPACING);
+            let model = Matrix4::from_translation(position);
+            let animated = scene.animations.contains_key(name) || default_animation(name).is_some();
+            let material = scene.materials.get(name).copied().unwrap_or_default();
+            if !animated && material == Material::Textured {
+                batch.add(&mesh, model);
+                continue;
+            }
+            let object = build_mesh_object(&self.device, &self.queue, &mut self.pools, &mesh, name, model);
+            let bind_group = self.create_bind_group(
+                object.transforms.binding(), include_bytes!("../res/tex/bricks.jpg"),
+                name,
+            );
+            objects.extend(bind_group.map(|bind_group| (object, bind_group)));
+        }
+        if !batch.is_empty() {
+            debug!("Batched {} static primitives", batch.len());
+            let (mesh, draws) = batch.finish();
+            let mut object =
+                build_mesh_object(&self.device, &self.queue, &mut self.pools, &mesh, "static_batch", Matrix4::identity());
+            object.draws = draws;
+            let bind_group = self.create_bind_group(
+                object.transforms.binding(), include_bytes!("../res/tex/bricks.jpg"),
+                "static_batch",
+            );
+            objects.extend(bind_group.map(|bind_group| (object, bind_group)));
+        }
+        objects
+    }
+
+    // a pile of crates falling onto the floor, added to the primitives
+    #[cfg(feature = "physics")]
+    fn drop_crates(&mut self) -> (Physics, Vec<(usize, RigidBodyHandle)>) {
+        let mut physics = Physics::new();
+        let extent = |count: usize| (count - 1) as f32 * INSTANCE_SPACING;
+        physics.add_ground(
+            (0.0, FLOOR_Y - FLOOR_THICKNESS, 0.0).into(),
+            (extent(INSTANCED_ROWS), FLOOR_Y, extent(INSTANCED_COLS)).into(),
+        );
+        let mesh = primitives::cube(CRATE_SIZE);
+        let mut bodies = Vec::new();
+        for i in 0..CRATE_COUNT {
+            // stacked a little off center and turned every which way, so they tumble as they land
+            let position = Vector3::from(CRATE_DROP) + Vector3::new((i % 3) as f32 * 0.4, i as f32 * CRATE_SIZE * 1.5, (i % 2) as f32 * 0.6);
+            let rotation = cgmath::Quaternion::from_axis_angle(Vector3::new(1.0, 2.0, 3.0).normalize(), cgmath::Deg(i as f32 * 37.0));
+            let body = physics.add_box(position, rotation, Vector3::new(1.0, 1.0, 1.0) * CRATE_SIZE / 2.0);
+            let model = physics.model(body);
+            let object = build_mesh_object(&self.device, &self.queue, &mut self.pools, &mesh, "crate", model);
+            let Some(bind_group) = self.create_bind_group(object.transforms.binding(), include_bytes!("../res/tex/tex4.jpg"), "crate") else {
+                continue;
+            };
+            bodies.push((self.primitives.len(), body));
+            self.primitives.push((object, bind_group));
+        }
+        (physics, bodies)
+    }
+
+    // throws the instanced spheres around the pen, or puts them back in their grid
+    fn set_bouncing_balls(&mut self, on: bool) {
+        let sphere = &mut self.pythagoras_sphere.0;
+        if on {
+            // the balls are placed in the world as they are, not moved by an animation
+            sphere.animation = None;
+            sphere.model = Matrix4::identity();
+            self.queue.write_buffer(
+                sphere.transforms.buffer(),
+                sphere.transforms.offset(),
+                bytemuck::cast_slice(&[graphics::RawMatrix { mat: sphere.model.into() }]),
+            );
+            let extent = |count: usize| (count - 1) as f32 * SPHERE_INSTANCE_SPACING;
+            let min = Point3::new(0.0, FLOOR_Y, 0.0);
+            let max = Point3::new(extent(SPHERE_INSTANCED_ROWS), FLOOR_Y + BALLS_DROP_HEIGHT, extent(SPHERE_INSTANCED_COLS));
+            self.balls = Some(BouncingBalls::new(&mut sphere.instances, SPHERE_RADIUS, min, max, BALLS_SEED));
+        } else if self.balls.take().is_some() {
+            sphere.instances = sphere_grid();
+        } else {
+            return;
+        }
+        let count = sphere.instances.len();
+        sphere.update_instances(&self.device, &mut self.uploads, 0..count);
+    }
+
+    // same bindings as the objects built in new. what the texture failed for is logged, the object
+    // is left out
+    fn create_bind_group(
+        &self,
+        transforms: wgpu::BufferBinding,
+        tex_bytes: &[u8],
+        tex_name: &str,
+    ) -> Option<wgpu::BindGroup> {
+        let bind_group = graphics::build_bind_group(
+            &self.bind_group_layout,
+            tex_bytes,
+            tex_name,
+            &self.device,
+            &self.queue,
+            vec![
+                wgpu::BufferBinding {
+                    buffer: &self.camera_uniform_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<CameraUniform>() as u64),
+                },
+                transforms,
+                wgpu::Buffer::as_entire_buffer_binding(&self.light_uniform_buffer),
+                wgpu::Buffer::as_entire_buffer_binding(self.light_buffer.buffer()),
+            ],
+        );
+        bind_group.map_err(|e| warn!("{}", e)).ok()
+    }
+
+    // returns whether the loaded scene had a flythrough to play
+    pub fn play_flythrough(&mut self) -> bool {
+        self.flythrough.play()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    // world space position of whatever was drawn at the given pixel last frame
+    pub fn pick(&self, pixel: (u32, u32)) -> Option<cgmath::Point3<f32>> {
+        // the depth buffer is the scene's size
+        let scene_config = self.scene_config();
+        let scene_pixel = (
+            (pixel.0 * scene_config.width / self.config.width).min(scene_config.width - 1),
+            (pixel.1 * scene_config.height / self.config.height).min(scene_config.height - 1),
+        );
+        let depth = self.depth_readback.read_depth(
+            &self.device,
+            &self.queue,
+            &self.depth_sample_view,
+            scene_pixel,
+        );
+        // cleared depth, nothing was drawn there
+        if depth >= 1.0 {
+            return None;
         }
+
+        picking::unproject(
+            self.camera.build_view_proj(),
+            pixel,
+            depth,
+            (self.size.width, self.size.height),
+        )
     }
 
     pub fn input(
@@ -181,12 +1603,46 @@ impl App {
         window_event: Option<&WindowEvent>,
         device_event: Option<&DeviceEvent>,
         window: &Window,
-        focused: bool,
+        focus: &FocusState,
     ) {
+        let focused = focus.is_focused();
         if let Some(event) = window_event {
             match event {
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::Grave),
+                            ..
+                        },
+                    ..
+                } if focused => {
+                    self.console.toggle();
+                    // the keys held now are let go of while the console has the keyboard
+                    self.input_state.release_keys();
+                }
+                WindowEvent::KeyboardInput { input, .. } if focused && self.console.is_open() => {
+                    self.console_key(input);
+                }
+                WindowEvent::ReceivedCharacter(c) if focused && self.console.is_open() => {
+                    self.console.type_char(*c);
+                }
                 WindowEvent::KeyboardInput { input, .. } if focused => {
                     self.input_state.update_keyboard(input);
+                    if input.state == ElementState::Pressed {
+                        if let Some(slot) = input.virtual_keycode.and_then(bookmark_slot) {
+                            self.use_bookmark(slot);
+                        }
+                    }
+                }
+                WindowEvent::ModifiersChanged(modifiers) => {
+                    self.modifiers = *modifiers;
+                }
+                WindowEvent::CursorMoved { position, .. } => {
+                    self.cursor_position = Some(*position);
+                }
+                WindowEvent::CursorLeft { .. } => {
+                    self.cursor_position = None;
                 }
                 WindowEvent::Resized(new_size) => {
                     self.resize(*new_size);
@@ -194,6 +1650,60 @@ impl App {
                 WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
                     self.resize(**new_inner_size);
                 }
+                WindowEvent::MouseWheel { delta, .. } if focused => {
+                    let notches = match delta {
+                        MouseScrollDelta::LineDelta(_, y) => *y,
+                        MouseScrollDelta::PixelDelta(pos) => (pos.y / PIXELS_PER_SCROLL_LINE) as f32,
+                    };
+                    self.camera.zoom(notches);
+                }
+                WindowEvent::MouseInput {
+                    state: ElementState::Pressed,
+                    button: MouseButton::Left,
+                    ..
+                } if focused => {
+                    let center = (self.size.width / 2, self.size.height / 2);
+                    match self.pick(center) {
+                        Some(p) => self.place_crate(p),
+                        None => info!("Nothing under the crosshair to place a crate on"),
+                    }
+                }
+                WindowEvent::MouseInput {
+                    state: ElementState::Pressed,
+                    button: MouseButton::Middle,
+                    ..
+                } if focused => {
+                    let center = (self.size.width / 2, self.size.height / 2);
+                    match self.pick(center) {
+                        Some(p) => self.start_drag(p),
+                        None => info!("Nothing under the crosshair to grab"),
+                    }
+                }
+                WindowEvent::MouseInput {
+                    state: ElementState::Released,
+                    button: MouseButton::Middle,
+                    ..
+                } => {
+                    if let Some(drag) = self.drag.take() {
+                        info!("Let go of {:?}", drag.target);
+                    }
+                }
+                WindowEvent::MouseInput {
+                    state: ElementState::Pressed,
+                    button: MouseButton::Right,
+                    ..
+                } if focused => {
+                    let center = (self.size.width / 2, self.size.height / 2);
+                    match self.pick(center) {
+                        Some(p) => {
+                            info!("Picked point: {}, {}, {}", p.x, p.y, p.z);
+                            let turn = self.camera.pose().yaw.to_radians();
+                            self.decals.add(Decal::target(p, turn));
+                            self.highlight_cube(p);
+                        }
+                        None => info!("Nothing under the crosshair"),
+                    }
+                }
                 _ => {}
             }
         }
@@ -201,62 +1711,412 @@ impl App {
             match event {
                 DeviceEvent::MouseMotion { delta } if focused => {
                     self.input_state.update_mouse(delta);
-                    window
-                        .set_cursor_position(PhysicalPosition::new(
+                    if focus.needs_recentering() {
+                        // not supported on every platform (e.g. the web), so failing here isn't fatal
+                        if let Err(e) = window.set_cursor_position(PhysicalPosition::new(
                             self.size.width / 2,
                             self.size.height / 2,
-                        ))
-                        .expect("Failed to set cursor position");
+                        )) {
+                            debug!("Failed to set cursor position: {}", e);
+                        }
+                    }
                 }
                 _ => {}
             }
         }
     }
 
-    pub fn update(&mut self) {
-        if self.input_state.tab_pressed && self.cooldowns.0 <= 0.0 {
-            self.selected_obj = match self.selected_obj {
-                0 => 1,
-                1 => 0,
-                _ => 0,
-            };
-            self.cooldowns.0 = 1.0;
+    // the nearest thing to the picked point that can be moved
+    fn grab_target(&self, point: Point3<f32>) -> Option<DragTarget> {
+        // the wave and the balls would put their instances straight back
+        let selected = match self.selected_obj {
+            0 if !self.wave_instances => Some(ObjectRef::Obj1),
+            1 => Some(ObjectRef::Obj2),
+            _ => None,
+        };
+        let instanced = selected
+            .into_iter()
+            .chain(self.balls.is_none().then_some(ObjectRef::Sphere))
+            .chain([ObjectRef::Placed]);
+        let mut targets = Vec::new();
+        for key in instanced {
+            let object = &self.object(key).0;
+            let shown = object.shown_instances.unwrap_or(0) as usize;
+            if let Some(grid) = &object.grid {
+                let near = grid.near(point, 0.0, object.model);
+                targets.extend(near.into_iter().filter(|&i| i < shown).map(|i| DragTarget::Instance(key, i)));
+            }
         }
 
-        if let (
-            Some(shown_instances1),
-            Some(shown_instances2),
-            Some(num_instances1),
-            Some(num_instances2),
-        ) = (
-            &mut self.obj1.0.shown_instances,
-            &mut self.obj2.0.shown_instances,
-            &self.obj1.0.num_instances,
-            &self.obj2.0.num_instances,
-        ) {
-            if self.input_state.up_pressed && self.cooldowns.1 <= 0.75 {
-                match self.selected_obj {
-                    0 if *shown_instances1 < *num_instances1 => *shown_instances1 += 1,
-                    1 if *shown_instances2 < *num_instances2 => *shown_instances2 += 1,
-                    _ => {}
-                }
-                self.cooldowns.1 = 1.0;
-            }
+        // batched primitives can't move on their own, and the physics moves its crates itself
+        #[cfg(feature = "physics")]
+        let simulated = |i: usize| self.physics.as_ref().is_some_and(|(_, bodies)| bodies.iter().any(|&(j, _)| j == i));
+        #[cfg(not(feature = "physics"))]
+        let simulated = |_: usize| false;
+        targets.extend(
+            (0..self.primitives.len())
+                .filter(|&i| self.primitives[i].0.draws.is_empty() && !simulated(i))
+                .map(|i| DragTarget::Object(ObjectRef::Primitive(i)))
+                .filter(|&target| (self.drag_origin(target) - point).magnitude2() <= GRAB_REACH * GRAB_REACH),
+        );
+        targets
+            .into_iter()
+            .map(|target| (target, (self.drag_origin(target) - point).magnitude2()))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(target, _)| target)
+    }
 
-            if self.input_state.down_pressed && self.cooldowns.1 <= 0.75 {
-                match self.selected_obj {
-                    0 if *shown_instances1 > 0 => *shown_instances1 -= 1,
-                    1 if *shown_instances2 > 0 => *shown_instances2 -= 1,
-                    _ => {}
-                }
-                self.cooldowns.1 = 1.0;
+    // where the target's origin is in the world
+    fn drag_origin(&self, target: DragTarget) -> Point3<f32> {
+        match target {
+            DragTarget::Object(key) => self.object(key).0.model.transform_point(Point3::origin()),
+            DragTarget::Instance(key, i) => {
+                let object = &self.object(key).0;
+                // same order as the vertex shader, the instance transform applies after the model one
+                (object.instances[i].matrix() * object.model).transform_point(Point3::origin())
             }
         }
+    }
 
-        self.cooldowns.0 -= self.delta_time * 5.0;
-        self.cooldowns.1 -= self.delta_time * 5.0;
+    fn start_drag(&mut self, point: Point3<f32>) {
+        let Some(target) = self.grab_target(point) else {
+            info!("Nothing that can be moved under the crosshair");
+            return;
+        };
+        if let DragTarget::Object(key) = target {
+            // its animation would keep putting it back
+            self.object_mut(key).0.animation = None;
+        }
+        let center = (self.size.width / 2, self.size.height / 2);
+        let ray = picking::ray(self.camera.build_view_proj(), center, (self.size.width, self.size.height));
+        self.drag = Some(Drag {
+            target,
+            offset: self.drag_origin(target) - point,
+            plane_point: point,
+            plane_normal: ray.map_or(Vector3::unit_z(), |(_, direction)| -direction),
+        });
+        info!("Grabbed {:?}", target);
+    }
 
-        let mouse_move = self.input_state.get_unhandled_mouse_move();
+    // moves what's held to where the crosshair meets its plane, or a level one while shift is held
+    // so it slides along the floor
+    fn update_drag(&mut self) {
+        let Some(drag) = &self.drag else {
+            return;
+        };
+        let normal = if self.modifiers.shift() { Vector3::unit_y() } else { drag.plane_normal };
+        let center = (self.size.width / 2, self.size.height / 2);
+        let point = picking::ray(self.camera.build_view_proj(), center, (self.size.width, self.size.height))
+            .and_then(|ray| picking::intersect_plane(ray, drag.plane_point, normal));
+        // looking away from the plane leaves it where it was
+        let Some(point) = point else {
+            return;
+        };
+        let target = drag.target;
+        let delta = point + drag.offset - self.drag_origin(target);
+        match target {
+            DragTarget::Object(key) => {
+                let (object, device, uploads) = self.object_mut(key);
+                object.model = Matrix4::from_translation(delta) * object.model;
+                uploads.write(device, object.transforms.buffer(), object.transforms.offset(), &[RawMatrix { mat: object.model.into() }]);
+            }
+            DragTarget::Instance(key, i) => {
+                let (object, device, uploads) = self.object_mut(key);
+                object.instances[i].trans += delta;
+                object.update_instances(device, uploads, i..i + 1);
+            }
+        }
+    }
+
+    // a crate resting on the point, turned to face the camera
+    fn place_crate(&mut self, point: Point3<f32>) {
+        let instance = Instance {
+            trans: point.to_vec() + Vector3::unit_y() * PLACED_SIZE / 2.0,
+            rot: cgmath::Quaternion::from_angle_y(cgmath::Deg(-self.camera.pose().yaw)),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+            tint: graphics::NO_TINT,
+        };
+        let placed = &mut self.placed.0;
+        if placed.add_instance(&self.device, &self.queue, &mut self.pools, &mut self.uploads, instance) {
+            debug!("Grew the placed crates' transforms to {}", placed.num_instances.unwrap_or(0));
+            if let Some(bind_group) = self.create_bind_group(self.placed.0.transforms.binding(), include_bytes!("../res/tex/tex4.jpg"), "texture_placed") {
+                self.placed.1 = bind_group;
+            }
+        }
+        info!("Placed crate {} at {}, {}, {}", self.placed.0.instances.len(), point.x, point.y, point.z);
+        #[cfg(feature = "audio")]
+        self.play_sound(Sound::Whoosh);
+    }
+
+    fn console_key(&mut self, input: &KeyboardInput) {
+        if input.state != ElementState::Pressed {
+            return;
+        }
+        match input.virtual_keycode {
+            Some(VirtualKeyCode::Back) => self.console.backspace(),
+            Some(VirtualKeyCode::Up) => self.console.browse(true),
+            Some(VirtualKeyCode::Down) => self.console.browse(false),
+            Some(VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter) => {
+                if let Some(command) = self.console.submit() {
+                    self.run_command(command);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn run_command(&mut self, command: Command) {
+        info!("Console: {:?}", command);
+        match command {
+            Command::Help => self.console.print(Command::USAGE),
+            Command::Clear => self.console.clear(),
+            Command::Teleport(position) => {
+                self.camera.set_pose(&CameraPose { position: position.into(), ..self.camera.pose() });
+                self.console.print(&format!("Moved to {} {} {}", position.x, position.y, position.z));
+            }
+            Command::Spawn(Spawnable::Cube) => {
+                let center = (self.size.width / 2, self.size.height / 2);
+                let point = self.pick(center).unwrap_or_else(|| self.camera.loc + self.camera.forward() * SPAWN_DISTANCE);
+                self.place_crate(point);
+                self.console.print(&format!("Spawned a cube at {:.1} {:.1} {:.1}", point.x, point.y, point.z));
+            }
+            Command::SetClearColor([r, g, b]) => {
+                self.clear_color = wgpu::Color { r, g, b, a: 1.0 };
+                self.console.print(&format!("Clear color {} {} {}", r, g, b));
+            }
+            Command::SetRenderScale(scale) => {
+                self.set_render_scale(scale);
+                self.console.print(&format!("Render scale {}", self.render_scale));
+            }
+            Command::SetCloudQuality(quality) => {
+                self.sky.set_cloud_quality(quality);
+                self.console.print(&format!("Clouds {}", quality.name()));
+            }
+            Command::SetWeather(weather) => {
+                self.weather.set_weather(weather);
+                self.console.print(&format!("Weather {}", weather.name()));
+            }
+            Command::SetVignette(vignette) => {
+                self.post_effects.vignette = vignette;
+                self.update_post();
+                self.console.print(&format!("Vignette {}", vignette));
+            }
+            Command::SetAberration(aberration) => {
+                self.post_effects.aberration = aberration;
+                self.update_post();
+                self.console.print(&format!("Chromatic aberration {}", aberration));
+            }
+            Command::Timescale(time_scale) => {
+                self.time_scale = time_scale as f64;
+                self.console.print(&format!("Timescale {}", time_scale));
+            }
+            Command::ReloadShaders => match self.reload_shaders() {
+                Ok(()) => self.console.print("Reloaded shader.wgsl"),
+                Err(e) => {
+                    warn!("{}", e);
+                    self.console.print(&e);
+                }
+            },
+        }
+    }
+
+    // builds the main shader's pipelines again from shader.wgsl as it is on disk now. the old ones
+    // are kept when it doesn't compile
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reload_shaders(&mut self) -> Result<(), String> {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src/shader.wgsl");
+        let source = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let shader = graphics::create_templated_shader(&self.device, self.config.format, "shader.wgsl", &source);
+        let pipelines = MainPipelines::new(
+            &self.device,
+            &self.config,
+            &shader,
+            &MainLayouts {
+                scene: &self.bind_group_layout,
+                bones: &self.bones_bind_group_layout,
+                morph: &self.morph_bind_group_layout,
+                reflection: &self.reflection_bind_group_layout,
+                environment: &self.environment_bind_group_layout,
+                emissive: &self.emissive_bind_group_layout,
+                screen: &self.screen_bind_group_layout,
+            },
+        );
+        if let Some(e) = pollster::block_on(self.device.pop_error_scope()) {
+            return Err(format!("Kept the old shader, shader.wgsl has errors: {}", e));
+        }
+
+        self.render_pipeline = pipelines.render_pipeline;
+        self.overdraw_pipeline = pipelines.overdraw_pipeline;
+        self.wireframe_pipeline = pipelines.wireframe_pipeline;
+        self.wireframe_overlay_pipeline = pipelines.wireframe_overlay_pipeline;
+        self.skinned_pipeline = pipelines.skinned_pipeline;
+        self.morph_pipeline = pipelines.morph_pipeline;
+        self.reflective_pipeline = pipelines.reflective_pipeline;
+        self.env_mapped_pipeline = pipelines.env_mapped_pipeline;
+        self.emissive_pipeline = pipelines.emissive_pipeline;
+        self.stenciled_pipeline = pipelines.stenciled_pipeline;
+        self.depth_prepass_pipeline = pipelines.depth_prepass_pipeline;
+        self.prepassed_pipeline = pipelines.prepassed_pipeline;
+        self.screen_pipeline = pipelines.screen_pipeline;
+        Ok(())
+    }
+
+    // the shader is baked into the build, there's no file to read on the web
+    #[cfg(target_arch = "wasm32")]
+    fn reload_shaders(&mut self) -> Result<(), String> {
+        Err("Shaders can't be reloaded on the web".to_string())
+    }
+
+    pub fn is_console_open(&self) -> bool {
+        self.console.is_open()
+    }
+
+    pub fn close_console(&mut self) {
+        self.console.close();
+    }
+
+    pub fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    #[cfg(feature = "audio")]
+    fn play_sound(&self, sound: Sound) {
+        if let Some(audio) = &self.audio {
+            audio.play(sound);
+        }
+    }
+
+    // tints the shown cube the point is on, and puts the one tinted before back
+    fn highlight_cube(&mut self, point: Point3<f32>) {
+        let cubes = &mut self.obj1.0;
+        let shown = if self.selected_obj == 0 { cubes.shown_instances.unwrap_or(0) as usize } else { 0 };
+        let model = cubes.model;
+        // the point is on the surface, so no further from the middle than a corner
+        let near = cubes.grid.as_ref().map_or(Vec::new(), |grid| grid.near(point, 0.0, model));
+        let picked = near
+            .into_iter()
+            .filter(|&i| i < shown)
+            .map(|i| {
+                let instance = &cubes.instances[i];
+                let transform = instance.matrix() * model;
+                let radius = CUBE_PICK_RADIUS * instance.scale.x.max(instance.scale.y).max(instance.scale.z);
+                (i, (transform.transform_point(Point3::origin()) - point).magnitude2(), radius)
+            })
+            .filter(|&(_, distance2, radius)| distance2 <= radius * radius)
+            .map(|(i, distance2, _)| (i, distance2))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i);
+        if picked == self.highlighted_cube {
+            return;
+        }
+        for (i, tint) in [(self.highlighted_cube, graphics::NO_TINT), (picked, HIGHLIGHT_TINT)] {
+            if let Some(i) = i {
+                cubes.instances[i].tint = tint;
+                cubes.update_instances(&self.device, &mut self.uploads, i..i + 1);
+            }
+        }
+        self.highlighted_cube = picked;
+    }
+
+    fn update_flythrough_input(&mut self) {
+        let input = &self.input_state;
+        if input.just_pressed(Action::AddKeyframe) {
+            self.flythrough.add_keyframe(self.camera.pose());
+            info!("Added flythrough keyframe {}", self.flythrough.keyframe_count());
+        }
+        if input.just_pressed(Action::ClearKeyframes) {
+            self.flythrough.clear();
+            info!("Cleared flythrough keyframes");
+        }
+        if input.just_pressed(Action::PlayFlythrough) {
+            if self.flythrough.is_playing() {
+                self.flythrough.stop();
+            } else if self.flythrough.play() {
+                self.bookmark_flight.stop();
+            } else {
+                info!("A flythrough needs at least 2 keyframes");
+            }
+        }
+    }
+
+    // the floor if shown plus the cubes drawn near the camera, used by it on the next frame
+    fn update_colliders(&mut self, cube_model: Matrix4<f32>) {
+        let floor_extent = |count: usize| (count - 1) as f32 * INSTANCE_SPACING;
+        self.colliders.clear();
+        // a replaced floor would otherwise leave an invisible ground to walk on
+        if self.show_floor {
+            self.colliders.push(Aabb::new(
+                (0.0, FLOOR_Y - FLOOR_THICKNESS, 0.0).into(),
+                (floor_extent(INSTANCED_ROWS), FLOOR_Y, floor_extent(INSTANCED_COLS)).into(),
+            ));
+        }
+
+        // the wave moves them away from where the cpu has them
+        if let (0, false, Some(grid)) = (self.selected_obj, self.wave_instances, &self.obj1.0.grid) {
+            let shown = self.obj1.0.shown_instances.unwrap_or(0) as usize;
+            let near = grid.near(self.camera.loc, COLLIDER_RANGE, cube_model);
+            self.colliders.extend(near.into_iter().filter(|&i| i < shown).map(|i| {
+                // same order as the vertex shader, the instance transform applies after the model one
+                let transform = self.obj1.0.instances[i].matrix() * cube_model;
+                Aabb::of_cube(&transform, 0.5)
+            }));
+        }
+    }
+
+    pub fn update(&mut self) {
+        logging::next_frame();
+        self.input_state.poll_gamepad();
+        self.input_state.begin_frame(self.delta_time);
+
+        if self.input_state.just_pressed(Action::ToggleObject) {
+            self.selected_obj = match self.selected_obj {
+                0 => 1,
+                1 => 0,
+                _ => 0,
+            };
+            #[cfg(feature = "audio")]
+            self.play_sound(Sound::Click);
+        }
+
+        if let (
+            Some(shown_instances1),
+            Some(shown_instances2),
+            Some(num_instances1),
+            Some(num_instances2),
+        ) = (
+            &mut self.obj1.0.shown_instances,
+            &mut self.obj2.0.shown_instances,
+            &self.obj1.0.num_instances,
+            &self.obj2.0.num_instances,
+        ) {
+            let input = &self.input_state;
+            let more = input.repeats(Action::MoreInstances, INSTANCE_REPEAT_DELAY, INSTANCE_REPEAT_RATE);
+            let fewer = input.repeats(Action::FewerInstances, INSTANCE_REPEAT_DELAY, INSTANCE_REPEAT_RATE);
+            let selected = match self.selected_obj {
+                0 => Some((shown_instances1, *num_instances1)),
+                1 => Some((shown_instances2, *num_instances2)),
+                _ => None,
+            };
+            if let Some((shown, num)) = selected {
+                #[cfg(feature = "audio")]
+                let before = *shown;
+                *shown = (*shown + more).min(num).saturating_sub(fewer);
+                // once per press, not for every instance added while it's held
+                #[cfg(feature = "audio")]
+                if *shown > before && input.just_pressed(Action::MoreInstances) {
+                    if let Some(audio) = &self.audio {
+                        audio.play(Sound::Whoosh);
+                    }
+                }
+                if input.just_released(Action::MoreInstances) || input.just_released(Action::FewerInstances) {
+                    debug!("Showing {} of {} instances", shown, num);
+                }
+            }
+        }
+
+        let mouse_move = self.input_state.get_unhandled_mouse_move();
 
         let (offset_x, offset_y) = mouse_move;
         let c = &mut self.clear_color;
@@ -270,117 +2130,1306 @@ impl App {
         if c.g < 0.0 { c.g = 0.0; }
         if c.b < 0.0 { c.b = 0.0; }
 
-        self.camera.update_pos(self.delta_time as f32, &self.input_state);
-        self.camera.update_look(
-            (mouse_move.0 as f32, mouse_move.1 as f32),
-            self.delta_time as f32,
-        );
+        // advanced by delta_time rather than read from the wall clock so recordings stay smooth
+        self.elapsed += self.delta_time;
+        let now = self.elapsed as f32;
+
+        self.update_flythrough_input();
+        // a playing flythrough takes over the camera until it ends or is stopped
+        let dt = self.delta_time as f32;
+        if let Some(pose) = self.flythrough.advance(dt).or_else(|| self.bookmark_flight.advance(dt)) {
+            self.camera.set_pose(&pose);
+        } else {
+            self.camera.update_pos(self.delta_time as f32, &self.input_state, &self.colliders);
+            #[cfg(feature = "audio")]
+            if self.camera.stepped() {
+                self.play_sound(Sound::Footstep);
+            }
+            let stick_look = self.input_state.stick_look();
+            self.camera.update_look(
+                (mouse_move.0 as f32 + stick_look.0 * dt, mouse_move.1 as f32 + stick_look.1 * dt),
+                self.input_state.roll_axis(),
+                self.delta_time as f32,
+            );
+        }
+        // the scripts go after the camera's own movement, so they can override it
+        #[cfg(feature = "scripting")]
+        let mut script_output = self.scripts.as_mut().map(|scripts| scripts.run(self.camera.pose(), now, dt));
+        #[cfg(feature = "scripting")]
+        if let Some(pose) = script_output.as_mut().and_then(|output| output.camera.take()) {
+            self.camera.set_pose(&pose);
+        }
+        self.camera.update_zoom(self.delta_time as f32);
         self.camera_uniform.update_view_proj(&self.camera);
-        self.queue.write_buffer(
-            &self.camera_uniform_buffer,
-            0,
-            bytemuck::cast_slice(&[self.camera_uniform]),
+        self.camera_offsets.clear();
+        self.reflection_offsets.clear();
+        self.portal_offsets.clear();
+        let view_proj = Matrix4::from(self.camera_uniform.mat);
+        let view = self.camera.build_view();
+        let seam_offsets = self.scene_camera().seam_offsets();
+        let mut write_camera = |slot: u32, view_proj: Matrix4<f32>, clip_plane| {
+            let dynamic_offset = slot * self.camera_uniform_stride;
+            self.uploads.write(
+                &self.device,
+                &self.camera_uniform_buffer,
+                dynamic_offset as wgpu::BufferAddress,
+                &[CameraUniform { view_proj: view_proj.into(), clip_plane }],
+            );
+            dynamic_offset
+        };
+        let mut main_views = Vec::new();
+        for (i, offset) in seam_offsets.into_iter().enumerate() {
+            let seam = Matrix4::from_translation(offset);
+            let camera_offset = write_camera(i as u32, view_proj * seam, CameraUniform::NO_CLIP);
+            self.camera_offsets.push(camera_offset);
+            main_views.push((camera_offset, Frustum::new(view_proj * seam)));
+            if let Some(reflection) = &self.reflection {
+                let mirrored = view_proj * reflection.mirror() * seam;
+                let slot = MAX_CAMERA_TILES + i as u32;
+                self.reflection_offsets.push(write_camera(slot, mirrored, reflection.clip_plane()));
+            }
+        }
+        if let Some(portal) = &self.portal {
+            for level in 1..=portal.levels() {
+                let slot = MAX_CAMERA_TILES * 2 + level - 1;
+                self.portal_offsets.push(write_camera(slot, view_proj * portal.through(level), portal.clip_plane()));
+            }
+            let c = self.clear_color;
+            portal.update(&self.queue, view_proj, [c.r as f32, c.g as f32, c.b as f32, 1.0]);
+        }
+        if let Some(map) = &self.map {
+            write_camera(MAP_CAMERA_SLOT, map.view_proj(self.camera.loc), CameraUniform::NO_CLIP);
+        }
+        write_camera(MINIMAP_CAMERA_SLOT, self.minimap.view_proj(), CameraUniform::NO_CLIP);
+        if let Some((tv, _)) = &self.tv {
+            write_camera(TV_CAMERA_SLOT, tv.view_proj(), CameraUniform::NO_CLIP);
+        }
+
+        let mut write_buffer = |dest: &wgpu::Buffer, offset, src: Matrix4<f32>| self.uploads.write(
+            &self.device,
+            dest,
+            offset,
+            &[super::graphics::RawMatrix {
+                mat: src.into(),
+            }],
         );
 
-        let now = std::time::Instant::now()
-            .duration_since(self.intial_instant)
-            .as_secs_f32();
-        let sin = now.sin();
-        let cos = now.cos();
+        let objects = [&mut self.obj1, &mut self.obj2, &mut self.floor, &mut self.pythagoras_sphere, &mut self.placed];
+        for (object, _) in objects.into_iter().chain(&mut self.primitives) {
+            let model = object.animation.as_ref().map(|clip| clip.sample(now));
+            #[cfg(feature = "scripting")]
+            let model = script_output
+                .as_mut()
+                .and_then(|output| output.transforms.remove(object.name))
+                .map(|transform| transform.matrix())
+                .or(model);
+            if let Some(model) = model {
+                object.model = model;
+                if !(self.push_constants && object.num_instances.is_none()) {
+                    write_buffer(object.transforms.buffer(), object.transforms.offset(), object.model);
+                }
+            }
+        }
+        #[cfg(feature = "scripting")]
+        if let (Some(scripts), Some(output)) = (&mut self.scripts, &script_output) {
+            for name in output.transforms.keys() {
+                scripts.warn_unknown(name);
+            }
+        }
+        #[cfg(feature = "physics")]
+        if let Some((physics, bodies)) = &mut self.physics {
+            physics.update(self.delta_time as f32);
+            for &(i, body) in bodies.iter() {
+                let object = &mut self.primitives[i].0;
+                object.model = physics.model(body);
+                if !self.push_constants {
+                    write_buffer(object.transforms.buffer(), object.transforms.offset(), object.model);
+                }
+            }
+        }
+        let cube_model = self.obj1.0.model;
+        if let Some((character, _)) = &self.character {
+            character.update(&self.queue, now);
+            write_buffer(&character.transforms_buf, 0, character_model(now));
+        }
+        let objects = [&mut self.obj1, &mut self.obj2, &mut self.floor, &mut self.pythagoras_sphere, &mut self.placed];
+        for (object, _) in objects.into_iter().chain(&mut self.primitives) {
+            if let Some(emission) = &mut object.emission {
+                emission.update(&self.queue, now);
+            }
+        }
+        if let Some(balls) = &mut self.balls {
+            let sphere = &mut self.pythagoras_sphere.0;
+            balls.update(&mut sphere.instances, self.delta_time as f32);
+            let count = sphere.instances.len();
+            sphere.update_instances(&self.device, &mut self.uploads, 0..count);
+        }
+        self.update_drag();
+        if self.wave_instances {
+            let cubes = &self.obj1.0;
+            let wave = self
+                .instance_wave
+                .get_or_insert_with(|| InstanceWave::new(&self.device, &cubes.transforms, &cubes.instances));
+            wave.update(&self.queue, now, cubes.shown_instances.unwrap_or(0));
+        } else if self.spin_instances {
+            let spin = cgmath::Quaternion::from_angle_y(cgmath::Rad(INSTANCE_SPIN_SPEED * self.delta_time as f32));
+            let cubes = &mut self.obj1.0;
+            // the hidden ones are left as they are until they're shown
+            let shown = cubes.shown_instances.unwrap_or(0) as usize;
+            for instance in &mut cubes.instances[..shown] {
+                instance.rot = spin * instance.rot;
+            }
+            cubes.update_instances(&self.device, &mut self.uploads, 0..shown);
+        }
+        // the wave moves the cubes on the gpu, away from where the grid has them
+        let cubes_culled = !self.wave_instances;
+        let objects = [
+            (&mut self.obj1, cubes_culled),
+            (&mut self.obj2, true),
+            (&mut self.pythagoras_sphere, true),
+            (&mut self.placed, true),
+        ];
+        for ((object, _), culled) in objects {
+            object.visible.clear();
+            if let (Some(grid), true) = (&object.grid, culled) {
+                let shown = object.shown_instances.unwrap_or(0) as usize;
+                for (camera_offset, frustum) in &main_views {
+                    object.visible.push((*camera_offset, grid.visible(frustum, object.model, shown)));
+                }
+            }
+        }
+        if let Some((blob, _)) = &mut self.morph_blob {
+            // squash, snout and bumps, each fading in and out at its own pace
+            let weights = [(now * 1.5).sin().max(0.0), (now * 0.7).sin() * 0.5 + 0.5, (now * 0.4).cos() * 0.5 + 0.5];
+            blob.set_weights(&self.queue, &weights);
+        }
+
+        let sun = if self.day_length > 0.0 {
+            self.time_of_day = (self.time_of_day + self.delta_time as f32 / self.day_length).fract();
+            let sun = Sun::at(self.time_of_day);
+            self.sky.update(&self.queue, view_proj, &sun, now);
+            sun
+        } else {
+            Sun::unlit()
+        };
+        let aspect = self.config.width as f32 / self.config.height as f32;
+        self.god_rays.update(&self.queue, view_proj, aspect, &sun);
+        self.flare.update(&self.queue, view_proj, aspect, &sun);
+        self.weather.update(dt);
+        if self.weather.rain() > 0.0 {
+            let rain = self.rain.get_or_insert_with(|| Rain::new(&self.device, self.config.format));
+            rain.update(&self.queue, dt, view_proj, self.camera.loc, &sun, self.weather.rain());
+        } else {
+            self.rain = None;
+        }
+        #[cfg(feature = "audio")]
+        if let Some(audio) = &mut self.audio {
+            let music = &self.scene.music;
+            let track = match &music.night {
+                Some(night) if sun.is_down() => Some(night),
+                _ => music.track.as_ref(),
+            };
+            audio.set_music(track.map(std::path::PathBuf::as_path));
+            audio.update(self.delta_time as f32);
+            // the view's first row is the camera's right in world space
+            audio.listen(self.camera.loc, Vector3::new(view.x.x, view.y.x, view.z.x));
+        }
+        #[cfg(feature = "audio")]
+        if let Some(audio) = &self.audio {
+            for (key, hum) in &self.hums {
+                let object = &self.object(*key).0;
+                audio.place(hum, object.model.transform_point(object.center));
+            }
+            let rain = self.weather.rain();
+            if rain <= 0.0 {
+                self.rain_sound = None;
+            } else {
+                if self.rain_sound.is_none() {
+                    self.rain_sound = audio.ambient(Sound::Rain);
+                }
+                if let Some(sound) = &self.rain_sound {
+                    audio.set_level(sound, rain);
+                }
+            }
+        }
+        if self.light_demo {
+            let center = Point3::new(
+                (SPHERE_INSTANCED_ROWS - 1) as f32 * SPHERE_INSTANCE_SPACING / 2.0,
+                FLOOR_Y + 5.0,
+                (SPHERE_INSTANCED_COLS - 1) as f32 * SPHERE_INSTANCE_SPACING / 2.0,
+            );
+            self.lights = lights::orbiting_demo(center, 60.0, now);
+        }
+        // the scripts' lights are only kept for the frame, on top of the demo's
+        #[cfg(feature = "scripting")]
+        if let Some(output) = &mut script_output {
+            if !self.light_demo {
+                self.lights.clear();
+            }
+            self.lights.append(&mut output.lights);
+        }
+        let light_count = self.light_buffer.upload(&self.queue, &self.lights);
+        let light_uniform = sun.light_uniform(self.camera.loc, light_count, self.view_mode, [self.weather.wetness(), FLOOR_Y]);
+        self.uploads.write(&self.device, &self.light_uniform_buffer, 0, &[light_uniform]);
+        if let Some(grass) = &mut self.grass {
+            grass.update(&self.queue, view_proj, now, light_uniform);
+        }
+        if self.show_light_gizmos {
+            for light in &self.lights {
+                light.draw_gizmo(&mut self.debug_draw);
+                let [r, g, b] = light.color;
+                self.billboards.draw_sprite(light.position, LIGHT_ICON_SIZE, Sprite::Light, [r, g, b, 1.0]);
+            }
+        }
+        if self.show_grid {
+            self.grid.update(&self.queue, view_proj, self.camera.loc);
+        }
+
+        self.update_colliders(cube_model);
+        if let Some(frozen_view_proj) = self.debug_shapes {
+            for collider in &self.colliders {
+                self.debug_draw.draw_aabb(collider, [1.0, 0.5, 0.0]);
+            }
+            self.debug_draw.draw_frustum(frozen_view_proj, [1.0, 1.0, 0.0]);
+            // emitters are invisible until they've spawned something
+            for emitter in &self.particles.emitters {
+                self.billboards.draw_sprite(emitter.position, EMITTER_ICON_SIZE, Sprite::Dot, emitter.start_color);
+            }
+        }
+        if let Some(scene_camera) = &self.scene_camera {
+            self.debug_draw.draw_frustum(scene_camera.build_view_proj(), [1.0, 0.0, 1.0]);
+            self.debug_draw.draw_sphere(scene_camera.loc, 0.5, [1.0, 0.0, 1.0]);
+        }
+        let scene_loc = self.scene_camera().loc;
+        if let Some((world, _)) = &mut self.voxels {
+            world.update(&self.device, scene_loc);
+        }
+        if let Some((metaballs, _)) = &mut self.metaballs {
+            metaballs.update(&self.device, &self.queue, now);
+        }
+        self.particles.update(dt);
+        if let Some(gpu_particles) = &mut self.gpu_particles {
+            gpu_particles.update(&self.queue, dt, now, view_proj, view);
+        }
+        self.particles.upload(&self.device, &self.queue, view_proj, self.camera.loc, view);
+
+        if self.input_state.just_pressed(Action::ClearDecals) {
+            self.decals.clear();
+        }
+        self.decals.upload(&self.queue, view_proj);
 
-        let obj1_model = Matrix4::from_angle_x(cgmath::Rad { 0: now })
-            * Matrix4::from_angle_y(cgmath::Rad { 0: now })
-            * Matrix4::from_angle_z(cgmath::Rad { 0: now });
+        if self.input_state.just_pressed(Action::ToggleProjection) {
+            self.camera.toggle_projection();
+            info!("Switched to {:?} projection", self.camera.projection());
+        }
 
-        let obj2_model = Matrix4::from_translation(Vector3::new(sin * 10.0, sin, cos * 10.0))
-            * Matrix4::from_scale(sin.abs() + 1.22);
+        if self.input_state.just_pressed(Action::ToggleLookMode) {
+            self.camera.toggle_look_mode();
+            info!("Switched to {:?} look mode", self.camera.look_mode());
+        }
 
-        let pythagoras_sphere_model = Matrix4::from_translation(Vector3::new(0.0, FLOOR_Y + 5.0, 0.0))
-            * Matrix4::from_axis_angle(Vector3::new(1.0, 1.0, 1.0).normalize(), cgmath::Rad { 0: now / 10.0 });
+        if self.input_state.just_pressed(Action::ToggleWalk) {
+            self.camera.toggle_movement_mode();
+            info!("Switched to {:?} movement", self.camera.movement_mode());
+        }
 
-        let write_buffer = |dest, src: Matrix4<f32>| self.queue.write_buffer(
-            dest,
-            0,
-            bytemuck::cast_slice(&[super::graphics::RawMatrix {
-                mat: src.into(),
-            }]),
-        );
+        self.update_terrain_input();
 
-        write_buffer(&self.obj1.0.model_buf, obj1_model);
-        write_buffer(&self.obj2.0.model_buf, obj2_model);
-        write_buffer(&self.pythagoras_sphere.0.model_buf, pythagoras_sphere_model);
+        if self.input_state.just_pressed(Action::ToggleLightGizmos) {
+            self.show_light_gizmos = !self.show_light_gizmos;
+            info!("Light gizmos {}", if self.show_light_gizmos { "shown" } else { "hidden" });
+        }
 
-        if self.input_state.f_pressed {
-            debug!(
-                "Player location: {}, {}, {}",
-                self.camera.loc.x, self.camera.loc.y, self.camera.loc.z
-            );
+        if self.input_state.just_pressed(Action::ToggleGrid) {
+            self.show_grid = !self.show_grid;
+            info!("Grid {}", if self.show_grid { "shown" } else { "hidden" });
+        }
+
+        if self.input_state.just_pressed(Action::ToggleDebugShapes) {
+            self.debug_shapes = match self.debug_shapes {
+                Some(_) => None,
+                None => Some(self.camera.build_view_proj()),
+            };
+            info!("Debug shapes {}", if self.debug_shapes.is_some() { "shown" } else { "hidden" });
+        }
+
+        if self.input_state.just_pressed(Action::ToggleDebugCamera) {
+            // coming back puts the view where the scene camera was left
+            match self.scene_camera.take() {
+                Some(scene_camera) => self.camera = scene_camera,
+                None => self.scene_camera = Some(self.camera.clone()),
+            }
+            info!("Debug camera {}", if self.scene_camera.is_some() { "detached" } else { "attached" });
+        }
+
+        if self.input_state.just_pressed(Action::CycleViewMode) {
+            self.view_mode = self.view_mode.next();
+            info!("Switched to {:?} view", self.view_mode);
+        }
+
+        if self.input_state.just_pressed(Action::CycleWireframe) {
+            if self.wireframe_pipeline.is_some() {
+                self.wireframe_mode = self.wireframe_mode.next();
+                info!("Wireframe {:?}", self.wireframe_mode);
+            } else {
+                warn!("Wireframe isn't available, the adapter doesn't support POLYGON_MODE_LINE");
+            }
+        }
+
+        if self.input_state.just_pressed(Action::ToggleHud) {
+            self.show_hud = !self.show_hud;
+        }
+        if self.show_hud {
+            self.draw_hud();
+        }
+
+        if self.input_state.just_pressed(Action::ToggleTelemetry) {
+            self.show_telemetry = !self.show_telemetry;
+        }
+        if self.show_telemetry {
+            self.draw_telemetry();
+        }
+
+        if self.input_state.just_pressed(Action::ToggleMinimap) {
+            self.show_minimap = !self.show_minimap;
+        }
+        if self.show_minimap {
+            self.minimap.place(&self.queue, self.config.width, self.config.height);
+            // where the camera is on it
+            if let Some((x, y)) = self.minimap.to_screen(self.camera.loc) {
+                let half = MINIMAP_MARKER_SIZE / 2.0;
+                self.sprites.draw_sprite(Icon::Crosshair, x - half, y - half, MINIMAP_MARKER_SIZE, MINIMAP_MARKER_SIZE, [1.0, 0.2, 0.2, 1.0]);
+            }
+        }
+
+        if self.input_state.just_pressed(Action::ToggleMarkers) {
+            self.show_markers = !self.show_markers;
+            info!("Sphere markers {}", if self.show_markers { "shown" } else { "hidden" });
+        }
+        if self.show_markers {
+            self.draw_sphere_markers(view_proj);
+        }
+
+        if self.input_state.just_pressed(Action::ToggleHelp) {
+            self.show_help = !self.show_help;
+        }
+        if self.show_help {
+            self.draw_help();
+        }
+        self.draw_focus();
+        if self.console.is_open() {
+            self.draw_console();
+        }
+        self.draw_cursor();
+
+        if self.input_state.just_pressed(Action::CycleColorGrade) {
+            self.color_grading.cycle();
+            self.update_post();
+            info!("Color grade {}", self.color_grading.name());
+        }
+
+        if self.input_state.just_pressed(Action::CycleLogLevel) {
+            info!("Log level {}", logging::cycle_level());
+        }
+
+        if self.input_state.just_pressed(Action::ToggleDepthPrepass) {
+            self.depth_prepass = !self.depth_prepass;
+            info!("Depth prepass {}", if self.depth_prepass { "on" } else { "off" });
+        }
+
+        if self.input_state.just_pressed(Action::SpinInstances) {
+            self.spin_instances = !self.spin_instances;
+            info!("Instance spin {}", if self.spin_instances { "on" } else { "off" });
+        }
+
+        if self.input_state.just_pressed(Action::ToggleInstanceWave) {
+            self.wave_instances = !self.wave_instances;
+            if !self.wave_instances {
+                // back to where the cpu has them
+                let cubes = &mut self.obj1.0;
+                cubes.update_instances(&self.device, &mut self.uploads, 0..cubes.instances.len());
+            }
+            info!("Instance wave {}", if self.wave_instances { "on" } else { "off" });
         }
+
+        self.debug_draw.flush(&self.device, &self.queue, view_proj);
+        self.text.flush(&self.device, &self.queue, self.config.width, self.config.height);
+        self.billboards.flush(&self.device, &self.queue, view, view_proj);
+        self.sprites.flush(&self.device, &self.queue, self.config.width, self.config.height);
+        self.input_state.end_frame();
     }
 
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let output = self.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("frame_encoder"),
+        self.build_render_queue();
+        let output = match &self.target {
+            RenderTarget::Surface(surface) => Some(surface.get_current_texture()?),
+            RenderTarget::Offscreen(_) => None,
+        };
+        #[cfg(feature = "clipboard")]
+        let mut copy = std::mem::take(&mut self.copy_requested)
+            .then(|| FrameCapture::new(&self.device, &self.config, CaptureOutput::Clipboard).ok())
+            .flatten();
+        #[cfg(not(feature = "clipboard"))]
+        let mut copy: Option<FrameCapture> = None;
+        let capturing = self.capture.is_some() || copy.is_some();
+        if capturing && !self.config.usage.contains(wgpu::TextureUsages::COPY_SRC) && self.capture_target.is_none() {
+            self.capture_target = Some(CaptureTarget::new(&self.device, &self.config));
+        }
+        let capture_target = self.capture_target.as_ref().filter(|_| capturing);
+
+        let surface_texture = match (&output, &self.target) {
+            (Some(output), _) => &output.texture,
+            (None, RenderTarget::Offscreen(texture)) => texture,
+            (None, RenderTarget::Surface(_)) => unreachable!(),
+        };
+        let surface_view = surface_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let texture = capture_target.map_or(surface_texture, CaptureTarget::texture);
+        let view = capture_target.map_or(&surface_view, CaptureTarget::view);
+
+        // the compute work ahead of the passes. stepping the particles changes them, so it can't
+        // share the app with the passes
+        let wave = self.instance_wave.as_ref().filter(|_| self.wave_instances);
+        let simulation = (self.gpu_particles.is_some() || self.rain.is_some() || wave.is_some()).then(|| {
+            let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("simulation_encoder"),
             });
+            if let Some(wave) = wave {
+                wave.animate(&mut encoder);
+            }
+            if let Some(gpu_particles) = &mut self.gpu_particles {
+                gpu_particles.simulate(&mut encoder);
+            }
+            if let Some(rain) = &self.rain {
+                rain.simulate(&mut encoder);
+            }
+            encoder.finish()
+        });
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("main_pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(self.clear_color),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_texture.0,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: true,
+        // the debug views that don't fill the scene normally draw it all in the main pass
+        let prepass = self.depth_prepass
+            && self.view_mode != ViewMode::Overdraw
+            && !(self.wireframe_mode == WireframeMode::Only && self.wireframe_pipeline.is_some());
+        let frame = FrameTargets {
+            view,
+            scene_view: self.post.as_ref().map_or(view, PostProcess::scene_view),
+            overlay_msaa: self.post.as_ref().map_or(self.msaa_target.as_ref(), PostProcess::msaa_target),
+            texture,
+            surface_view: capture_target.map(|_| &surface_view),
+            prepass,
+            copy: copy.as_ref(),
+        };
+        let passes = self.frame_passes(&frame);
+        let command_buffers = graphics::encode_passes(&self.device, &passes, |&pass, encoder| {
+            self.encode_pass(pass, encoder, &frame)
+        });
+
+        // the uniform copies go first so the passes see this frame's values, the passes then run
+        // in the order they're listed
+        self.queue.submit(self.uploads.finish().into_iter().chain(simulation).chain(command_buffers));
+        self.uploads.recall();
+        // a frame that can't be written stops the recording rather than failing every frame after
+        if let Some(Err(e)) = self.capture.as_mut().map(|capture| capture.save_frame(&self.device)) {
+            warn!("Stopped recording: {}", e);
+            self.capture = None;
+        }
+        if let Some(Err(e)) = copy.as_mut().map(|copy| copy.save_frame(&self.device)) {
+            warn!("{}", e);
+        }
+        if let Some(output) = output {
+            output.present();
+        }
+        Ok(())
+    }
+
+    // the passes this frame needs, in the order they have to run
+    fn frame_passes(&self, frame: &FrameTargets) -> Vec<FramePass> {
+        let lit = self.view_mode == ViewMode::Lit;
+        [
+            (FramePass::Tv, self.tv.is_some() && self.filled_and_lit()),
+            (FramePass::Reflection, self.mirrored_floor().is_some()),
+            (FramePass::DepthPrepass, frame.prepass),
+            (FramePass::Main, true),
+            (FramePass::Decals, !self.decals.is_empty() && lit),
+            (FramePass::Particles, !self.particles.is_empty() && lit),
+            (FramePass::GodRays, self.god_rays.is_visible() && lit),
+            (FramePass::Flare, self.flare.is_visible() && lit),
+            (FramePass::Post, self.post.is_some()),
+            (FramePass::Minimap, self.show_minimap),
+            (
+                FramePass::Overlay,
+                self.show_hud
+                    || self.show_telemetry
+                    || self.show_markers
+                    || self.show_help
+                    || self.show_minimap
+                    || self.focused.is_some()
+                    || self.console.is_open(),
+            ),
+            (FramePass::Capture, self.capture.is_some() || frame.copy.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(pass, needed)| needed.then_some(pass))
+        .collect()
+    }
+
+    // records one pass, called from several threads at once for the different passes
+    fn encode_pass(&self, pass: FramePass, encoder: &mut wgpu::CommandEncoder, frame: &FrameTargets) {
+        let mirrored_floor = self.mirrored_floor();
+        let plain_floor = self.show_floor && mirrored_floor.is_none();
+        // materials only show when the scene is lit, otherwise everything is drawn alike
+        let materials = self.filled_and_lit();
+        match pass {
+            // the tv's picture has to be finished before any pass that shows it
+            FramePass::Tv => {
+                let Some((tv, _)) = &self.tv else {
+                    return;
+                };
+                let mut render_pass = tv.begin_pass(encoder, self.output_clear_color());
+                render_pass.set_pipeline(&self.render_pipeline);
+                let camera_offset = TV_CAMERA_SLOT * self.camera_uniform_stride;
+                self.draw_scene(&mut render_pass, &[camera_offset], self.show_floor, false);
+            }
+            // the sky isn't mirrored, the reflection shows the clear color where it would be
+            FramePass::Reflection => {
+                let Some(reflection) = self.mirrored_floor() else {
+                    return;
+                };
+                let mut render_pass = reflection.begin_pass(encoder, self.output_clear_color());
+                render_pass.set_pipeline(&self.render_pipeline);
+                self.draw_scene(&mut render_pass, &self.reflection_offsets, false, true);
+                self.draw_materials(&mut render_pass, &self.reflection_offsets, false);
+            }
+            // the depth of the objects the main pipeline draws, so the dense instance grids only get
+            // shaded once per pixel however many of them overlap
+            FramePass::DepthPrepass => {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("depth_prepass"),
+                    color_attachments: &[],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &self.depth_texture.0,
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(1.0),
+                            store: true,
+                        }),
+                        stencil_ops: None,
                     }),
-                    stencil_ops: None,
-                }),
+                });
+                render_pass.set_pipeline(&self.depth_prepass_pipeline);
+                self.draw_scene(&mut render_pass, &self.camera_offsets, plain_floor, materials);
+            }
+            FramePass::Main => {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("main_pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: self.msaa_target.as_ref().unwrap_or(frame.scene_view),
+                        resolve_target: self.msaa_target.as_ref().map(|_| frame.scene_view),
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(self.output_clear_color()),
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &self.depth_texture.0,
+                        depth_ops: Some(wgpu::Operations {
+                            load: if frame.prepass { wgpu::LoadOp::Load } else { wgpu::LoadOp::Clear(1.0) },
+                            store: true,
+                        }),
+                        stencil_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(0),
+                            store: false,
+                        }),
+                    }),
+                });
+
+                if self.day_length > 0.0 && self.view_mode == ViewMode::Lit {
+                    self.sky.draw(&mut render_pass);
+                }
+                let fill_pipeline = match (self.view_mode, self.wireframe_mode, &self.wireframe_pipeline) {
+                    (ViewMode::Overdraw, _, _) => &self.overdraw_pipeline,
+                    (_, WireframeMode::Only, Some(wireframe)) => wireframe,
+                    _ if frame.prepass => &self.prepassed_pipeline,
+                    _ => &self.render_pipeline,
+                };
+                let rp = &mut render_pass;
+                rp.set_pipeline(fill_pipeline);
+                self.draw_scene(rp, &self.camera_offsets, plain_floor, materials);
+                if materials {
+                    self.draw_materials(rp, &self.camera_offsets, plain_floor);
+                }
+                if let Some(reflection) = mirrored_floor {
+                    rp.set_pipeline(&self.reflective_pipeline);
+                    rp.set_bind_group(1, reflection.bind_group(), &[]);
+                    for &camera_offset in &self.camera_offsets {
+                        self.render_obj(rp, &self.floor, camera_offset);
+                    }
+                }
+                if let (WireframeMode::Overlay, Some(overlay)) = (self.wireframe_mode, &self.wireframe_overlay_pipeline) {
+                    rp.set_pipeline(overlay);
+                    self.draw_scene(rp, &self.camera_offsets, self.show_floor, false);
+                }
+                if let Some((character, bind_group)) = &self.character {
+                    rp.set_pipeline(&self.skinned_pipeline);
+                    for &camera_offset in &self.camera_offsets {
+                        rp.set_bind_group(0, bind_group, &[camera_offset]);
+                        character.draw(rp);
+                    }
+                }
+                if let Some((blob, bind_group)) = &self.morph_blob {
+                    rp.set_pipeline(&self.morph_pipeline);
+                    for &camera_offset in &self.camera_offsets {
+                        rp.set_bind_group(0, bind_group, &[camera_offset]);
+                        blob.draw(rp);
+                    }
+                }
+                if let Some(grass) = &self.grass {
+                    if self.view_mode == ViewMode::Lit {
+                        grass.draw(rp);
+                    }
+                }
+                if let Some(portal) = self.portal.as_ref().filter(|_| materials) {
+                    self.draw_portal(rp, portal);
+                }
+                if let Some(gpu_particles) = &self.gpu_particles {
+                    if self.view_mode == ViewMode::Lit {
+                        gpu_particles.draw(rp);
+                    }
+                }
+                if let Some(rain) = self.rain.as_ref().filter(|_| self.view_mode == ViewMode::Lit) {
+                    rain.draw(rp);
+                }
+                self.billboards.draw(rp);
+                self.debug_draw.draw(rp);
+                if self.show_grid {
+                    self.grid.draw(rp);
+                }
+            }
+            // like the particles, reads the depth buffer instead of attaching it
+            FramePass::Decals => {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("decals_pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: self.msaa_target.as_ref().unwrap_or(frame.scene_view),
+                        resolve_target: self.msaa_target.as_ref().map(|_| frame.scene_view),
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+                self.decals.draw(&mut render_pass);
+            }
+            // reads the depth buffer the main pass just wrote, so it can't be attached in this pass
+            FramePass::Particles => {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("particles_pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: self.msaa_target.as_ref().unwrap_or(frame.scene_view),
+                        resolve_target: self.msaa_target.as_ref().map(|_| frame.scene_view),
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+                self.particles.draw(&mut render_pass);
+            }
+            FramePass::GodRays => {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("god_rays_pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: self.msaa_target.as_ref().unwrap_or(frame.scene_view),
+                        resolve_target: self.msaa_target.as_ref().map(|_| frame.scene_view),
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+                self.god_rays.draw(&mut render_pass);
+            }
+            FramePass::Flare => {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("flare_pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: self.msaa_target.as_ref().unwrap_or(frame.scene_view),
+                        resolve_target: self.msaa_target.as_ref().map(|_| frame.scene_view),
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+                self.flare.draw(&mut render_pass);
+            }
+            FramePass::Post => {
+                if let Some(post) = &self.post {
+                    post.draw(encoder, frame.view, self.color_grading.current());
+                }
+            }
+            // drawn like the main pass, from above
+            FramePass::Minimap => {
+                let mut render_pass = self.minimap.begin_pass(encoder, self.output_clear_color());
+                render_pass.set_pipeline(&self.render_pipeline);
+                let camera_offset = MINIMAP_CAMERA_SLOT * self.camera_uniform_stride;
+                self.draw_scene(&mut render_pass, &[camera_offset], self.show_floor, false);
+            }
+            // screen space things on top of everything, after the particles
+            FramePass::Overlay => {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("overlay_pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: frame.overlay_msaa.unwrap_or(frame.view),
+                        resolve_target: frame.overlay_msaa.map(|_| frame.view),
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+                if self.show_minimap {
+                    self.minimap.draw(&mut render_pass);
+                }
+                self.sprites.draw(&mut render_pass);
+                self.text.draw(&mut render_pass);
+            }
+            FramePass::Capture => {
+                for capture in self.capture.iter().chain(frame.copy) {
+                    capture.copy_frame(encoder, frame.texture);
+                }
+                if let Some((target, surface_view)) = self.capture_target.as_ref().zip(frame.surface_view) {
+                    target.present(encoder, surface_view);
+                }
+            }
+        }
+    }
+
+    // clear_color is linear, same as the shader output
+    fn output_clear_color(&self) -> wgpu::Color {
+        // the debug views need a neutral background, and overdraw has to start from zero
+        if self.view_mode != ViewMode::Lit {
+            return wgpu::Color::BLACK;
+        }
+        let c = self.clear_color;
+        if graphics::needs_manual_gamma(self.config.format) {
+            let gamma = |x: f64| x.powf(1.0 / 2.2);
+            wgpu::Color { r: gamma(c.r), g: gamma(c.g), b: gamma(c.b), a: c.a }
+        } else {
+            c
+        }
+    }
+
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = Some(focused);
+    }
+
+    pub fn set_fps(&mut self, fps: u32) {
+        self.fps = Some(fps);
+    }
+
+    // fps, where the camera is and which object the instance keys act on
+    fn draw_hud(&mut self) {
+        let fps = self.fps.map_or_else(|| "-".to_string(), |fps| fps.to_string());
+        let loc = self.camera.loc;
+        let selected = match self.selected_obj {
+            0 => Some(("cubes", &self.obj1.0)),
+            1 => Some(("pyramids", &self.obj2.0)),
+            _ => None,
+        };
+        let selected = selected.map_or_else(
+            || "none".to_string(),
+            |(name, obj)| format!("{} {}/{}", name, obj.shown_instances.unwrap_or(1), obj.num_instances.unwrap_or(1)),
+        );
+        let mut hud = format!(
+            "FPS {}\nXYZ {:.1} {:.1} {:.1}\nObject: {}",
+            fps, loc.x, loc.y, loc.z, selected
+        );
+        if self.scene_camera.is_some() {
+            hud.push_str("\nDebug camera");
+        }
+        self.text.draw_text(&hud, HUD_MARGIN, HUD_MARGIN, HUD_SCALE, [1.0, 1.0, 1.0, 1.0]);
+
+        // the debug toggles as a row of icons under the text, dimmed when off
+        let (_, hud_height) = TextRenderer::measure(&hud, HUD_SCALE);
+        let toggles = [(Icon::Light, self.show_light_gizmos), (Icon::Grid, self.show_grid)];
+        for (i, (icon, on)) in toggles.into_iter().enumerate() {
+            let x = HUD_MARGIN + i as f32 * (HUD_ICON_SIZE + HUD_MARGIN);
+            let alpha = if on { 1.0 } else { 0.3 };
+            self.sprites.draw_sprite(icon, x, HUD_MARGIN * 2.0 + hud_height, HUD_ICON_SIZE, HUD_ICON_SIZE, [1.0, 1.0, 1.0, alpha]);
+        }
+
+        // the render modes go in the top right corner
+        let modes = format!(
+            "{:?} view\nWireframe {:?}\nPrepass {}\nLog {}",
+            self.view_mode,
+            self.wireframe_mode,
+            if self.depth_prepass { "on" } else { "off" },
+            logging::level()
+        );
+        let (width, modes_height) = TextRenderer::measure(&modes, HUD_SCALE);
+        let x = self.config.width as f32 - width - HUD_MARGIN;
+        self.text.draw_text(&modes, x, HUD_MARGIN, HUD_SCALE, [1.0, 1.0, 0.6, 1.0]);
+
+        // and under them what's been allocated on the gpu
+        let stats = graphics::resource_stats(HUD_LARGEST_RESOURCES);
+        let (buffers, buffer_bytes) = stats.buffers;
+        let (textures, texture_bytes) = stats.textures;
+        let mut gpu = format!(
+            "GPU ~{}\n{} buffers {}\n{} textures {}",
+            megabytes(buffer_bytes + texture_bytes),
+            buffers,
+            megabytes(buffer_bytes),
+            textures,
+            megabytes(texture_bytes),
+        );
+        for (label, bytes) in stats.largest {
+            gpu.push_str(&format!("\n{} {}", label, megabytes(bytes)));
+        }
+        let (width, _) = TextRenderer::measure(&gpu, HUD_SCALE);
+        let x = self.config.width as f32 - width - HUD_MARGIN;
+        self.text.draw_text(&gpu, x, HUD_MARGIN * 2.0 + modes_height, HUD_SCALE, [0.7, 0.9, 1.0, 1.0]);
+    }
+
+    // the crosshair while the mouse is captured, otherwise a hint on how to capture it. the help
+    // menu covers the middle of the screen and says as much
+    fn draw_focus(&mut self) {
+        let (width, height) = (self.config.width as f32, self.config.height as f32);
+        match self.focused {
+            Some(true) => self.sprites.draw_sprite(
+                Icon::Crosshair,
+                ((width - CROSSHAIR_SIZE) / 2.0).round(),
+                ((height - CROSSHAIR_SIZE) / 2.0).round(),
+                CROSSHAIR_SIZE,
+                CROSSHAIR_SIZE,
+                [1.0, 1.0, 1.0, 0.8],
+            ),
+            Some(false) if !self.show_help => {
+                let hint = "Unfocused, click to capture";
+                let (text_width, text_height) = TextRenderer::measure(hint, HUD_SCALE);
+                let (panel_width, panel_height) = (text_width + HUD_MARGIN * 2.0, text_height + HUD_MARGIN * 2.0);
+                let x = ((width - panel_width) / 2.0).round();
+                let y = ((height - panel_height) / 2.0).round();
+                self.sprites.draw_sprite(Icon::Solid, x, y, panel_width, panel_height, [0.0, 0.0, 0.0, 0.7]);
+                self.text.draw_text(hint, x + HUD_MARGIN, y + HUD_MARGIN, HUD_SCALE, [1.0, 1.0, 1.0, 1.0]);
+            }
+            _ => {}
+        }
+    }
+
+    // whether the frame has the app's cursor, in place of the system one while the mouse is free
+    pub fn draws_cursor(&self) -> bool {
+        self.focused == Some(false) && self.cursor_position.is_some()
+    }
+
+    // on top of everything else
+    fn draw_cursor(&mut self) {
+        let Some(position) = self.cursor_position.filter(|_| self.draws_cursor()) else {
+            return;
+        };
+        let x = position.x as f32 - (CURSOR_SIZE * Icon::CURSOR_TIP).round();
+        let y = position.y as f32 - (CURSOR_SIZE * Icon::CURSOR_TIP).round();
+        self.sprites.draw_sprite(Icon::CursorOutline, x, y, CURSOR_SIZE, CURSOR_SIZE, [0.0, 0.0, 0.0, 0.9]);
+        self.sprites.draw_sprite(Icon::Cursor, x, y, CURSOR_SIZE, CURSOR_SIZE, [1.0, 1.0, 1.0, 1.0]);
+    }
+
+    fn draw_telemetry(&mut self) {
+        let pose = self.camera.pose();
+        let [x, y, z] = pose.position;
+        let forward = self.camera.forward();
+        // the world axis it's looking closest along
+        let facing = [(forward.x, 'X'), (forward.y, 'Y'), (forward.z, 'Z')]
+            .into_iter()
+            .max_by(|a, b| a.0.abs().total_cmp(&b.0.abs()))
+            .map(|(along, axis)| format!("{}{}", if along < 0.0 { '-' } else { '+' }, axis))
+            .unwrap_or_default();
+        let telemetry = format!(
+            "Pos {:.2} {:.2} {:.2}\nYaw {:.1} Pitch {:.1}\nSpeed {:.2}\nFacing {} ({:.2} {:.2} {:.2})",
+            x,
+            y,
+            z,
+            pose.yaw,
+            pose.pitch,
+            self.camera.velocity().magnitude(),
+            facing,
+            forward.x,
+            forward.y,
+            forward.z,
+        );
+        let (_, height) = TextRenderer::measure(&telemetry, HUD_SCALE);
+        let y = self.config.height as f32 - height - HUD_MARGIN;
+        self.text.draw_text(&telemetry, HUD_MARGIN, y, HUD_SCALE, [0.6, 1.0, 0.6, 1.0]);
+    }
+
+    // the output and what's being typed on a dark panel across the top of the screen
+    fn draw_console(&mut self) {
+        let mut text = self.console.lines().join("\n");
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&format!("{}{}_", Console::PROMPT, self.console.input()));
+
+        // always tall enough for a full page of output, so it doesn't grow as lines come in
+        let page = vec!["-"; Console::MAX_LINES + 1].join("\n");
+        let (_, page_height) = TextRenderer::measure(&page, HUD_SCALE);
+        let (_, text_height) = TextRenderer::measure(&text, HUD_SCALE);
+        let width = self.config.width as f32;
+        self.sprites.draw_sprite(Icon::Solid, 0.0, 0.0, width, page_height + HUD_MARGIN * 2.0, [0.0, 0.0, 0.0, 0.8]);
+        let y = HUD_MARGIN + page_height - text_height;
+        self.text.draw_text(&text, HUD_MARGIN, y, HUD_SCALE, [0.9, 0.9, 0.9, 1.0]);
+    }
+
+    // every action and its key on a dark panel in the middle of the screen
+    fn draw_help(&mut self) {
+        let bindings = self.input_state.bindings();
+        let lines = input::Action::ALL
+            .iter()
+            .map(|&action| format!("{:<10} {:?}", format!("{:?}", bindings.key(action)), action))
+            .collect::<Vec<_>>();
+        let help = format!("Controls (F1 to close)\n\n{}", lines.join("\n"));
+
+        let (text_width, text_height) = TextRenderer::measure(&help, HUD_SCALE);
+        let (panel_width, panel_height) = (text_width + HUD_MARGIN * 4.0, text_height + HUD_MARGIN * 4.0);
+        let x = ((self.config.width as f32 - panel_width) / 2.0).round();
+        let y = ((self.config.height as f32 - panel_height) / 2.0).round();
+        self.sprites.draw_sprite(Icon::Solid, x, y, panel_width, panel_height, [0.0, 0.0, 0.0, 0.7]);
+        self.text.draw_text(&help, x + HUD_MARGIN * 2.0, y + HUD_MARGIN * 2.0, HUD_SCALE, [1.0, 1.0, 1.0, 1.0]);
+    }
+
+    // a billboard above each instanced sphere, labelled with how far away it is
+    fn draw_sphere_markers(&mut self, view_proj: Matrix4<f32>) {
+        let (width, height) = (self.config.width as f32, self.config.height as f32);
+        for x in 0..SPHERE_INSTANCED_ROWS {
+            for z in 0..SPHERE_INSTANCED_COLS {
+                let marker = Point3::new(
+                    x as f32 * SPHERE_INSTANCE_SPACING,
+                    FLOOR_Y + 5.0 + SPHERE_RADIUS + MARKER_HEIGHT,
+                    z as f32 * SPHERE_INSTANCE_SPACING,
+                );
+                self.billboards.draw_sprite(marker, MARKER_SIZE, Sprite::Marker, [0.3, 0.9, 1.0, 1.0]);
+
+                // the label goes just above the marker, unless it's behind the camera or off screen
+                let label = marker + Vector3::unit_y() * MARKER_SIZE * 2.0;
+                let clip = view_proj * label.to_homogeneous();
+                if clip.w <= 0.0 {
+                    continue;
+                }
+                let ndc = clip.truncate() / clip.w;
+                if ndc.x.abs() > 1.0 || ndc.y.abs() > 1.0 || ndc.z > 1.0 {
+                    continue;
+                }
+                let distance = format!("{:.0}m", (marker - self.camera.loc).magnitude());
+                let (text_width, text_height) = TextRenderer::measure(&distance, 1.0);
+                let screen_x = (ndc.x + 1.0) * 0.5 * width - text_width / 2.0;
+                let screen_y = (1.0 - ndc.y) * 0.5 * height - text_height;
+                self.text.draw_text(&distance, screen_x, screen_y, 1.0, [0.3, 0.9, 1.0, 1.0]);
+            }
+        }
+    }
+
+    // whether the scene is drawn with the main pipeline, where materials and reflections show
+    fn filled_and_lit(&self) -> bool {
+        let filled = self.wireframe_mode != WireframeMode::Only || self.wireframe_pipeline.is_none();
+        filled && self.view_mode == ViewMode::Lit
+    }
+
+    // where the scene is streamed in and wrapped around from, the view itself unless it's detached
+    fn scene_camera(&self) -> &Camera {
+        self.scene_camera.as_ref().unwrap_or(&self.camera)
+    }
+
+    // the floor when it's shown and reflects the scene
+    fn mirrored_floor(&self) -> Option<&PlanarReflection> {
+        self.reflection.as_ref().filter(|_| self.show_floor && self.filled_and_lit())
+    }
+
+    // the objects whose material can be set, the selected one of the cubes and pyramids first
+    fn scene_objects(&self) -> impl Iterator<Item = ObjectRef> {
+        let selected = match self.selected_obj {
+            0 => Some(ObjectRef::Obj1),
+            1 => Some(ObjectRef::Obj2),
+            _ => None,
+        };
+        selected
+            .into_iter()
+            .chain([ObjectRef::Sphere, ObjectRef::Floor])
+            .chain((0..self.primitives.len()).map(ObjectRef::Primitive))
+            .chain((!self.placed.0.instances.is_empty()).then_some(ObjectRef::Placed))
+    }
+
+    // along with what's needed to upload its changes
+    fn object_mut(&mut self, object: ObjectRef) -> (&mut RenderObject, &wgpu::Device, &mut Uploads) {
+        let object = match object {
+            ObjectRef::Obj1 => &mut self.obj1,
+            ObjectRef::Obj2 => &mut self.obj2,
+            ObjectRef::Sphere => &mut self.pythagoras_sphere,
+            ObjectRef::Floor => &mut self.floor,
+            ObjectRef::Primitive(i) => &mut self.primitives[i],
+            ObjectRef::Placed => &mut self.placed,
+        };
+        (&mut object.0, &self.device, &mut self.uploads)
+    }
+
+    fn object(&self, object: ObjectRef) -> &(RenderObject, wgpu::BindGroup) {
+        match object {
+            ObjectRef::Obj1 => &self.obj1,
+            ObjectRef::Obj2 => &self.obj2,
+            ObjectRef::Sphere => &self.pythagoras_sphere,
+            ObjectRef::Floor => &self.floor,
+            ObjectRef::Primitive(i) => &self.primitives[i],
+            ObjectRef::Placed => &self.placed,
+        }
+    }
+
+    // sorted from where the view is, the reflection and the other cameras draw in the same order
+    fn build_render_queue(&mut self) {
+        let mut queue = std::mem::replace(&mut self.render_queue, RenderQueue::new());
+        queue.clear();
+        for (i, key) in self.scene_objects().enumerate() {
+            let object = &self.object(key).0;
+            let (pipeline, group) = match (object.material, &object.emission) {
+                (Material::Chrome, _) => (DrawPipeline::Chrome, 0),
+                // each has its own color
+                (Material::Emissive { .. }, Some(_)) => (DrawPipeline::Emissive, i),
+                _ => (DrawPipeline::Scene, 0),
+            };
+            let center = object.model.transform_point(object.center);
+            queue.push(DrawItem {
+                key,
+                pipeline,
+                group,
+                depth: (center - self.camera.loc).magnitude2(),
+                // nothing drawn with the main shader blends yet
+                transparent: false,
             });
+        }
+        queue.sort();
+        self.render_queue = queue;
+    }
 
-            render_pass.set_pipeline(&self.render_pipeline);
-            let rp = &mut render_pass;
-            match self.selected_obj {
-                0 => App::render_obj(rp, &self.obj1),
-                1 => App::render_obj(rp, &self.obj2),
-                _ => {}
+    // the view through the portal, nested as deep as its levels go. each level is only drawn
+    // where the stencil holds its number, the entrance raises it by one wherever it shows
+    fn draw_portal<'a>(&'a self, rp: &mut wgpu::RenderPass<'a>, portal: &'a Portal) {
+        for (level, &camera_offset) in self.portal_offsets.iter().enumerate() {
+            let level = level as u32;
+            portal.mark(rp, level);
+            portal.open(rp, level);
+            rp.set_pipeline(&self.stenciled_pipeline);
+            rp.set_stencil_reference(level + 1);
+            self.draw_scene(rp, &[camera_offset], self.show_floor, false);
+        }
+        portal.cap(rp);
+        portal.close(rp);
+    }
+
+    // everything drawn with the main shader, the pipeline is set by the caller. the floor is
+    // left out of its own reflection, and objects with materials are left for draw_materials when
+    // skip_materials is set
+    fn draw_scene<'a>(&'a self, rp: &mut wgpu::RenderPass<'a>, camera_offsets: &[u32], floor: bool, skip_materials: bool) {
+        for &camera_offset in camera_offsets {
+            for item in self.render_queue.iter() {
+                if (skip_materials && item.pipeline != DrawPipeline::Scene) || (!floor && item.key == ObjectRef::Floor) {
+                    continue;
+                }
+                self.render_obj(rp, self.object(item.key), camera_offset);
+            }
+            if let Some((terrain, bind_group)) = &self.terrain {
+                rp.set_bind_group(0, bind_group, &[camera_offset]);
+                terrain.draw(rp);
+            }
+            if let Some((world, bind_group)) = &self.voxels {
+                rp.set_bind_group(0, bind_group, &[camera_offset]);
+                world.draw(rp);
+            }
+            if let Some((metaballs, bind_group)) = &self.metaballs {
+                rp.set_bind_group(0, bind_group, &[camera_offset]);
+                metaballs.draw(rp);
             }
-            App::render_obj(rp, &self.pythagoras_sphere);
-            App::render_obj(rp, &self.floor);
         }
+    }
 
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
-        Ok(())
+    // the objects draw_scene skipped, each with its material's pipeline, and the tv
+    fn draw_materials<'a>(&'a self, rp: &mut wgpu::RenderPass<'a>, camera_offsets: &[u32], floor: bool) {
+        let mut bound = None;
+        for item in self.render_queue.iter() {
+            if item.pipeline == DrawPipeline::Scene || (!floor && item.key == ObjectRef::Floor) {
+                continue;
+            }
+            let object = self.object(item.key);
+            // the queue keeps draws sharing a pipeline and bind group together
+            if bound != Some((item.pipeline, item.group)) {
+                match (item.pipeline, &object.0.emission) {
+                    (DrawPipeline::Chrome, _) => {
+                        rp.set_pipeline(&self.env_mapped_pipeline);
+                        rp.set_bind_group(1, self.sky.environment_bind_group(), &[]);
+                    }
+                    (DrawPipeline::Emissive, Some(emission)) => {
+                        rp.set_pipeline(&self.emissive_pipeline);
+                        rp.set_bind_group(1, emission.bind_group(), &[]);
+                    }
+                    _ => continue,
+                }
+                bound = Some((item.pipeline, item.group));
+            }
+            for &camera_offset in camera_offsets {
+                self.render_obj(rp, object, camera_offset);
+            }
+        }
+        if let Some((tv, bind_group)) = &self.tv {
+            rp.set_pipeline(&self.screen_pipeline);
+            for &camera_offset in camera_offsets {
+                rp.set_bind_group(0, bind_group, &[camera_offset]);
+                tv.draw(rp);
+            }
+        }
     }
 
     fn render_obj<'a>(
+        &self,
         render_pass: &mut wgpu::RenderPass<'a>,
         obj: &'a (RenderObject, wgpu::BindGroup),
+        camera_offset: u32,
     ) {
-        render_pass.set_bind_group(0, &obj.1, &[]);
-        render_pass.set_vertex_buffer(0, obj.0.vertices.slice(..));
-        if let Some(ref buf) = obj.0.instances_buffer {
-            render_pass.set_vertex_buffer(1, buf.slice(..));
-        }
-        render_pass.set_index_buffer(obj.0.indices.slice(..), wgpu::IndexFormat::Uint32);
-        render_pass.draw_indexed(
-            0..obj.0.num_indices,
-            0,
-            0..obj.0.shown_instances.unwrap_or(1),
-        );
+        let push_model = self.push_constants && obj.0.num_instances.is_none();
+        if push_model {
+            let constants = graphics::DrawConstants::new(obj.0.model, graphics::DrawConstants::OWN_MODEL);
+            render_pass.set_push_constants(wgpu::ShaderStages::VERTEX, 0, bytemuck::bytes_of(&constants));
+        }
+        render_pass.set_bind_group(0, &obj.1, &[camera_offset]);
+        render_pass.set_vertex_buffer(0, obj.0.vertices.slice());
+        render_pass.set_index_buffer(obj.0.indices.slice(), wgpu::IndexFormat::Uint32);
+        for draw in &obj.0.draws {
+            render_pass.draw_indexed(draw.indices.clone(), draw.base_vertex, 0..1);
+        }
+        let visible = obj.0.visible.iter().find(|(offset, _)| *offset == camera_offset);
+        if let Some((_, runs)) = visible {
+            for run in runs {
+                render_pass.draw_indexed(0..obj.0.num_indices, 0, run.clone());
+            }
+        } else if obj.0.draws.is_empty() {
+            render_pass.draw_indexed(
+                0..obj.0.num_indices,
+                0,
+                0..obj.0.shown_instances.unwrap_or(1),
+            );
+        }
+        // the constants outlive the draw, the terrain and the rest drawn after go by their transforms
+        if push_model {
+            render_pass.set_push_constants(
+                wgpu::ShaderStages::VERTEX,
+                graphics::DrawConstants::FLAGS_OFFSET,
+                bytemuck::bytes_of(&0u32),
+            );
+        }
+    }
+}
+
+fn megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+// between 0.6 and 1.4, stepping through the sizes in an order that doesn't look like a pattern
+fn instance_size(i: usize) -> f32 {
+    0.6 + 0.2 * ((i * 7) % 5) as f32
+}
+
+fn bookmark_slot(key: VirtualKeyCode) -> Option<usize> {
+    match key {
+        VirtualKeyCode::Key1 => Some(0),
+        VirtualKeyCode::Key2 => Some(1),
+        VirtualKeyCode::Key3 => Some(2),
+        VirtualKeyCode::Key4 => Some(3),
+        VirtualKeyCode::Key5 => Some(4),
+        VirtualKeyCode::Key6 => Some(5),
+        VirtualKeyCode::Key7 => Some(6),
+        VirtualKeyCode::Key8 => Some(7),
+        VirtualKeyCode::Key9 => Some(8),
+        _ => None,
+    }
+}
+
+// what the objects do when the scene doesn't say otherwise
+fn default_animation(name: &str) -> Option<AnimationClip> {
+    use std::f32::consts::{PI, TAU};
+    match name {
+        // tumbling around all three axes, a radian a second
+        "cubes" => Some(AnimationClip {
+            rotation: vec![
+                Keyframe::new(0.0, [0.0; 3], Easing::Linear),
+                Keyframe::new(TAU, [360.0; 3], Easing::Linear),
+            ],
+            ..Default::default()
+        }),
+        // circling while bobbing up and down and pulsing in size twice a lap
+        "pyramids" => {
+            const STEPS: usize = 16;
+            let position = (0..=STEPS)
+                .map(|i| {
+                    let t = i as f32 / STEPS as f32 * TAU;
+                    Keyframe::new(t, [t.sin() * 10.0, t.sin(), t.cos() * 10.0], Easing::Linear)
+                })
+                .collect();
+            let scale = (0..=4)
+                .map(|i| {
+                    let (size, easing) = if i % 2 == 0 { (1.22, Easing::EaseOut) } else { (2.22, Easing::EaseIn) };
+                    Keyframe::new(i as f32 * PI / 2.0, size, easing)
+                })
+                .collect();
+            Some(AnimationClip { position, scale, ..Default::default() })
+        }
+        // resting on the floor, slowly turning
+        "spheres" => Some(AnimationClip {
+            position: vec![Keyframe::new(0.0, [0.0, FLOOR_Y + 5.0, 0.0], Easing::Linear)],
+            rotation: vec![
+                Keyframe::new(0.0, [0.0; 3], Easing::Linear),
+                Keyframe::new(TAU * 10.0, [360.0; 3], Easing::Linear),
+            ],
+            ..Default::default()
+        }),
+        _ => None,
+    }
+}
+
+// where the character is at time, turned to face the way it's walking
+fn character_model(time: f32) -> Matrix4<f32> {
+    let (start, end) = (Vector3::from(CHARACTER_PATH[0]), Vector3::from(CHARACTER_PATH[1]));
+    let length = (end - start).magnitude();
+    let travelled = (time * CHARACTER_SPEED).rem_euclid(length * 2.0);
+    let (from, to, t) = if travelled < length {
+        (start, end, travelled / length)
+    } else {
+        (end, start, travelled / length - 1.0)
+    };
+    let direction = to - from;
+    // the model faces +z
+    let yaw = cgmath::Rad(direction.x.atan2(direction.z));
+    Matrix4::from_translation(from.lerp(to, t)) * Matrix4::from_angle_y(yaw) * Matrix4::from_scale(CHARACTER_SCALE)
+}
+
+fn terrain_heightmap(config: &TerrainConfig) -> Option<Heightmap> {
+    if let Some(noise) = &config.noise {
+        return Some(Heightmap::from_noise(noise));
+    }
+
+    let bytes = match &config.heightmap {
+        Some(path) => match std::fs::read(path) {
+            Ok(bytes) => std::borrow::Cow::Owned(bytes),
+            Err(e) => {
+                warn!("Failed to read heightmap {}: {}", path.display(), e);
+                return None;
+            }
+        },
+        None => std::borrow::Cow::Borrowed(&include_bytes!("../res/terrain/heightmap.png")[..]),
+    };
+    match Heightmap::from_image(&bytes) {
+        Ok(heightmap) => Some(heightmap),
+        Err(e) => {
+            warn!("Failed to decode heightmap: {}", e);
+            None
+        }
     }
 }
 
@@ -392,24 +3441,24 @@ fn build_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
                 visibility: wgpu::ShaderStages::VERTEX,
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<CameraUniform>() as u64),
                 },
                 count: None,
             },
-            wgpu::BindGroupLayoutEntry { // model matrix uniform
+            wgpu::BindGroupLayoutEntry { // model and instance matrices storage
                 binding: 1,
                 visibility: wgpu::ShaderStages::VERTEX,
                 ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
                     has_dynamic_offset: false,
                     min_binding_size: None,
                 },
                 count: None,
             },
-            wgpu::BindGroupLayoutEntry { // is instanced uniform
+            wgpu::BindGroupLayoutEntry { // sun and ambient light uniform
                 binding: 2,
-                visibility: wgpu::ShaderStages::VERTEX,
+                visibility: wgpu::ShaderStages::FRAGMENT,
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
                     has_dynamic_offset: false,
@@ -417,9 +3466,19 @@ fn build_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
                 },
                 count: None,
             },
-            wgpu::BindGroupLayoutEntry { // texture data
+            wgpu::BindGroupLayoutEntry { // point and spot lights
                 binding: 3,
                 visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry { // texture data
+                binding: 4,
+                visibility: wgpu::ShaderStages::FRAGMENT,
                 ty: wgpu::BindingType::Texture {
                     multisampled: false,
                     view_dimension: wgpu::TextureViewDimension::D2,
@@ -428,7 +3487,7 @@ fn build_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
                 count: None,
             },
             wgpu::BindGroupLayoutEntry { // texture sampler
-                binding: 4,
+                binding: 5,
                 visibility: wgpu::ShaderStages::FRAGMENT,
                 ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                 count: None,
@@ -438,234 +3497,270 @@ fn build_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
     })
 }
 
-fn build_obj1(device: &wgpu::Device, instances: &Vec<Instance>) -> RenderObject {
+fn build_obj1(device: &wgpu::Device, queue: &wgpu::Queue, pools: &mut MeshPools, instances: &[Instance]) -> RenderObject {
     RenderObject {
-        vertices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("vertices_obj1"),
-            contents: bytemuck::cast_slice(&[
-                graphics::Vertex { position: [0.5, 0.5, 0.5], tex_coords: [1.0, 0.0] }, // 0
-                graphics::Vertex { position: [-0.5, 0.5, 0.5], tex_coords: [0.0, 0.0] }, // 1
-                graphics::Vertex { position: [0.5, -0.5, 0.5], tex_coords: [1.0, 1.0] }, // 2
-                graphics::Vertex { position: [-0.5, -0.5, 0.5], tex_coords: [0.0, 1.0] }, // 3
-                graphics::Vertex { position: [-0.5, 0.5, 0.5], tex_coords: [1.0, 0.0] }, // 4
-                graphics::Vertex { position: [-0.5, 0.5, -0.5], tex_coords: [0.0, 0.0] }, // 5
-                graphics::Vertex { position: [-0.5, -0.5, 0.5], tex_coords: [1.0, 1.0] }, // 6
-                graphics::Vertex { position: [-0.5, -0.5, -0.5], tex_coords: [0.0, 1.0] }, // 7
-                graphics::Vertex { position: [0.5, 0.5, 0.5], tex_coords: [1.0, 0.0] }, // 8
-                graphics::Vertex { position: [0.5, 0.5, -0.5], tex_coords: [0.0, 0.0] }, // 9
-                graphics::Vertex { position: [-0.5, 0.5, 0.5], tex_coords: [1.0, 1.0] }, // 10
-                graphics::Vertex { position: [-0.5, 0.5, -0.5], tex_coords: [0.0, 1.0] }, // 11
-                graphics::Vertex { position: [-0.5, 0.5, -0.5], tex_coords: [1.0, 0.0] }, // 12
-                graphics::Vertex { position: [0.5, 0.5, -0.5], tex_coords: [0.0, 0.0] }, // 13
-                graphics::Vertex { position: [-0.5, -0.5, -0.5], tex_coords: [1.0, 1.0] }, // 14
-                graphics::Vertex { position: [0.5, -0.5, -0.5], tex_coords: [0.0, 1.0] }, // 15
-                graphics::Vertex { position: [0.5, 0.5, -0.5], tex_coords: [1.0, 0.0] }, // 16
-                graphics::Vertex { position: [0.5, 0.5, 0.5], tex_coords: [0.0, 0.0] }, // 17
-                graphics::Vertex { position: [0.5, -0.5, -0.5], tex_coords: [1.0, 1.0] }, // 18
-                graphics::Vertex { position: [0.5, -0.5, 0.5], tex_coords: [0.0, 1.0] }, // 19
-                graphics::Vertex { position: [0.5, -0.5, 0.5], tex_coords: [1.0, 0.0] }, // 20
-                graphics::Vertex { position: [-0.5, -0.5, 0.5], tex_coords: [0.0, 0.0] }, // 21
-                graphics::Vertex { position: [0.5, -0.5, -0.5], tex_coords: [1.0, 1.0] }, // 22
-                graphics::Vertex { position: [-0.5, -0.5, -0.5], tex_coords: [0.0, 1.0] }, // 23
-            ]),
-            usage: wgpu::BufferUsages::VERTEX,
-        }),
-        indices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("indices_obj1"),
-            contents: bytemuck::cast_slice(&[
-                0u32, 1, 2,
-                1, 3, 2,
-                4, 5, 6,
-                5, 7, 6,
-                8, 9, 10,
-                9, 11, 10,
-                12, 13, 14,
-                13, 15, 14,
-                16, 17, 18,
-                17, 19, 18,
-                20, 21, 22,
-                21, 23, 22,
-            ]),
-            usage: wgpu::BufferUsages::INDEX,
-        }),
-        model_buf: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("model_obj1"),
-            contents: bytemuck::cast_slice(&[super::graphics::RawMatrix {
-                mat: Matrix4::identity().into(),
-            }]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        }),
-        is_instanced_buf: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("is_instanced_obj1"),
-            contents: bytemuck::cast_slice(&[1u32]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        }),
+        name: "cubes",
+        animation: default_animation("cubes"),
+        material: Material::default(),
+        emission: None,
+        model: Matrix4::identity(),
+        center: bounds_center(instances.iter().map(|instance| Point3::from_vec(instance.trans))),
+        vertices: pools.vertices.alloc(device, queue, &[
+            graphics::Vertex { position: [0.5, 0.5, 0.5], tex_coords: [1.0, 0.0], ..Default::default() }, // 0
+            graphics::Vertex { position: [-0.5, 0.5, 0.5], tex_coords: [0.0, 0.0], ..Default::default() }, // 1
+            graphics::Vertex { position: [0.5, -0.5, 0.5], tex_coords: [1.0, 1.0], ..Default::default() }, // 2
+            graphics::Vertex { position: [-0.5, -0.5, 0.5], tex_coords: [0.0, 1.0], ..Default::default() }, // 3
+            graphics::Vertex { position: [-0.5, 0.5, 0.5], tex_coords: [1.0, 0.0], ..Default::default() }, // 4
+            graphics::Vertex { position: [-0.5, 0.5, -0.5], tex_coords: [0.0, 0.0], ..Default::default() }, // 5
+            graphics::Vertex { position: [-0.5, -0.5, 0.5], tex_coords: [1.0, 1.0], ..Default::default() }, // 6
+            graphics::Vertex { position: [-0.5, -0.5, -0.5], tex_coords: [0.0, 1.0], ..Default::default() }, // 7
+            graphics::Vertex { position: [0.5, 0.5, 0.5], tex_coords: [1.0, 0.0], ..Default::default() }, // 8
+            graphics::Vertex { position: [0.5, 0.5, -0.5], tex_coords: [0.0, 0.0], ..Default::default() }, // 9
+            graphics::Vertex { position: [-0.5, 0.5, 0.5], tex_coords: [1.0, 1.0], ..Default::default() }, // 10
+            graphics::Vertex { position: [-0.5, 0.5, -0.5], tex_coords: [0.0, 1.0], ..Default::default() }, // 11
+            graphics::Vertex { position: [-0.5, 0.5, -0.5], tex_coords: [1.0, 0.0], ..Default::default() }, // 12
+            graphics::Vertex { position: [0.5, 0.5, -0.5], tex_coords: [0.0, 0.0], ..Default::default() }, // 13
+            graphics::Vertex { position: [-0.5, -0.5, -0.5], tex_coords: [1.0, 1.0], ..Default::default() }, // 14
+            graphics::Vertex { position: [0.5, -0.5, -0.5], tex_coords: [0.0, 1.0], ..Default::default() }, // 15
+            graphics::Vertex { position: [0.5, 0.5, -0.5], tex_coords: [1.0, 0.0], ..Default::default() }, // 16
+            graphics::Vertex { position: [0.5, 0.5, 0.5], tex_coords: [0.0, 0.0], ..Default::default() }, // 17
+            graphics::Vertex { position: [0.5, -0.5, -0.5], tex_coords: [1.0, 1.0], ..Default::default() }, // 18
+            graphics::Vertex { position: [0.5, -0.5, 0.5], tex_coords: [0.0, 1.0], ..Default::default() }, // 19
+            graphics::Vertex { position: [0.5, -0.5, 0.5], tex_coords: [1.0, 0.0], ..Default::default() }, // 20
+            graphics::Vertex { position: [-0.5, -0.5, 0.5], tex_coords: [0.0, 0.0], ..Default::default() }, // 21
+            graphics::Vertex { position: [0.5, -0.5, -0.5], tex_coords: [1.0, 1.0], ..Default::default() }, // 22
+            graphics::Vertex { position: [-0.5, -0.5, -0.5], tex_coords: [0.0, 1.0], ..Default::default() }, // 23
+        ]),
+        indices: pools.indices.alloc(device, queue, &[
+            0u32, 1, 2,
+            1, 3, 2,
+            4, 5, 6,
+            5, 7, 6,
+            8, 9, 10,
+            9, 11, 10,
+            12, 13, 14,
+            13, 15, 14,
+            16, 17, 18,
+            17, 19, 18,
+            20, 21, 22,
+            21, 23, 22,
+        ]),
+        transforms: pools.transforms.alloc(device, queue, &graphics::transforms(Matrix4::identity(), &instances.iter().map(Instance::as_raw).collect::<Vec<_>>())),
         num_indices: 36,
-        instances_buffer: Some(
-            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("obj1_instance_buffer"),
-                contents: bytemuck::cast_slice(
-                    &instances.iter().map(Instance::as_raw).collect::<Vec<_>>(),
-                ),
-                usage: wgpu::BufferUsages::VERTEX,
-            }),
-        ),
         num_instances: Some(instances.len() as u32),
         shown_instances: Some((INSTANCED_ROWS * INSTANCED_COLS) as u32),
+        instances: instances.to_vec(),
+        grid: Some(InstanceGrid::new(instances, CUBE_MESH_RADIUS)),
+        visible: Vec::new(),
+        draws: Vec::new(),
     }
 }
 
-fn build_obj2(device: &wgpu::Device, instances: &Vec<Instance>) -> RenderObject {
+fn build_obj2(device: &wgpu::Device, queue: &wgpu::Queue, pools: &mut MeshPools, instances: &[Instance]) -> RenderObject {
     RenderObject {
-        vertices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("vertices_obj2"),
-            contents: bytemuck::cast_slice(&[
-                graphics::Vertex { position: [0.0, 0.5, 0.0], tex_coords: [0.5, 0.0] }, // 0
-                graphics::Vertex { position: [-0.5, -0.5, -0.5], tex_coords: [0.0, 1.0] }, // 1
-                graphics::Vertex { position: [-0.5, -0.5, 0.5], tex_coords: [1.0, 1.0] }, // 2
-                graphics::Vertex { position: [0.5, -0.5, 0.5], tex_coords: [0.0, 1.0] }, // 3
-                graphics::Vertex { position: [0.5, -0.5, -0.5], tex_coords: [1.0, 1.0] }, // 4
-                graphics::Vertex { position: [-0.5, -0.5, -0.5], tex_coords: [0.0, 1.0] }, // 5
-                graphics::Vertex { position: [-0.5, -0.5, 0.5], tex_coords: [0.0, 0.0] }, // 6
-                graphics::Vertex { position: [0.5, -0.5, 0.5], tex_coords: [1.0, 0.0] }, // 7
-                graphics::Vertex { position: [0.5, -0.5, -0.5], tex_coords: [1.0, 1.0] }, // 8
-            ]),
-            usage: wgpu::BufferUsages::VERTEX,
-        }),
-        indices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("indices_obj2"),
-            contents: bytemuck::cast_slice(&[
-                0u32, 2, 3,
-                0, 1, 2,
-                0, 4, 1,
-                0, 3, 4,
-                7, 6, 8,
-                6, 5, 8,
-            ]),
-            usage: wgpu::BufferUsages::INDEX,
-        }),
-        model_buf: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("model_obj2"),
-            contents: bytemuck::cast_slice(&[super::graphics::RawMatrix {
-                mat: Matrix4::identity().into(),
-            }]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        }),
-        is_instanced_buf: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("is_instanced_obj2"),
-            contents: bytemuck::cast_slice(&[1u32]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        }),
+        name: "pyramids",
+        animation: default_animation("pyramids"),
+        material: Material::default(),
+        emission: None,
+        model: Matrix4::identity(),
+        center: bounds_center(instances.iter().map(|instance| Point3::from_vec(instance.trans))),
+        vertices: pools.vertices.alloc(device, queue, &[
+            graphics::Vertex { position: [0.0, 0.5, 0.0], tex_coords: [0.5, 0.0], ..Default::default() }, // 0
+            graphics::Vertex { position: [-0.5, -0.5, -0.5], tex_coords: [0.0, 1.0], ..Default::default() }, // 1
+            graphics::Vertex { position: [-0.5, -0.5, 0.5], tex_coords: [1.0, 1.0], ..Default::default() }, // 2
+            graphics::Vertex { position: [0.5, -0.5, 0.5], tex_coords: [0.0, 1.0], ..Default::default() }, // 3
+            graphics::Vertex { position: [0.5, -0.5, -0.5], tex_coords: [1.0, 1.0], ..Default::default() }, // 4
+            graphics::Vertex { position: [-0.5, -0.5, -0.5], tex_coords: [0.0, 1.0], ..Default::default() }, // 5
+            graphics::Vertex { position: [-0.5, -0.5, 0.5], tex_coords: [0.0, 0.0], ..Default::default() }, // 6
+            graphics::Vertex { position: [0.5, -0.5, 0.5], tex_coords: [1.0, 0.0], ..Default::default() }, // 7
+            graphics::Vertex { position: [0.5, -0.5, -0.5], tex_coords: [1.0, 1.0], ..Default::default() }, // 8
+        ]),
+        indices: pools.indices.alloc(device, queue, &[
+            0u32, 2, 3,
+            0, 1, 2,
+            0, 4, 1,
+            0, 3, 4,
+            7, 6, 8,
+            6, 5, 8,
+        ]),
+        transforms: pools.transforms.alloc(device, queue, &graphics::transforms(Matrix4::identity(), &instances.iter().map(Instance::as_raw).collect::<Vec<_>>())),
         num_indices: 18,
-        instances_buffer: Some(
-            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("obj2_instance_buffer"),
-                contents: bytemuck::cast_slice(
-                    &instances.iter().map(Instance::as_raw).collect::<Vec<_>>(),
-                ),
-                usage: wgpu::BufferUsages::VERTEX,
-            }),
-        ),
         num_instances: Some(instances.len() as u32),
         shown_instances: Some((INSTANCED_ROWS * INSTANCED_COLS) as u32),
+        instances: instances.to_vec(),
+        grid: Some(InstanceGrid::new(instances, CUBE_MESH_RADIUS)),
+        visible: Vec::new(),
+        draws: Vec::new(),
     }
 }
 
-fn build_floor(device: &wgpu::Device) -> RenderObject {
+fn build_floor(device: &wgpu::Device, queue: &wgpu::Queue, pools: &mut MeshPools) -> RenderObject {
     RenderObject {
-        vertices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("vertices_floor"),
-            contents: bytemuck::cast_slice(&[
-                graphics::Vertex {
-                    position: [0.0, FLOOR_Y, 0.0],
-                    tex_coords: [0.0, 0.0],
-                },
-                graphics::Vertex {
-                    position: [0.0, FLOOR_Y, (INSTANCED_COLS - 1) as f32 * INSTANCE_SPACING],
-                    tex_coords: [0.0, 5.0],
-                },
-                graphics::Vertex {
-                    position: [(INSTANCED_ROWS - 1) as f32 * INSTANCE_SPACING, FLOOR_Y, 0.0],
-                    tex_coords: [5.0, 0.0],
-                },
-                graphics::Vertex {
-                    position: [
-                        (INSTANCED_ROWS - 1) as f32 * INSTANCE_SPACING,
-                        FLOOR_Y,
-                        (INSTANCED_COLS - 1) as f32 * INSTANCE_SPACING,
-                    ],
-                    tex_coords: [5.0, 5.0],
-                },
-            ]),
-            usage: wgpu::BufferUsages::VERTEX,
-        }),
-        indices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("indices_floor"),
-            contents: bytemuck::cast_slice(&[
-                0u32, 1, 2, 
-                1, 3, 2, 
-                1, 0, 2, 
-                3, 1, 2
-            ]),
-            usage: wgpu::BufferUsages::INDEX,
-        }),
-        model_buf: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("model_floor"),
-            contents: bytemuck::cast_slice(&[super::graphics::RawMatrix {
-                mat: Matrix4::identity().into(),
-            }]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        }),
-        is_instanced_buf: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("is_instanced_floor"),
-            contents: bytemuck::cast_slice(&[0u32]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        }),
+        name: "floor",
+        animation: default_animation("floor"),
+        material: Material::default(),
+        emission: None,
+        model: Matrix4::identity(),
+        center: Point3::new(
+            (INSTANCED_ROWS - 1) as f32 * INSTANCE_SPACING / 2.0,
+            FLOOR_Y,
+            (INSTANCED_COLS - 1) as f32 * INSTANCE_SPACING / 2.0,
+        ),
+        vertices: pools.vertices.alloc(device, queue, &[
+            graphics::Vertex {
+                position: [0.0, FLOOR_Y, 0.0],
+                tex_coords: [0.0, 0.0],
+                normal: [0.0, 1.0, 0.0],
+            },
+            graphics::Vertex {
+                position: [0.0, FLOOR_Y, (INSTANCED_COLS - 1) as f32 * INSTANCE_SPACING],
+                tex_coords: [0.0, 5.0],
+                normal: [0.0, 1.0, 0.0],
+            },
+            graphics::Vertex {
+                position: [(INSTANCED_ROWS - 1) as f32 * INSTANCE_SPACING, FLOOR_Y, 0.0],
+                tex_coords: [5.0, 0.0],
+                normal: [0.0, 1.0, 0.0],
+            },
+            graphics::Vertex {
+                position: [
+                    (INSTANCED_ROWS - 1) as f32 * INSTANCE_SPACING,
+                    FLOOR_Y,
+                    (INSTANCED_COLS - 1) as f32 * INSTANCE_SPACING,
+                ],
+                tex_coords: [5.0, 5.0],
+                normal: [0.0, 1.0, 0.0],
+            },
+        ]),
+        indices: pools.indices.alloc(device, queue, &[
+            0u32, 1, 2, 
+            1, 3, 2, 
+            1, 0, 2, 
+            3, 1, 2
+        ]),
+        transforms: pools.transforms.alloc(device, queue, &graphics::transforms(Matrix4::identity(), &[])),
         num_indices: 12,
-        instances_buffer: None,
         num_instances: None,
         shown_instances: None,
+        instances: Vec::new(),
+        grid: None,
+        visible: Vec::new(),
+        draws: Vec::new(),
+    }
+}
+
+// a single, non instanced object from a generated mesh
+fn build_mesh_object(device: &wgpu::Device, queue: &wgpu::Queue, pools: &mut MeshPools, mesh: &Mesh, name: &'static str, model: Matrix4<f32>) -> RenderObject {
+    RenderObject {
+        name,
+        animation: default_animation(name),
+        material: Material::default(),
+        emission: None,
+        model,
+        center: bounds_center(mesh.vertices.iter().map(|vertex| Point3::from(vertex.position))),
+        vertices: pools.vertices.alloc(device, queue, &mesh.vertices),
+        indices: pools.indices.alloc(device, queue, &mesh.indices),
+        transforms: pools.transforms.alloc(device, queue, &graphics::transforms(model, &[])),
+        num_indices: mesh.indices.len() as u32,
+        num_instances: None,
+        shown_instances: None,
+        instances: Vec::new(),
+        grid: None,
+        visible: Vec::new(),
+        draws: Vec::new(),
+    }
+}
+
+// the middle of the box around the points
+fn bounds_center(points: impl Iterator<Item = Point3<f32>>) -> Point3<f32> {
+    let (min, max) = points.fold(
+        (Point3::new(f32::MAX, f32::MAX, f32::MAX), Point3::new(f32::MIN, f32::MIN, f32::MIN)),
+        |(min, max), p| {
+            (
+                Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+            )
+        },
+    );
+    if min.x > max.x {
+        return Point3::new(0.0, 0.0, 0.0);
+    }
+    min.midpoint(max)
+}
+
+fn sphere_mesh(kind: SphereMesh) -> Mesh {
+    match kind {
+        SphereMesh::Pythagoras => {
+            let (vertices, indices) = gen_sphere((0.0, 0.0, 0.0), SPHERE_RADIUS as f64, 75);
+            Mesh {
+                vertices: vertices.into_vec(),
+                indices: indices.into_vec(),
+            }
+        }
+        SphereMesh::Uv => primitives::uv_sphere(SPHERE_RADIUS, 48, 24),
+        SphereMesh::Ico => primitives::icosphere(SPHERE_RADIUS, 4),
+    }
+}
+
+// resting on the floor in rows, once the spheres' animation has moved them down to it
+fn sphere_grid() -> Vec<Instance> {
+    (0..SPHERE_INSTANCED_ROWS)
+        .flat_map(|x| {
+            (0..SPHERE_INSTANCED_COLS).map(move |z| Instance {
+                trans: Vector3::new(x as f32 * SPHERE_INSTANCE_SPACING, 0.0, z as f32 * SPHERE_INSTANCE_SPACING),
+                rot: cgmath::Quaternion::from_axis_angle(cgmath::Vector3::unit_z(), cgmath::Deg(0.0)),
+                scale: Vector3::new(1.0, 1.0, 1.0),
+                tint: graphics::NO_TINT,
+            })
+        })
+        .collect()
+}
+
+// no crates until the first click, with room for a few before the transforms have to grow
+fn build_placed(device: &wgpu::Device, queue: &wgpu::Queue, pools: &mut MeshPools) -> RenderObject {
+    let Mesh { vertices, indices } = primitives::cube(PLACED_SIZE);
+    let empty = vec![bytemuck::Zeroable::zeroed(); PLACED_CAPACITY];
+
+    RenderObject {
+        name: "placed",
+        animation: None,
+        material: Material::default(),
+        emission: None,
+        model: Matrix4::identity(),
+        center: Point3::origin(),
+        vertices: pools.vertices.alloc(device, queue, &vertices),
+        indices: pools.indices.alloc(device, queue, &indices),
+        transforms: pools.transforms.alloc(device, queue, &graphics::transforms(Matrix4::identity(), &empty)),
+        num_indices: indices.len() as u32,
+        num_instances: Some(PLACED_CAPACITY as u32),
+        shown_instances: Some(0),
+        instances: Vec::new(),
+        grid: Some(InstanceGrid::new(&[], PLACED_SIZE * CUBE_MESH_RADIUS)),
+        visible: Vec::new(),
+        draws: Vec::new(),
     }
 }
 
-fn build_sphere(device: &wgpu::Device, instances: &Vec<Instance>) -> RenderObject {
-    let (vertices, indices) = gen_sphere((0.0, 0.0, 0.0), 5.0, 75);
+fn build_sphere(device: &wgpu::Device, queue: &wgpu::Queue, pools: &mut MeshPools, instances: &[Instance], kind: SphereMesh) -> RenderObject {
+    let Mesh { vertices, indices } = sphere_mesh(kind);
 
     RenderObject {
-        vertices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("vertices_sphere"),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: wgpu::BufferUsages::VERTEX,
-        }),
-        indices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("indices_sphere"),
-            contents: bytemuck::cast_slice(&indices),
-            usage: wgpu::BufferUsages::INDEX,
-        }),
-        model_buf: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("model_sphere"),
-            contents: bytemuck::cast_slice(&[super::graphics::RawMatrix {
-                mat: Matrix4::identity().into(),
-            }]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        }),
-        is_instanced_buf: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("is_instanced_sphere"),
-            contents: bytemuck::cast_slice(&[1u32]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        }),
+        name: "spheres",
+        animation: default_animation("spheres"),
+        material: Material::default(),
+        emission: None,
+        model: Matrix4::identity(),
+        center: bounds_center(instances.iter().map(|instance| Point3::from_vec(instance.trans))),
+        vertices: pools.vertices.alloc(device, queue, &vertices),
+        indices: pools.indices.alloc(device, queue, &indices),
+        transforms: pools.transforms.alloc(device, queue, &graphics::transforms(Matrix4::identity(), &instances.iter().map(Instance::as_raw).collect::<Vec<_>>())),
         num_indices: indices.len() as u32,
-        instances_buffer: Some(
-            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("sphere_instance_buffer"),
-                contents: bytemuck::cast_slice(
-                    &instances.iter().map(Instance::as_raw).collect::<Vec<_>>(),
-                ),
-                usage: wgpu::BufferUsages::VERTEX,
-            }),
-        ),
         num_instances: Some(instances.len() as u32),
         shown_instances: Some(instances.len() as u32),
+        instances: instances.to_vec(),
+        grid: Some(InstanceGrid::new(instances, SPHERE_RADIUS)),
+        visible: Vec::new(),
+        draws: Vec::new(),
     }
 }
 
@@ -693,10 +3788,12 @@ fn gen_sphere(pos: (f64, f64, f64), radius: f64, lod: u32) -> (Box<[Vertex]>, Bo
             vertices[(i * iters + j) * 2] = Vertex {
                 position: [px as f32, py as f32, pz1 as f32],
                 tex_coords: tex,
+                normal: [(x / radius) as f32, (y / radius) as f32, (z / radius) as f32],
             };
             vertices[(i * iters + j) * 2 + 1] = Vertex {
                 position: [px as f32, py as f32, pz2 as f32],
                 tex_coords: tex,
+                normal: [(x / radius) as f32, (y / radius) as f32, (-z / radius) as f32],
             };
 
             x += layer_factor;
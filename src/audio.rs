@@ -0,0 +1,266 @@
+use cgmath::{Point3, Vector3};
+use log::{debug, info, warn};
+use rodio::source::Buffered;
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source, SpatialSink};
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sound {
+    Footstep,
+    // switching between the cubes and pyramids
+    Click,
+    // instances appearing
+    Whoosh,
+    // a low drone given off by objects, looped
+    Hum,
+    // looped all around the listener while it rains
+    Rain,
+}
+
+impl Sound {
+    const ALL: [Sound; 5] = [Sound::Footstep, Sound::Click, Sound::Whoosh, Sound::Hum, Sound::Rain];
+
+    fn bytes(self) -> &'static [u8] {
+        match self {
+            Sound::Footstep => include_bytes!("../res/audio/footstep.wav"),
+            Sound::Click => include_bytes!("../res/audio/click.wav"),
+            Sound::Whoosh => include_bytes!("../res/audio/whoosh.wav"),
+            Sound::Hum => include_bytes!("../res/audio/hum.wav"),
+            Sound::Rain => include_bytes!("../res/audio/rain.wav"),
+        }
+    }
+
+    // how loud each is next to the others, the master volume applies on top
+    fn gain(self) -> f32 {
+        match self {
+            Sound::Footstep => 0.4,
+            Sound::Click => 0.6,
+            Sound::Whoosh => 0.8,
+            Sound::Hum => 1.0,
+            Sound::Rain => 0.7,
+        }
+    }
+}
+
+type Clip = Buffered<Decoder<Cursor<&'static [u8]>>>;
+
+// a sound looping from somewhere in the scene, moved with Audio::place
+pub struct SpatialSound {
+    sound: Sound,
+    sink: SpatialSink,
+}
+
+// a sound looping everywhere at once rather than from a point, faded with Audio::set_level
+pub struct AmbientSound {
+    sound: Sound,
+    sink: Sink,
+}
+
+// a looped music track, decoded as it plays
+struct Track {
+    path: PathBuf,
+    sink: Sink,
+    // 0 silent to 1 at the music volume
+    level: f32,
+    // the one being faded in, the rest fade out
+    current: bool,
+}
+
+// sound effects, decoded once up front and mixed by rodio on a thread of its own
+pub struct Audio {
+    handle: OutputStreamHandle,
+    // the audio thread closes the stream once this is dropped
+    _stop: mpsc::Sender<()>,
+    // by Sound
+    clips: Vec<Clip>,
+    volume: f32,
+    // the track fading in last, any others fading out
+    tracks: Vec<Track>,
+    // what was last asked for, kept even when it failed to open so it isn't tried every frame
+    music: Option<PathBuf>,
+    music_volume: f32,
+    // where the listener's ears are, already scaled like the emitters
+    ears: [[f32; 3]; 2],
+}
+
+impl Audio {
+    // seconds for one track to fade into the next
+    const CROSSFADE: f32 = 3.0;
+    // world units between the ears
+    const EAR_SPACING: f32 = 0.3;
+    // world units from an emitter it can be heard at full volume, beyond that it falls off with
+    // the square of the distance. rodio's falloff starts at 1, so positions are divided by this
+    const FULL_VOLUME_DISTANCE: f32 = 8.0;
+
+    // None without an output device or when the sounds can't be decoded, everything carries on
+    // silently then
+    pub fn new() -> Option<Self> {
+        match Self::open() {
+            Ok(audio) => Some(audio),
+            Err(e) => {
+                warn!("Sounds are off: {}", e);
+                None
+            }
+        }
+    }
+
+    fn open() -> Result<Self, Box<dyn std::error::Error>> {
+        // the stream can't leave the thread it was opened on, so it's kept on one that lives as
+        // long as this does and only the handle comes back
+        let (sender, receiver) = mpsc::channel();
+        let (stop, stopped) = mpsc::channel::<()>();
+        std::thread::Builder::new()
+            .name("audio".into())
+            .spawn(move || match OutputStream::try_default() {
+                Ok((_stream, handle)) => {
+                    if sender.send(Ok(handle)).is_ok() {
+                        // nothing is ever sent, it returns when the sender is dropped
+                        let _ = stopped.recv();
+                    }
+                }
+                Err(e) => {
+                    let _ = sender.send(Err(e));
+                }
+            })?;
+        // the thread going away without a word is an error too
+        let handle = receiver.recv()??;
+
+        let clips = Sound::ALL
+            .iter()
+            .map(|sound| match Decoder::new(Cursor::new(sound.bytes())) {
+                Ok(decoder) => Ok(decoder.buffered()),
+                Err(e) => Err(format!("failed to decode {:?}: {}", sound, e)),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Audio {
+            handle,
+            _stop: stop,
+            clips,
+            volume: 1.0,
+            tracks: Vec::new(),
+            music: None,
+            music_volume: 1.0,
+            ears: [[-Self::EAR_SPACING / 2.0, 0.0, 0.0], [Self::EAR_SPACING / 2.0, 0.0, 0.0]].map(Self::scaled),
+        })
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn set_music_volume(&mut self, volume: f32) {
+        self.music_volume = volume.clamp(0.0, 1.0);
+    }
+
+    // crossfades from whatever's playing to the track, or fades out to silence for None. cheap to
+    // call every frame with the same track
+    pub fn set_music(&mut self, path: Option<&Path>) {
+        if self.music.as_deref() == path {
+            return;
+        }
+        self.music = path.map(Path::to_path_buf);
+        for track in &mut self.tracks {
+            track.current = false;
+        }
+        let Some(path) = path else {
+            return;
+        };
+
+        // switching back before the fade finished picks it up from where it's got to
+        if let Some(i) = self.tracks.iter().position(|track| track.path == path) {
+            let mut track = self.tracks.remove(i);
+            track.current = true;
+            self.tracks.push(track);
+            return;
+        }
+        match self.open_track(path) {
+            Ok(sink) => {
+                info!("Playing {}", path.display());
+                self.tracks.push(Track { path: path.to_path_buf(), sink, level: 0.0, current: true });
+            }
+            Err(e) => warn!("Failed to play {}: {}", path.display(), e),
+        }
+    }
+
+    fn open_track(&self, path: &Path) -> Result<Sink, Box<dyn std::error::Error>> {
+        let source = Decoder::new_looped(BufReader::new(File::open(path)?))?;
+        let sink = Sink::try_new(&self.handle)?;
+        sink.set_volume(0.0);
+        sink.append(source);
+        Ok(sink)
+    }
+
+    // the listener's position and which way is to their right, before placing the emitters
+    pub fn listen(&mut self, position: Point3<f32>, right: Vector3<f32>) {
+        let offset = right * Self::EAR_SPACING / 2.0;
+        self.ears = [position - offset, position + offset].map(|ear| Self::scaled(ear.into()));
+    }
+
+    // starts the sound looping at position, None when it can't be played
+    pub fn emitter(&self, sound: Sound, position: Point3<f32>) -> Option<SpatialSound> {
+        let [left, right] = self.ears;
+        let sink = match SpatialSink::try_new(&self.handle, Self::scaled(position.into()), left, right) {
+            Ok(sink) => sink,
+            Err(e) => {
+                debug!("Failed to play {:?}: {}", sound, e);
+                return None;
+            }
+        };
+        sink.set_volume(self.volume * sound.gain());
+        sink.append(self.clips[sound as usize].clone().repeat_infinite());
+        Some(SpatialSound { sound, sink })
+    }
+
+    // catches the sound up with where it and the listener are now, every frame
+    pub fn place(&self, emitter: &SpatialSound, position: Point3<f32>) {
+        let [left, right] = self.ears;
+        emitter.sink.set_emitter_position(Self::scaled(position.into()));
+        emitter.sink.set_left_ear_position(left);
+        emitter.sink.set_right_ear_position(right);
+        emitter.sink.set_volume(self.volume * emitter.sound.gain());
+    }
+
+    // starts the sound looping silently, None when it can't be played
+    pub fn ambient(&self, sound: Sound) -> Option<AmbientSound> {
+        let sink = match Sink::try_new(&self.handle) {
+            Ok(sink) => sink,
+            Err(e) => {
+                debug!("Failed to play {:?}: {}", sound, e);
+                return None;
+            }
+        };
+        sink.set_volume(0.0);
+        sink.append(self.clips[sound as usize].clone().repeat_infinite());
+        Some(AmbientSound { sound, sink })
+    }
+
+    // level is 0 silent to 1 at the sound's usual volume
+    pub fn set_level(&self, ambient: &AmbientSound, level: f32) {
+        ambient.sink.set_volume(self.volume * ambient.sound.gain() * level);
+    }
+
+    fn scaled(position: [f32; 3]) -> [f32; 3] {
+        position.map(|x| x / Self::FULL_VOLUME_DISTANCE)
+    }
+
+    // moves the fades along, dropping the tracks that have gone quiet
+    pub fn update(&mut self, dt: f32) {
+        let step = dt / Self::CROSSFADE;
+        for track in &mut self.tracks {
+            track.level = if track.current { (track.level + step).min(1.0) } else { (track.level - step).max(0.0) };
+            track.sink.set_volume(track.level * self.music_volume);
+        }
+        self.tracks.retain(|track| track.current || track.level > 0.0);
+    }
+
+    // mixed in with whatever's already playing
+    pub fn play(&self, sound: Sound) {
+        let clip = self.clips[sound as usize].clone().amplify(self.volume * sound.gain());
+        if let Err(e) = self.handle.play_raw(clip.convert_samples()) {
+            debug!("Failed to play {:?}: {}", sound, e);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use cgmath::{InnerSpace, Point3, Vector3, Zero};
+
+use crate::graphics::Instance;
+
+// balls thrown around a walled pen, bouncing off its floor and walls and knocking into each
+// other. the balls are an instanced object's instances, moved in place on the cpu
+pub struct BouncingBalls {
+    velocities: Vec<Vector3<f32>>,
+    radius: f32,
+    min: Point3<f32>,
+    max: Point3<f32>,
+}
+
+impl BouncingBalls {
+    const GRAVITY: f32 = 9.81;
+    // metres per second in any direction, up to
+    const MAX_SPEED: f32 = 20.0;
+    // a long frame is cut short rather than letting balls tunnel through each other
+    const MAX_STEP: f32 = 1.0 / 30.0;
+
+    // scatters the instances over the pen in random sizes, each with a random velocity, the same
+    // every time for a given seed. radius is an unscaled instance's, the pen's floor is at min.y
+    pub fn new(instances: &mut [Instance], radius: f32, min: Point3<f32>, max: Point3<f32>, seed: u32) -> Self {
+        // xorshift, zero would get it stuck so it's nudged away
+        let mut rng = seed.max(1);
+        let mut random = move || {
+            rng ^= rng << 13;
+            rng ^= rng >> 17;
+            rng ^= rng << 5;
+            (rng >> 8) as f32 / (1 << 24) as f32
+        };
+        let velocities = instances
+            .iter_mut()
+            .map(|instance| {
+                let size = 0.4 + 0.6 * random();
+                instance.scale = Vector3::new(size, size, size);
+                let r = radius * size;
+                instance.trans = Vector3::new(
+                    min.x + r + (max.x - min.x - r * 2.0) * random(),
+                    min.y + r + (max.y - min.y - r * 2.0) * random(),
+                    min.z + r + (max.z - min.z - r * 2.0) * random(),
+                );
+                Vector3::new(random() * 2.0 - 1.0, random() * 2.0 - 1.0, random() * 2.0 - 1.0) * Self::MAX_SPEED
+            })
+            .collect();
+        BouncingBalls { velocities, radius, min, max }
+    }
+
+    pub fn update(&mut self, instances: &mut [Instance], dt: f32) {
+        let dt = dt.min(Self::MAX_STEP);
+        for (instance, velocity) in instances.iter_mut().zip(&mut self.velocities) {
+            velocity.y -= Self::GRAVITY * dt;
+            instance.trans += *velocity * dt;
+
+            // the pen is open at the top, gravity brings them back
+            let r = self.radius * instance.scale.x;
+            for axis in 0..3 {
+                if instance.trans[axis] - r < self.min[axis] {
+                    instance.trans[axis] = self.min[axis] + r;
+                    velocity[axis] = velocity[axis].abs();
+                } else if axis != 1 && instance.trans[axis] + r > self.max[axis] {
+                    instance.trans[axis] = self.max[axis] - r;
+                    velocity[axis] = -velocity[axis].abs();
+                }
+            }
+        }
+
+        // every pair is checked, fine for a few dozen balls
+        for i in 0..instances.len() {
+            for j in i + 1..instances.len() {
+                let (ri, rj) = (self.radius * instances[i].scale.x, self.radius * instances[j].scale.x);
+                let offset = instances[j].trans - instances[i].trans;
+                let distance = offset.magnitude();
+                if distance >= ri + rj || distance.is_zero() {
+                    continue;
+                }
+                let normal = offset / distance;
+
+                // pushed apart so they only touch, the heavier one moving less
+                let (mi, mj) = (ri.powi(3), rj.powi(3));
+                let overlap = ri + rj - distance;
+                instances[i].trans -= normal * overlap * mj / (mi + mj);
+                instances[j].trans += normal * overlap * mi / (mi + mj);
+
+                // an elastic collision along the line between their centers, unless they're
+                // already moving apart
+                let approach = (self.velocities[i] - self.velocities[j]).dot(normal);
+                if approach > 0.0 {
+                    let impulse = 2.0 * approach / (mi + mj);
+                    self.velocities[i] -= normal * impulse * mj;
+                    self.velocities[j] += normal * impulse * mi;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use cgmath::{InnerSpace, Matrix, Matrix4, Point3, SquareMatrix, Transform, Vector3};
+use std::ops::Range;
+
+use crate::primitives::Mesh;
+
+// one of the meshes in a batch, its indices are relative to its first vertex
+#[derive(Clone, Debug)]
+pub struct BatchDraw {
+    pub indices: Range<u32>,
+    pub base_vertex: i32,
+}
+
+// meshes that never move and share a texture and material, moved into place once and put in one
+// vertex and index buffer. they're drawn one after another without binding anything in between
+pub struct MeshBatch {
+    mesh: Mesh,
+    draws: Vec<BatchDraw>,
+}
+
+impl MeshBatch {
+    pub fn new() -> Self {
+        MeshBatch {
+            mesh: Mesh { vertices: Vec::new(), indices: Vec::new() },
+            draws: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, mesh: &Mesh, model: Matrix4<f32>) {
+        // normals go through the inverse transpose so they stay square to scaled surfaces
+        let normal_matrix = model.invert().unwrap_or(Matrix4::identity()).transpose();
+        let base_vertex = self.mesh.vertices.len() as i32;
+        self.mesh.vertices.extend(mesh.vertices.iter().map(|vertex| {
+            let mut vertex = *vertex;
+            vertex.position = model.transform_point(Point3::from(vertex.position)).into();
+            let normal = normal_matrix.transform_vector(Vector3::from(vertex.normal));
+            // meshes without normals keep them zero for flat shading
+            if normal.magnitude2() > 0.0 {
+                vertex.normal = normal.normalize().into();
+            }
+            vertex
+        }));
+        let first = self.mesh.indices.len() as u32;
+        self.mesh.indices.extend_from_slice(&mesh.indices);
+        self.draws.push(BatchDraw {
+            indices: first..self.mesh.indices.len() as u32,
+            base_vertex,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.draws.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.draws.is_empty()
+    }
+
+    pub fn finish(self) -> (Mesh, Vec<BatchDraw>) {
+        (self.mesh, self.draws)
+    }
+}
@@ -0,0 +1,186 @@
+use cgmath::{Matrix4, Point3};
+
+use crate::graphics;
+
+// the shapes a billboard can have, drawn by the shader so no textures are needed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sprite {
+    Dot,
+    Light,
+    Marker,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SpriteInstance {
+    position: [f32; 3],
+    size: f32,
+    color: [f32; 4],
+    sprite: u32,
+}
+
+impl SpriteInstance {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] = wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Uint32];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<SpriteInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct BillboardUniform {
+    // the vertex shader turns the quads to face the camera with this
+    view: [[f32; 4]; 4],
+    view_proj: [[f32; 4]; 4],
+}
+
+// immediate mode quads that always face the camera: queue them during the frame, flush once, then draw.
+// they're hidden behind the scene but don't hide anything themselves
+pub struct BillboardRenderer {
+    sprites: Vec<SpriteInstance>,
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    instance_buffer: wgpu::Buffer,
+    // sprites the buffer has room for, it's recreated larger when a frame needs more
+    capacity: usize,
+    instance_count: u32,
+}
+
+impl BillboardRenderer {
+    const INITIAL_CAPACITY: usize = 256;
+
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = graphics::create_templated_shader(device, format, "billboard.wgsl", include_str!("billboard.wgsl"));
+
+        let uniform_buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some("billboard_uniform_buffer"),
+            size: std::mem::size_of::<BillboardUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("billboard_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("billboard_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("billboard_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("billboard_pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[SpriteInstance::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: graphics::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: graphics::SAMPLE_COUNT,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        BillboardRenderer {
+            sprites: Vec::new(),
+            pipeline,
+            uniform_buffer,
+            bind_group,
+            instance_buffer: create_instance_buffer(device, Self::INITIAL_CAPACITY),
+            capacity: Self::INITIAL_CAPACITY,
+            instance_count: 0,
+        }
+    }
+
+    // size is half the width of the quad in world units
+    pub fn draw_sprite(&mut self, position: Point3<f32>, size: f32, sprite: Sprite, color: [f32; 4]) {
+        self.sprites.push(SpriteInstance {
+            position: position.into(),
+            size,
+            color,
+            sprite: sprite as u32,
+        });
+    }
+
+    // uploads everything queued this frame and starts the next one empty
+    pub fn flush(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, view: Matrix4<f32>, view_proj: Matrix4<f32>) {
+        let uniform = BillboardUniform {
+            view: view.into(),
+            view_proj: view_proj.into(),
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+        if self.sprites.len() > self.capacity {
+            self.capacity = self.sprites.len().next_power_of_two();
+            self.instance_buffer = create_instance_buffer(device, self.capacity);
+        }
+        if !self.sprites.is_empty() {
+            queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&self.sprites));
+        }
+        self.instance_count = self.sprites.len() as u32;
+        self.sprites.clear();
+    }
+
+    // after everything opaque, so the scene hides the sprites behind it
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.instance_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        render_pass.draw(0..6, 0..self.instance_count);
+    }
+}
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    graphics::create_buffer(device, &wgpu::BufferDescriptor {
+        label: Some("billboard_instances"),
+        size: (std::mem::size_of::<SpriteInstance>() * capacity) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
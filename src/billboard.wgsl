@@ -0,0 +1,78 @@
+struct BillboardUniform {
+    view: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: BillboardUniform;
+
+struct SpriteInput {
+    // xyz is the center, w half the width of the quad
+    @location(0) position_size: vec4<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) sprite: u32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // -1 to 1 across the quad
+    @location(0) corner: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) @interpolate(flat) sprite: u32,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, sprite: SpriteInput) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, -1.0), vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, 1.0), vec2<f32>(-1.0, 1.0),
+    );
+    let corner = corners[index];
+    // the rows of the view rotation are the camera's right and up in world space
+    let right = vec3<f32>(camera.view[0].x, camera.view[1].x, camera.view[2].x);
+    let up = vec3<f32>(camera.view[0].y, camera.view[1].y, camera.view[2].y);
+    let world_pos = sprite.position_size.xyz + (right * corner.x + up * corner.y) * sprite.position_size.w;
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(world_pos, 1.0);
+    out.corner = corner;
+    out.color = sprite.color;
+    out.sprite = sprite.sprite;
+    return out;
+}
+
+// how much of each sprite covers this point of the quad, the cases are Sprite in billboard.rs
+fn coverage(sprite: u32, p: vec2<f32>) -> f32 {
+    let r = length(p);
+    switch sprite {
+        // a sun with eight rays
+        case 1u: {
+            let core = 1.0 - smoothstep(0.3, 0.35, r);
+            let rays = pow(abs(cos(atan2(p.y, p.x) * 4.0)), 16.0) * (1.0 - smoothstep(0.8, 0.95, r));
+            return max(core, rays * step(0.45, r));
+        }
+        // a ring around a dot
+        case 2u: {
+            let ring = 1.0 - smoothstep(0.1, 0.15, abs(r - 0.75));
+            let center = 1.0 - smoothstep(0.2, 0.25, r);
+            return max(ring, center);
+        }
+        // a soft round blob
+        default: {
+            let falloff = clamp(1.0 - r * r, 0.0, 1.0);
+            return falloff * falloff;
+        }
+    }
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let alpha = in.color.a * coverage(in.sprite, in.corner);
+    if alpha <= 0.0 {
+        discard;
+    }
+
+    var color = in.color.rgb;
+    color = apply_output_gamma(color);
+    return vec4<f32>(color, alpha);
+}
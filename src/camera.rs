@@ -1,11 +1,11 @@
-use cgmath::{InnerSpace, Point3, Vector3, Matrix4, Vector2};
+use cgmath::{Deg, InnerSpace, Point3, Quaternion, Rotation, Rotation3, Vector3, Matrix4, Vector2};
 
+use crate::collision::Aabb;
 use crate::input;
-use crate::app::INSTANCED_ROWS;
-use crate::app::INSTANCED_COLS;
-use crate::app::INSTANCE_SPACING;
+use crate::input::Action;
+use crate::scene::{CameraPose, WorldBounds};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Camera {
     pub loc: Point3<f32>,
     pub vel: Vector3<f32>,
@@ -13,10 +13,67 @@ pub struct Camera {
     forward: Vector3<f32>,
     up: Vector3<f32>,
     right: Vector3<f32>,
-    yaw: f32,
-    pitch: f32,
+    // rotates the camera's local axes (forward +x, up +y, right +z) into world space
+    orientation: Quaternion<f32>,
+    // horizontal walking direction, kept from the last frame it was well defined
+    heading: Vector3<f32>,
+    look_mode: LookMode,
+    movement_mode: MovementMode,
+    // standing on something in walk mode, so a jump is allowed
+    grounded: bool,
     aspect: f32,
     speed: f32,
+    bounds: WorldBounds,
+    fovy: f32,
+    // fovy eases towards this, so wheel zooming doesn't jump in steps
+    target_fovy: f32,
+    // degrees per mouse count
+    sensitivity: f32,
+    // exponential smoothing time constants in seconds, 0 applies input immediately
+    look_smoothing: f32,
+    movement_smoothing: f32,
+    // look input that smoothing hasn't applied yet, in degrees of (yaw, pitch)
+    pending_look: (f32, f32),
+    // the velocity actually used to move, trails vel when movement smoothing is on
+    smoothed_vel: Vector3<f32>,
+    projection: Projection,
+    // world units visible vertically in orthographic mode, at the default fov
+    ortho_extent: f32,
+    // widening the view and shaking it while sprinting
+    sprint_effects: bool,
+    // seconds the shake has run for, it only advances while sprinting
+    shake_time: f32,
+    // how strongly walking bobs the view, 0 turns it off
+    head_bob: f32,
+    // radians through the walk cycle, a step every pi
+    bob_phase: f32,
+    // 0 standing still to 1 walking at full speed, eased in and out
+    bob_weight: f32,
+    // a foot came down this frame
+    stepped: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LookMode {
+    // yaw around the world up axis, pitch limited to straight up/down, no roll
+    Fps,
+    // every rotation is relative to the camera itself, so it can roll and loop over
+    Free,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovementMode {
+    Fly,
+    // gravity, jumping and collisions, stepping up anything low enough
+    Walk,
+    // flying straight through everything, only the world's edges stop it
+    Noclip,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Projection {
+    Perspective,
+    Orthographic,
 }
 
 pub const GL_TO_WGPU: Matrix4<f32> = Matrix4::new(
@@ -26,6 +83,19 @@ pub const GL_TO_WGPU: Matrix4<f32> = Matrix4::new(
     0.0, 0.0, 0.5, 1.0,
 );
 
+// orthographic straight down on center with -z at the top, extent world units across the shorter
+// side and everything within height above or below center in view
+pub fn top_down_view_proj(center: Point3<f32>, extent: f32, height: f32, aspect: f32) -> Matrix4<f32> {
+    let eye = Point3::new(center.x, center.y + height, center.z);
+    let view = Matrix4::look_at_rh(eye, center, -Vector3::unit_z());
+    let (half_w, half_h) = if aspect >= 1.0 {
+        (extent / 2.0 * aspect, extent / 2.0)
+    } else {
+        (extent / 2.0, extent / 2.0 / aspect)
+    };
+    GL_TO_WGPU * cgmath::ortho(-half_w, half_w, -half_h, half_h, 0.0, height * 2.0) * view
+}
+
 impl Camera {
     const WORLD_UP: Vector3<f32> = Vector3 {
         x: 0.0,
@@ -37,17 +107,46 @@ impl Camera {
     const WALK_SPEED: f32 = 5.0;
     const DEACCELERATION: f32 = 5.0;
     const ACCELERATION: f32 = 5.0;
-    const BORDER_SPACE: f32 = 150.0;
-    const MAX_POS: Vector3<f32> = Vector3 {
-        x: INSTANCED_ROWS as f32 * INSTANCE_SPACING + Self::BORDER_SPACE,
-        y: 100.0,
-        z: INSTANCED_COLS as f32 * INSTANCE_SPACING + Self::BORDER_SPACE
-    };
-    const MIN_POS: Vector3<f32> = Vector3 { x: -Self::BORDER_SPACE, y: -Self::BORDER_SPACE, z: -Self::BORDER_SPACE };
-    const FOVY: f32 = 90.0;
+    // how close to a wrapping edge the camera has to be before the other side is drawn across the seam
+    const SEAM_MARGIN: f32 = 100.0;
+    pub const DEFAULT_FOVY: f32 = 90.0;
+    const MIN_FOVY: f32 = 10.0;
+    const MAX_FOVY: f32 = 120.0;
+    // degrees of fov per wheel notch
+    const ZOOM_STEP: f32 = 5.0;
+    // how quickly fovy catches up to the target, higher is snappier
+    const ZOOM_SMOOTHING: f32 = 12.0;
     const ZNEAR: f32 = 0.1;
     const ZFAR: f32 = 1000.0;
-    const SENS: f32 = 20.0;
+    pub const DEFAULT_SENS: f32 = 0.15;
+    pub const DEFAULT_ORTHO_EXTENT: f32 = 200.0;
+    const GRAVITY: f32 = 30.0;
+    const JUMP_SPEED: f32 = 12.0;
+    const MAX_FALL_SPEED: f32 = 50.0;
+    // the walking body is a box around the camera, from the feet to just above the eye
+    const EYE_HEIGHT: f32 = 1.6;
+    const HEAD_HEIGHT: f32 = 0.2;
+    const BODY_HALF_WIDTH: f32 = 0.3;
+    // flying, the camera is a small box around the eye
+    const FLY_HALF_SIZE: f32 = 0.25;
+    // how many surfaces a move can slide along before the rest of it is dropped
+    const MAX_SLIDES: usize = 3;
+    // the highest ledge walking climbs without a jump
+    const STEP_HEIGHT: f32 = 0.5;
+    // degrees per second while holding a roll key
+    const ROLL_SPEED: f32 = 90.0;
+    // degrees the fov widens by at full sprint
+    const SPRINT_FOV_KICK: f32 = 10.0;
+    // at full sprint, how far the image moves in normalized device coordinates and how fast
+    const SHAKE_AMOUNT: f32 = 0.004;
+    const SHAKE_SPEED: f32 = 14.0;
+    // at full strength, how far the view rises and falls with each step and sways between feet
+    const BOB_HEIGHT: f32 = 0.06;
+    const BOB_SWAY: f32 = 0.04;
+    // world units walked per step
+    const BOB_STRIDE: f32 = 1.4;
+    // seconds for the bob to fade in and out as walking starts and stops
+    const BOB_SMOOTHING: f32 = 0.125;
 
     pub fn new(
         loc: Point3<f32>,
@@ -62,80 +161,337 @@ impl Camera {
             forward: Vector3::new(0.0, 0.0, 0.0),
             up: Vector3::new(0.0, 0.0, 0.0),
             right: Vector3::new(0.0, 0.0, 0.0),
-            yaw,
-            pitch,
+            orientation: orientation(yaw, pitch, 0.0),
+            heading: Vector3::new(1.0, 0.0, 0.0),
+            look_mode: LookMode::Fps,
+            movement_mode: MovementMode::Fly,
+            grounded: false,
             aspect,
             speed: Self::WALK_SPEED,
+            bounds: WorldBounds::default(),
+            fovy: Self::DEFAULT_FOVY,
+            target_fovy: Self::DEFAULT_FOVY,
+            sensitivity: Self::DEFAULT_SENS,
+            look_smoothing: 0.0,
+            movement_smoothing: 0.0,
+            pending_look: (0.0, 0.0),
+            smoothed_vel: Vector3::new(0.0, 0.0, 0.0),
+            projection: Projection::Perspective,
+            ortho_extent: Self::DEFAULT_ORTHO_EXTENT,
+            sprint_effects: true,
+            shake_time: 0.0,
+            head_bob: 1.0,
+            bob_phase: 0.0,
+            bob_weight: 0.0,
+            stepped: false,
         };
         cam.calc_vecs();
         cam
     }
 
+    // bobbed while walking, the camera itself stays where it is
+    pub fn build_view(&self) -> Matrix4<f32> {
+        let bob = self.head_bob * self.bob_weight;
+        let eye = self.loc
+            - self.up * ((self.bob_phase * 2.0).cos() * Self::BOB_HEIGHT * bob)
+            + self.right * (self.bob_phase.cos() * Self::BOB_SWAY * bob);
+        Matrix4::look_at_rh(eye, eye + self.forward, self.up)
+    }
+
     pub fn build_view_proj(&self) -> Matrix4<f32> {
-        let view = Matrix4::look_at_rh(self.loc, self.loc + self.forward, self.up);
-        let proj = cgmath::perspective(cgmath::Deg(Self::FOVY), self.aspect, Self::ZNEAR, Self::ZFAR);
-        GL_TO_WGPU * proj * view
+        let view = self.build_view();
+        let sprint = self.sprint_amount();
+        let fovy = self.fovy + Self::SPRINT_FOV_KICK * sprint;
+        let proj = match self.projection {
+            Projection::Perspective => {
+                cgmath::perspective(cgmath::Deg(fovy), self.aspect, Self::ZNEAR, Self::ZFAR)
+            }
+            Projection::Orthographic => {
+                // scaled by the fov so wheel zooming still works
+                let half_h = self.ortho_extent * (fovy / Self::DEFAULT_FOVY) / 2.0;
+                let half_w = half_h * self.aspect;
+                cgmath::ortho(-half_w, half_w, -half_h, half_h, Self::ZNEAR, Self::ZFAR)
+            }
+        };
+        // the shake moves the whole image after projection, so near and far things move together
+        // like a hand held camera
+        let t = self.shake_time * Self::SHAKE_SPEED;
+        let shake = Vector3::new(
+            (t * 1.3).sin() + (t * 2.9).sin() * 0.5,
+            (t * 1.7).cos() + (t * 3.7).sin() * 0.5,
+            0.0,
+        ) * Self::SHAKE_AMOUNT * sprint;
+        Matrix4::from_translation(shake) * GL_TO_WGPU * proj * view
+    }
+
+    // 0 walking up to 1 at full sprint, eased at both ends. speed ramps up and down linearly
+    fn sprint_amount(&self) -> f32 {
+        if !self.sprint_effects {
+            return 0.0;
+        }
+        let t = (self.speed - Self::WALK_SPEED) / (Self::SPRINT_SPEED - Self::WALK_SPEED);
+        t * t * (3.0 - 2.0 * t)
     }
 
-    pub fn update_pos(&mut self, dt: f32, input: &input::InputState) {
+    pub fn update_pos(&mut self, dt: f32, input: &input::InputState, colliders: &[Aabb]) {
+        let colliders = if self.movement_mode == MovementMode::Noclip { &[] } else { colliders };
         self.update_acc(input);
         self.update_vel(dt);
         self.update_speed(dt, input);
+        if self.movement_mode == MovementMode::Walk {
+            self.update_fall(dt, input);
+        }
+        let start = self.loc;
         self.update_loc(dt);
+        let displacement = self.loc - start;
+        self.loc = start;
+        self.sweep(displacement, colliders);
+
+        let (min, max) = (self.bounds.min, self.bounds.max);
+        if self.bounds.wrap {
+            self.loc.x = wrap(self.loc.x, min[0], max[0]);
+            self.loc.z = wrap(self.loc.z, min[2], max[2]);
+        } else {
+            self.stop_at(0, min[0], max[0]);
+            self.stop_at(2, min[2], max[2]);
+        }
+
+        // whatever moved into the camera since last frame
+        self.collide(colliders);
+        if self.movement_mode == MovementMode::Walk && self.loc.y < min[1] {
+            self.grounded = true;
+        }
+        self.stop_at(1, min[1], max[1]);
+        self.update_bob(dt);
+    }
+
+    // steps along with how fast the camera walks, fading out in the air and flying
+    fn update_bob(&mut self, dt: f32) {
+        use std::f32::consts::{PI, TAU};
+        let walking = self.movement_mode == MovementMode::Walk && self.grounded;
+        let speed = if walking { Vector2::new(self.smoothed_vel.x, self.smoothed_vel.z).magnitude() * self.speed } else { 0.0 };
+        let walked = speed * dt;
+        let target = (speed / Self::SPRINT_SPEED).min(1.0);
+        self.bob_weight += (target - self.bob_weight) * smoothing_factor(Self::BOB_SMOOTHING, dt);
+        let phase = self.bob_phase + walked / Self::BOB_STRIDE * PI;
+        // a step every half cycle, when the view is at its lowest and furthest to one side
+        self.stepped = walking && (phase / PI).floor() > (self.bob_phase / PI).floor();
+        self.bob_phase = phase % TAU;
+    }
+
+    pub fn toggle_movement_mode(&mut self) {
+        self.movement_mode = match self.movement_mode {
+            MovementMode::Fly => MovementMode::Walk,
+            MovementMode::Walk => MovementMode::Noclip,
+            MovementMode::Noclip => MovementMode::Fly,
+        };
+        self.vel.y = 0.0;
+        self.grounded = false;
+    }
+
+    pub fn movement_mode(&self) -> MovementMode {
+        self.movement_mode
+    }
+
+    fn update_fall(&mut self, dt: f32, input: &input::InputState) {
+        self.vel.y = (self.vel.y - Self::GRAVITY * dt).max(-Self::MAX_FALL_SPEED);
+        if self.grounded && input.just_pressed(Action::Jump) {
+            self.vel.y = Self::JUMP_SPEED;
+        }
+        self.grounded = false;
+    }
+
+    // the box the camera collides as, from the feet to just above the eye when walking
+    fn body(&self) -> Aabb {
+        match self.movement_mode {
+            MovementMode::Walk => {
+                let half_extents = Vector3::new(
+                    Self::BODY_HALF_WIDTH,
+                    (Self::EYE_HEIGHT + Self::HEAD_HEIGHT) / 2.0,
+                    Self::BODY_HALF_WIDTH,
+                );
+                // from the eye down to the middle of the body
+                let center_offset = Vector3::new(0.0, (Self::HEAD_HEIGHT - Self::EYE_HEIGHT) / 2.0, 0.0);
+                Aabb::around(self.loc + center_offset, half_extents)
+            }
+            MovementMode::Fly | MovementMode::Noclip => {
+                Aabb::around(self.loc, Vector3::new(1.0, 1.0, 1.0) * Self::FLY_HALF_SIZE)
+            }
+        }
+    }
+
+    // moves the body as far as it goes before hitting something, then slides the rest of the way
+    // along what it hit. fast enough moves would otherwise pass straight through thin colliders
+    fn sweep(&mut self, mut displacement: Vector3<f32>, colliders: &[Aabb]) {
+        for _ in 0..Self::MAX_SLIDES {
+            let Some((t, normal)) = self.first_hit(displacement, colliders) else {
+                self.loc += displacement;
+                return;
+            };
+            self.loc += displacement * t;
+            displacement *= 1.0 - t;
+            // walking into the side of something low, the rest of the move carries on on top of it
+            if normal.y == 0.0 && self.grounded && self.movement_mode == MovementMode::Walk && self.step_up(displacement, colliders) {
+                return;
+            }
+            // what's left of the move, without the part going into the surface
+            displacement -= normal * displacement.dot(normal);
+            self.stop_along(normal);
+            if displacement.magnitude2() == 0.0 {
+                return;
+            }
+        }
+    }
+
+    // lifts the body by the step height, moves it across and sets it back down on what it stepped
+    // onto. leaves it where it was and returns false when there's no room above or nothing to
+    // stand on, like a wall taller than a step
+    fn step_up(&mut self, displacement: Vector3<f32>, colliders: &[Aabb]) -> bool {
+        let up = Vector3::unit_y() * Self::STEP_HEIGHT;
+        let across = Vector3::new(displacement.x, 0.0, displacement.z);
+        let start = self.loc;
+        for step in [up, across] {
+            if self.first_hit(step, colliders).is_some() {
+                self.loc = start;
+                return false;
+            }
+            self.loc += step;
+        }
+        match self.first_hit(-up, colliders) {
+            Some((t, _)) => {
+                self.loc -= up * t;
+                true
+            }
+            // stepped off into the air, walking on would have been right
+            None => {
+                self.loc = start;
+                false
+            }
+        }
+    }
 
-        if self.loc.x > Self::MAX_POS.x {
-            self.loc.x = Self::MAX_POS.x;
-            self.vel.x = -self.vel.x;
+    // the first collider the body hits moving by displacement, how far along the move and the
+    // surface's normal
+    fn first_hit(&self, displacement: Vector3<f32>, colliders: &[Aabb]) -> Option<(f32, Vector3<f32>)> {
+        let body = self.body();
+        colliders
+            .iter()
+            .filter_map(|collider| body.sweep(displacement, collider))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+
+    // pushes the body out of everything it overlaps, stopping movement into what it hit
+    fn collide(&mut self, colliders: &[Aabb]) {
+        for collider in colliders {
+            let Some(push) = self.body().push_out(collider) else {
+                continue;
+            };
+            self.loc += push;
+            self.stop_along(push);
         }
-        if self.loc.y > Self::MAX_POS.y {
-            self.loc.y = Self::MAX_POS.y;
-            self.vel.y = -self.vel.y;
+    }
+
+    // after hitting a surface facing the given way
+    fn stop_along(&mut self, normal: Vector3<f32>) {
+        // both velocities, or smoothing would keep easing the body back into the collider
+        for vel in [&mut self.vel, &mut self.smoothed_vel] {
+            if normal.x != 0.0 {
+                vel.x = 0.0;
+            }
+            if normal.z != 0.0 {
+                vel.z = 0.0;
+            }
+            if normal.y > 0.0 {
+                vel.y = vel.y.max(0.0);
+            } else if normal.y < 0.0 {
+                vel.y = vel.y.min(0.0);
+            }
         }
-        if self.loc.z > Self::MAX_POS.z {
-            self.loc.z = Self::MAX_POS.z;
-            self.vel.z = -self.vel.z;
+        if normal.y > 0.0 {
+            self.grounded = true;
+        }
+    }
+
+    // keeps the camera between the world's edges on one axis, stopping it against them
+    fn stop_at(&mut self, axis: usize, min: f32, max: f32) {
+        let loc = self.loc[axis];
+        if loc < min || loc > max {
+            self.loc[axis] = loc.clamp(min, max);
+            self.vel[axis] = 0.0;
+            self.smoothed_vel[axis] = 0.0;
+        }
+    }
+
+    pub fn set_bounds(&mut self, bounds: WorldBounds) {
+        self.bounds = bounds;
+    }
+
+    // world space offsets the scene has to be drawn at so objects on the far side of a wrapping
+    // edge show up across the seam. always starts with the untranslated scene
+    pub fn seam_offsets(&self) -> Vec<Vector3<f32>> {
+        if !self.bounds.wrap {
+            return vec![Vector3::new(0.0, 0.0, 0.0)];
         }
-        if self.loc.x < Self::MIN_POS.x {
-            self.loc.x = Self::MIN_POS.x;
-            self.vel.x = -self.vel.x;
+
+        let (min, max) = (self.bounds.min, self.bounds.max);
+        let seam_offset = |loc: f32, min: f32, max: f32| {
+            if loc > max - Self::SEAM_MARGIN {
+                Some(max - min)
+            } else if loc < min + Self::SEAM_MARGIN {
+                Some(min - max)
+            } else {
+                None
+            }
+        };
+        let x = seam_offset(self.loc.x, min[0], max[0]);
+        let z = seam_offset(self.loc.z, min[2], max[2]);
+
+        let mut offsets = vec![Vector3::new(0.0, 0.0, 0.0)];
+        if let Some(x) = x {
+            offsets.push(Vector3::new(x, 0.0, 0.0));
         }
-        if self.loc.y < Self::MIN_POS.y {
-            self.loc.y = Self::MIN_POS.y;
-            self.vel.y = -self.vel.y;
+        if let Some(z) = z {
+            offsets.push(Vector3::new(0.0, 0.0, z));
         }
-        if self.loc.z < Self::MIN_POS.z {
-            self.loc.z = Self::MIN_POS.z;
-            self.vel.z = -self.vel.z;
+        if let (Some(x), Some(z)) = (x, z) {
+            offsets.push(Vector3::new(x, 0.0, z));
         }
+        offsets
     }
 
     fn update_loc(&mut self, dt: f32) {
+        let t = smoothing_factor(self.movement_smoothing, dt);
+        self.smoothed_vel += (self.vel - self.smoothed_vel) * t;
+
         let s = self.speed;
-        let v = &self.vel;
+        let v = &self.smoothed_vel;
 
         self.loc.x += s * v.x * dt;
-        self.loc.y += s * v.y * dt;
         self.loc.z += s * v.z * dt;
+        // sprinting shouldn't make jumps and falls faster
+        if self.movement_mode == MovementMode::Walk {
+            self.loc.y += v.y * dt;
+        } else {
+            self.loc.y += s * v.y * dt;
+        }
     }
 
     fn update_speed(&mut self, dt: f32, input: &input::InputState) {
-        if input.ctrl_pressed && input.movement_key_pressed() {
+        if input.pressed(Action::Sprint) && input.movement_key_pressed() {
             self.speed += dt * 5.0;
         } else {
             self.speed -= dt * 5.0;
         }
 
-        if self.speed > Self::SPRINT_SPEED {
-            self.speed = Self::SPRINT_SPEED;
-        }
-        if self.speed < Self::WALK_SPEED {
-            self.speed = Self::WALK_SPEED;
+        self.speed = self.speed.clamp(Self::WALK_SPEED, Self::SPRINT_SPEED);
+        if self.speed > Self::WALK_SPEED {
+            self.shake_time += dt;
         }
     }
 
     fn update_vel(&mut self, dt: f32) {
-        let forward = Vector3::new(self.forward.x, 0.0, self.forward.z).normalize();
-        let right = Vector3::new(self.right.x, 0.0, self.right.z).normalize();
+        let forward = self.heading;
+        let right = forward.cross(Self::WORLD_UP);
 
         self.vel.x += self.acc.x * forward.x * dt;
         self.vel.z += self.acc.x * forward.z * dt;
@@ -182,8 +538,8 @@ impl Camera {
             self.vel.z = decreased.y;
         }
 
-        // deaccelerate y
-        if self.acc.y == 0.0 {
+        // deaccelerate y, gravity handles it while walking
+        if self.acc.y == 0.0 && self.movement_mode != MovementMode::Walk {
             step(&mut self.vel.y, 0.0, amp);
         }
     }
@@ -191,63 +547,201 @@ impl Camera {
     fn update_acc(&mut self, input: &input::InputState) {
         self.acc = Vector3::new(0.0, 0.0, 0.0);
         let acc = Self::ACCELERATION + Self::DEACCELERATION;
-        if input.forward_pressed {
-            self.acc.x += acc;
+        let (forward, right) = input.move_axes();
+        self.acc.x += acc * forward;
+        self.acc.z += acc * right;
+        if self.movement_mode == MovementMode::Walk {
+            return;
         }
-        if input.backward_pressed {
-            self.acc.x -= acc;
-        }
-        if input.right_pressed {
-            self.acc.z += acc;
-        }
-        if input.left_pressed {
-            self.acc.z -= acc;
-        }
-        if input.space_pressed {
+        if input.pressed(Action::Jump) {
             self.acc.y += acc;
         }
-        if input.shift_pressed {
+        if input.pressed(Action::Crouch) {
             self.acc.y -= acc;
         }
     }
 
-    pub fn update_look(&mut self, look: (f32, f32), dt: f32) {
-        self.yaw += Self::SENS * look.0 * dt;
-        self.pitch += Self::SENS * -look.1 * dt;
+    // look is the mouse motion accumulated over this frame, already a distance so it isn't
+    // scaled by dt. roll is -1..1 from the roll keys
+    pub fn update_look(&mut self, look: (f32, f32), roll: f32, dt: f32) {
+        self.pending_look.0 += self.sensitivity * look.0;
+        self.pending_look.1 += self.sensitivity * -look.1;
+        let t = smoothing_factor(self.look_smoothing, dt);
+        let yaw = self.pending_look.0 * t;
+        let pitch = self.pending_look.1 * t;
+        self.pending_look.0 -= yaw;
+        self.pending_look.1 -= pitch;
 
-        if self.yaw > 360.0 {
-            self.yaw = 0.0;
-        }
-        if self.yaw < 0.0 {
-            self.yaw = 360.0;
-        }
-        if self.pitch > 89.99 {
-            self.pitch = 89.99;
-        }
-        if self.pitch < -89.99 {
-            self.pitch = -89.99;
+        match self.look_mode {
+            LookMode::Fps => {
+                let pose = self.pose();
+                let pitch = (pose.pitch + pitch).clamp(-90.0, 90.0);
+                self.orientation = orientation(pose.yaw + yaw, pitch, 0.0);
+            }
+            LookMode::Free => {
+                let roll = Self::ROLL_SPEED * roll * dt;
+                self.orientation = self.orientation
+                    * Quaternion::from_angle_y(Deg(-yaw))
+                    * Quaternion::from_angle_z(Deg(pitch))
+                    * Quaternion::from_angle_x(Deg(roll));
+            }
         }
+        // keep floating point drift from slowly scaling the view
+        self.orientation = self.orientation.normalize();
 
         self.calc_vecs();
     }
 
+    pub fn toggle_look_mode(&mut self) {
+        self.look_mode = match self.look_mode {
+            LookMode::Fps => LookMode::Free,
+            LookMode::Free => {
+                // level the horizon again
+                let pose = self.pose();
+                self.orientation = orientation(pose.yaw, pose.pitch, 0.0);
+                self.calc_vecs();
+                LookMode::Fps
+            }
+        };
+    }
+
+    pub fn look_mode(&self) -> LookMode {
+        self.look_mode
+    }
+
+    pub fn set_pose(&mut self, pose: &CameraPose) {
+        self.loc = pose.position.into();
+        self.vel = Vector3::new(0.0, 0.0, 0.0);
+        self.smoothed_vel = self.vel;
+        self.pending_look = (0.0, 0.0);
+        let roll = if self.look_mode == LookMode::Fps { 0.0 } else { pose.roll };
+        self.orientation = orientation(pose.yaw, pose.pitch, roll);
+        self.calc_vecs();
+    }
+
+    // the orientation as euler angles in degrees, yaw in 0..360
+    pub fn pose(&self) -> CameraPose {
+        let pitch = self.forward.y.clamp(-1.0, 1.0).asin();
+        // straight up or down the forward vector says nothing about yaw, the heading still does
+        let level_forward = if self.forward.y.abs() > 0.9999 { self.heading } else { self.forward };
+        let yaw = level_forward.z.atan2(level_forward.x);
+
+        // roll is how far up has turned away from where it would be without any roll
+        let level_right = level_forward.cross(Self::WORLD_UP).normalize();
+        let level_up = level_right.cross(self.forward).normalize();
+        let roll = self.up.dot(level_right).atan2(self.up.dot(level_up));
+
+        CameraPose {
+            position: self.loc.into(),
+            yaw: yaw.to_degrees().rem_euclid(360.0),
+            pitch: pitch.to_degrees(),
+            roll: roll.to_degrees(),
+        }
+    }
+
+    pub fn set_fovy(&mut self, fovy: f32) {
+        self.fovy = fovy.clamp(Self::MIN_FOVY, Self::MAX_FOVY);
+        self.target_fovy = self.fovy;
+    }
+
+    // positive notches (wheel up) zoom in
+    pub fn zoom(&mut self, notches: f32) {
+        self.target_fovy = (self.target_fovy - notches * Self::ZOOM_STEP).clamp(Self::MIN_FOVY, Self::MAX_FOVY);
+    }
+
+    pub fn update_zoom(&mut self, dt: f32) {
+        let t = 1.0 - (-Self::ZOOM_SMOOTHING * dt).exp();
+        self.fovy += (self.target_fovy - self.fovy) * t;
+    }
+
+    pub fn toggle_projection(&mut self) {
+        self.projection = match self.projection {
+            Projection::Perspective => Projection::Orthographic,
+            Projection::Orthographic => Projection::Perspective,
+        };
+    }
+
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    pub fn set_ortho_extent(&mut self, extent: f32) {
+        self.ortho_extent = extent;
+    }
+
+    pub fn set_sensitivity(&mut self, sensitivity: f32) {
+        self.sensitivity = sensitivity;
+    }
+
+    pub fn set_smoothing(&mut self, look: f32, movement: f32) {
+        self.look_smoothing = look;
+        self.movement_smoothing = movement;
+    }
+
+    pub fn forward(&self) -> Vector3<f32> {
+        self.forward
+    }
+
+    // world units per second it's actually moving at, the same scaling update_loc does
+    pub fn velocity(&self) -> Vector3<f32> {
+        let v = self.smoothed_vel * self.speed;
+        let y = if self.movement_mode == MovementMode::Walk { self.smoothed_vel.y } else { v.y };
+        Vector3::new(v.x, y, v.z)
+    }
+
+    // walking put a foot down in the last update, for footsteps
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
+    pub fn stepped(&self) -> bool {
+        self.stepped
+    }
+
+    pub fn set_head_bob(&mut self, head_bob: f32) {
+        self.head_bob = head_bob;
+    }
+
+    pub fn set_sprint_effects(&mut self, on: bool) {
+        self.sprint_effects = on;
+    }
+
     pub fn set_aspect(&mut self, aspect: f32) {
         self.aspect = aspect;
     }
 
     fn calc_vecs(&mut self) {
-        let forward = Vector3 {
-            x: self.yaw.to_radians().cos() * self.pitch.to_radians().cos(),
-            y: self.pitch.to_radians().sin(),
-            z: self.yaw.to_radians().sin() * self.pitch.to_radians().cos(),
-        };
+        self.forward = self.orientation.rotate_vector(Vector3::unit_x()).normalize();
+        self.up = self.orientation.rotate_vector(Vector3::unit_y()).normalize();
+        self.right = self.orientation.rotate_vector(Vector3::unit_z()).normalize();
 
-        self.forward = forward.normalize();
-        self.right = forward.cross(Camera::WORLD_UP).normalize();
-        self.up = self.right.cross(forward).normalize();
+        // blending in up keeps the heading defined when looking straight up or down
+        let heading = self.forward - self.up * self.forward.y;
+        let heading = Vector3::new(heading.x, 0.0, heading.z);
+        if heading.magnitude2() > 1e-6 {
+            self.heading = heading.normalize();
+        }
     }
 }
 
+// yaw turns from +x towards +z, pitch tilts up from the horizon, roll turns clockwise around forward
+fn orientation(yaw: f32, pitch: f32, roll: f32) -> Quaternion<f32> {
+    Quaternion::from_angle_y(Deg(-yaw))
+        * Quaternion::from_angle_z(Deg(pitch))
+        * Quaternion::from_angle_x(Deg(roll))
+}
+
+// how far to move towards a target this frame so that the remaining distance decays with the
+// given time constant, the same amount per second whatever the frame rate
+fn smoothing_factor(time_constant: f32, dt: f32) -> f32 {
+    if time_constant <= 0.0 {
+        1.0
+    } else {
+        1.0 - (-dt / time_constant).exp()
+    }
+}
+
+fn wrap(loc: f32, min: f32, max: f32) -> f32 {
+    min + (loc - min).rem_euclid(max - min)
+}
+
 fn step(x: &mut f32, to: f32, amp: f32) {
     if *x < to {
         *x += amp;
@@ -0,0 +1,291 @@
+use log::{debug, info};
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::graphics;
+
+pub enum CaptureOutput {
+    // numbered frames inside a directory
+    Sequence(PathBuf),
+    // every frame overwrites the same file
+    File(PathBuf),
+    // replaces what's on the clipboard, for pasting a frame somewhere without saving it first
+    #[cfg(feature = "clipboard")]
+    Clipboard,
+}
+
+pub struct FrameCapture {
+    output: CaptureOutput,
+    frame: u32,
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    format: wgpu::TextureFormat,
+}
+
+impl FrameCapture {
+    const BYTES_PER_PIXEL: u32 = 4;
+
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        output: CaptureOutput,
+    ) -> Result<Self, AppError> {
+        // anything else, like a float or 10 bit surface, doesn't have 4 bytes to a pixel in the
+        // order the images are written in
+        if !matches!(
+            config.format,
+            wgpu::TextureFormat::Rgba8Unorm
+                | wgpu::TextureFormat::Rgba8UnormSrgb
+                | wgpu::TextureFormat::Bgra8Unorm
+                | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            return Err(AppError::CaptureFormat(config.format));
+        }
+        let dir = match &output {
+            CaptureOutput::Sequence(dir) => Some(dir.as_path()),
+            CaptureOutput::File(path) => Some(path.parent().unwrap_or_else(|| Path::new("."))),
+            #[cfg(feature = "clipboard")]
+            CaptureOutput::Clipboard => None,
+        };
+        if let Some(dir) = dir {
+            std::fs::create_dir_all(dir).map_err(|e| AppError::Capture(dir.to_path_buf(), e.into()))?;
+            info!("Capturing frames to {}", dir.display());
+        }
+
+        let (buffer, padded_bytes_per_row) = create_readback_buffer(device, config.width, config.height);
+        Ok(FrameCapture {
+            output,
+            frame: 0,
+            buffer,
+            width: config.width,
+            height: config.height,
+            padded_bytes_per_row,
+            format: config.format,
+        })
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        let (buffer, padded_bytes_per_row) = create_readback_buffer(device, config.width, config.height);
+        self.buffer = buffer;
+        self.padded_bytes_per_row = padded_bytes_per_row;
+        self.width = config.width;
+        self.height = config.height;
+        self.format = config.format;
+    }
+
+    // records a copy of the presented texture into the readback buffer
+    pub fn copy_frame(&self, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture) {
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(self.padded_bytes_per_row),
+                    rows_per_image: std::num::NonZeroU32::new(self.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    // must be called after the encoder containing copy_frame has been submitted
+    pub fn save_frame(&mut self, device: &wgpu::Device) -> Result<(), AppError> {
+        let slice = self.buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| ());
+        device.poll(wgpu::Maintain::Wait);
+
+        let unpadded_bytes_per_row = (self.width * Self::BYTES_PER_PIXEL) as usize;
+        let mut pixels = Vec::with_capacity(unpadded_bytes_per_row * self.height as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..unpadded_bytes_per_row]);
+            }
+        }
+        self.buffer.unmap();
+
+        if let wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb = self.format {
+            for pixel in pixels.chunks_mut(Self::BYTES_PER_PIXEL as usize) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        let path = match &self.output {
+            CaptureOutput::Sequence(dir) => dir.join(format!("frame_{:05}.png", self.frame)),
+            CaptureOutput::File(path) => path.clone(),
+            #[cfg(feature = "clipboard")]
+            CaptureOutput::Clipboard => {
+                // there not being a clipboard, like without a display server, isn't worth stopping for
+                match copy_to_clipboard(pixels, self.width, self.height) {
+                    Ok(()) => info!("Copied the frame to the clipboard"),
+                    Err(e) => log::warn!("Failed to copy the frame to the clipboard: {}", e),
+                }
+                self.frame += 1;
+                return Ok(());
+            }
+        };
+        image::save_buffer(&path, &pixels, self.width, self.height, image::ColorType::Rgba8)
+            .map_err(|e| AppError::Capture(path.clone(), e))?;
+        debug!("Captured frame {}", path.display());
+        self.frame += 1;
+        Ok(())
+    }
+
+    pub fn frames_captured(&self) -> u32 {
+        self.frame
+    }
+}
+
+// what the frame's passes draw into while it's recorded, for surfaces that can't be copied from
+// (see graphics::surface_usage). the capture copies this instead, and it's drawn onto the surface
+// after
+pub struct CaptureTarget {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl CaptureTarget {
+    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader at capture.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("capture.wgsl").into()),
+        });
+        let texture = graphics::create_texture(device, &wgpu::TextureDescriptor {
+            label: Some("capture_target"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("capture_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("capture_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            }],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("capture_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("capture_pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        CaptureTarget {
+            texture,
+            view,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    // draws the recorded frame onto the surface's, once it's been copied
+    pub fn present(&self, encoder: &mut wgpu::CommandEncoder, frame: &wgpu::TextureView) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("capture_present_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: frame,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(feature = "clipboard")]
+fn copy_to_clipboard(pixels: Vec<u8>, width: u32, height: u32) -> Result<(), arboard::Error> {
+    let image = arboard::ImageData {
+        width: width as usize,
+        height: height as usize,
+        bytes: pixels.into(),
+    };
+    arboard::Clipboard::new()?.set_image(image)
+}
+
+fn create_readback_buffer(device: &wgpu::Device, width: u32, height: u32) -> (wgpu::Buffer, u32) {
+    let unpadded_bytes_per_row = width * FrameCapture::BYTES_PER_PIXEL;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+    let buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+        label: Some("frame_capture_buffer"),
+        size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    (buffer, padded_bytes_per_row)
+}
@@ -0,0 +1,16 @@
+// the frame recorded in the capture target, drawn onto the surface as it is
+@group(0) @binding(0)
+var frame_tex: texture_2d<f32>;
+
+// one triangle covering the whole screen, the parts past its edges are clipped
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+}
+
+// the target is the frame's size, so every pixel is loaded from the texel under it
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(frame_tex, vec2<i32>(position.xy), 0);
+}
@@ -0,0 +1,105 @@
+use cgmath::{Matrix4, Point3, Transform, Vector3};
+
+// axis aligned bounding box in world space
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Point3<f32>, max: Point3<f32>) -> Self {
+        Aabb { min, max }
+    }
+
+    pub fn around(center: Point3<f32>, half_extents: Vector3<f32>) -> Self {
+        Aabb {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+
+    // the box around a transformed cube of the given half size, centered on its local origin
+    pub fn of_cube(transform: &Matrix4<f32>, half_size: f32) -> Self {
+        let corners = (0..8).map(|i| {
+            let corner = Point3::new(
+                if i & 1 == 0 { -half_size } else { half_size },
+                if i & 2 == 0 { -half_size } else { half_size },
+                if i & 4 == 0 { -half_size } else { half_size },
+            );
+            transform.transform_point(corner)
+        });
+
+        let mut aabb = Aabb::new(
+            Point3::new(f32::MAX, f32::MAX, f32::MAX),
+            Point3::new(f32::MIN, f32::MIN, f32::MIN),
+        );
+        for corner in corners {
+            aabb.min = Point3::new(aabb.min.x.min(corner.x), aabb.min.y.min(corner.y), aabb.min.z.min(corner.z));
+            aabb.max = Point3::new(aabb.max.x.max(corner.x), aabb.max.y.max(corner.y), aabb.max.z.max(corner.z));
+        }
+        aabb
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.x < other.max.x && self.max.x > other.min.x
+            && self.min.y < other.max.y && self.max.y > other.min.y
+            && self.min.z < other.max.z && self.max.z > other.min.z
+    }
+
+    // where self first touches other when moved by displacement, as the fraction of it travelled
+    // before the hit and the face's outward normal. None when it misses or already overlaps, which
+    // push_out deals with
+    pub fn sweep(&self, displacement: Vector3<f32>, other: &Aabb) -> Option<(f32, Vector3<f32>)> {
+        // the same as a ray from self's middle against other grown by self's half size
+        let half = (self.max - self.min) / 2.0;
+        let origin = self.min + half;
+        let (min, max) = (other.min - half, other.max + half);
+
+        let mut enter = f32::MIN;
+        let mut exit = f32::MAX;
+        let mut normal = Vector3::new(0.0, 0.0, 0.0);
+        for axis in 0..3 {
+            let (o, d) = (origin[axis], displacement[axis]);
+            if d == 0.0 {
+                if o <= min[axis] || o >= max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let (t1, t2) = ((min[axis] - o) / d, (max[axis] - o) / d);
+            let (near, far) = if t1 < t2 { (t1, t2) } else { (t2, t1) };
+            if near > enter {
+                enter = near;
+                normal = Vector3::new(0.0, 0.0, 0.0);
+                normal[axis] = -d.signum();
+            }
+            exit = exit.min(far);
+        }
+        (enter <= exit && (0.0..1.0).contains(&enter)).then_some((enter, normal))
+    }
+
+    // the smallest translation that moves self out of other, along a single axis
+    pub fn push_out(&self, other: &Aabb) -> Option<Vector3<f32>> {
+        if !self.intersects(other) {
+            return None;
+        }
+
+        let axis = |min: f32, max: f32, other_min: f32, other_max: f32| {
+            let up = other_max - min;
+            let down = other_min - max;
+            if up < -down { up } else { down }
+        };
+        let x = axis(self.min.x, self.max.x, other.min.x, other.max.x);
+        let y = axis(self.min.y, self.max.y, other.min.y, other.max.y);
+        let z = axis(self.min.z, self.max.z, other.min.z, other.max.z);
+
+        Some(if y.abs() <= x.abs() && y.abs() <= z.abs() {
+            Vector3::new(0.0, y, 0.0)
+        } else if x.abs() <= z.abs() {
+            Vector3::new(x, 0.0, 0.0)
+        } else {
+            Vector3::new(0.0, 0.0, z)
+        })
+    }
+}
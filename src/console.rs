@@ -0,0 +1,190 @@
+use cgmath::Point3;
+
+use crate::sky::CloudQuality;
+use crate::weather::Weather;
+
+// what can be spawned in front of the camera
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Spawnable {
+    // a crate like the left mouse button places
+    Cube,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Help,
+    Clear,
+    // moves the camera there without changing where it looks
+    Teleport(Point3<f32>),
+    Spawn(Spawnable),
+    SetClearColor([f64; 3]),
+    // the scene's resolution relative to the window's
+    SetRenderScale(f32),
+    SetCloudQuality(CloudQuality),
+    SetWeather(Weather),
+    // the post pass's lens effects, 0 to 1
+    SetVignette(f32),
+    SetAberration(f32),
+    // how fast time passes, 1 is normal
+    Timescale(f32),
+    ReloadShaders,
+}
+
+impl Command {
+    pub const USAGE: &'static str = "help\nclear\ntp x y z\nspawn cube\nset clear_color r g b\nset render_scale s\nset clouds off|low|medium|high\nset weather clear|rain\nset vignette s\nset aberration s\ntimescale t\nreload shaders";
+
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            ["help"] => Ok(Command::Help),
+            ["clear"] => Ok(Command::Clear),
+            ["tp", x, y, z] => {
+                let [x, y, z] = [x, y, z].map(|n| number(n));
+                Ok(Command::Teleport(Point3::new(x?, y?, z?)))
+            }
+            ["spawn", "cube"] => Ok(Command::Spawn(Spawnable::Cube)),
+            ["spawn", what] => Err(format!("Can't spawn {}, only cube", what)),
+            ["set", "clear_color", r, g, b] => {
+                let [r, g, b] = [r, g, b].map(|n| number(n));
+                Ok(Command::SetClearColor([r?, g?, b?].map(|c: f32| c.clamp(0.0, 1.0) as f64)))
+            }
+            ["set", "render_scale", s] => Ok(Command::SetRenderScale(number(s)?)),
+            ["set", "clouds", quality] => CloudQuality::ALL
+                .into_iter()
+                .find(|q| q.name() == *quality)
+                .map(Command::SetCloudQuality)
+                .ok_or_else(|| format!("Clouds can be off, low, medium or high, not {}", quality)),
+            ["set", "weather", weather] => Weather::ALL
+                .into_iter()
+                .find(|w| w.name() == *weather)
+                .map(Command::SetWeather)
+                .ok_or_else(|| format!("The weather can be clear or rain, not {}", weather)),
+            ["set", "vignette", s] => Ok(Command::SetVignette(number(s)?.clamp(0.0, 1.0))),
+            ["set", "aberration", s] => Ok(Command::SetAberration(number(s)?.clamp(0.0, 1.0))),
+            ["set", name, ..] => Err(format!("Unknown setting {}", name)),
+            ["timescale", t] => {
+                let t = number(t)?;
+                if t < 0.0 {
+                    return Err("The timescale can't be negative".to_string());
+                }
+                Ok(Command::Timescale(t))
+            }
+            ["reload", "shaders"] => Ok(Command::ReloadShaders),
+            [] => Err("Nothing to run".to_string()),
+            [name, ..] => Err(format!("Unknown command {}, try help", name)),
+        }
+    }
+}
+
+fn number(word: &str) -> Result<f32, String> {
+    word.parse::<f32>()
+        .ok()
+        .filter(|n| n.is_finite())
+        .ok_or_else(|| format!("{} isn't a number", word))
+}
+
+// a line of text typed into a panel dropped down over the top of the screen, and what the
+// commands run from it printed
+pub struct Console {
+    open: bool,
+    input: String,
+    // oldest first, only the last MAX_LINES are kept
+    lines: Vec<String>,
+    // what was run, oldest first, and how far back up and down are browsing through it
+    history: Vec<String>,
+    browsing: Option<usize>,
+}
+
+impl Console {
+    pub const MAX_LINES: usize = 12;
+    pub const PROMPT: &'static str = "> ";
+
+    pub fn new() -> Self {
+        Console {
+            open: false,
+            input: String::new(),
+            lines: Vec::new(),
+            history: Vec::new(),
+            browsing: None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn print(&mut self, text: &str) {
+        self.lines.extend(text.lines().map(str::to_string));
+        let excess = self.lines.len().saturating_sub(Self::MAX_LINES);
+        self.lines.drain(..excess);
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    // printable ascii only, the font has nothing else. the key that opens the console is left out
+    // too, it arrives as a character straight after
+    pub fn type_char(&mut self, c: char) {
+        if (' '..='~').contains(&c) && c != '`' {
+            self.input.push(c);
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        self.input.pop();
+    }
+
+    // steps through what was run before, back is further into the past
+    pub fn browse(&mut self, back: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+        let last = self.history.len() - 1;
+        self.browsing = match (self.browsing, back) {
+            (None, true) => Some(last),
+            (None, false) => None,
+            (Some(i), true) => Some(i.saturating_sub(1)),
+            (Some(i), false) if i < last => Some(i + 1),
+            (Some(_), false) => None,
+        };
+        self.input = self.browsing.map(|i| self.history[i].clone()).unwrap_or_default();
+    }
+
+    // the typed line as a command, echoed to the output with any error. None when it was blank
+    pub fn submit(&mut self) -> Option<Command> {
+        let line = std::mem::take(&mut self.input);
+        self.browsing = None;
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+        self.print(&format!("{}{}", Self::PROMPT, line));
+        if self.history.last().map(String::as_str) != Some(line) {
+            self.history.push(line.to_string());
+        }
+        match Command::parse(line) {
+            Ok(command) => Some(command),
+            Err(e) => {
+                self.print(&e);
+                None
+            }
+        }
+    }
+}
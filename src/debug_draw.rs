@@ -0,0 +1,244 @@
+use cgmath::{Angle, InnerSpace, Matrix4, Point3, SquareMatrix, Transform, Vector3};
+
+use crate::collision::Aabb;
+use crate::graphics;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LineVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+}
+
+impl LineVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3];
+
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<LineVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+// immediate mode lines: anything can queue shapes during the frame's update, flush uploads them
+// all at once and they're drawn unlit over the scene, then the list starts over
+pub struct DebugDraw {
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+    // vertices the buffer has room for, it's recreated larger when a frame needs more
+    capacity: usize,
+    vertices: Vec<LineVertex>,
+    vertex_count: u32,
+}
+
+impl DebugDraw {
+    const CIRCLE_SEGMENTS: usize = 16;
+    const INITIAL_CAPACITY: usize = 4096;
+
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = graphics::create_templated_shader(device, format, "debug_lines.wgsl", include_str!("debug_lines.wgsl"));
+
+        let uniform_buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some("debug_draw_uniform_buffer"),
+            size: std::mem::size_of::<graphics::RawMatrix>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("debug_draw_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("debug_draw_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("debug_draw_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("debug_draw_pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[LineVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                ..Default::default()
+            },
+            // hidden behind geometry like everything else, but doesn't occlude anything itself
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: graphics::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: graphics::SAMPLE_COUNT,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        DebugDraw {
+            pipeline,
+            uniform_buffer,
+            bind_group,
+            vertex_buffer: create_vertex_buffer(device, Self::INITIAL_CAPACITY),
+            capacity: Self::INITIAL_CAPACITY,
+            vertices: Vec::new(),
+            vertex_count: 0,
+        }
+    }
+
+    pub fn draw_line(&mut self, from: Point3<f32>, to: Point3<f32>, color: [f32; 3]) {
+        self.vertices.push(LineVertex { position: from.into(), color });
+        self.vertices.push(LineVertex { position: to.into(), color });
+    }
+
+    // a circle around center in the plane of u and v, which also give its radius
+    pub fn draw_circle(&mut self, center: Point3<f32>, u: Vector3<f32>, v: Vector3<f32>, color: [f32; 3]) {
+        let point = |i: usize| {
+            let angle = cgmath::Rad(i as f32 * std::f32::consts::TAU / Self::CIRCLE_SEGMENTS as f32);
+            center + u * angle.cos() + v * angle.sin()
+        };
+        for i in 0..Self::CIRCLE_SEGMENTS {
+            self.draw_line(point(i), point(i + 1), color);
+        }
+    }
+
+    // a circle in each of the three axis planes
+    pub fn draw_sphere(&mut self, center: Point3<f32>, radius: f32, color: [f32; 3]) {
+        let (x, y, z) = (Vector3::unit_x() * radius, Vector3::unit_y() * radius, Vector3::unit_z() * radius);
+        self.draw_circle(center, x, y, color);
+        self.draw_circle(center, y, z, color);
+        self.draw_circle(center, z, x, color);
+    }
+
+    // four edges from the apex and the circle around the base
+    pub fn draw_cone(&mut self, apex: Point3<f32>, direction: Vector3<f32>, length: f32, angle: cgmath::Deg<f32>, color: [f32; 3]) {
+        let direction = direction.normalize();
+        let base_center = apex + direction * length;
+        let base_radius = length * angle.tan();
+
+        // any two axes perpendicular to the cone's direction
+        let helper = if direction.y.abs() < 0.99 { Vector3::unit_y() } else { Vector3::unit_x() };
+        let u = direction.cross(helper).normalize() * base_radius;
+        let v = direction.cross(u);
+
+        for edge in [u, v, -u, -v] {
+            self.draw_line(apex, base_center + edge, color);
+        }
+        self.draw_circle(base_center, u, v, color);
+    }
+
+    pub fn draw_aabb(&mut self, aabb: &Aabb, color: [f32; 3]) {
+        let corners = std::array::from_fn(|i| {
+            Point3::new(
+                if i & 1 == 0 { aabb.min.x } else { aabb.max.x },
+                if i & 2 == 0 { aabb.min.y } else { aabb.max.y },
+                if i & 4 == 0 { aabb.min.z } else { aabb.max.z },
+            )
+        });
+        self.draw_box(&corners, color);
+    }
+
+    // the volume a camera with this view/projection matrix sees
+    pub fn draw_frustum(&mut self, view_proj: Matrix4<f32>, color: [f32; 3]) {
+        let Some(inverse) = view_proj.invert() else {
+            return;
+        };
+        let corners = std::array::from_fn(|i| {
+            let ndc = Point3::new(
+                if i & 1 == 0 { -1.0 } else { 1.0 },
+                if i & 2 == 0 { -1.0 } else { 1.0 },
+                if i & 4 == 0 { 0.0 } else { 1.0 },
+            );
+            inverse.transform_point(ndc)
+        });
+        self.draw_box(&corners, color);
+    }
+
+    // the twelve edges between corners numbered with x as bit 0, y as bit 1 and z as bit 2
+    fn draw_box(&mut self, corners: &[Point3<f32>; 8], color: [f32; 3]) {
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.draw_line(corners[i], corners[i | bit], color);
+                }
+            }
+        }
+    }
+
+    // uploads everything queued since the last flush and starts a new list
+    pub fn flush(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, view_proj: Matrix4<f32>) {
+        self.vertex_count = self.vertices.len() as u32;
+        if self.vertices.is_empty() {
+            return;
+        }
+        if self.vertices.len() > self.capacity {
+            self.capacity = self.vertices.len().next_power_of_two();
+            self.vertex_buffer = create_vertex_buffer(device, self.capacity);
+        }
+
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[graphics::RawMatrix { mat: view_proj.into() }]),
+        );
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+        self.vertices.clear();
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+}
+
+fn create_vertex_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    graphics::create_buffer(device, &wgpu::BufferDescriptor {
+        label: Some("debug_draw_vertex_buffer"),
+        size: (std::mem::size_of::<LineVertex>() * capacity) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
@@ -0,0 +1,376 @@
+use cgmath::{Matrix4, Point3, SquareMatrix};
+use std::collections::VecDeque;
+
+use crate::graphics;
+
+// a texture pressed down onto whatever is below it, like a target marker on the ground
+#[derive(Debug, Clone, Copy)]
+pub struct Decal {
+    pub center: Point3<f32>,
+    // half the width of the square the texture covers
+    pub size: f32,
+    // how far above and below the center it still reaches, for bumpy ground
+    pub depth: f32,
+    // around y, in radians
+    pub turn: f32,
+    // linear, the alpha scales the whole decal
+    pub color: [f32; 4],
+}
+
+impl Decal {
+    // a red ring with a cross through it, for marking where something was clicked
+    pub fn target(center: Point3<f32>, turn: f32) -> Self {
+        Decal {
+            center,
+            size: 3.0,
+            depth: 2.0,
+            turn,
+            color: [1.0, 0.1, 0.05, 0.9],
+        }
+    }
+}
+
+// the per instance data of a decal box
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct DecalRaw {
+    center_size: [f32; 4],
+    params: [f32; 4],
+    color: [f32; 4],
+}
+
+impl DecalRaw {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x4];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<DecalRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct DecalUniform {
+    view_proj: [[f32; 4]; 4],
+    inv_view_proj: [[f32; 4]; 4],
+}
+
+// pixels per side of the target texture
+const TEXTURE_SIZE: u32 = 128;
+// samples per pixel side when drawing it, for smooth edges
+const SUPERSAMPLES: u32 = 4;
+
+// decals drawn as boxes after the scene. each pixel a box covers gets the scene's position back
+// from the depth buffer and is only colored where that position lies inside the box, so the
+// texture follows the ground without the decal needing any geometry of its own
+pub struct Decals {
+    decals: VecDeque<Decal>,
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    texture_view: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+    // recreated along with the depth texture
+    bind_group: wgpu::BindGroup,
+    uniform_buffer: wgpu::Buffer,
+    instance_buffer: wgpu::Buffer,
+    instance_count: u32,
+}
+
+impl Decals {
+    // the oldest decal makes room for a new one past this
+    const MAX_DECALS: usize = 64;
+
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        depth_view: &wgpu::TextureView,
+    ) -> Self {
+        let texture_type = if graphics::SAMPLE_COUNT > 1 {
+            "texture_depth_multisampled_2d"
+        } else {
+            "texture_depth_2d"
+        };
+        let source = include_str!("decals.wgsl").replace("DEPTH_TEXTURE_TYPE", texture_type);
+        let shader = graphics::create_templated_shader(device, format, "decals.wgsl", &source);
+
+        let size = wgpu::Extent3d {
+            width: TEXTURE_SIZE,
+            height: TEXTURE_SIZE,
+            depth_or_array_layers: 1,
+        };
+        let texture = graphics::create_texture(device, &wgpu::TextureDescriptor {
+            label: Some("decal_texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &target_pixels(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(TEXTURE_SIZE * 4),
+                rows_per_image: std::num::NonZeroU32::new(TEXTURE_SIZE),
+            },
+            size,
+        );
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("decal_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let uniform_buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some("decals_uniform_buffer"),
+            size: std::mem::size_of::<DecalUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let instance_buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some("decals_instances"),
+            size: (std::mem::size_of::<DecalRaw>() * Self::MAX_DECALS) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("decals_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: graphics::SAMPLE_COUNT > 1,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("decals_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("decals_pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[DecalRaw::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            // the inside faces, so the decal still shows with the camera inside its box
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Front),
+                ..Default::default()
+            },
+            // the depth texture is read by the shader instead, it can't be attached at the same time
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: graphics::SAMPLE_COUNT,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let bind_group = create_bind_group(
+            device,
+            &bind_group_layout,
+            &uniform_buffer,
+            depth_view,
+            &texture_view,
+            &sampler,
+        );
+        Decals {
+            decals: VecDeque::new(),
+            pipeline,
+            bind_group_layout,
+            texture_view,
+            sampler,
+            bind_group,
+            uniform_buffer,
+            instance_buffer,
+            instance_count: 0,
+        }
+    }
+
+    // has to be called whenever the depth texture is recreated, like on resize
+    pub fn set_depth_view(&mut self, device: &wgpu::Device, depth_view: &wgpu::TextureView) {
+        self.bind_group = create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.uniform_buffer,
+            depth_view,
+            &self.texture_view,
+            &self.sampler,
+        );
+    }
+
+    pub fn add(&mut self, decal: Decal) {
+        if self.decals.len() == Self::MAX_DECALS {
+            self.decals.pop_front();
+        }
+        self.decals.push_back(decal);
+    }
+
+    pub fn clear(&mut self) {
+        self.decals.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.decals.is_empty()
+    }
+
+    pub fn upload(&mut self, queue: &wgpu::Queue, view_proj: Matrix4<f32>) {
+        let uniform = DecalUniform {
+            view_proj: view_proj.into(),
+            inv_view_proj: view_proj.invert().unwrap_or_else(Matrix4::identity).into(),
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+        let instances = self
+            .decals
+            .iter()
+            .map(|decal| {
+                let (sin, cos) = decal.turn.sin_cos();
+                DecalRaw {
+                    center_size: [decal.center.x, decal.center.y, decal.center.z, decal.size],
+                    params: [cos, sin, decal.depth, 0.0],
+                    color: decal.color,
+                }
+            })
+            .collect::<Vec<_>>();
+        if !instances.is_empty() {
+            queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+        }
+        self.instance_count = instances.len() as u32;
+    }
+
+    // in a pass without a depth attachment, after the scene is drawn
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.instance_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        // the 12 triangles of a box
+        render_pass.draw(0..36, 0..self.instance_count);
+    }
+}
+
+// white with two rings and a cross in the alpha channel
+fn target_pixels() -> Vec<u8> {
+    let coverage = |x: f32, y: f32| {
+        let r = (x * x + y * y).sqrt();
+        let ring = |radius: f32, width: f32| (r - radius).abs() < width;
+        let cross = (x.abs() < 0.04 || y.abs() < 0.04) && r < 0.75;
+        ring(0.85, 0.07) || ring(0.45, 0.05) || cross
+    };
+    let mut pixels = vec![0u8; (TEXTURE_SIZE * TEXTURE_SIZE * 4) as usize];
+    let samples = (SUPERSAMPLES * SUPERSAMPLES) as f32;
+    for row in 0..TEXTURE_SIZE {
+        for column in 0..TEXTURE_SIZE {
+            let mut covered = 0;
+            for sy in 0..SUPERSAMPLES {
+                for sx in 0..SUPERSAMPLES {
+                    let to_texture = |p: u32, s: u32| {
+                        ((p * SUPERSAMPLES + s) as f32 + 0.5) / (TEXTURE_SIZE * SUPERSAMPLES) as f32 * 2.0 - 1.0
+                    };
+                    covered += coverage(to_texture(column, sx), to_texture(row, sy)) as u32;
+                }
+            }
+            let texel = ((row * TEXTURE_SIZE + column) * 4) as usize;
+            pixels[texel..texel + 4].copy_from_slice(&[255, 255, 255, (covered as f32 / samples * 255.0).round() as u8]);
+        }
+    }
+    pixels
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    depth_view: &wgpu::TextureView,
+    texture_view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("decals_bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(depth_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(texture_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    })
+}
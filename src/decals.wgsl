@@ -0,0 +1,89 @@
+// DEPTH_TEXTURE_TYPE is substituted before compiling (see decals.rs)
+
+struct DecalUniform {
+    view_proj: mat4x4<f32>,
+    inv_view_proj: mat4x4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> decals: DecalUniform;
+
+@group(0) @binding(1)
+var depth_tex: DEPTH_TEXTURE_TYPE;
+
+@group(0) @binding(2)
+var decal_tex: texture_2d<f32>;
+@group(0) @binding(3)
+var decal_sampler: sampler;
+
+// see DecalRaw in decals.rs
+struct InstanceInput {
+    // xyz is the center, w half the width of the square the texture covers
+    @location(0) center_size: vec4<f32>,
+    // x and y are the cosine and sine of the turn around y, z half the height of the box
+    @location(1) params: vec4<f32>,
+    @location(2) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) center_size: vec4<f32>,
+    @location(1) params: vec4<f32>,
+    @location(2) color: vec4<f32>,
+}
+
+// the box's 8 corners are the bits of the index, x in the first and z in the last. the faces wind
+// counter clockwise seen from outside
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, instance: InstanceInput) -> VertexOutput {
+    var corners = array<u32, 36>(
+        4u, 6u, 2u, 4u, 2u, 0u, 1u, 3u, 7u, 1u, 7u, 5u,
+        1u, 5u, 4u, 1u, 4u, 0u, 2u, 6u, 7u, 2u, 7u, 3u,
+        2u, 3u, 1u, 2u, 1u, 0u, 4u, 5u, 7u, 4u, 7u, 6u,
+    );
+    let corner = corners[index];
+    let local = vec3<f32>(
+        f32(corner & 1u) * 2.0 - 1.0,
+        f32((corner >> 1u) & 1u) * 2.0 - 1.0,
+        f32((corner >> 2u) & 1u) * 2.0 - 1.0,
+    ) * vec3<f32>(instance.center_size.w, instance.params.z, instance.center_size.w);
+    let c = instance.params.x;
+    let s = instance.params.y;
+    let turned = vec3<f32>(local.x * c + local.z * s, local.y, -local.x * s + local.z * c);
+
+    var out: VertexOutput;
+    out.clip_position = decals.view_proj * vec4<f32>(instance.center_size.xyz + turned, 1.0);
+    out.center_size = instance.center_size;
+    out.params = instance.params;
+    out.color = instance.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // whatever the scene drew at this pixel, back in world space
+    let coords = vec2<i32>(in.clip_position.xy);
+    let depth = textureLoad(depth_tex, coords, 0);
+    let size = vec2<f32>(textureDimensions(depth_tex));
+    let ndc = vec2<f32>(in.clip_position.x / size.x * 2.0 - 1.0, 1.0 - in.clip_position.y / size.y * 2.0);
+    let world_h = decals.inv_view_proj * vec4<f32>(ndc, depth, 1.0);
+    let world_pos = world_h.xyz / world_h.w;
+    // the surface's slope, so walls the box cuts into don't get the texture smeared down them
+    let normal = normalize(cross(dpdx(world_pos), dpdy(world_pos)));
+    let facing = smoothstep(0.5, 0.8, abs(normal.y));
+
+    // into the box's space, turned back the other way
+    let offset = world_pos - in.center_size.xyz;
+    let c = in.params.x;
+    let s = in.params.y;
+    let local = vec3<f32>(offset.x * c - offset.z * s, offset.y, offset.x * s + offset.z * c);
+    let uv = local.xz / in.center_size.w * 0.5 + 0.5;
+    let texel = textureSample(decal_tex, decal_sampler, uv);
+    if any(abs(local.xz) > vec2<f32>(in.center_size.w)) || abs(local.y) > in.params.z || depth >= 1.0 {
+        discard;
+    }
+
+    var color = in.color.rgb;
+    color = apply_output_gamma(color);
+    return vec4<f32>(color, texel.a * in.color.a * facing);
+}
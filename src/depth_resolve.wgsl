@@ -0,0 +1,24 @@
+// DEPTH_TEXTURE_TYPE and SAMPLE_COUNT are substituted before compiling (see picking.rs)
+@group(0) @binding(0)
+var depth_tex: DEPTH_TEXTURE_TYPE;
+
+@group(0) @binding(1)
+var<uniform> pixel: vec4<u32>;
+
+struct ResolvedDepth {
+    depth: f32
+}
+
+@group(0) @binding(2)
+var<storage, read_write> resolved: ResolvedDepth;
+
+@compute @workgroup_size(1)
+fn cs_main() {
+    let coords = vec2<i32>(pixel.xy);
+    // keep the nearest sample so geometry only partially covering the pixel still gets picked
+    var depth = 1.0;
+    for (var i = 0; i < SAMPLE_COUNT; i++) {
+        depth = min(depth, textureLoad(depth_tex, coords, i));
+    }
+    resolved.depth = depth;
+}
@@ -0,0 +1,70 @@
+use crate::graphics;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct EmissiveUniform {
+    // rgb multiplies the texture, w is pulses per second
+    color: [f32; 4],
+    // x is the time
+    params: [f32; 4],
+}
+
+// what an emissive object binds as the emissive pipeline's second group
+pub struct Emission {
+    uniform: EmissiveUniform,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Emission {
+    pub fn new(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, color: [f32; 3], pulse: f32) -> Self {
+        let uniform = EmissiveUniform {
+            color: [color[0], color[1], color[2], pulse],
+            params: [0.0; 4],
+        };
+        let buffer = graphics::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+            label: Some("emissive_uniform_buffer"),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("emissive_bind_group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        Emission { uniform, buffer, bind_group }
+    }
+
+    // only needed while it pulses
+    pub fn update(&mut self, queue: &wgpu::Queue, time: f32) {
+        if self.uniform.color[3] == 0.0 {
+            return;
+        }
+        self.uniform.params[0] = time;
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+}
+
+// the second bind group of the emissive pipeline, after the one every object has
+pub fn build_emissive_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("emissive_bind_group_layout"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    })
+}
@@ -0,0 +1,75 @@
+use std::fmt;
+use std::path::PathBuf;
+
+// what stops the app from starting or from carrying on, reported before exiting rather than
+// panicking
+#[derive(Debug)]
+pub enum AppError {
+    // none of the adapters on the chosen backends can be used, or draw to the window
+    NoAdapter,
+    Device(wgpu::RequestDeviceError),
+    Window(winit::error::OsError),
+    // an embedded image that wouldn't decode, with the name it was loaded under
+    Texture(String, image::ImageError),
+    Io(PathBuf, std::io::Error),
+    Render(wgpu::SurfaceError),
+    // a captured frame, or the directory it goes in, that couldn't be written
+    Capture(PathBuf, image::ImageError),
+    // a frame in a format the capture can't turn into rgba8
+    CaptureFormat(wgpu::TextureFormat),
+    // the browser's futures can't be blocked on to build a new device
+    #[cfg(target_arch = "wasm32")]
+    DeviceLost,
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AppError::NoAdapter => write!(f, "No suitable graphics adapter found"),
+            AppError::Device(e) => write!(f, "Failed to retrieve device: {}", e),
+            AppError::Window(e) => write!(f, "Failed to build window: {}", e),
+            AppError::Texture(name, e) => write!(f, "Failed to load texture {}: {}", name, e),
+            AppError::Io(path, e) => write!(f, "Failed to read {}: {}", path.display(), e),
+            AppError::Render(e) => write!(f, "Failed to render: {}", e),
+            AppError::Capture(path, e) => write!(f, "Failed to write {}: {}", path.display(), e),
+            AppError::CaptureFormat(format) => write!(f, "Frames in {:?} can't be captured", format),
+            #[cfg(target_arch = "wasm32")]
+            AppError::DeviceLost => write!(f, "The device was lost, reload the page to start again"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AppError::NoAdapter => None,
+            #[cfg(target_arch = "wasm32")]
+            AppError::DeviceLost => None,
+            AppError::Device(e) => Some(e),
+            AppError::Window(e) => Some(e),
+            AppError::Texture(_, e) => Some(e),
+            AppError::Io(_, e) => Some(e),
+            AppError::Render(e) => Some(e),
+            AppError::Capture(_, e) => Some(e),
+            AppError::CaptureFormat(_) => None,
+        }
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for AppError {
+    fn from(e: wgpu::RequestDeviceError) -> Self {
+        AppError::Device(e)
+    }
+}
+
+impl From<winit::error::OsError> for AppError {
+    fn from(e: winit::error::OsError) -> Self {
+        AppError::Window(e)
+    }
+}
+
+impl From<wgpu::SurfaceError> for AppError {
+    fn from(e: wgpu::SurfaceError) -> Self {
+        AppError::Render(e)
+    }
+}
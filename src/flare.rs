@@ -0,0 +1,185 @@
+use cgmath::Matrix4;
+
+use crate::graphics;
+use crate::sky::Sun;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct FlareUniform {
+    // xy is where the sun is in ndc, z the frame's aspect ratio
+    sun: [f32; 4],
+    // the sun's color scaled by how strong the flare is
+    color: [f32; 4],
+}
+
+// the glints a camera lens scatters from the sun, a row of sprites along the line from the sun
+// through the middle of the frame. the shader reads the depth around the sun, so they fade out as
+// it goes behind something
+pub struct LensFlare {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    // recreated along with the depth texture
+    bind_group: wgpu::BindGroup,
+    uniform_buffer: wgpu::Buffer,
+    visible: bool,
+}
+
+impl LensFlare {
+    // the sprites along the line, see ELEMENTS in flare.wgsl
+    const ELEMENT_COUNT: u32 = 7;
+    // how far past the frame's edge, in ndc, the sun can be before the flare is gone
+    const EDGE_FADE: (f32, f32) = (0.8, 1.3);
+
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, depth_view: &wgpu::TextureView) -> Self {
+        let texture_type = if graphics::SAMPLE_COUNT > 1 {
+            "texture_depth_multisampled_2d"
+        } else {
+            "texture_depth_2d"
+        };
+        let source = include_str!("flare.wgsl").replace("DEPTH_TEXTURE_TYPE", texture_type);
+        let shader = graphics::create_templated_shader(device, format, "flare.wgsl", &source);
+
+        let uniform_buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some("flare_uniform_buffer"),
+            size: std::mem::size_of::<FlareUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("flare_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: graphics::SAMPLE_COUNT > 1,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("flare_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("flare_pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState { color: additive, alpha: additive }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // the depth texture is read by the shader instead, it can't be attached at the same time
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: graphics::SAMPLE_COUNT,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let bind_group = create_bind_group(device, &bind_group_layout, &uniform_buffer, depth_view);
+        LensFlare {
+            pipeline,
+            bind_group_layout,
+            bind_group,
+            uniform_buffer,
+            visible: false,
+        }
+    }
+
+    // has to be called whenever the depth texture is recreated, like on resize
+    pub fn set_depth_view(&mut self, device: &wgpu::Device, depth_view: &wgpu::TextureView) {
+        self.bind_group = create_bind_group(device, &self.bind_group_layout, &self.uniform_buffer, depth_view);
+    }
+
+    // false while the sun is behind the camera, well off the frame or set, there's nothing to draw then
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, view_proj: Matrix4<f32>, aspect: f32, sun: &Sun) {
+        self.visible = false;
+        let sun_color = sun.color();
+        if sun_color.iter().all(|&c| c <= 0.0) {
+            return;
+        }
+        let Some(position) = sun.screen_position(view_proj) else {
+            return;
+        };
+        let (x, y) = (position.x, position.y);
+        let (start, end) = Self::EDGE_FADE;
+        let strength = 1.0 - ((x.abs().max(y.abs()) - start) / (end - start)).clamp(0.0, 1.0);
+        if strength <= 0.0 {
+            return;
+        }
+        self.visible = true;
+        let [r, g, b] = sun_color.map(|c| c * strength);
+        let uniform = FlareUniform {
+            sun: [x, y, aspect, 0.0],
+            color: [r, g, b, 1.0],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    // in a pass without a depth attachment, after the scene is drawn
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..6, 0..Self::ELEMENT_COUNT);
+    }
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    depth_view: &wgpu::TextureView,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("flare_bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(depth_view),
+            },
+        ],
+    })
+}
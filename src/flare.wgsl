@@ -0,0 +1,110 @@
+// DEPTH_TEXTURE_TYPE is substituted before compiling (see flare.rs)
+
+// the depth is read on a grid this many texels across around the sun, the flare's strength is
+// the part of it that's open sky
+let OCCLUSION_GRID: i32 = 5;
+// texels between the grid's points
+let OCCLUSION_SPACING: i32 = 3;
+
+let GLOW: u32 = 0u;
+let DISC: u32 = 1u;
+let RING: u32 = 2u;
+let HEXAGON: u32 = 3u;
+
+struct FlareUniform {
+    sun: vec4<f32>,
+    color: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> flare: FlareUniform;
+
+@group(0) @binding(1)
+var depth_tex: DEPTH_TEXTURE_TYPE;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // -1 to 1 across the quad
+    @location(0) corner: vec2<f32>,
+    @location(1) color: vec3<f32>,
+    @location(2) @interpolate(flat) shape: u32,
+}
+
+// the sky is left at the cleared depth, anything drawn in front of it is nearer
+fn sun_visibility() -> f32 {
+    let size = vec2<i32>(textureDimensions(depth_tex));
+    let center = vec2<i32>((flare.sun.xy * vec2<f32>(0.5, -0.5) + 0.5) * vec2<f32>(size));
+    var open = 0;
+    for (var y = 0; y < OCCLUSION_GRID; y++) {
+        for (var x = 0; x < OCCLUSION_GRID; x++) {
+            let offset = (vec2<i32>(x, y) - OCCLUSION_GRID / 2) * OCCLUSION_SPACING;
+            let coords = clamp(center + offset, vec2<i32>(0), size - 1);
+            if textureLoad(depth_tex, coords, 0) >= 1.0 {
+                open++;
+            }
+        }
+    }
+    return f32(open) / f32(OCCLUSION_GRID * OCCLUSION_GRID);
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, @builtin(instance_index) element: u32) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, -1.0), vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, 1.0), vec2<f32>(-1.0, 1.0),
+    );
+    // how far along the line from the sun (0) through the middle (1) to the far side, its size
+    // as a part of the frame's height, and its shape
+    var elements = array<vec3<f32>, 7>(
+        vec3<f32>(0.0, 0.35, f32(GLOW)),
+        vec3<f32>(0.3, 0.05, f32(DISC)),
+        vec3<f32>(0.55, 0.09, f32(HEXAGON)),
+        vec3<f32>(0.8, 0.04, f32(DISC)),
+        vec3<f32>(1.2, 0.12, f32(HEXAGON)),
+        vec3<f32>(1.5, 0.25, f32(RING)),
+        vec3<f32>(1.9, 0.07, f32(DISC)),
+    );
+    var tints = array<vec3<f32>, 7>(
+        vec3<f32>(0.8, 0.7, 0.5),
+        vec3<f32>(0.3, 0.2, 0.1),
+        vec3<f32>(0.1, 0.25, 0.15),
+        vec3<f32>(0.15, 0.2, 0.35),
+        vec3<f32>(0.2, 0.1, 0.25),
+        vec3<f32>(0.08, 0.12, 0.25),
+        vec3<f32>(0.3, 0.15, 0.08),
+    );
+    let corner = corners[index];
+    let e = elements[element];
+    let center = flare.sun.xy * (1.0 - e.x);
+    // the sizes are in ndc heights, so x is squeezed back to keep them round
+    let position = center + corner * e.y * vec2<f32>(1.0 / flare.sun.z, 1.0);
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(position, 0.0, 1.0);
+    out.corner = corner;
+    out.color = tints[element] * flare.color.rgb * sun_visibility();
+    out.shape = u32(e.z);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let r = length(in.corner);
+    var strength: f32;
+    if in.shape == GLOW {
+        strength = exp(-r * r * 6.0) * (1.0 - smoothstep(0.7, 1.0, r));
+    } else if in.shape == DISC {
+        strength = 1.0 - smoothstep(0.75, 1.0, r);
+    } else if in.shape == RING {
+        strength = 1.0 - smoothstep(0.0, 0.12, abs(r - 0.85));
+    } else {
+        // the distance to the nearest of the hexagon's flat sides
+        let p = abs(in.corner);
+        let d = max(p.x * 0.866 + p.y * 0.5, p.y);
+        strength = 1.0 - smoothstep(0.8, 0.95, d);
+    }
+
+    var color = in.color * strength;
+    color = apply_output_gamma(color);
+    return vec4<f32>(color, 0.0);
+}
@@ -0,0 +1,119 @@
+use cgmath::Vector3;
+
+use crate::scene::CameraPose;
+
+// a camera path through recorded poses, played back along a catmull-rom spline
+// that passes through every keyframe
+pub struct Flythrough {
+    keyframes: Vec<CameraPose>,
+    // seconds into playback, None while stopped
+    playback: Option<f32>,
+    // time spent travelling between two neighbouring keyframes
+    segment_duration: f32,
+}
+
+impl Flythrough {
+    pub fn new(segment_duration: f32) -> Self {
+        Flythrough {
+            keyframes: Vec::new(),
+            playback: None,
+            segment_duration,
+        }
+    }
+
+    pub fn set_keyframes(&mut self, keyframes: Vec<CameraPose>) {
+        self.keyframes = keyframes;
+        self.playback = None;
+    }
+
+    pub fn add_keyframe(&mut self, pose: CameraPose) {
+        self.keyframes.push(pose);
+    }
+
+    pub fn clear(&mut self) {
+        self.set_keyframes(Vec::new());
+    }
+
+    pub fn keyframe_count(&self) -> usize {
+        self.keyframes.len()
+    }
+
+    // returns whether playback started, a path needs at least two keyframes
+    pub fn play(&mut self) -> bool {
+        if self.keyframes.len() < 2 {
+            return false;
+        }
+        self.playback = Some(0.0);
+        true
+    }
+
+    pub fn stop(&mut self) {
+        self.playback = None;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    // advances playback and returns the pose to put the camera in, stops after the last keyframe
+    pub fn advance(&mut self, dt: f32) -> Option<CameraPose> {
+        let time = self.playback? + dt;
+        let segments = self.keyframes.len() - 1;
+        let end = segments as f32 * self.segment_duration;
+        if time >= end {
+            self.playback = None;
+            return self.keyframes.last().cloned();
+        }
+        self.playback = Some(time);
+
+        let t = time / self.segment_duration;
+        let segment = (t as usize).min(segments - 1);
+        Some(self.sample(segment, t - segment as f32))
+    }
+
+    fn sample(&self, segment: usize, t: f32) -> CameraPose {
+        // the curve needs a neighbour on each side, the ends reuse their own keyframe
+        let last = self.keyframes.len() - 1;
+        let indices = [
+            segment.saturating_sub(1),
+            segment,
+            segment + 1,
+            (segment + 2).min(last),
+        ];
+
+        // yaw wraps around at 360, so unwrap it to take the short way between keyframes
+        let mut yaws = indices.map(|i| self.keyframes[i].yaw);
+        for i in 1..yaws.len() {
+            yaws[i] = yaws[i - 1] + (yaws[i] - yaws[i - 1] + 180.0).rem_euclid(360.0) - 180.0;
+        }
+
+        let pos = indices.map(|i| Vector3::from(self.keyframes[i].position));
+        let position = catmull_rom(pos[0], pos[1], pos[2], pos[3], t);
+        let yaw = catmull_rom(yaws[0], yaws[1], yaws[2], yaws[3], t);
+        let pitch = indices.map(|i| self.keyframes[i].pitch);
+        let pitch = catmull_rom(pitch[0], pitch[1], pitch[2], pitch[3], t);
+        let roll = indices.map(|i| self.keyframes[i].roll);
+        let roll = catmull_rom(roll[0], roll[1], roll[2], roll[3], t);
+
+        CameraPose {
+            position: position.into(),
+            yaw: yaw.rem_euclid(360.0),
+            pitch: pitch.clamp(-90.0, 90.0),
+            roll,
+        }
+    }
+}
+
+// uniform catmull-rom between p1 (t = 0) and p2 (t = 1)
+fn catmull_rom<T>(p0: T, p1: T, p2: T, p3: T, t: f32) -> T
+where
+    T: Copy + std::ops::Add<Output = T> + std::ops::Sub<Output = T> + std::ops::Mul<f32, Output = T>,
+{
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5
+}
@@ -0,0 +1,73 @@
+use log::debug;
+use winit::window::Window;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Capture {
+    // cursor free, input goes to other windows
+    Released,
+    // the window manager keeps the cursor inside the window for us
+    Grabbed,
+    // grabbing isn't supported here, so the cursor is warped back to the center after every move
+    Recentering,
+}
+
+// whether the app owns the mouse. shared by the event loop, which decides when to capture,
+// and App::input, which only reacts to input while captured
+pub struct FocusState {
+    capture: Capture,
+    // whether the system cursor is hidden over the window
+    cursor_hidden: bool,
+}
+
+impl FocusState {
+    pub fn new() -> Self {
+        FocusState {
+            capture: Capture::Released,
+            cursor_hidden: false,
+        }
+    }
+
+    pub fn capture(&mut self, window: &Window) {
+        if self.is_focused() {
+            return;
+        }
+
+        self.capture = match window.set_cursor_grab(true) {
+            Ok(_) => Capture::Grabbed,
+            Err(e) => {
+                debug!("Cursor grab unavailable, recentering instead: {}", e);
+                Capture::Recentering
+            }
+        };
+        self.update_cursor(window, false);
+    }
+
+    pub fn release(&mut self, window: &Window) {
+        if self.capture == Capture::Grabbed {
+            if let Err(e) = window.set_cursor_grab(false) {
+                debug!("Failed to release cursor grab: {}", e);
+            }
+        }
+        self.capture = Capture::Released;
+        self.update_cursor(window, false);
+    }
+
+    // the system cursor is hidden while captured, and while the app draws its own in its place.
+    // drawn is whether the frame just presented had the app's cursor, so the system one is back
+    // as soon as frames stop being presented
+    pub fn update_cursor(&mut self, window: &Window, drawn: bool) {
+        let hidden = self.is_focused() || drawn;
+        if hidden != self.cursor_hidden {
+            window.set_cursor_visible(!hidden);
+            self.cursor_hidden = hidden;
+        }
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.capture != Capture::Released
+    }
+
+    pub fn needs_recentering(&self) -> bool {
+        self.capture == Capture::Recentering
+    }
+}
@@ -0,0 +1,20 @@
+// put in front of every shader made with graphics::create_templated_shader. MANUAL_GAMMA_VALUE is
+// substituted there, true when the target isn't srgb and so won't encode what's written to it
+let MANUAL_GAMMA: bool = MANUAL_GAMMA_VALUE;
+let GAMMA: f32 = 2.2;
+
+// the linear color as the target should store it
+fn apply_output_gamma(color: vec3<f32>) -> vec3<f32> {
+    if MANUAL_GAMMA {
+        return pow(color, vec3<f32>(1.0 / GAMMA));
+    }
+    return color;
+}
+
+// undoes apply_output_gamma, for reading back what an earlier pass wrote
+fn remove_output_gamma(color: vec3<f32>) -> vec3<f32> {
+    if MANUAL_GAMMA {
+        return pow(color, vec3<f32>(GAMMA));
+    }
+    return color;
+}
@@ -0,0 +1,65 @@
+// DEPTH_TEXTURE_TYPE is substituted before compiling (see godrays.rs)
+
+// steps from each pixel towards the sun, more makes smoother rays
+let SAMPLES: i32 = 48;
+// the part of the way to the sun the steps cover, rays past this don't reach the pixel
+let DENSITY: f32 = 0.9;
+// each step counts for this much less than the one before, so the rays fade with distance
+let DECAY: f32 = 0.96;
+let EXPOSURE: f32 = 0.6;
+// how tightly the glow the rays carry hugs the sun, larger is smaller
+let GLOW_FALLOFF: f32 = 12.0;
+
+struct GodRaysUniform {
+    sun: vec4<f32>,
+    color: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> rays: GodRaysUniform;
+
+@group(0) @binding(1)
+var depth_tex: DEPTH_TEXTURE_TYPE;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// one triangle covering the whole screen, the parts past its edges are clipped
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(depth_tex));
+    let sun = rays.sun.xy * vec2<f32>(0.5, -0.5) + 0.5;
+    // squeezed so the glow around the sun is round
+    let aspect = vec2<f32>(rays.sun.z, 1.0);
+    let step = (sun - in.uv) * DENSITY / f32(SAMPLES);
+
+    var uv = in.uv;
+    var weight = 1.0;
+    var light = 0.0;
+    for (var i = 0; i < SAMPLES; i++) {
+        uv += step;
+        let coords = clamp(vec2<i32>(uv * vec2<f32>(size)), vec2<i32>(0), size - 1);
+        // the sky is left at the cleared depth, anything drawn in front of it is nearer
+        if textureLoad(depth_tex, coords, 0) >= 1.0 {
+            let distance = length((uv - sun) * aspect);
+            light += exp(-distance * distance * GLOW_FALLOFF) * weight;
+        }
+        weight *= DECAY;
+    }
+
+    var color = rays.color.rgb * light * EXPOSURE / f32(SAMPLES);
+    color = apply_output_gamma(color);
+    return vec4<f32>(color, 0.0);
+}
@@ -0,0 +1,265 @@
+use cgmath::{Matrix4, Point3, SquareMatrix, Vector3};
+
+use crate::graphics;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuParticle {
+    position: [f32; 4],
+    velocity: [f32; 4],
+}
+
+impl GpuParticle {
+    const ATTRIBS: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<GpuParticle>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SimUniform {
+    view_proj: [[f32; 4]; 4],
+    view: [[f32; 4]; 4],
+    attractor: [f32; 4],
+    cloud: [f32; 4],
+    params: [f32; 4],
+    count: [u32; 4],
+}
+
+// a million particles swirling around a wandering attractor, simulated entirely in a compute pass.
+// the state lives in two storage buffers, each step reads one and writes the other, and the one
+// written last is drawn straight from the gpu as instanced quads
+pub struct GpuParticles {
+    center: Point3<f32>,
+    uniform: SimUniform,
+    uniform_buffer: wgpu::Buffer,
+    buffers: [wgpu::Buffer; 2],
+    // bind_groups[i] reads buffers[i] and writes the other one
+    compute_bind_groups: [wgpu::BindGroup; 2],
+    init_pipeline: wgpu::ComputePipeline,
+    update_pipeline: wgpu::ComputePipeline,
+    render_pipeline: wgpu::RenderPipeline,
+    render_bind_group: wgpu::BindGroup,
+    // the buffer holding the latest state
+    current: usize,
+    // the buffers start out empty, the first frame fills them in on the gpu
+    needs_init: bool,
+}
+
+impl GpuParticles {
+    const COUNT: u32 = 1_000_000;
+    const WORKGROUP_SIZE: u32 = 256;
+    const CLOUD_RADIUS: f32 = 60.0;
+    const ATTRACTOR_STRENGTH: f32 = 20000.0;
+    // steps longer than this would fling particles passing close to the attractor far away
+    const MAX_TIME_STEP: f32 = 1.0 / 30.0;
+
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, center: Point3<f32>) -> Self {
+        let shader = graphics::create_templated_shader(device, format, "gpu_particles.wgsl", include_str!("gpu_particles.wgsl"));
+
+        let uniform_buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some("gpu_particles_uniform_buffer"),
+            size: std::mem::size_of::<SimUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let buffers = [0, 1].map(|i| {
+            graphics::create_buffer(device, &wgpu::BufferDescriptor {
+                label: Some(&format!("gpu_particles_buffer_{}", i)),
+                size: (std::mem::size_of::<GpuParticle>() as u32 * Self::COUNT) as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+                mapped_at_creation: false,
+            })
+        });
+
+        let uniform_entry = wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let compute_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("gpu_particles_compute_bind_group_layout"),
+            entries: &[uniform_entry, storage_entry(1, true), storage_entry(2, false)],
+        });
+        let render_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("gpu_particles_render_bind_group_layout"),
+            entries: &[uniform_entry],
+        });
+
+        let compute_bind_groups = [0, 1].map(|i| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(&format!("gpu_particles_compute_bind_group_{}", i)),
+                layout: &compute_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: buffers[i].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: buffers[1 - i].as_entire_binding(),
+                    },
+                ],
+            })
+        });
+        let render_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("gpu_particles_render_bind_group"),
+            layout: &render_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let compute_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("gpu_particles_compute_pipeline_layout"),
+            bind_group_layouts: &[&compute_layout],
+            push_constant_ranges: &[],
+        });
+        let compute_pipeline = |label, entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&compute_pipeline_layout),
+                module: &shader,
+                entry_point,
+            })
+        };
+        let init_pipeline = compute_pipeline("gpu_particles_init_pipeline", "cs_init");
+        let update_pipeline = compute_pipeline("gpu_particles_update_pipeline", "cs_update");
+
+        let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("gpu_particles_render_pipeline_layout"),
+            bind_group_layouts: &[&render_layout],
+            push_constant_ranges: &[],
+        });
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("gpu_particles_render_pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[GpuParticle::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState { color: additive, alpha: additive }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // hidden behind the scene but left out of the depth buffer, they're see-through
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: graphics::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: graphics::SAMPLE_COUNT,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        GpuParticles {
+            center,
+            uniform: SimUniform {
+                view_proj: Matrix4::identity().into(),
+                view: Matrix4::identity().into(),
+                attractor: [center.x, center.y, center.z, Self::ATTRACTOR_STRENGTH],
+                cloud: [center.x, center.y, center.z, Self::CLOUD_RADIUS],
+                params: [0.0; 4],
+                count: [Self::COUNT, 0, 0, 0],
+            },
+            uniform_buffer,
+            buffers,
+            compute_bind_groups,
+            init_pipeline,
+            update_pipeline,
+            render_pipeline,
+            render_bind_group,
+            current: 0,
+            needs_init: true,
+        }
+    }
+
+    // moves the attractor along a slow figure of eight through the cloud
+    pub fn update(
+        &mut self,
+        queue: &wgpu::Queue,
+        dt: f32,
+        time: f32,
+        view_proj: Matrix4<f32>,
+        view: Matrix4<f32>,
+    ) {
+        let radius = Self::CLOUD_RADIUS * 0.5;
+        let attractor = self.center + Vector3::new((time * 0.3).sin(), (time * 0.2).sin() * 0.5, (time * 0.6).sin() * 0.5) * radius;
+        self.uniform.view_proj = view_proj.into();
+        self.uniform.view = view.into();
+        self.uniform.attractor = [attractor.x, attractor.y, attractor.z, Self::ATTRACTOR_STRENGTH];
+        self.uniform.params = [dt.min(Self::MAX_TIME_STEP), 0.0, 0.0, 0.0];
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+    }
+
+    // records this frame's simulation step, before the pass that draws the particles
+    pub fn simulate(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let workgroups = Self::COUNT.div_ceil(Self::WORKGROUP_SIZE);
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("gpu_particles_pass"),
+        });
+        if self.needs_init {
+            // written into the buffer the first step then reads from
+            compute_pass.set_pipeline(&self.init_pipeline);
+            compute_pass.set_bind_group(0, &self.compute_bind_groups[1 - self.current], &[]);
+            compute_pass.dispatch_workgroups(workgroups, 1, 1);
+            self.needs_init = false;
+        }
+        compute_pass.set_pipeline(&self.update_pipeline);
+        compute_pass.set_bind_group(0, &self.compute_bind_groups[self.current], &[]);
+        compute_pass.dispatch_workgroups(workgroups, 1, 1);
+        self.current = 1 - self.current;
+    }
+
+    // after everything opaque, so the scene hides the particles behind it
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.render_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.buffers[self.current].slice(..));
+        render_pass.draw(0..6, 0..Self::COUNT);
+    }
+}
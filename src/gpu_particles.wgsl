@@ -0,0 +1,132 @@
+// keeps the pull finite for particles passing right through the attractor
+let SOFTENING: f32 = 25.0;
+// fraction of the velocity lost per second
+let DAMPING: f32 = 0.05;
+// half the width of a particle quad in world units
+let PARTICLE_SIZE: f32 = 0.15;
+// speed at which the color has gone fully from cold to hot
+let HOT_SPEED: f32 = 40.0;
+// a million of them pile up quickly, so each only adds a little light
+let BRIGHTNESS: f32 = 0.12;
+
+struct SimUniform {
+    view_proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+    // xyz is where everything is pulled towards, w how strongly
+    attractor: vec4<f32>,
+    // xyz is the middle of the starting cloud, w its radius
+    cloud: vec4<f32>,
+    // x is the time step
+    params: vec4<f32>,
+    // x is the particle count
+    count: vec4<u32>,
+}
+
+struct Particle {
+    position: vec4<f32>,
+    velocity: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> sim: SimUniform;
+
+@group(0) @binding(1)
+var<storage, read> src: array<Particle>;
+
+@group(0) @binding(2)
+var<storage, read_write> dst: array<Particle>;
+
+// pcg, good enough to scatter the particles without any visible pattern
+fn hash(v: u32) -> u32 {
+    let state = v * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn random(seed: u32) -> f32 {
+    return f32(hash(seed)) / 4294967295.0;
+}
+
+// a ball of particles circling the y axis, so they start out orbiting instead of falling straight in
+@compute @workgroup_size(256)
+fn cs_init(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= sim.count.x {
+        return;
+    }
+
+    let u = random(i * 3u);
+    let v = random(i * 3u + 1u);
+    let w = random(i * 3u + 2u);
+    let theta = u * 6.2831853;
+    let y = v * 2.0 - 1.0;
+    let ring = sqrt(1.0 - y * y);
+    // the cube root spreads them evenly through the volume instead of bunching up in the middle
+    let offset = vec3<f32>(ring * cos(theta), y, ring * sin(theta)) * pow(w, 1.0 / 3.0) * sim.cloud.w;
+
+    let radius = max(length(offset.xz), 1.0);
+    let tangent = normalize(vec3<f32>(-offset.z, 0.0, offset.x) + vec3<f32>(0.0001, 0.0, 0.0));
+    let orbit_speed = sqrt(sim.attractor.w / radius);
+
+    dst[i] = Particle(vec4<f32>(sim.cloud.xyz + offset, 1.0), vec4<f32>(tangent * orbit_speed, 0.0));
+}
+
+@compute @workgroup_size(256)
+fn cs_update(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= sim.count.x {
+        return;
+    }
+
+    let dt = sim.params.x;
+    let particle = src[i];
+    let to_attractor = sim.attractor.xyz - particle.position.xyz;
+    let dist2 = dot(to_attractor, to_attractor) + SOFTENING;
+    let acceleration = to_attractor * (sim.attractor.w / (dist2 * sqrt(dist2)));
+
+    let velocity = (particle.velocity.xyz + acceleration * dt) * (1.0 - DAMPING * dt);
+    let position = particle.position.xyz + velocity * dt;
+    dst[i] = Particle(vec4<f32>(position, 1.0), vec4<f32>(velocity, 0.0));
+}
+
+struct InstanceInput {
+    @location(0) position: vec4<f32>,
+    @location(1) velocity: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // -1 to 1 across the quad
+    @location(0) corner: vec2<f32>,
+    @location(1) color: vec3<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, instance: InstanceInput) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, -1.0), vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, 1.0), vec2<f32>(-1.0, 1.0),
+    );
+    let corner = corners[index];
+    // the rows of the view rotation are the camera's right and up in world space
+    let right = vec3<f32>(sim.view[0].x, sim.view[1].x, sim.view[2].x);
+    let up = vec3<f32>(sim.view[0].y, sim.view[1].y, sim.view[2].y);
+    let offset = right * corner.x + up * corner.y;
+    let world_pos = instance.position.xyz + offset * PARTICLE_SIZE;
+    let heat = clamp(length(instance.velocity.xyz) / HOT_SPEED, 0.0, 1.0);
+
+    var out: VertexOutput;
+    out.clip_position = sim.view_proj * vec4<f32>(world_pos, 1.0);
+    out.corner = corner;
+    out.color = mix(vec3<f32>(0.1, 0.3, 1.0), vec3<f32>(1.0, 0.5, 0.1), heat);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let falloff = clamp(1.0 - dot(in.corner, in.corner), 0.0, 1.0);
+    var color = in.color * falloff * BRIGHTNESS;
+    color = apply_output_gamma(color);
+    // added on top of the scene, the alpha is left as it is
+    return vec4<f32>(color, 0.0);
+}
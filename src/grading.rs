@@ -0,0 +1,175 @@
+use log::warn;
+
+use crate::graphics;
+
+// the looks cycled through after none, each a lookup table laid out as a strip of size square
+// slices side by side. red runs across each slice, green down it and blue from one slice to the
+// next, all in srgb
+const LOOKS: [(&str, &[u8]); 4] = [
+    ("warm", include_bytes!("../res/luts/warm.png")),
+    ("cool", include_bytes!("../res/luts/cool.png")),
+    ("noir", include_bytes!("../res/luts/noir.png")),
+    ("teal_orange", include_bytes!("../res/luts/teal_orange.png")),
+];
+
+struct Look {
+    name: &'static str,
+    bind_group: wgpu::BindGroup,
+}
+
+// color grading lookup tables as 3d textures, applied by the post pass to the finished scene
+pub struct ColorGrading {
+    bind_group_layout: wgpu::BindGroupLayout,
+    looks: Vec<Look>,
+    // into looks, None leaves the colors alone
+    current: Option<usize>,
+}
+
+impl ColorGrading {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("grading_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D3,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        // blends between the table's entries, so a small table still grades smoothly
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("grading_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let looks = LOOKS
+            .iter()
+            .filter_map(|&(name, bytes)| match load_lut(device, queue, name, bytes) {
+                Ok(view) => {
+                    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: Some(name),
+                        layout: &bind_group_layout,
+                        entries: &[
+                            wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: wgpu::BindingResource::TextureView(&view),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 1,
+                                resource: wgpu::BindingResource::Sampler(&sampler),
+                            },
+                        ],
+                    });
+                    Some(Look { name, bind_group })
+                }
+                Err(e) => {
+                    warn!("Skipping the {} color grade: {}", name, e);
+                    None
+                }
+            })
+            .collect();
+
+        ColorGrading {
+            bind_group_layout,
+            looks,
+            current: None,
+        }
+    }
+
+    pub fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    // the look being applied, None when the colors are left alone
+    pub fn current(&self) -> Option<&wgpu::BindGroup> {
+        self.current.map(|i| &self.looks[i].bind_group)
+    }
+
+    pub fn name(&self) -> &str {
+        self.current.map_or("none", |i| self.looks[i].name)
+    }
+
+    pub fn index(&self) -> Option<usize> {
+        self.current
+    }
+
+    pub fn set_index(&mut self, index: Option<usize>) {
+        self.current = index.filter(|&i| i < self.looks.len());
+    }
+
+    // none, then each of the looks in turn, then none again
+    pub fn cycle(&mut self) {
+        self.current = match self.current {
+            None if !self.looks.is_empty() => Some(0),
+            Some(i) if i + 1 < self.looks.len() => Some(i + 1),
+            _ => None,
+        };
+    }
+}
+
+// the strip's slices stacked into a cube
+fn load_lut(device: &wgpu::Device, queue: &wgpu::Queue, name: &str, bytes: &[u8]) -> Result<wgpu::TextureView, String> {
+    let image = image::load_from_memory(bytes).map_err(|e| e.to_string())?.into_rgba8();
+    let (width, height) = image.dimensions();
+    if width != height * height {
+        return Err(format!("a {}x{} strip isn't {} slices of {}x{}", width, height, height, height, height));
+    }
+
+    // the strip's rows run across every slice, the texture wants each slice's rows together
+    let row_bytes = (height * 4) as usize;
+    let mut texels = Vec::with_capacity(image.as_raw().len());
+    for slice in 0..height as usize {
+        for row in image.as_raw().chunks(width as usize * 4) {
+            texels.extend_from_slice(&row[slice * row_bytes..(slice + 1) * row_bytes]);
+        }
+    }
+
+    let size = wgpu::Extent3d {
+        width: height,
+        height,
+        depth_or_array_layers: height,
+    };
+    let texture = graphics::create_texture(device, &wgpu::TextureDescriptor {
+        label: Some(&format!("grading_lut_{}", name)),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D3,
+        // the table is srgb in and out, the shader converts around it
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+    });
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        &texels,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: std::num::NonZeroU32::new(height * 4),
+            rows_per_image: std::num::NonZeroU32::new(height),
+        },
+        size,
+    );
+    Ok(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+}
@@ -1,23 +1,58 @@
-const WIREFRAME: bool = false;
-const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+use log::{error, info, warn};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use wgpu::util::DeviceExt;
+
+use crate::error::AppError;
+
+// with a stencil so portals can mask out where the view through them goes
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24PlusStencil8;
+#[cfg(not(target_arch = "wasm32"))]
+const DEFAULT_BACKENDS: wgpu::Backends = wgpu::Backends::PRIMARY;
+#[cfg(target_arch = "wasm32")]
+const DEFAULT_BACKENDS: wgpu::Backends = wgpu::Backends::BROWSER_WEBGPU;
+// shared by the pipeline, the depth buffer and the msaa color target so they always agree
+pub const SAMPLE_COUNT: u32 = 4;
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, Default)]
 pub struct Vertex {
     pub position: [f32; 3],
     pub tex_coords: [f32; 2],
+    // zero for meshes without normals, which get flat shading from the shader instead
+    pub normal: [f32; 3],
+}
+
+// a vertex moved by up to four bones of a skeleton, see skinning.rs
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, Default)]
+pub struct SkinnedVertex {
+    pub position: [f32; 3],
+    pub tex_coords: [f32; 2],
+    pub normal: [f32; 3],
+    // indices into the skin's joints, with how much each one pulls the vertex along
+    pub joints: [u16; 4],
+    pub weights: [f32; 4],
 }
 
 #[derive(Clone)]
 pub struct Instance {
     pub trans: cgmath::Vector3<f32>,
     pub rot: cgmath::Quaternion<f32>,
+    // along the instance's own axes, before it's turned
+    pub scale: cgmath::Vector3<f32>,
+    // multiplies the texture's color, NO_TINT leaves it as it is
+    pub tint: [f32; 4],
 }
 
+pub const NO_TINT: [f32; 4] = [1.0; 4];
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct InstanceRaw {
     pub model_mat: RawMatrix,
+    pub tint: [f32; 4],
 }
 
 #[repr(C)]
@@ -26,6 +61,19 @@ pub struct RawMatrix {
     pub mat: [[f32; 4]; 4],
 }
 
+// one per pass and seam copy in the camera buffer
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniform {
+    pub view_proj: [[f32; 4]; 4],
+    // world space plane, anything on its negative side is left out. NO_CLIP keeps everything
+    pub clip_plane: [f32; 4],
+}
+
+impl CameraUniform {
+    pub const NO_CLIP: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+}
+
 impl Vertex {
     pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
         use std::mem::size_of;
@@ -43,21 +91,120 @@ impl Vertex {
                     shader_location: 1,
                     format: wgpu::VertexFormat::Float32x2,
                 },
+                wgpu::VertexAttribute { // normal
+                    offset: size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+            ],
+        }
+    }
+}
+
+impl SkinnedVertex {
+    // the same locations as Vertex for what they have in common
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem::size_of;
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<SkinnedVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute { // position
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute { // tex coords
+                    offset: size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute { // normal
+                    offset: size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute { // joints
+                    offset: size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Uint16x4,
+                },
+                wgpu::VertexAttribute { // weights
+                    offset: (size_of::<[f32; 8]>() + size_of::<[u16; 4]>()) as wgpu::BufferAddress,
+                    shader_location: 8,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
             ],
         }
     }
 }
 
 impl Instance {
+    pub fn matrix(&self) -> cgmath::Matrix4<f32> {
+        cgmath::Matrix4::from_translation(self.trans)
+            * cgmath::Matrix4::from(self.rot)
+            * cgmath::Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+
     pub fn as_raw(&self) -> InstanceRaw {
         InstanceRaw { 
             model_mat: RawMatrix { 
-                mat: (cgmath::Matrix4::from_translation(self.trans) * cgmath::Matrix4::from(self.rot)).into()
-            }
+                mat: self.matrix().into()
+            },
+            tint: self.tint,
         }
     }
 }
 
+// what the second binding of the main bind group holds, the model matrix followed by one matrix
+// and tint per instance. the vertex shader picks the instance's by instance_index, so objects drawn
+// once get a single identity instance. the model's tint is unused
+pub fn transforms(model: cgmath::Matrix4<f32>, instances: &[InstanceRaw]) -> Vec<InstanceRaw> {
+    let identity = [InstanceRaw { model_mat: RawMatrix::new(), tint: NO_TINT }];
+    let instances = if instances.is_empty() { &identity[..] } else { instances };
+    std::iter::once(InstanceRaw { model_mat: RawMatrix { mat: model.into() }, tint: NO_TINT })
+        .chain(instances.iter().copied())
+        .collect()
+}
+
+// pushed before drawing an object without instances where the device has push constants, so its
+// model matrix doesn't need writing to its transforms every frame. see DrawConstants in shader.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DrawConstants {
+    pub model: [[f32; 4]; 4],
+    pub flags: u32,
+    // the struct is padded to 16 bytes on the shader's side
+    _padding: [u32; 3],
+}
+
+impl DrawConstants {
+    pub const SIZE: u32 = std::mem::size_of::<Self>() as u32;
+    // where flags starts, to clear them after a draw
+    pub const FLAGS_OFFSET: u32 = std::mem::size_of::<[[f32; 4]; 4]>() as u32;
+    // use model instead of the first of the object's transforms
+    pub const OWN_MODEL: u32 = 1;
+
+    pub fn new(model: cgmath::Matrix4<f32>, flags: u32) -> Self {
+        DrawConstants { model: model.into(), flags, _padding: [0; 3] }
+    }
+}
+
+// whether the main pipelines take DrawConstants, the device only has the feature when the adapter
+// had room for them
+pub fn has_push_constants(device: &wgpu::Device) -> bool {
+    device.features().contains(wgpu::Features::PUSH_CONSTANTS)
+}
+
+// the transforms of an object drawn once, the model matrix is at offset 0 to be written again
+pub fn create_transforms_buffer(device: &wgpu::Device, label: &str, model: cgmath::Matrix4<f32>) -> wgpu::Buffer {
+    create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+        label: Some(label),
+        contents: bytemuck::cast_slice(&transforms(model, &[])),
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
 impl InstanceRaw {
     pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
         use std::mem::size_of;
@@ -84,6 +231,11 @@ impl InstanceRaw {
                     offset: (size_of::<[f32; 4]>() * 3) as wgpu::BufferAddress,
                     shader_location: 5,
                     format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute { // tint
+                    offset: (size_of::<[f32; 4]>() * 4) as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float32x4,
                 }
             ],
         }
@@ -103,50 +255,337 @@ impl RawMatrix {
     }
 }
 
-pub fn create_wgpu_context(
-    window: &winit::window::Window,
-) -> (
-    wgpu::Surface,
+// where frames end up: the window's swapchain, or a plain texture when running headless
+pub enum RenderTarget {
+    Surface(wgpu::Surface),
+    Offscreen(wgpu::Texture),
+}
+
+// which backends/adapter to create the device from
+pub struct ContextOptions {
+    pub backends: wgpu::Backends,
+    // index into the startup adapter listing, or (part of) an adapter name
+    pub adapter: Option<String>,
+    pub vsync: bool,
+}
+
+impl ContextOptions {
+    pub fn new(backends: Option<&str>, adapter: Option<&str>, vsync: bool) -> Self {
+        ContextOptions {
+            backends: select_backends(backends),
+            adapter: adapter.map(str::to_owned),
+            vsync,
+        }
+    }
+}
+
+// an explicit comma separated list (e.g. from the command line) wins over the WGPU_BACKEND env var,
+// which wins over the platform's primary backends (vulkan/metal/dx12/webgpu)
+fn select_backends(names: Option<&str>) -> wgpu::Backends {
+    let backends = names
+        .map(|names| wgpu::util::parse_backends_from_comma_list(&names.to_lowercase()))
+        .or_else(wgpu::util::backend_bits_from_env)
+        .unwrap_or(DEFAULT_BACKENDS);
+
+    if backends.is_empty() {
+        warn!("No known backend in {:?}, falling back to the defaults", names);
+        return DEFAULT_BACKENDS;
+    }
+    backends
+}
+
+// what App is built from, windowed or not. the instance and adapter are kept for the surfaces
+// of any windows opened later
+pub type WgpuContext = (
+    RenderTarget,
+    (wgpu::Instance, wgpu::Adapter),
     wgpu::Device,
     wgpu::Queue,
     wgpu::SurfaceConfiguration,
     wgpu::ShaderModule,
-) {
+);
+
+pub async fn create_wgpu_context(
+    window: &winit::window::Window,
+    options: &ContextOptions,
+) -> Result<WgpuContext, AppError> {
     let size = window.inner_size();
-    let instance = wgpu::Instance::new(wgpu::Backends::VULKAN);
+    let instance = wgpu::Instance::new(options.backends);
     let surface = unsafe { instance.create_surface(window) };
-    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
-        power_preference: wgpu::PowerPreference::HighPerformance,
-        compatible_surface: Some(&surface),
-        force_fallback_adapter: false,
-    }))
-    .expect("Failed to retrieve adapter");
-
-    let (device, queue) = pollster::block_on(adapter.request_device(
-        &wgpu::DeviceDescriptor {
-            features: wgpu::Features::POLYGON_MODE_LINE,
-            limits: wgpu::Limits::default(),
-            label: Some("main_device"),
-        },
-        None,
-    ))
-    .expect("Failed to retrieve device");
+    let (adapter, device, queue) = request_device(&instance, options, Some(&surface)).await?;
 
     let config = wgpu::SurfaceConfiguration {
-        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-        format: surface.get_supported_formats(&adapter)[0],
+        usage: surface_usage(adapter.get_info().backend),
+        format: pick_surface_format(&surface.get_supported_formats(&adapter)),
         width: size.width,
         height: size.height,
-        present_mode: wgpu::PresentMode::Fifo,
+        present_mode: pick_present_mode(&surface.get_supported_modes(&adapter), options.vsync),
     };
     surface.configure(&device, &config);
 
-    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-        label: Some("shader at shader.wgsl"),
-        source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
+    let shader = create_shader(&device, config.format);
+
+    Ok((RenderTarget::Surface(surface), (instance, adapter), device, queue, config, shader))
+}
+
+// COPY_SRC lets recordings read frames straight back from the surface. wgpu 0.13 can't tell what
+// usages a surface supports, only its formats and present modes, so it goes by the backend: metal
+// and gl surfaces can't be copied from, and configuring them with it is an error. their frames
+// are recorded through a capture::CaptureTarget instead
+fn surface_usage(backend: wgpu::Backend) -> wgpu::TextureUsages {
+    match backend {
+        wgpu::Backend::Vulkan | wgpu::Backend::Dx12 | wgpu::Backend::BrowserWebGpu => {
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC
+        }
+        _ => wgpu::TextureUsages::RENDER_ATTACHMENT,
+    }
+}
+
+pub async fn create_headless_context(
+    width: u32,
+    height: u32,
+    options: &ContextOptions,
+) -> Result<WgpuContext, AppError> {
+    let instance = wgpu::Instance::new(options.backends);
+    let (adapter, device, queue) = request_device(&instance, options, None).await?;
+
+    // never used to configure a surface, but keeps the size/format bookkeeping identical to windowed mode
+    let config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        width,
+        height,
+        present_mode: wgpu::PresentMode::Fifo,
+    };
+
+    let target = create_offscreen_target(&device, &config);
+    let shader = create_shader(&device, config.format);
+
+    Ok((RenderTarget::Offscreen(target), (instance, adapter), device, queue, config, shader))
+}
+
+pub fn create_offscreen_target(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+) -> wgpu::Texture {
+    create_texture(device, &wgpu::TextureDescriptor {
+        label: Some("offscreen_target"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: config.usage,
+    })
+}
+
+async fn request_device(
+    instance: &wgpu::Instance,
+    options: &ContextOptions,
+    compatible_surface: Option<&wgpu::Surface>,
+) -> Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue), AppError> {
+    let adapter = match find_adapter(instance, options, compatible_surface) {
+        Some(adapter) => adapter,
+        None => instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                compatible_surface,
+                force_fallback_adapter: false,
+            })
+            .await
+            .ok_or(AppError::NoAdapter)?,
+    };
+    let info = adapter.get_info();
+    info!("Using adapter: {} ({:?}, {:?})", info.name, info.backend, info.device_type);
+
+    // line polygon mode isn't available on webgpu, only ask for it where the adapter has it
+    let mut features = adapter.features() & wgpu::Features::POLYGON_MODE_LINE;
+    let mut limits = if cfg!(target_arch = "wasm32") {
+        wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits())
+    } else {
+        wgpu::Limits::default()
+    };
+    // same for push constants, the main pipelines use uniforms for everything without them
+    if adapter.features().contains(wgpu::Features::PUSH_CONSTANTS)
+        && adapter.limits().max_push_constant_size >= DrawConstants::SIZE
+    {
+        features |= wgpu::Features::PUSH_CONSTANTS;
+        limits.max_push_constant_size = DrawConstants::SIZE;
+    }
+    info!("Push constants: {}", features.contains(wgpu::Features::PUSH_CONSTANTS));
+
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                features,
+                limits,
+                label: Some("main_device"),
+            },
+            None,
+        )
+        .await?;
+
+    Ok((adapter, device, queue))
+}
+
+// how wgpu words the errors from a device that's gone, after a driver reset or the gpu being
+// switched or unplugged. wgpu 0.13 has no variant or callback for it: the core wraps its
+// DeviceError::Lost transparently, so it never turns up as a source that could be downcast to,
+// and the message is all there is to go on
+const DEVICE_LOST_MESSAGE: &str = "device is lost";
+
+// wgpu panics on every error it isn't asked to catch unless it's given a handler. this one logs
+// them and carries on, except for those from a lost device, which set the returned flag for the
+// event loop to check once a frame and build everything again on a new device
+pub fn watch_errors(device: &wgpu::Device) -> Arc<AtomicBool> {
+    let lost = Arc::new(AtomicBool::new(false));
+    let flag = lost.clone();
+    device.on_uncaptured_error(move |error| match error {
+        wgpu::Error::Validation { description, .. } if description.contains(DEVICE_LOST_MESSAGE) => {
+            if !flag.swap(true, Ordering::Relaxed) {
+                error!("The device was lost: {}", description);
+            }
+        }
+        error => error!("wgpu error: {}", error),
     });
+    lost
+}
 
-    (surface, device, queue, config, shader)
+// lists the usable adapters and returns the one asked for in options, if any.
+// None means wgpu's own (high performance) choice should be used
+#[cfg(not(target_arch = "wasm32"))]
+fn find_adapter(
+    instance: &wgpu::Instance,
+    options: &ContextOptions,
+    compatible_surface: Option<&wgpu::Surface>,
+) -> Option<wgpu::Adapter> {
+    let mut adapters = instance
+        .enumerate_adapters(options.backends)
+        .filter(|adapter| compatible_surface.is_none_or(|surface| adapter.is_surface_supported(surface)))
+        .collect::<Vec<_>>();
+
+    info!("Available adapters for {:?}:", options.backends);
+    for (i, adapter) in adapters.iter().enumerate() {
+        let info = adapter.get_info();
+        info!("    [{}] {} ({:?}, {:?})", i, info.name, info.backend, info.device_type);
+    }
+
+    let choice = options.adapter.as_ref()?;
+    let index = match choice.parse::<usize>() {
+        Ok(index) if index < adapters.len() => Some(index),
+        Ok(_) => None,
+        Err(_) => adapters
+            .iter()
+            .position(|adapter| adapter.get_info().name.to_lowercase().contains(&choice.to_lowercase())),
+    };
+
+    match index {
+        Some(index) => Some(adapters.swap_remove(index)),
+        None => {
+            warn!("No adapter matching {:?}, letting wgpu choose", choice);
+            None
+        }
+    }
+}
+
+// adapters can't be enumerated in the browser
+#[cfg(target_arch = "wasm32")]
+fn find_adapter(
+    _instance: &wgpu::Instance,
+    _options: &ContextOptions,
+    _compatible_surface: Option<&wgpu::Surface>,
+) -> Option<wgpu::Adapter> {
+    None
+}
+
+// the scene is lit and blended in linear space, so an srgb target keeps brightness consistent
+// across platforms. the first supported format is used when there isn't one
+fn pick_surface_format(formats: &[wgpu::TextureFormat]) -> wgpu::TextureFormat {
+    match formats.iter().find(|format| format.describe().srgb) {
+        Some(format) => *format,
+        None => {
+            warn!("No srgb surface format available, gamma correcting in the shader instead");
+            formats[0]
+        }
+    }
+}
+
+// wgpu 0.13 won't fall back from a mode the surface doesn't support, configuring it is an error.
+// gl only presents with fifo, and so does metal on older macs, so without vsync immediate is used
+// where it's there, then mailbox. fifo is always supported
+fn pick_present_mode(modes: &[wgpu::PresentMode], vsync: bool) -> wgpu::PresentMode {
+    let preferred = [wgpu::PresentMode::Immediate, wgpu::PresentMode::Mailbox];
+    match preferred.into_iter().find(|mode| !vsync && modes.contains(mode)) {
+        Some(mode) => mode,
+        None => {
+            if !vsync {
+                warn!("Vsync can't be turned off on this surface, presenting with fifo");
+            }
+            wgpu::PresentMode::Fifo
+        }
+    }
+}
+
+// true when writing to this format won't apply the linear -> srgb conversion for us
+pub fn needs_manual_gamma(format: wgpu::TextureFormat) -> bool {
+    !format.describe().srgb
+}
+
+fn create_shader(device: &wgpu::Device, format: wgpu::TextureFormat) -> wgpu::ShaderModule {
+    create_templated_shader(device, format, "shader.wgsl", include_str!("shader.wgsl"))
+}
+
+// for shaders that write to the surface, with gamma.wgsl put in front so they can gamma correct
+// themselves when the format won't
+pub fn create_templated_shader(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    name: &str,
+    source: &str,
+) -> wgpu::ShaderModule {
+    let source = format!("{}\n{}", include_str!("gamma.wgsl"), source)
+        .replace("MANUAL_GAMMA_VALUE", &needs_manual_gamma(format).to_string())
+        .replace("DRAW_CONSTANTS_SPACE", if has_push_constants(device) { "push_constant" } else { "private" });
+
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(&format!("shader at {}", name)),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    })
+}
+
+// the variants of the main pipeline, they share the shader and the bind group layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipelineKind {
+    Fill,
+    // every fragment is added on top of what's there without any depth testing,
+    // so the picture shows how many times each pixel gets drawn
+    Overdraw,
+    // needs Features::POLYGON_MODE_LINE
+    Wireframe,
+    // flat colored edges drawn over the filled scene, pulled towards the camera so they win the depth test
+    WireframeOverlay,
+    // filled like Fill, for SkinnedVertex meshes with their bones bound as the second group
+    Skinned,
+    // filled like Fill, with the morph targets' deltas and weights bound as the second group
+    Morph,
+    // filled like Fill and blended with the planar reflection bound as the second group
+    Reflective,
+    // shiny like chrome, reflecting the sky's cubemap bound as the second group
+    EnvMapped,
+    // unlit and glowing in the color bound as the second group
+    Emissive,
+    // filled like Fill, only where the stencil equals the pass's reference, for the view through a portal
+    Stenciled,
+    // unlit, showing the texture bound as the second group across its uv, for the tv
+    Screen,
+    // only writes depth, in a pass of its own before the main one
+    DepthPrepass,
+    // filled like Fill where the depth prepass left the nearest surface, without writing depth
+    Prepassed,
 }
 
 pub fn build_pipeline(
@@ -154,27 +593,70 @@ pub fn build_pipeline(
     device: &wgpu::Device,
     shader: &wgpu::ShaderModule,
     config: &wgpu::SurfaceConfiguration,
+    kind: PipelineKind,
 ) -> wgpu::RenderPipeline {
+    let additive = wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::One,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    };
+    let overdraw = kind == PipelineKind::Overdraw;
+    let overlay = kind == PipelineKind::WireframeOverlay;
+
+    let push_constant_ranges = if has_push_constants(device) {
+        vec![wgpu::PushConstantRange { stages: wgpu::ShaderStages::VERTEX, range: 0..DrawConstants::SIZE }]
+    } else {
+        Vec::new()
+    };
     let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("main_pipeline_layout"),
         bind_group_layouts,
-        push_constant_ranges: &[],
+        push_constant_ranges: &push_constant_ranges,
     });
 
     let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some("main_pipeline"),
+        label: Some(match kind {
+            PipelineKind::Fill => "main_pipeline",
+            PipelineKind::Overdraw => "overdraw_pipeline",
+            PipelineKind::Wireframe => "wireframe_pipeline",
+            PipelineKind::WireframeOverlay => "wireframe_overlay_pipeline",
+            PipelineKind::Skinned => "skinned_pipeline",
+            PipelineKind::Morph => "morph_pipeline",
+            PipelineKind::Reflective => "reflective_pipeline",
+            PipelineKind::EnvMapped => "env_mapped_pipeline",
+            PipelineKind::Emissive => "emissive_pipeline",
+            PipelineKind::Stenciled => "stenciled_pipeline",
+            PipelineKind::Screen => "screen_pipeline",
+            PipelineKind::DepthPrepass => "depth_prepass_pipeline",
+            PipelineKind::Prepassed => "prepassed_pipeline",
+        }),
         layout: Some(&render_pipeline_layout),
         vertex: wgpu::VertexState {
-            module: &shader,
-            entry_point: "vs_main",
-            buffers: &[Vertex::desc(), InstanceRaw::desc()],
+            module: shader,
+            entry_point: match kind {
+                PipelineKind::Skinned => "vs_skinned",
+                PipelineKind::Morph => "vs_morph",
+                _ => "vs_main",
+            },
+            buffers: &[if kind == PipelineKind::Skinned { SkinnedVertex::desc() } else { Vertex::desc() }],
         },
-        fragment: Some(wgpu::FragmentState {
-            module: &shader,
-            entry_point: "fs_main",
+        fragment: (kind != PipelineKind::DepthPrepass).then_some(wgpu::FragmentState {
+            module: shader,
+            entry_point: match kind {
+                PipelineKind::WireframeOverlay => "fs_wireframe",
+                PipelineKind::Reflective => "fs_reflective",
+                PipelineKind::EnvMapped => "fs_env_mapped",
+                PipelineKind::Emissive => "fs_emissive",
+                PipelineKind::Screen => "fs_screen",
+                _ => "fs_main",
+            },
             targets: &[Some(wgpu::ColorTargetState {
                 format: config.format,
-                blend: Some(wgpu::BlendState::REPLACE),
+                blend: Some(if overdraw {
+                    wgpu::BlendState { color: additive, alpha: additive }
+                } else {
+                    wgpu::BlendState::REPLACE
+                }),
                 write_mask: wgpu::ColorWrites::ALL,
             })],
         }),
@@ -183,23 +665,39 @@ pub fn build_pipeline(
             strip_index_format: None,
             front_face: wgpu::FrontFace::Ccw,
             cull_mode: None,
-            polygon_mode: if WIREFRAME {
-                wgpu::PolygonMode::Line
-            } else {
-                wgpu::PolygonMode::Fill
+            polygon_mode: match kind {
+                PipelineKind::Wireframe | PipelineKind::WireframeOverlay => wgpu::PolygonMode::Line,
+                _ => wgpu::PolygonMode::Fill,
             },
             unclipped_depth: false,
             conservative: false,
         },
         depth_stencil: Some(wgpu::DepthStencilState {
             format: DEPTH_FORMAT,
-            depth_write_enabled: true,
-            depth_compare: wgpu::CompareFunction::Less,
-            stencil: wgpu::StencilState::default(),
-            bias: wgpu::DepthBiasState::default(),
+            depth_write_enabled: !overdraw && !overlay && kind != PipelineKind::Prepassed,
+            depth_compare: match kind {
+                PipelineKind::Overdraw => wgpu::CompareFunction::Always,
+                PipelineKind::WireframeOverlay => wgpu::CompareFunction::LessEqual,
+                PipelineKind::Prepassed => wgpu::CompareFunction::Equal,
+                _ => wgpu::CompareFunction::Less,
+            },
+            stencil: if kind == PipelineKind::Stenciled {
+                let equal = wgpu::StencilFaceState {
+                    compare: wgpu::CompareFunction::Equal,
+                    ..Default::default()
+                };
+                wgpu::StencilState { front: equal, back: equal, read_mask: !0, write_mask: 0 }
+            } else {
+                wgpu::StencilState::default()
+            },
+            bias: if overlay {
+                wgpu::DepthBiasState { constant: -2, slope_scale: -1.0, clamp: 0.0 }
+            } else {
+                wgpu::DepthBiasState::default()
+            },
         }),
         multisample: wgpu::MultisampleState {
-            count: 1,
+            count: SAMPLE_COUNT,
             mask: !0,
             alpha_to_coverage_enabled: false,
         },
@@ -209,42 +707,222 @@ pub fn build_pipeline(
     render_pipeline
 }
 
+// each pass recorded into a command buffer of its own, on rayon's threads. the buffers come back
+// in the passes' order, ready for a single submit
+#[cfg(not(target_arch = "wasm32"))]
+pub fn encode_passes<P: Sync>(
+    device: &wgpu::Device,
+    passes: &[P],
+    encode: impl Fn(&P, &mut wgpu::CommandEncoder) + Sync,
+) -> Vec<wgpu::CommandBuffer> {
+    use rayon::prelude::*;
+    passes.par_iter().map(|pass| encode_pass(device, pass, &encode)).collect()
+}
+
+// there are no threads to share the work with on the web
+#[cfg(target_arch = "wasm32")]
+pub fn encode_passes<P>(
+    device: &wgpu::Device,
+    passes: &[P],
+    encode: impl Fn(&P, &mut wgpu::CommandEncoder),
+) -> Vec<wgpu::CommandBuffer> {
+    passes.iter().map(|pass| encode_pass(device, pass, &encode)).collect()
+}
+
+fn encode_pass<P>(device: &wgpu::Device, pass: &P, encode: &impl Fn(&P, &mut wgpu::CommandEncoder)) -> wgpu::CommandBuffer {
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("pass_encoder"),
+    });
+    encode(pass, &mut encoder);
+    encoder.finish()
+}
+
+// what the main shader outputs, cycled through to look at the geometry instead of the shading
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewMode {
+    Lit,
+    Normals,
+    Depth,
+    Uvs,
+    Overdraw,
+}
+
+impl ViewMode {
+    pub fn next(self) -> Self {
+        match self {
+            ViewMode::Lit => ViewMode::Normals,
+            ViewMode::Normals => ViewMode::Depth,
+            ViewMode::Depth => ViewMode::Uvs,
+            ViewMode::Uvs => ViewMode::Overdraw,
+            ViewMode::Overdraw => ViewMode::Lit,
+        }
+    }
+
+    // the switch cases in shader.wgsl
+    pub fn index(self) -> u32 {
+        self as u32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireframeMode {
+    Off,
+    // only the edges, shaded like the filled faces would be
+    Only,
+    // the filled scene with its edges drawn on top
+    Overlay,
+}
+
+impl WireframeMode {
+    pub fn next(self) -> Self {
+        match self {
+            WireframeMode::Off => WireframeMode::Only,
+            WireframeMode::Only => WireframeMode::Overlay,
+            WireframeMode::Overlay => WireframeMode::Off,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceKind {
+    Buffer,
+    Texture,
+}
+
+// the size of everything made through create_buffer, create_buffer_init and create_texture, by
+// label and id. wgpu doesn't say when a resource is freed, so those all have id 0 and a new one
+// replaces whatever had its label before. that covers everything rebuilt on resize or scene
+// changes. resources made many at a time under one label, like chunks, are made through
+// create_counted_buffer_init instead, with an id of their own that's removed when they're dropped
+static RESOURCES: Mutex<BTreeMap<(String, u64), (ResourceKind, u64)>> = Mutex::new(BTreeMap::new());
+static NEXT_RESOURCE_ID: AtomicU64 = AtomicU64::new(1);
+
+fn track_resource(label: wgpu::Label, id: u64, kind: ResourceKind, size: u64) {
+    let label = label.unwrap_or("unlabeled").to_string();
+    if let Ok(mut resources) = RESOURCES.lock() {
+        resources.insert((label, id), (kind, size));
+    }
+}
+
+// a buffer that's counted for as long as it's alive rather than by its label
+pub struct CountedBuffer {
+    buffer: wgpu::Buffer,
+    key: (String, u64),
+}
+
+impl std::ops::Deref for CountedBuffer {
+    type Target = wgpu::Buffer;
+
+    fn deref(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+}
+
+impl Drop for CountedBuffer {
+    fn drop(&mut self) {
+        if let Ok(mut resources) = RESOURCES.lock() {
+            resources.remove(&self.key);
+        }
+    }
+}
+
+pub fn create_buffer(device: &wgpu::Device, desc: &wgpu::BufferDescriptor) -> wgpu::Buffer {
+    track_resource(desc.label, 0, ResourceKind::Buffer, desc.size);
+    device.create_buffer(desc)
+}
+
+pub fn create_buffer_init(device: &wgpu::Device, desc: &wgpu::util::BufferInitDescriptor) -> wgpu::Buffer {
+    track_resource(desc.label, 0, ResourceKind::Buffer, init_size(desc));
+    device.create_buffer_init(desc)
+}
+
+pub fn create_counted_buffer_init(device: &wgpu::Device, desc: &wgpu::util::BufferInitDescriptor) -> CountedBuffer {
+    let id = NEXT_RESOURCE_ID.fetch_add(1, Ordering::Relaxed);
+    track_resource(desc.label, id, ResourceKind::Buffer, init_size(desc));
+    CountedBuffer {
+        buffer: device.create_buffer_init(desc),
+        key: (desc.label.unwrap_or("unlabeled").to_string(), id),
+    }
+}
+
+// padded up to a multiple of 4 by wgpu
+fn init_size(desc: &wgpu::util::BufferInitDescriptor) -> u64 {
+    (desc.contents.len() as u64).div_ceil(wgpu::COPY_BUFFER_ALIGNMENT) * wgpu::COPY_BUFFER_ALIGNMENT
+}
+
+pub fn create_texture(device: &wgpu::Device, desc: &wgpu::TextureDescriptor) -> wgpu::Texture {
+    let info = desc.format.describe();
+    let (block_width, block_height) = (info.block_dimensions.0 as u32, info.block_dimensions.1 as u32);
+    let size = (0..desc.mip_level_count)
+        .map(|level| {
+            let mip = desc.size.mip_level_size(level, desc.dimension == wgpu::TextureDimension::D3);
+            let blocks = mip.width.div_ceil(block_width) as u64 * mip.height.div_ceil(block_height) as u64;
+            blocks * mip.depth_or_array_layers as u64 * info.block_size as u64
+        })
+        .sum::<u64>()
+        * desc.sample_count as u64;
+    track_resource(desc.label, 0, ResourceKind::Texture, size);
+    device.create_texture(desc)
+}
+
+// counts and bytes of everything tracked, with the biggest few by label
+pub struct ResourceStats {
+    pub buffers: (usize, u64),
+    pub textures: (usize, u64),
+    pub largest: Vec<(String, u64)>,
+}
+
+pub fn resource_stats(largest: usize) -> ResourceStats {
+    let resources = RESOURCES.lock().map(|resources| resources.clone()).unwrap_or_default();
+    let total = |kind| {
+        let sizes = resources.values().filter(|(k, _)| *k == kind).map(|(_, size)| size);
+        (sizes.clone().count(), sizes.sum())
+    };
+    let mut by_size = resources.iter().map(|((label, _), &(_, size))| (label.clone(), size)).collect::<Vec<_>>();
+    by_size.sort_by_key(|&(_, size)| std::cmp::Reverse(size));
+    by_size.truncate(largest);
+    ResourceStats {
+        buffers: total(ResourceKind::Buffer),
+        textures: total(ResourceKind::Texture),
+        largest: by_size,
+    }
+}
+
 pub fn build_bind_group(
     bind_group_layout: &wgpu::BindGroupLayout,
     tex_bytes: &[u8],
     name: &str,
     device: &wgpu::Device,
     queue: &wgpu::Queue,
-    uniforms: Vec<&wgpu::Buffer>,
-) -> wgpu::BindGroup {
-    let (view, sampler, _) = load_texture(device, queue, tex_bytes, name);
+    buffers: Vec<wgpu::BufferBinding>,
+) -> Result<wgpu::BindGroup, AppError> {
+    let (view, sampler, _) = load_texture(device, queue, tex_bytes, name)?;
 
     let mut entries = Vec::new();
 
-    for (i, buffer) in uniforms.iter().enumerate() {
+    let num_buffers = buffers.len() as u32;
+    for (i, buffer) in buffers.into_iter().enumerate() {
         entries.push(wgpu::BindGroupEntry {
             binding: i as u32,
-            resource: wgpu::BindingResource::Buffer(buffer.as_entire_buffer_binding()),
+            resource: wgpu::BindingResource::Buffer(buffer),
         });
     }
 
     entries.push(wgpu::BindGroupEntry {
-        binding: uniforms.len() as u32,
+        binding: num_buffers,
         resource: wgpu::BindingResource::TextureView(&view),
     });
 
     entries.push(wgpu::BindGroupEntry {
-        binding: uniforms.len() as u32 + 1,
+        binding: num_buffers + 1,
         resource: wgpu::BindingResource::Sampler(&sampler),
     });
 
-    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+    Ok(device.create_bind_group(&wgpu::BindGroupDescriptor {
         layout: bind_group_layout,
         entries: &entries,
         label: Some(name),
-    });
-
-    bind_group
+    }))
 }
 
 fn load_texture(
@@ -252,8 +930,8 @@ fn load_texture(
     queue: &wgpu::Queue,
     data: &[u8],
     name: &str,
-) -> (wgpu::TextureView, wgpu::Sampler, wgpu::Texture) {
-    let tex_img = image::load_from_memory(data).expect("Failed to load image");
+) -> Result<(wgpu::TextureView, wgpu::Sampler, wgpu::Texture), AppError> {
+    let tex_img = image::load_from_memory(data).map_err(|e| AppError::Texture(name.to_string(), e))?;
     let tex_rgba = tex_img.to_rgba8();
 
     use image::GenericImageView;
@@ -265,7 +943,7 @@ fn load_texture(
         depth_or_array_layers: 1,
     };
 
-    let tex = device.create_texture(&wgpu::TextureDescriptor {
+    let tex = create_texture(device, &wgpu::TextureDescriptor {
         size: tex_size,
         mip_level_count: 1,
         sample_count: 1,
@@ -302,7 +980,7 @@ fn load_texture(
         ..Default::default()
     });
 
-    (view, sampler, tex)
+    Ok((view, sampler, tex))
 }
 
 pub fn create_depth_texture(
@@ -316,11 +994,11 @@ pub fn create_depth_texture(
         depth_or_array_layers: 1,
     };
 
-    let tex = device.create_texture(&wgpu::TextureDescriptor {
+    let tex = create_texture(device, &wgpu::TextureDescriptor {
         label: Some(label),
         size,
         mip_level_count: 1,
-        sample_count: 1,
+        sample_count: SAMPLE_COUNT,
         dimension: wgpu::TextureDimension::D2,
         format: DEPTH_FORMAT,
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
@@ -342,3 +1020,38 @@ pub fn create_depth_texture(
 
     (view, sampler, tex)
 }
+
+// the depth half of a depth texture, the whole texture can't be bound for shaders to read
+pub fn create_depth_sample_view(texture: &wgpu::Texture) -> wgpu::TextureView {
+    texture.create_view(&wgpu::TextureViewDescriptor {
+        aspect: wgpu::TextureAspect::DepthOnly,
+        ..Default::default()
+    })
+}
+
+// multisampled color target that gets resolved into the frame, None when msaa is off
+pub fn create_msaa_target(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    label: &str,
+) -> Option<wgpu::TextureView> {
+    if SAMPLE_COUNT == 1 {
+        return None;
+    }
+
+    let tex = create_texture(device, &wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: SAMPLE_COUNT,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+    });
+
+    Some(tex.create_view(&wgpu::TextureViewDescriptor::default()))
+}
@@ -0,0 +1,251 @@
+use cgmath::{Deg, Matrix4, SquareMatrix, Vector3};
+use serde::{Deserialize, Serialize};
+
+use crate::graphics::{self, InstanceRaw, RawMatrix, Vertex};
+use crate::sky::LightUniform;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct GrassConfig {
+    // blades scattered over the floor, every one of them its own instance
+    pub count: u32,
+    pub seed: u32,
+    // of an average blade, each one is scaled randomly around these
+    pub height: f32,
+    pub width: f32,
+    // degrees around y the wind blows towards, 0 is +x
+    pub wind_direction: f32,
+    // how far the tips lean in the strongest gusts, relative to the blade's height
+    pub wind_strength: f32,
+}
+
+impl Default for GrassConfig {
+    fn default() -> Self {
+        GrassConfig {
+            count: 50_000,
+            seed: 1,
+            height: 2.5,
+            width: 0.25,
+            wind_direction: 30.0,
+            wind_strength: 0.4,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct GrassUniform {
+    view_proj: [[f32; 4]; 4],
+    // xy is the direction the wind blows in the xz plane, z the strength and w the time
+    wind: [f32; 4],
+    light: LightUniform,
+}
+
+// a field of grass blades, one small mesh drawn once per blade with a model matrix each. the
+// blades lean with the wind in the vertex shader, so nothing but the uniform changes per frame
+pub struct Grass {
+    pipeline: wgpu::RenderPipeline,
+    uniform: GrassUniform,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    vertices: wgpu::Buffer,
+    indices: wgpu::Buffer,
+    num_indices: u32,
+    instances: wgpu::Buffer,
+    count: u32,
+}
+
+impl Grass {
+    // rows of vertices up a blade, the tip is one more on top
+    const SEGMENTS: u32 = 4;
+
+    // scatters the blades over the rectangle from min to max in x and z, at min's height
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, config: &GrassConfig, min: [f32; 3], max: [f32; 3]) -> Self {
+        let shader = graphics::create_templated_shader(device, format, "grass.wgsl", include_str!("grass.wgsl"));
+
+        let uniform = GrassUniform {
+            view_proj: Matrix4::identity().into(),
+            wind: [0.0; 4],
+            light: bytemuck::Zeroable::zeroed(),
+        };
+        let uniform_buffer = graphics::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+            label: Some("grass_uniform_buffer"),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("grass_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("grass_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("grass_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("grass_pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc(), InstanceRaw::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            // the blades are flat cards seen from both sides
+            primitive: wgpu::PrimitiveState {
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: graphics::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: graphics::SAMPLE_COUNT,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let (blade_vertices, blade_indices) = Self::blade();
+        let instances = Self::scatter(config, min, max);
+        let wind_angle = config.wind_direction.to_radians();
+
+        Grass {
+            pipeline,
+            uniform: GrassUniform {
+                wind: [wind_angle.cos(), wind_angle.sin(), config.wind_strength, 0.0],
+                ..uniform
+            },
+            uniform_buffer,
+            bind_group,
+            vertices: graphics::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+                label: Some("vertices_grass"),
+                contents: bytemuck::cast_slice(&blade_vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+            indices: graphics::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+                label: Some("indices_grass"),
+                contents: bytemuck::cast_slice(&blade_indices),
+                usage: wgpu::BufferUsages::INDEX,
+            }),
+            num_indices: blade_indices.len() as u32,
+            instances: graphics::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+                label: Some("instances_grass"),
+                contents: bytemuck::cast_slice(&instances),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+            count: instances.len() as u32,
+        }
+    }
+
+    // a unit tall blade narrowing to a point, in the xy plane. tex_coords.y goes from 0 at the
+    // root to 1 at the tip, which is how much the wind moves each vertex
+    fn blade() -> (Vec<Vertex>, Vec<u16>) {
+        let mut vertices = Vec::new();
+        for row in 0..Self::SEGMENTS {
+            let h = row as f32 / Self::SEGMENTS as f32;
+            let half_width = 0.5 * (1.0 - h);
+            for (x, u) in [(-half_width, 0.0), (half_width, 1.0)] {
+                vertices.push(Vertex {
+                    position: [x, h, 0.0],
+                    tex_coords: [u, h],
+                    normal: [0.0, 0.0, 1.0],
+                });
+            }
+        }
+        vertices.push(Vertex {
+            position: [0.0, 1.0, 0.0],
+            tex_coords: [0.5, 1.0],
+            normal: [0.0, 0.0, 1.0],
+        });
+
+        let mut indices = Vec::new();
+        for row in 0..Self::SEGMENTS as u16 - 1 {
+            let (a, b, c, d) = (row * 2, row * 2 + 1, row * 2 + 2, row * 2 + 3);
+            indices.extend([a, b, d, a, d, c]);
+        }
+        let top = Self::SEGMENTS as u16 * 2 - 2;
+        indices.extend([top, top + 1, top + 2]);
+        (vertices, indices)
+    }
+
+    // random positions, turns around y and sizes, the same every time for a given seed
+    fn scatter(config: &GrassConfig, min: [f32; 3], max: [f32; 3]) -> Vec<InstanceRaw> {
+        // xorshift, zero would get it stuck so it's nudged away
+        let mut rng = config.seed.max(1);
+        let mut random = move || {
+            rng ^= rng << 13;
+            rng ^= rng >> 17;
+            rng ^= rng << 5;
+            (rng >> 8) as f32 / (1 << 24) as f32
+        };
+        (0..config.count)
+            .map(|_| {
+                let x = min[0] + (max[0] - min[0]) * random();
+                let z = min[2] + (max[2] - min[2]) * random();
+                let turn = Deg(random() * 360.0);
+                let height = config.height * (0.6 + 0.8 * random());
+                let width = config.width * (0.7 + 0.6 * random());
+                let model = Matrix4::from_translation(Vector3::new(x, min[1], z))
+                    * Matrix4::from_angle_y(turn)
+                    * Matrix4::from_nonuniform_scale(width, height, width);
+                InstanceRaw {
+                    model_mat: RawMatrix { mat: model.into() },
+                    tint: graphics::NO_TINT,
+                }
+            })
+            .collect()
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, view_proj: Matrix4<f32>, time: f32, light: LightUniform) {
+        self.uniform.view_proj = view_proj.into();
+        self.uniform.wind[3] = time;
+        self.uniform.light = light;
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+    }
+
+    // binds its own instances, anything drawn after it that relies on slot 1 needs to rebind it
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertices.slice(..));
+        render_pass.set_vertex_buffer(1, self.instances.slice(..));
+        render_pass.set_index_buffer(self.indices.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(0..self.num_indices, 0, 0..self.count);
+    }
+}
@@ -0,0 +1,87 @@
+let ROOT_COLOR: vec3<f32> = vec3<f32>(0.05, 0.2, 0.03);
+let TIP_COLOR: vec3<f32> = vec3<f32>(0.45, 0.65, 0.15);
+
+// the same layout as LightUniform in sky.rs
+struct Light {
+    sun_dir: vec4<f32>,
+    sun_color: vec4<f32>,
+    ambient: vec4<f32>,
+    camera_pos: vec4<f32>,
+    light_count: vec4<u32>,
+    wet_floor: vec4<f32>,
+}
+
+struct GrassUniform {
+    view_proj: mat4x4<f32>,
+    // xy is the direction the wind blows in the xz plane, z the strength and w the time
+    wind: vec4<f32>,
+    light: Light,
+}
+
+@group(0) @binding(0)
+var<uniform> grass: GrassUniform;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(6) normal: vec3<f32>,
+}
+
+struct InstanceInput {
+    @location(2) model_0: vec4<f32>,
+    @location(3) model_1: vec4<f32>,
+    @location(4) model_2: vec4<f32>,
+    @location(5) model_3: vec4<f32>,
+    @location(7) tint: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    // 0 at the root to 1 at the tip
+    @location(1) height: f32,
+    @location(2) tint: vec3<f32>,
+}
+
+@vertex
+fn vs_main(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    let root = instance.model_3.xyz;
+    let blade_height = length(instance.model_1.xyz);
+    var world_pos = (model * vec4<f32>(vertex.position, 1.0)).xyz;
+
+    // slow waves rolling across the field in the wind's direction, with faster flutter on top
+    // that's different for every blade
+    let dir = vec2<f32>(grass.wind.x, grass.wind.y);
+    let time = grass.wind.w;
+    let wave = sin(dot(root.xz, dir) * 0.05 - time * 1.5) * 0.5 + 0.5;
+    let flutter = sin(time * 4.0 + root.x * 1.7 + root.z * 2.3) * 0.15;
+    // the root stays put and the bend grows towards the tip
+    let bend = vertex.tex_coords.y * vertex.tex_coords.y * (wave + flutter) * grass.wind.z * blade_height;
+    world_pos = world_pos + vec3<f32>(dir.x, 0.0, dir.y) * bend;
+    // leaning over pulls the tip down a bit so the blade doesn't stretch
+    world_pos.y = world_pos.y - bend * bend / (2.0 * blade_height);
+
+    var out: VertexOutput;
+    out.clip_position = grass.view_proj * vec4<f32>(world_pos, 1.0);
+    out.normal = normalize((model * vec4<f32>(vertex.normal, 0.0)).xyz);
+    out.height = vertex.tex_coords.y;
+    out.tint = instance.tint.rgb;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput, @builtin(front_facing) front: bool) -> @location(0) vec4<f32> {
+    var normal = in.normal;
+    if !front {
+        normal = -normal;
+    }
+    // the cards are thin, so light coming through from behind still brightens them a little
+    let facing = dot(normal, grass.light.sun_dir.xyz);
+    let diffuse = max(facing, 0.0) + max(-facing, 0.0) * 0.3;
+    let albedo = mix(ROOT_COLOR, TIP_COLOR, in.height) * in.tint;
+    var color = albedo * (grass.light.ambient.rgb + grass.light.sun_color.rgb * diffuse);
+
+    color = apply_output_gamma(color);
+    return vec4<f32>(color, 1.0);
+}
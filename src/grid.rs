@@ -0,0 +1,174 @@
+use cgmath::{Matrix4, Point3, SquareMatrix};
+
+use crate::debug_draw::LineVertex;
+use crate::graphics;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct GridUniform {
+    // first, so the axis lines can share this buffer with the debug line shader
+    view_proj: [[f32; 4]; 4],
+    inv_view_proj: [[f32; 4]; 4],
+    camera_pos: [f32; 4],
+}
+
+// a ground grid on y = 0 that reaches the horizon, plus red, green and blue lines along the
+// world x, y and z axes, for keeping track of where everything is when far away from the scene
+pub struct GridRenderer {
+    grid_pipeline: wgpu::RenderPipeline,
+    axes_pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    axes_vertices: wgpu::Buffer,
+    num_axes_vertices: u32,
+}
+
+impl GridRenderer {
+    const AXIS_LENGTH: f32 = 10000.0;
+
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let grid_shader = graphics::create_templated_shader(device, format, "grid.wgsl", include_str!("grid.wgsl"));
+        let axes_shader = graphics::create_templated_shader(device, format, "debug_lines.wgsl", include_str!("debug_lines.wgsl"));
+
+        let uniform_buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some("grid_uniform_buffer"),
+            size: std::mem::size_of::<GridUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("grid_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("grid_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("grid_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |label, shader, buffers, topology, blend| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: shader,
+                    entry_point: "vs_main",
+                    buffers,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(blend),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology,
+                    ..Default::default()
+                },
+                // tested against the scene but left out of the depth buffer, the grid is see-through
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: graphics::DEPTH_FORMAT,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::LessEqual,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: graphics::SAMPLE_COUNT,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
+        };
+        let grid_pipeline = pipeline(
+            "grid_pipeline",
+            &grid_shader,
+            &[],
+            wgpu::PrimitiveTopology::TriangleList,
+            wgpu::BlendState::ALPHA_BLENDING,
+        );
+        let axes_pipeline = pipeline(
+            "grid_axes_pipeline",
+            &axes_shader,
+            &[LineVertex::desc()],
+            wgpu::PrimitiveTopology::LineList,
+            wgpu::BlendState::REPLACE,
+        );
+
+        let axes = axis_lines(Self::AXIS_LENGTH);
+        let axes_vertices = graphics::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+            label: Some("grid_axes_vertices"),
+            contents: bytemuck::cast_slice(&axes),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        GridRenderer {
+            grid_pipeline,
+            axes_pipeline,
+            uniform_buffer,
+            bind_group,
+            axes_vertices,
+            num_axes_vertices: axes.len() as u32,
+        }
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue, view_proj: Matrix4<f32>, camera_pos: Point3<f32>) {
+        let uniform = GridUniform {
+            view_proj: view_proj.into(),
+            inv_view_proj: view_proj.invert().unwrap_or_else(Matrix4::identity).into(),
+            camera_pos: [camera_pos.x, camera_pos.y, camera_pos.z, 1.0],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    // after everything opaque, so the scene hides the grid where it's in front of it
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_pipeline(&self.axes_pipeline);
+        render_pass.set_vertex_buffer(0, self.axes_vertices.slice(..));
+        render_pass.draw(0..self.num_axes_vertices, 0..1);
+        render_pass.set_pipeline(&self.grid_pipeline);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+// full color towards positive, darker towards negative
+fn axis_lines(length: f32) -> Vec<LineVertex> {
+    let mut vertices = Vec::new();
+    for axis in 0..3 {
+        let mut color = [0.0; 3];
+        color[axis] = 1.0;
+        let mut end = [0.0; 3];
+        end[axis] = length;
+
+        let negative_color = color.map(|c| c * 0.35);
+        let negative_end = end.map(|e| -e);
+        vertices.push(LineVertex { position: [0.0; 3], color });
+        vertices.push(LineVertex { position: end, color });
+        vertices.push(LineVertex { position: [0.0; 3], color: negative_color });
+        vertices.push(LineVertex { position: negative_end, color: negative_color });
+    }
+    vertices
+}
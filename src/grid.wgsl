@@ -0,0 +1,75 @@
+// lines every MINOR_SPACING units, brighter every MAJOR_SPACING
+let MINOR_SPACING: f32 = 10.0;
+let MAJOR_SPACING: f32 = 100.0;
+// the grid fades out completely at this distance from the camera
+let FADE_DISTANCE: f32 = 1500.0;
+
+struct GridUniform {
+    view_proj: mat4x4<f32>,
+    inv_view_proj: mat4x4<f32>,
+    camera_pos: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> grid: GridUniform;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+};
+
+// a single triangle covering the whole screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    let ndc = uv * 2.0 - 1.0;
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+    out.ndc = ndc;
+    return out;
+}
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    // where the plane is hit, so the scene hides the grid where it's in front of it
+    @builtin(frag_depth) depth: f32,
+}
+
+// 1 on a line, falling to 0 within about a pixel of it
+fn lines(coord: vec2<f32>, spacing: f32) -> f32 {
+    let c = coord / spacing;
+    let dist = abs(fract(c - 0.5) - 0.5) / fwidth(c);
+    return 1.0 - min(min(dist.x, dist.y), 1.0);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    // the view ray through this pixel, from its near plane point to its far plane point
+    let near_h = grid.inv_view_proj * vec4<f32>(in.ndc, 0.0, 1.0);
+    let far_h = grid.inv_view_proj * vec4<f32>(in.ndc, 1.0, 1.0);
+    let near = near_h.xyz / near_h.w;
+    let far = far_h.xyz / far_h.w;
+
+    // where it crosses y = 0, only between the near and far planes
+    let t = -near.y / (far.y - near.y);
+    let hit = near + (far - near) * t;
+
+    let minor = lines(hit.xz, MINOR_SPACING);
+    let major = lines(hit.xz, MAJOR_SPACING);
+    let fade = 1.0 - smoothstep(0.0, FADE_DISTANCE, distance(hit.xz, grid.camera_pos.xz));
+
+    // derivatives above need every pixel, so only discard once they're taken
+    if t <= 0.0 || t >= 1.0 {
+        discard;
+    }
+
+    var color = mix(vec3<f32>(0.35), vec3<f32>(0.8), major);
+    color = apply_output_gamma(color);
+
+    let clip = grid.view_proj * vec4<f32>(hit, 1.0);
+    var out: FragmentOutput;
+    out.color = vec4<f32>(color, max(minor * 0.4, major * 0.8) * fade);
+    out.depth = clip.z / clip.w;
+    return out;
+}
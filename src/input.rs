@@ -1,79 +1,446 @@
+use serde::{Deserialize, Serialize};
 use winit::event::{ElementState, KeyboardInput, VirtualKeyCode};
 
+// everything the app and camera respond to, decoupled from the keys that trigger them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Crouch,
+    Sprint,
+    ToggleObject,
+    MoreInstances,
+    FewerInstances,
+    ToggleTelemetry,
+    ToggleProjection,
+    AddKeyframe,
+    PlayFlythrough,
+    ClearKeyframes,
+    RollLeft,
+    RollRight,
+    ToggleLookMode,
+    ToggleWalk,
+    ToggleLightGizmos,
+    FewerTerrainOctaves,
+    MoreTerrainOctaves,
+    LowerTerrain,
+    RaiseTerrain,
+    ReseedTerrain,
+    ToggleGrid,
+    ToggleDebugShapes,
+    CycleViewMode,
+    CycleWireframe,
+    ToggleHud,
+    ToggleMarkers,
+    ToggleHelp,
+    ClearDecals,
+    ToggleDebugCamera,
+    ToggleMinimap,
+    CycleLogLevel,
+    ToggleDepthPrepass,
+    SpinInstances,
+    ToggleInstanceWave,
+    CycleColorGrade,
+}
+
+impl Action {
+    pub const ALL: [Action; 40] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Jump,
+        Action::Crouch,
+        Action::Sprint,
+        Action::ToggleObject,
+        Action::MoreInstances,
+        Action::FewerInstances,
+        Action::ToggleTelemetry,
+        Action::ToggleProjection,
+        Action::AddKeyframe,
+        Action::PlayFlythrough,
+        Action::ClearKeyframes,
+        Action::RollLeft,
+        Action::RollRight,
+        Action::ToggleLookMode,
+        Action::ToggleWalk,
+        Action::ToggleLightGizmos,
+        Action::FewerTerrainOctaves,
+        Action::MoreTerrainOctaves,
+        Action::LowerTerrain,
+        Action::RaiseTerrain,
+        Action::ReseedTerrain,
+        Action::ToggleGrid,
+        Action::ToggleDebugShapes,
+        Action::CycleViewMode,
+        Action::CycleWireframe,
+        Action::ToggleHud,
+        Action::ToggleMarkers,
+        Action::ToggleHelp,
+        Action::ClearDecals,
+        Action::ToggleDebugCamera,
+        Action::ToggleMinimap,
+        Action::CycleLogLevel,
+        Action::ToggleDepthPrepass,
+        Action::SpinInstances,
+        Action::ToggleInstanceWave,
+        Action::CycleColorGrade,
+    ];
+}
+
 pub struct InputState {
-    pub space_pressed: bool,
-    pub shift_pressed: bool,
-    pub forward_pressed: bool,
-    pub backward_pressed: bool,
-    pub left_pressed: bool,
-    pub right_pressed: bool,
-    pub tab_pressed: bool,
-    pub up_pressed: bool,
-    pub down_pressed: bool,
-    pub ctrl_pressed: bool,
-    pub f_pressed: bool,
+    pressed: [bool; Action::ALL.len()],
+    // combined keyboard and gamepad state as of the last end_frame, for edge detection
+    previous: [bool; Action::ALL.len()],
+    // seconds each action has been held, as of this frame
+    held_time: [f64; Action::ALL.len()],
+    frame_time: f64,
     unhandled_mouse_move: (f64, f64),
+    bindings: KeyBindings,
+    // kept apart from the keyboard so releasing a key doesn't release a held button
+    gamepad_pressed: [bool; Action::ALL.len()],
+    // (forward, right) and (yaw, pitch), each in -1..1 after the deadzone
+    move_stick: (f32, f32),
+    look_stick: (f32, f32),
+    #[cfg(feature = "gamepad")]
+    gamepad: Option<Gamepad>,
+}
+
+#[cfg(feature = "gamepad")]
+struct Gamepad {
+    gilrs: gilrs::Gilrs,
+    // the pad that sent the most recent event, its sticks drive the camera
+    active: Option<gilrs::GamepadId>,
+}
+
+// one key per action. the settings file only needs to list the actions it rebinds
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub move_forward: VirtualKeyCode,
+    pub move_backward: VirtualKeyCode,
+    pub move_left: VirtualKeyCode,
+    pub move_right: VirtualKeyCode,
+    pub jump: VirtualKeyCode,
+    pub crouch: VirtualKeyCode,
+    pub sprint: VirtualKeyCode,
+    pub toggle_object: VirtualKeyCode,
+    pub more_instances: VirtualKeyCode,
+    pub fewer_instances: VirtualKeyCode,
+    pub toggle_telemetry: VirtualKeyCode,
+    pub toggle_projection: VirtualKeyCode,
+    pub add_keyframe: VirtualKeyCode,
+    pub play_flythrough: VirtualKeyCode,
+    pub clear_keyframes: VirtualKeyCode,
+    pub roll_left: VirtualKeyCode,
+    pub roll_right: VirtualKeyCode,
+    pub toggle_look_mode: VirtualKeyCode,
+    pub toggle_walk: VirtualKeyCode,
+    pub toggle_light_gizmos: VirtualKeyCode,
+    pub fewer_terrain_octaves: VirtualKeyCode,
+    pub more_terrain_octaves: VirtualKeyCode,
+    pub lower_terrain: VirtualKeyCode,
+    pub raise_terrain: VirtualKeyCode,
+    pub reseed_terrain: VirtualKeyCode,
+    pub toggle_grid: VirtualKeyCode,
+    pub toggle_debug_shapes: VirtualKeyCode,
+    pub cycle_view_mode: VirtualKeyCode,
+    pub cycle_wireframe: VirtualKeyCode,
+    pub toggle_hud: VirtualKeyCode,
+    pub toggle_markers: VirtualKeyCode,
+    pub toggle_help: VirtualKeyCode,
+    pub clear_decals: VirtualKeyCode,
+    pub toggle_debug_camera: VirtualKeyCode,
+    pub toggle_minimap: VirtualKeyCode,
+    pub cycle_log_level: VirtualKeyCode,
+    pub toggle_depth_prepass: VirtualKeyCode,
+    pub spin_instances: VirtualKeyCode,
+    pub toggle_instance_wave: VirtualKeyCode,
+    pub cycle_color_grade: VirtualKeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            move_forward: VirtualKeyCode::W,
+            move_backward: VirtualKeyCode::S,
+            move_left: VirtualKeyCode::A,
+            move_right: VirtualKeyCode::D,
+            jump: VirtualKeyCode::Space,
+            crouch: VirtualKeyCode::LShift,
+            sprint: VirtualKeyCode::LControl,
+            toggle_object: VirtualKeyCode::Tab,
+            more_instances: VirtualKeyCode::Up,
+            fewer_instances: VirtualKeyCode::Down,
+            toggle_telemetry: VirtualKeyCode::F,
+            toggle_projection: VirtualKeyCode::P,
+            add_keyframe: VirtualKeyCode::K,
+            play_flythrough: VirtualKeyCode::L,
+            clear_keyframes: VirtualKeyCode::J,
+            roll_left: VirtualKeyCode::Q,
+            roll_right: VirtualKeyCode::E,
+            toggle_look_mode: VirtualKeyCode::V,
+            toggle_walk: VirtualKeyCode::G,
+            toggle_light_gizmos: VirtualKeyCode::F3,
+            fewer_terrain_octaves: VirtualKeyCode::LBracket,
+            more_terrain_octaves: VirtualKeyCode::RBracket,
+            lower_terrain: VirtualKeyCode::Minus,
+            raise_terrain: VirtualKeyCode::Equals,
+            reseed_terrain: VirtualKeyCode::R,
+            toggle_grid: VirtualKeyCode::F4,
+            toggle_debug_shapes: VirtualKeyCode::F5,
+            cycle_view_mode: VirtualKeyCode::F6,
+            cycle_wireframe: VirtualKeyCode::F7,
+            toggle_hud: VirtualKeyCode::F8,
+            toggle_markers: VirtualKeyCode::M,
+            toggle_help: VirtualKeyCode::F1,
+            clear_decals: VirtualKeyCode::Back,
+            toggle_debug_camera: VirtualKeyCode::F2,
+            toggle_minimap: VirtualKeyCode::N,
+            cycle_log_level: VirtualKeyCode::F12,
+            toggle_depth_prepass: VirtualKeyCode::Z,
+            spin_instances: VirtualKeyCode::X,
+            toggle_instance_wave: VirtualKeyCode::C,
+            cycle_color_grade: VirtualKeyCode::O,
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn key(&self, action: Action) -> VirtualKeyCode {
+        match action {
+            Action::MoveForward => self.move_forward,
+            Action::MoveBackward => self.move_backward,
+            Action::MoveLeft => self.move_left,
+            Action::MoveRight => self.move_right,
+            Action::Jump => self.jump,
+            Action::Crouch => self.crouch,
+            Action::Sprint => self.sprint,
+            Action::ToggleObject => self.toggle_object,
+            Action::MoreInstances => self.more_instances,
+            Action::FewerInstances => self.fewer_instances,
+            Action::ToggleTelemetry => self.toggle_telemetry,
+            Action::ToggleProjection => self.toggle_projection,
+            Action::AddKeyframe => self.add_keyframe,
+            Action::PlayFlythrough => self.play_flythrough,
+            Action::ClearKeyframes => self.clear_keyframes,
+            Action::RollLeft => self.roll_left,
+            Action::RollRight => self.roll_right,
+            Action::ToggleLookMode => self.toggle_look_mode,
+            Action::ToggleWalk => self.toggle_walk,
+            Action::ToggleLightGizmos => self.toggle_light_gizmos,
+            Action::FewerTerrainOctaves => self.fewer_terrain_octaves,
+            Action::MoreTerrainOctaves => self.more_terrain_octaves,
+            Action::LowerTerrain => self.lower_terrain,
+            Action::RaiseTerrain => self.raise_terrain,
+            Action::ReseedTerrain => self.reseed_terrain,
+            Action::ToggleGrid => self.toggle_grid,
+            Action::ToggleDebugShapes => self.toggle_debug_shapes,
+            Action::CycleViewMode => self.cycle_view_mode,
+            Action::CycleWireframe => self.cycle_wireframe,
+            Action::ToggleHud => self.toggle_hud,
+            Action::ToggleMarkers => self.toggle_markers,
+            Action::ToggleHelp => self.toggle_help,
+            Action::ClearDecals => self.clear_decals,
+            Action::ToggleDebugCamera => self.toggle_debug_camera,
+            Action::ToggleMinimap => self.toggle_minimap,
+            Action::CycleLogLevel => self.cycle_log_level,
+            Action::ToggleDepthPrepass => self.toggle_depth_prepass,
+            Action::SpinInstances => self.spin_instances,
+            Action::ToggleInstanceWave => self.toggle_instance_wave,
+            Action::CycleColorGrade => self.cycle_color_grade,
+        }
+    }
 }
 
 impl InputState {
-    const SPACE: VirtualKeyCode = VirtualKeyCode::Space;
-    const SHIFT: VirtualKeyCode = VirtualKeyCode::LShift;
-    const FORWARD: VirtualKeyCode = VirtualKeyCode::W;
-    const BACK: VirtualKeyCode = VirtualKeyCode::S;
-    const LEFT: VirtualKeyCode = VirtualKeyCode::A;
-    const RIGHT: VirtualKeyCode = VirtualKeyCode::D;
-    const TAB: VirtualKeyCode = VirtualKeyCode::Tab;
-    const UP: VirtualKeyCode = VirtualKeyCode::Up;
-    const DOWN: VirtualKeyCode = VirtualKeyCode::Down;
-    const CTRL: VirtualKeyCode = VirtualKeyCode::LControl;
-    const F: VirtualKeyCode = VirtualKeyCode::F;
-
-    pub fn new() -> Self {
+    // stick deflection below this is treated as resting
+    #[cfg(feature = "gamepad")]
+    const STICK_DEADZONE: f32 = 0.15;
+    // mouse counts per second a fully deflected look stick is worth
+    const STICK_LOOK_SPEED: f32 = 800.0;
+
+    pub fn new(bindings: KeyBindings) -> Self {
         InputState {
-            space_pressed: false,
-            shift_pressed: false,
-            forward_pressed: false,
-            backward_pressed: false,
-            left_pressed: false,
-            right_pressed: false,
-            tab_pressed: false,
-            up_pressed: false,
-            down_pressed: false,
-            ctrl_pressed: false,
-            f_pressed: false,
+            pressed: [false; Action::ALL.len()],
+            previous: [false; Action::ALL.len()],
+            held_time: [0.0; Action::ALL.len()],
+            frame_time: 0.0,
             unhandled_mouse_move: (0.0, 0.0),
+            bindings,
+            gamepad_pressed: [false; Action::ALL.len()],
+            move_stick: (0.0, 0.0),
+            look_stick: (0.0, 0.0),
+            #[cfg(feature = "gamepad")]
+            gamepad: match gilrs::Gilrs::new() {
+                Ok(gilrs) => Some(Gamepad { gilrs, active: None }),
+                Err(e) => {
+                    log::warn!("Gamepad input unavailable: {}", e);
+                    None
+                }
+            },
         }
     }
 
-    pub fn update_keyboard(&mut self, input: &KeyboardInput) {
-        match input {
-            KeyboardInput {
-                state,
-                virtual_keycode,
-                ..
-            } => {
-                if let Some(key) = virtual_keycode {
-                    match *key {
-                        Self::SPACE => self.space_pressed = if let ElementState::Pressed = state { true } else { false },
-                        Self::SHIFT => self.shift_pressed = if let ElementState::Pressed = state { true } else { false },
-                        Self::FORWARD => self.forward_pressed = if let ElementState::Pressed = state { true } else { false },
-                        Self::BACK => self.backward_pressed = if let ElementState::Pressed = state { true } else { false },
-                        Self::LEFT => self.left_pressed = if let ElementState::Pressed = state { true } else { false },
-                        Self::RIGHT => self.right_pressed = if let ElementState::Pressed = state { true } else { false },
-                        Self::TAB => self.tab_pressed = if let ElementState::Pressed = state { true } else { false },
-                        Self::UP => self.up_pressed = if let ElementState::Pressed = state { true } else { false },
-                        Self::DOWN => self.down_pressed = if let ElementState::Pressed = state { true } else { false },
-                        Self::CTRL => self.ctrl_pressed = if let ElementState::Pressed = state { true } else { false },
-                        Self::F => self.f_pressed = if let ElementState::Pressed = state { true } else { false },
-                        _ => {}
+    pub fn bindings(&self) -> &KeyBindings {
+        &self.bindings
+    }
+
+    pub fn set_bindings(&mut self, bindings: KeyBindings) {
+        // anything held under the old bindings would otherwise never be released
+        self.release_keys();
+        self.bindings = bindings;
+    }
+
+    // for when key releases are about to go somewhere else
+    pub fn release_keys(&mut self) {
+        self.pressed = [false; Action::ALL.len()];
+    }
+
+    // true while the action is down, including the frame it went down on
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed[action as usize] || self.gamepad_pressed[action as usize]
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.pressed(action) && !self.previous[action as usize]
+    }
+
+    // down this frame and the one before
+    pub fn held(&self, action: Action) -> bool {
+        self.pressed(action) && self.previous[action as usize]
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        !self.pressed(action) && self.previous[action as usize]
+    }
+
+    // how many times a held action fires this frame: once when pressed, then rate times
+    // a second once it has been held for delay seconds
+    pub fn repeats(&self, action: Action, delay: f64, rate: f64) -> u32 {
+        if self.just_pressed(action) {
+            return 1;
+        }
+        if !self.held(action) {
+            return 0;
+        }
+
+        let fired = |held: f64| {
+            if held < delay {
+                0
+            } else {
+                ((held - delay) * rate) as u32 + 1
+            }
+        };
+        let held = self.held_time[action as usize];
+        fired(held) - fired(held - self.frame_time)
+    }
+
+    // call at the start of a frame, after events have been handled
+    pub fn begin_frame(&mut self, dt: f64) {
+        self.frame_time = dt;
+        for action in Action::ALL {
+            self.held_time[action as usize] = if self.held(action) {
+                self.held_time[action as usize] + dt
+            } else {
+                0.0
+            };
+        }
+    }
+
+    // call at the end of a frame, edges are reported relative to the state at this point
+    pub fn end_frame(&mut self) {
+        for action in Action::ALL {
+            self.previous[action as usize] = self.pressed(action);
+        }
+    }
+
+    // (forward, right) movement in -1..1 from the movement keys and the left stick
+    pub fn move_axes(&self) -> (f32, f32) {
+        let key_axis = |pos: Action, neg: Action| {
+            self.pressed(pos) as i32 as f32 - self.pressed(neg) as i32 as f32
+        };
+        let forward = key_axis(Action::MoveForward, Action::MoveBackward) + self.move_stick.0;
+        let right = key_axis(Action::MoveRight, Action::MoveLeft) + self.move_stick.1;
+        (forward.clamp(-1.0, 1.0), right.clamp(-1.0, 1.0))
+    }
+
+    // -1..1, positive rolls clockwise
+    pub fn roll_axis(&self) -> f32 {
+        self.pressed(Action::RollRight) as i32 as f32 - self.pressed(Action::RollLeft) as i32 as f32
+    }
+
+    // look input from the right stick in mouse counts per second, so it has to be scaled by the frame time
+    pub fn stick_look(&self) -> (f32, f32) {
+        (
+            self.look_stick.0 * Self::STICK_LOOK_SPEED,
+            self.look_stick.1 * Self::STICK_LOOK_SPEED,
+        )
+    }
+
+    // drains pending gamepad events, call once per frame before reading input
+    #[cfg(feature = "gamepad")]
+    pub fn poll_gamepad(&mut self) {
+        use gilrs::{Axis, EventType};
+
+        let Some(gamepad) = &mut self.gamepad else {
+            return;
+        };
+
+        while let Some(gilrs::Event { id, event, .. }) = gamepad.gilrs.next_event() {
+            gamepad.active = Some(id);
+            match event {
+                EventType::ButtonPressed(button, _) | EventType::ButtonReleased(button, _) => {
+                    if let Some(action) = button_action(button) {
+                        self.gamepad_pressed[action as usize] =
+                            matches!(event, EventType::ButtonPressed(..));
                     }
                 }
+                EventType::Disconnected => {
+                    gamepad.active = None;
+                    self.gamepad_pressed = [false; Action::ALL.len()];
+                }
+                _ => {}
             }
         }
+
+        let pad = gamepad.active.map(|id| gamepad.gilrs.gamepad(id));
+        let axis = |axis| pad.map_or(0.0, |pad| pad.value(axis));
+        // stick up is positive y, which is forward for movement and looking up for look
+        let (right, forward) = deadzone(axis(Axis::LeftStickX), axis(Axis::LeftStickY));
+        let (yaw, pitch) = deadzone(axis(Axis::RightStickX), axis(Axis::RightStickY));
+        self.move_stick = (forward, right);
+        self.look_stick = (yaw, -pitch);
     }
 
+    #[cfg(not(feature = "gamepad"))]
+    pub fn poll_gamepad(&mut self) {}
+
+    pub fn update_keyboard(&mut self, input: &KeyboardInput) {
+        let KeyboardInput {
+            state,
+            virtual_keycode,
+            ..
+        } = input;
+
+        if let Some(key) = *virtual_keycode {
+            let pressed = *state == ElementState::Pressed;
+            // a key bound to several actions drives all of them
+            for action in Action::ALL {
+                if self.bindings.key(action) == key {
+                    self.pressed[action as usize] = pressed;
+                }
+            }
+        }
+    }
+
+    // several motion events can arrive between two frames, none of them should be lost
     pub fn update_mouse(&mut self, delta: &(f64, f64)) {
-        self.unhandled_mouse_move = *delta;
+        self.unhandled_mouse_move.0 += delta.0;
+        self.unhandled_mouse_move.1 += delta.1;
     }
 
     pub fn get_unhandled_mouse_move(&mut self) -> (f64, f64) {
@@ -83,8 +450,41 @@ impl InputState {
     }
 
     pub fn movement_key_pressed(&self) -> bool {
-        self.space_pressed || self.shift_pressed ||
-        self.forward_pressed || self.backward_pressed ||
-        self.left_pressed || self.right_pressed
+        self.move_stick != (0.0, 0.0) || [
+            Action::MoveForward,
+            Action::MoveBackward,
+            Action::MoveLeft,
+            Action::MoveRight,
+            Action::Jump,
+            Action::Crouch,
+        ]
+        .into_iter()
+        .any(|action| self.pressed(action))
+    }
+}
+
+// radial deadzone, rescaled so the output still starts at 0 right outside of it
+#[cfg(feature = "gamepad")]
+fn deadzone(x: f32, y: f32) -> (f32, f32) {
+    let len = (x * x + y * y).sqrt();
+    if len <= InputState::STICK_DEADZONE {
+        return (0.0, 0.0);
+    }
+    let scale = ((len - InputState::STICK_DEADZONE) / (1.0 - InputState::STICK_DEADZONE)).min(1.0) / len;
+    (x * scale, y * scale)
+}
+
+#[cfg(feature = "gamepad")]
+fn button_action(button: gilrs::Button) -> Option<Action> {
+    use gilrs::Button;
+
+    match button {
+        Button::South => Some(Action::Jump),
+        Button::East => Some(Action::Crouch),
+        Button::LeftThumb => Some(Action::Sprint),
+        Button::North => Some(Action::ToggleObject),
+        Button::DPadUp => Some(Action::MoreInstances),
+        Button::DPadDown => Some(Action::FewerInstances),
+        _ => None,
     }
 }
@@ -0,0 +1,140 @@
+use crate::graphics::{self, Instance};
+use crate::pool::PoolSlice;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct WaveUniform {
+    params: [f32; 4],
+    count: [u32; 4],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct BaseInstance {
+    position: [f32; 4],
+    rotation: [f32; 4],
+    scale: [f32; 4],
+}
+
+// a wave rolling through an instanced object, moved in a compute pass that writes the instances'
+// matrices straight into its transforms. the cpu only sets the time each frame, its copy of the
+// instances stays where they were placed
+pub struct InstanceWave {
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::ComputePipeline,
+    count: u32,
+}
+
+impl InstanceWave {
+    const WORKGROUP_SIZE: u32 = 64;
+    const AMPLITUDE: f32 = 2.0;
+    // world units per radian of the wave
+    const WAVELENGTH: f32 = 6.0;
+    // radians per second each instance turns
+    const SPIN_SPEED: f32 = 1.0;
+
+    // transforms is the object's, holding its model matrix and then instances
+    pub fn new(device: &wgpu::Device, transforms: &PoolSlice, instances: &[Instance]) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader at instance_wave.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("instance_wave.wgsl").into()),
+        });
+
+        let uniform_buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some("instance_wave_uniform_buffer"),
+            size: std::mem::size_of::<WaveUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let base = instances
+            .iter()
+            .map(|instance| BaseInstance {
+                position: [instance.trans.x, instance.trans.y, instance.trans.z, 1.0],
+                rotation: [instance.rot.v.x, instance.rot.v.y, instance.rot.v.z, instance.rot.s],
+                scale: [instance.scale.x, instance.scale.y, instance.scale.z, 1.0],
+            })
+            .collect::<Vec<_>>();
+        let base_buffer = graphics::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+            label: Some("instance_wave_base_buffer"),
+            contents: bytemuck::cast_slice(&base),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let buffer_entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("instance_wave_bind_group_layout"),
+            entries: &[
+                buffer_entry(0, wgpu::BufferBindingType::Uniform),
+                buffer_entry(1, wgpu::BufferBindingType::Storage { read_only: true }),
+                buffer_entry(2, wgpu::BufferBindingType::Storage { read_only: false }),
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("instance_wave_bind_group"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: base_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(transforms.binding()),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("instance_wave_pipeline_layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("instance_wave_pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        InstanceWave {
+            uniform_buffer,
+            bind_group,
+            pipeline,
+            count: 0,
+        }
+    }
+
+    // count is how many of the instances are shown, the rest are left alone
+    pub fn update(&mut self, queue: &wgpu::Queue, time: f32, count: u32) {
+        self.count = count;
+        let uniform = WaveUniform {
+            params: [time, Self::AMPLITUDE, Self::WAVELENGTH, Self::SPIN_SPEED],
+            count: [count, 0, 0, 0],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    // before the passes drawing the object
+    pub fn animate(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("instance_wave_pass"),
+        });
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch_workgroups(self.count.div_ceil(Self::WORKGROUP_SIZE), 1, 1);
+    }
+}
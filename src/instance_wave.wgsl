@@ -0,0 +1,74 @@
+struct WaveUniform {
+    // x is the time, y how high the wave lifts the instances, z its length and w how fast the
+    // instances turn
+    params: vec4<f32>,
+    // x is the number of instances to move
+    count: vec4<u32>,
+}
+
+// where an instance was placed, the wave moves it from there
+struct BaseInstance {
+    position: vec4<f32>,
+    // a quaternion, xyz is the vector part and w the scalar
+    rotation: vec4<f32>,
+    scale: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> wave: WaveUniform;
+
+@group(0) @binding(1)
+var<storage, read> base: array<BaseInstance>;
+
+// see InstanceRaw in graphics.rs, only the matrix is written and the tint is kept
+struct Transform {
+    model: mat4x4<f32>,
+    tint: vec4<f32>,
+}
+
+// the instanced object's transforms, see graphics::transforms. the instances start at index 1
+@group(0) @binding(2)
+var<storage, read_write> transforms: array<Transform>;
+
+let WAVE_SPEED: f32 = 2.0;
+
+fn rotation_matrix(q: vec4<f32>) -> mat3x3<f32> {
+    let x = q.x;
+    let y = q.y;
+    let z = q.z;
+    let w = q.w;
+    return mat3x3<f32>(
+        vec3<f32>(1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y + w * z), 2.0 * (x * z - w * y)),
+        vec3<f32>(2.0 * (x * y - w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z + w * x)),
+        vec3<f32>(2.0 * (x * z + w * y), 2.0 * (y * z - w * x), 1.0 - 2.0 * (x * x + y * y)),
+    );
+}
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= wave.count.x {
+        return;
+    }
+    let instance = base[i];
+    let time = wave.params.x;
+
+    // rolls diagonally across the grid
+    let phase = (instance.position.x + instance.position.z) / wave.params.z - time * WAVE_SPEED;
+    let position = instance.position.xyz + vec3<f32>(0.0, wave.params.y * sin(phase), 0.0);
+
+    // turned about the vertical on top of the placed rotation
+    let angle = time * wave.params.w;
+    let c = cos(angle);
+    let s = sin(angle);
+    let spin = mat3x3<f32>(vec3<f32>(c, 0.0, -s), vec3<f32>(0.0, 1.0, 0.0), vec3<f32>(s, 0.0, c));
+    let s = instance.scale;
+    let r = spin * rotation_matrix(instance.rotation) * mat3x3<f32>(s.x, 0.0, 0.0, 0.0, s.y, 0.0, 0.0, 0.0, s.z);
+
+    transforms[i + 1u].model = mat4x4<f32>(
+        vec4<f32>(r[0], 0.0),
+        vec4<f32>(r[1], 0.0),
+        vec4<f32>(r[2], 0.0),
+        vec4<f32>(position, 1.0),
+    );
+}
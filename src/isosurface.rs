@@ -0,0 +1,174 @@
+use cgmath::{InnerSpace, Matrix4, Point3, SquareMatrix, Vector3};
+
+use crate::graphics::{self, Vertex};
+
+// each cube of the grid is split into six tetrahedra around its 0-7 diagonal, corners numbered
+// with x as bit 0, y as bit 1 and z as bit 2. unlike the classic 256 case table this has no
+// ambiguous cases, at the cost of a few more triangles
+const TETRAHEDRA: [[usize; 4]; 6] = [
+    [0, 1, 3, 7],
+    [0, 3, 2, 7],
+    [0, 2, 6, 7],
+    [0, 6, 4, 7],
+    [0, 4, 5, 7],
+    [0, 5, 1, 7],
+];
+
+// blobs that merge as they get close, the surface is where their summed influence equals ISO_LEVEL
+pub struct Metaballs {
+    center: Point3<f32>,
+    balls: Vec<(Point3<f32>, f32)>,
+    vertex_buffer: wgpu::Buffer,
+    // vertices the buffer has room for, it's recreated larger when a frame needs more
+    capacity: usize,
+    vertex_count: u32,
+    pub transforms_buf: wgpu::Buffer,
+}
+
+impl Metaballs {
+    const ISO_LEVEL: f32 = 1.0;
+    // cells along each side of the sampled grid
+    const RESOLUTION: usize = 32;
+    // half the width of the sampled cube, balls are kept well inside it
+    const EXTENT: f32 = 20.0;
+    const BALL_COUNT: usize = 5;
+    const INITIAL_CAPACITY: usize = 16 * 1024;
+
+    pub fn new(device: &wgpu::Device, center: Point3<f32>) -> Self {
+        Metaballs {
+            center,
+            balls: Vec::new(),
+            vertex_buffer: create_vertex_buffer(device, Self::INITIAL_CAPACITY),
+            capacity: Self::INITIAL_CAPACITY,
+            vertex_count: 0,
+            transforms_buf: graphics::create_transforms_buffer(device, "transforms_metaballs", Matrix4::identity()),
+        }
+    }
+
+    // moves the balls to where they are at time and rebuilds the mesh around them
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, time: f32) {
+        self.balls = (0..Self::BALL_COUNT)
+            .map(|i| {
+                let phase = i as f32 * 1.7;
+                let offset = Vector3::new(
+                    (time * 0.7 + phase).sin(),
+                    (time * 0.9 + phase * 2.0).sin(),
+                    (time * 0.5 + phase * 3.0).cos(),
+                ) * (Self::EXTENT * 0.5);
+                (self.center + offset, 4.0 + i as f32)
+            })
+            .collect();
+
+        let vertices = self.polygonize();
+        if vertices.len() > self.capacity {
+            self.capacity = vertices.len().next_power_of_two();
+            self.vertex_buffer = create_vertex_buffer(device, self.capacity);
+        }
+        if !vertices.is_empty() {
+            queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        }
+        self.vertex_count = vertices.len() as u32;
+    }
+
+    // expects the main pipeline and the metaballs' bind group to be set already
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+
+    fn field(&self, p: Point3<f32>) -> f32 {
+        self.balls
+            .iter()
+            .map(|&(center, radius)| radius * radius / (p - center).magnitude2().max(f32::EPSILON))
+            .sum()
+    }
+
+    // points out of the surface, the field falls off away from every ball
+    fn normal(&self, p: Point3<f32>) -> Vector3<f32> {
+        let gradient = self.balls.iter().fold(Vector3::new(0.0, 0.0, 0.0), |sum, &(center, radius)| {
+            let d = p - center;
+            let len2 = d.magnitude2().max(f32::EPSILON);
+            sum - d * (2.0 * radius * radius / (len2 * len2))
+        });
+        let normal = -gradient;
+        if normal.magnitude2() > 0.0 { normal.normalize() } else { Vector3::unit_y() }
+    }
+
+    fn polygonize(&self) -> Vec<Vertex> {
+        let n = Self::RESOLUTION;
+        let cell = Self::EXTENT * 2.0 / n as f32;
+        let min = self.center - Vector3::new(Self::EXTENT, Self::EXTENT, Self::EXTENT);
+        let point = |x: usize, y: usize, z: usize| min + Vector3::new(x as f32, y as f32, z as f32) * cell;
+
+        // every grid point is shared by up to eight cubes, so sample each once up front
+        let samples = (0..=n)
+            .flat_map(|z| (0..=n).flat_map(move |y| (0..=n).map(move |x| (x, y, z))))
+            .map(|(x, y, z)| self.field(point(x, y, z)))
+            .collect::<Vec<_>>();
+        let sample = |x: usize, y: usize, z: usize| samples[(z * (n + 1) + y) * (n + 1) + x];
+
+        let mut vertices = Vec::new();
+        for z in 0..n {
+            for y in 0..n {
+                for x in 0..n {
+                    let corners: [(Point3<f32>, f32); 8] = std::array::from_fn(|i| {
+                        let (cx, cy, cz) = (x + (i & 1), y + ((i >> 1) & 1), z + ((i >> 2) & 1));
+                        (point(cx, cy, cz), sample(cx, cy, cz))
+                    });
+                    for tetrahedron in &TETRAHEDRA {
+                        self.polygonize_tetrahedron(tetrahedron.map(|i| corners[i]), &mut vertices);
+                    }
+                }
+            }
+        }
+        vertices
+    }
+
+    fn polygonize_tetrahedron(&self, corners: [(Point3<f32>, f32); 4], out: &mut Vec<Vertex>) {
+        let (inside, outside): (Vec<_>, Vec<_>) = corners.iter().partition(|(_, value)| *value >= Self::ISO_LEVEL);
+        let crossing = |a: &(Point3<f32>, f32), b: &(Point3<f32>, f32)| {
+            let t = (Self::ISO_LEVEL - a.1) / (b.1 - a.1);
+            a.0 + (b.0 - a.0) * t
+        };
+
+        let mut push = |p: Point3<f32>| {
+            out.push(Vertex {
+                position: p.into(),
+                // a planar projection is enough to show the texture moving over the surface
+                tex_coords: [p.x / Self::EXTENT, p.z / Self::EXTENT],
+                normal: self.normal(p).into(),
+            })
+        };
+
+        match (inside.len(), outside.len()) {
+            (1, 3) | (3, 1) => {
+                let (single, others) = if inside.len() == 1 { (inside[0], &outside) } else { (outside[0], &inside) };
+                for other in others.iter() {
+                    push(crossing(single, other));
+                }
+            }
+            (2, 2) => {
+                let a = crossing(inside[0], outside[0]);
+                let b = crossing(inside[0], outside[1]);
+                let c = crossing(inside[1], outside[1]);
+                let d = crossing(inside[1], outside[0]);
+                for p in [a, b, c, a, c, d] {
+                    push(p);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn create_vertex_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    graphics::create_buffer(device, &wgpu::BufferDescriptor {
+        label: Some("vertices_metaballs"),
+        size: (std::mem::size_of::<Vertex>() * capacity) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
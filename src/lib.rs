@@ -0,0 +1,566 @@
+use winit::{
+    dpi::PhysicalSize,
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    monitor::{MonitorHandle, VideoMode},
+    window::{Fullscreen, Icon, Window, WindowBuilder},
+};
+use std::path::{Path, PathBuf};
+use log::{info, debug, warn};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+mod animation;
+#[cfg(feature = "audio")]
+mod audio;
+mod batch;
+mod app;
+mod balls;
+mod billboard;
+mod camera;
+mod capture;
+mod collision;
+mod console;
+mod debug_draw;
+mod decals;
+mod emissive;
+mod error;
+mod flare;
+mod flythrough;
+mod focus;
+mod godrays;
+mod gpu_particles;
+mod grading;
+mod graphics;
+mod grass;
+mod grid;
+mod input;
+mod instance_wave;
+mod isosurface;
+mod lights;
+mod logging;
+mod map_window;
+mod minimap;
+mod morph;
+mod noise;
+mod particles;
+#[cfg(feature = "physics")]
+mod physics;
+mod picking;
+mod pool;
+mod portal;
+mod post;
+mod primitives;
+mod reflection;
+mod render_queue;
+mod scene;
+#[cfg(feature = "scripting")]
+mod scripting;
+mod skinning;
+mod settings;
+mod sky;
+mod spatial;
+mod sprites;
+mod text;
+mod terrain;
+mod timing;
+mod tv;
+mod uploads;
+mod voxel;
+#[cfg(not(target_arch = "wasm32"))]
+mod watch;
+mod weather;
+
+pub use error::AppError;
+pub use graphics::ContextOptions;
+#[cfg(not(target_arch = "wasm32"))]
+pub use logging::init as init_logging;
+pub use settings::Settings;
+
+// fixed timestep used while recording, independent of how long frames actually take
+const CAPTURE_TIMESTEP: f64 = 1.0 / 60.0;
+
+// pixels per side of the top-down map window opened with F10
+#[cfg(not(target_arch = "wasm32"))]
+const MAP_WINDOW_SIZE: u32 = 400;
+
+const THUMBNAIL_WIDTH: u32 = 320;
+const THUMBNAIL_HEIGHT: u32 = 180;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum FullscreenMode {
+    Borderless,
+    // takes over the monitor with one of its video modes
+    Exclusive,
+}
+
+pub struct WindowOptions {
+    pub size: (u32, u32),
+    // relative to the chosen monitor
+    pub position: (i32, i32),
+    pub fullscreen: bool,
+    // used at startup and when toggling with F11
+    pub fullscreen_mode: FullscreenMode,
+    // index into the available monitors, the primary monitor when None
+    pub monitor: Option<usize>,
+    // the video mode exclusive fullscreen switches to. what's left unset is picked from the
+    // monitor's modes, its current size first and then the highest refresh rate
+    pub fullscreen_resolution: Option<(u32, u32)>,
+    pub refresh_rate: Option<u16>,
+}
+
+impl Default for WindowOptions {
+    fn default() -> Self {
+        WindowOptions {
+            size: (1600, 900),
+            position: (100, 50),
+            fullscreen: false,
+            fullscreen_mode: FullscreenMode::Borderless,
+            monitor: None,
+            fullscreen_resolution: None,
+            refresh_rate: None,
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(start)]
+pub fn start() {
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+    console_log::init_with_level(log::Level::Info).expect("Failed to initialize logger");
+    wasm_bindgen_futures::spawn_local(async {
+        let result = run(ContextOptions::new(None, None, true), WindowOptions::default(), None, Settings::default(), None);
+        if let Err(e) = result.await {
+            log::error!("{}", e);
+        }
+    });
+}
+
+// settings is what the run uses, saved what was loaded from settings_path. what changes while
+// running is copied into saved and written back to settings_path when the window closes, so
+// overrides that only went into settings don't stick. nothing is written without saved, when the
+// file couldn't be parsed
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run_app(
+    options: ContextOptions,
+    window_options: WindowOptions,
+    scene: Option<PathBuf>,
+    settings: Settings,
+    saved: Option<Settings>,
+    settings_path: PathBuf,
+) -> Result<(), AppError> {
+    pollster::block_on(run(options, window_options, scene, settings, saved.map(|saved| (saved, settings_path))))
+}
+
+// renders a fixed number of frames without opening a window and writes them to captures/headless
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run_headless(
+    frames: u32,
+    size: (u32, u32),
+    options: ContextOptions,
+    scene: Option<PathBuf>,
+) -> Result<(), AppError> {
+    info!("Rendering {} frames headlessly...", frames);
+
+    let mut app = pollster::block_on(app::App::new_headless(size.0, size.1, &options))?;
+    if let Some(path) = scene {
+        load_scene(&mut app, &path);
+    }
+    // scenes with a camera path give repeatable runs for comparing captures or timings
+    if app.play_flythrough() {
+        info!("Playing the scene's flythrough");
+    }
+    app.start_recording(std::path::PathBuf::from("captures/headless"))?;
+    for _ in 0..frames {
+        app.delta_time = CAPTURE_TIMESTEP;
+        app.update();
+        app.render()?;
+    }
+
+    info!("Done.");
+    Ok(())
+}
+
+// renders every scene file in dir from its starting camera pose into dir/thumbnails/<name>.png
+#[cfg(not(target_arch = "wasm32"))]
+pub fn generate_thumbnails(dir: &Path, options: ContextOptions) -> Result<(), AppError> {
+    let mut scenes = std::fs::read_dir(dir)
+        .map_err(|e| AppError::Io(dir.to_path_buf(), e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect::<Vec<_>>();
+    scenes.sort();
+
+    let mut app = pollster::block_on(app::App::new_headless(THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT, &options))?;
+    for path in scenes {
+        let scene = match scene::Scene::load(&path) {
+            Ok(scene) => scene,
+            Err(e) => {
+                warn!("Skipping {}: {}", path.display(), e);
+                continue;
+            }
+        };
+
+        let Some(name) = path.file_stem() else {
+            warn!("Skipping {}: it has no name", path.display());
+            continue;
+        };
+        let out = dir.join("thumbnails").join(name).with_extension("png");
+        app.apply_scene(&scene);
+        app.delta_time = 0.0;
+        app.update();
+        if app.render_to_file(out.clone())? {
+            info!("Wrote {}", out.display());
+        }
+    }
+    Ok(())
+}
+
+// an error when the app can't go on
+#[cfg(not(target_arch = "wasm32"))]
+fn recreate_device(
+    app: &mut app::App,
+    window: &Window,
+    options: &ContextOptions,
+    settings: &Settings,
+) -> Result<(), AppError> {
+    warn!("Recreating the device...");
+    pollster::block_on(app.recreate(window, options, settings))
+}
+
+// the browser's futures can't be blocked on, the page has to be reloaded
+#[cfg(target_arch = "wasm32")]
+fn recreate_device(
+    _app: &mut app::App,
+    _window: &Window,
+    _options: &ContextOptions,
+    _settings: &Settings,
+) -> Result<(), AppError> {
+    Err(AppError::DeviceLost)
+}
+
+// the event loop never returns, so what ended it is reported once the settings are saved
+#[cfg(not(target_arch = "wasm32"))]
+fn exit_with_error(e: &AppError) {
+    log::error!("{}", e);
+    std::process::exit(1);
+}
+
+#[cfg(target_arch = "wasm32")]
+fn exit_with_error(e: &AppError) {
+    log::error!("{}", e);
+}
+
+fn load_scene(app: &mut app::App, path: &Path) {
+    match scene::Scene::load(path) {
+        Ok(scene) => app.apply_scene(&scene),
+        Err(e) => warn!("Failed to load scene {}: {}", path.display(), e),
+    }
+}
+
+// a file saved halfway through an edit can fail to parse, the scene stays as it was until the
+// next save
+#[cfg(not(target_arch = "wasm32"))]
+fn reload_scene(app: &mut app::App, path: &Path) {
+    match scene::Scene::load(path) {
+        Ok(scene) => app.reload_scene(&scene),
+        Err(e) => warn!("Failed to reload scene {}: {}", path.display(), e),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_settings(settings: &Settings, path: &Path) {
+    settings.save(path);
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save_settings(_settings: &Settings, _path: &Path) {}
+
+// None leaves the platform's default icon, which isn't worth stopping for
+fn window_icon() -> Option<Icon> {
+    let image = match image::load_from_memory(include_bytes!("../res/icon.png")) {
+        Ok(image) => image.into_rgba8(),
+        Err(e) => {
+            warn!("Failed to decode window icon: {}", e);
+            return None;
+        }
+    };
+    let (width, height) = image.dimensions();
+    Icon::from_rgba(image.into_raw(), width, height).map_err(|e| warn!("Failed to create window icon: {}", e)).ok()
+}
+
+fn fullscreen(window: &Window, options: &WindowOptions) -> Fullscreen {
+    let monitor = options
+        .monitor
+        .and_then(|i| window.available_monitors().nth(i))
+        .or_else(|| window.primary_monitor());
+
+    match options.fullscreen_mode {
+        FullscreenMode::Borderless => Fullscreen::Borderless(monitor),
+        FullscreenMode::Exclusive => match monitor.as_ref().and_then(|monitor| video_mode(monitor, options)) {
+            Some(mode) => {
+                info!("Exclusive fullscreen at {}x{} {}Hz", mode.size().width, mode.size().height, mode.refresh_rate());
+                Fullscreen::Exclusive(mode)
+            }
+            None => {
+                warn!("No monitor or video mode for exclusive fullscreen, going borderless instead");
+                Fullscreen::Borderless(monitor)
+            }
+        },
+    }
+}
+
+// the best of the monitor's modes matching the options, or of all of them when none do
+fn video_mode(monitor: &MonitorHandle, options: &WindowOptions) -> Option<VideoMode> {
+    let size = options.fullscreen_resolution.map(|(width, height)| PhysicalSize::new(width, height));
+    let matches = |mode: &VideoMode| {
+        size.is_none_or(|size| mode.size() == size)
+            && options.refresh_rate.is_none_or(|hz| mode.refresh_rate() == hz)
+    };
+    let best = |modes: Vec<VideoMode>| {
+        modes.into_iter().max_by_key(|mode| {
+            let pixels = mode.size().width * mode.size().height;
+            (size.unwrap_or(monitor.size()) == mode.size(), mode.refresh_rate(), pixels, mode.bit_depth())
+        })
+    };
+    let (matching, others) = monitor.video_modes().partition::<Vec<_>, _>(matches);
+    if matching.is_empty() {
+        let available = others
+            .iter()
+            .map(|mode| format!("{}x{} {}Hz", mode.size().width, mode.size().height, mode.refresh_rate()))
+            .collect::<Vec<_>>();
+        warn!("No video mode matches the one asked for, the monitor has {}", available.join(", "));
+        return best(others);
+    }
+    best(matching)
+}
+
+async fn run(
+    options: ContextOptions,
+    window_options: WindowOptions,
+    scene: Option<PathBuf>,
+    settings: Settings,
+    mut saved: Option<(Settings, PathBuf)>,
+) -> Result<(), AppError> {
+    let event_loop = EventLoop::new();
+
+    info!("Initializing... Please wait.");
+
+    let monitor_position = window_options
+        .monitor
+        .and_then(|i| event_loop.available_monitors().nth(i))
+        .map_or((0, 0), |monitor| (monitor.position().x, monitor.position().y));
+    let window = WindowBuilder::new()
+        .with_inner_size(winit::dpi::PhysicalSize::new(window_options.size.0, window_options.size.1))
+        .with_position(winit::dpi::PhysicalPosition::new(
+            monitor_position.0 + window_options.position.0,
+            monitor_position.1 + window_options.position.1,
+        ))
+        .with_title("learning_wgpu")
+        .with_window_icon(window_icon())
+        .with_visible(false)
+        .build(&event_loop)?;
+    if window_options.fullscreen {
+        window.set_fullscreen(Some(fullscreen(&window, &window_options)));
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        use winit::platform::web::WindowExtWebSys;
+        web_sys::window()
+            .and_then(|win| win.document())
+            .and_then(|doc| doc.body())
+            .and_then(|body| body.append_child(&web_sys::Element::from(window.canvas())).ok())
+            .expect("Failed to append canvas to document body");
+    }
+
+    info!("Size of application on stack: {}kb", &(std::mem::size_of::<app::App>() as f64 / 1024.0).to_string()[0..4]);
+    let mut app = app::App::new(&window, &options).await?;
+    app.apply_settings(&settings);
+    if let Some(path) = &scene {
+        load_scene(&mut app, path);
+    }
+    // edits to the scene file show up without restarting
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut scene_watcher = scene.map(watch::FileWatcher::new);
+    let mut timer = timing::FrameTimer::new(settings.delta_smoothing_frames, settings.max_delta_time);
+    let mut limiter = timing::FrameLimiter::new(settings.max_fps);
+    let mut focus = focus::FocusState::new();
+    let mut error = None;
+    info!("Done initializing.");
+
+    window.set_visible(true);
+    event_loop.run(move |event, target, control_flow| {
+        match event {
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => match event {
+                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(key),
+                            ..
+                        },
+                    ..
+                } => {
+                    match key {
+                        VirtualKeyCode::Escape if app.is_console_open() => app.close_console(),
+                        VirtualKeyCode::Escape => {
+                            if !focus.is_focused() {
+                                *control_flow = ControlFlow::Exit;
+                            } else {
+                                focus.release(&window);
+                            }
+                        }
+                        VirtualKeyCode::F11 => {
+                            window.set_fullscreen(
+                                if window.fullscreen().is_none() {
+                                    Some(fullscreen(&window, &window_options))
+                                } else {
+                                    None
+                                }
+                            );
+                        }
+                        #[cfg(feature = "clipboard")]
+                        VirtualKeyCode::Snapshot => app.copy_frame_to_clipboard(),
+                        #[cfg(not(target_arch = "wasm32"))]
+                        VirtualKeyCode::F9 => app.toggle_recording(),
+                        #[cfg(not(target_arch = "wasm32"))]
+                        VirtualKeyCode::F10 => {
+                            if app.map_window().is_some() {
+                                app.close_map();
+                            } else {
+                                let map = WindowBuilder::new()
+                                    .with_inner_size(winit::dpi::PhysicalSize::new(MAP_WINDOW_SIZE, MAP_WINDOW_SIZE))
+                                    .with_title("learning_wgpu map")
+                                    .with_window_icon(window_icon())
+                                    .build(target);
+                                match map {
+                                    Ok(map) => app.open_map(map),
+                                    Err(e) => warn!("Failed to build map window: {}", e),
+                                }
+                            }
+                        }
+                        _ => app.input(Some(event), None, &window, &focus)
+                    }
+                }
+                // once captured, clicks go to the app
+                WindowEvent::MouseInput {
+                    state: ElementState::Pressed,
+                    button: MouseButton::Left,
+                    ..
+                } if !focus.is_focused() => focus.capture(&window),
+                WindowEvent::Focused(true) => focus.capture(&window),
+                WindowEvent::Focused(false) => focus.release(&window),
+                _ => app.input(Some(event), None, &window, &focus)
+            },
+            // the map only shows the scene, input goes to the main window
+            Event::WindowEvent { ref event, window_id }
+                if app.map_window().is_some_and(|map| map.id() == window_id) => match event {
+                WindowEvent::CloseRequested => app.close_map(),
+                WindowEvent::Resized(new_size) => app.resize_map(*new_size),
+                WindowEvent::ScaleFactorChanged { new_inner_size, .. } => app.resize_map(**new_inner_size),
+                _ => {}
+            },
+            Event::DeviceEvent { ref event, .. } => {
+                app.input(None, Some(event), &window, &focus);
+            }
+            Event::RedrawRequested(window_id) if window_id == window.id() => {
+                app.set_focused(focus.is_focused());
+                app.update();
+                // a minimized window has nothing to draw into until it's restored, and there's no
+                // device to draw with once recreating it failed
+                let size = window.inner_size();
+                if size.width == 0 || size.height == 0 || error.is_some() {
+                    focus.update_cursor(&window, false);
+                    return;
+                }
+                // a loss wgpu's error handler flagged since the last frame is dealt with before
+                // drawing. wgpu 0.13 still panics when submitting or presenting is the first to run
+                // into it, it has no error to return from either
+                if app.is_device_lost() {
+                    if let Err(e) = recreate_device(&mut app, &window, &options, &settings) {
+                        error = Some(e);
+                        *control_flow = ControlFlow::Exit;
+                        focus.update_cursor(&window, false);
+                        return;
+                    }
+                }
+                let presented = match app.render() {
+                    Ok(_) => !app.is_device_lost(),
+                    // the device is fine, the surface only has to be configured again
+                    Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                        app.resize(size);
+                        false
+                    }
+                    Err(wgpu::SurfaceError::OutOfMemory) => {
+                        *control_flow = ControlFlow::Exit;
+                        false
+                    }
+                    Err(e) => {
+                        debug!("SurfaceError: {:?}", e);
+                        false
+                    }
+                };
+                focus.update_cursor(&window, presented && app.draws_cursor());
+            }
+            Event::RedrawRequested(window_id) if app.map_window().is_some_and(|map| map.id() == window_id) => {
+                let Some(size) = app.map_window().map(|map| map.inner_size()) else {
+                    return;
+                };
+                if size.width == 0 || size.height == 0 {
+                    return;
+                }
+                match app.render_map() {
+                    Ok(_) => {}
+                    Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => app.resize_map(size),
+                    Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
+                    Err(e) => debug!("SurfaceError: {:?}", e),
+                }
+            }
+            Event::MainEventsCleared => {
+                if let Some(fps) = timer.fps() {
+                    app.set_fps(fps);
+                }
+
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some(watcher) = &mut scene_watcher {
+                    if watcher.changed() {
+                        reload_scene(&mut app, watcher.path());
+                    }
+                }
+
+                limiter.wait();
+                let delta_time = timer.tick();
+                app.delta_time = (if app.is_recording() { CAPTURE_TIMESTEP } else { delta_time }) * app.time_scale();
+                window.request_redraw();
+                if let Some(map) = app.map_window() {
+                    map.request_redraw();
+                }
+            }
+            Event::LoopDestroyed => {
+                if let Some((saved, path)) = &mut saved {
+                    saved.bookmarks = app.bookmarks();
+                    let fullscreen = window.fullscreen().is_some();
+                    if fullscreen != settings.fullscreen {
+                        saved.fullscreen = fullscreen;
+                    }
+                    // keep the windowed resolution rather than the monitor's while fullscreen
+                    let size = window.inner_size();
+                    if !fullscreen && [size.width, size.height] != settings.resolution {
+                        saved.resolution = [size.width, size.height];
+                    }
+                    save_settings(saved, path);
+                }
+                if let Some(e) = &error {
+                    exit_with_error(e);
+                }
+            }
+            _ => {}
+        }
+    });
+}
@@ -0,0 +1,155 @@
+use cgmath::{InnerSpace, Point3, Vector3};
+
+use crate::debug_draw::DebugDraw;
+use crate::graphics;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    Point,
+    // a cone around direction, full strength inside inner_angle fading out towards outer_angle
+    Spot {
+        direction: Vector3<f32>,
+        inner_angle: cgmath::Deg<f32>,
+        outer_angle: cgmath::Deg<f32>,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Light {
+    pub kind: LightKind,
+    pub position: Point3<f32>,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    // distance at which the light has faded out completely
+    pub range: f32,
+}
+
+// layout of one light in the storage buffer read by shader.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightRaw {
+    // w is 0 for point lights and 1 for spot lights
+    position: [f32; 4],
+    // rgb premultiplied by intensity, w is the range
+    color: [f32; 4],
+    // xyz is the spot direction, w the cosine of the outer angle
+    direction: [f32; 4],
+    // x is the cosine of the inner angle
+    params: [f32; 4],
+}
+
+impl Light {
+    // size of the sphere drawn at the light
+    const GIZMO_RADIUS: f32 = 1.0;
+    // spot cones are drawn this long, or shorter if the light's range is
+    const GIZMO_CONE_LENGTH: f32 = 15.0;
+
+    // a small sphere in the light's color, plus the outer cone of spot lights
+    pub fn draw_gizmo(&self, debug_draw: &mut DebugDraw) {
+        debug_draw.draw_sphere(self.position, Self::GIZMO_RADIUS, self.color);
+        if let LightKind::Spot { direction, outer_angle, .. } = self.kind {
+            let length = Self::GIZMO_CONE_LENGTH.min(self.range);
+            debug_draw.draw_cone(self.position, direction, length, outer_angle, self.color);
+        }
+    }
+
+    pub fn to_raw(self) -> LightRaw {
+        let (kind, direction, cos_outer, cos_inner) = match self.kind {
+            LightKind::Point => (0.0, Vector3::new(0.0, -1.0, 0.0), -1.0, -1.0),
+            LightKind::Spot { direction, inner_angle, outer_angle } => (
+                1.0,
+                direction.normalize(),
+                cgmath::Angle::cos(outer_angle),
+                cgmath::Angle::cos(inner_angle),
+            ),
+        };
+
+        LightRaw {
+            position: [self.position.x, self.position.y, self.position.z, kind],
+            color: [
+                self.color[0] * self.intensity,
+                self.color[1] * self.intensity,
+                self.color[2] * self.intensity,
+                self.range,
+            ],
+            direction: direction.extend(cos_outer).into(),
+            params: [cos_inner, 0.0, 0.0, 0.0],
+        }
+    }
+}
+
+// holds up to MAX_LIGHTS lights on the gpu, rewritten every frame
+pub struct LightBuffer {
+    buffer: wgpu::Buffer,
+}
+
+impl LightBuffer {
+    pub const MAX_LIGHTS: usize = 32;
+
+    pub fn new(device: &wgpu::Device) -> Self {
+        LightBuffer {
+            buffer: graphics::create_buffer(device, &wgpu::BufferDescriptor {
+                label: Some("light_storage_buffer"),
+                size: (std::mem::size_of::<LightRaw>() * Self::MAX_LIGHTS) as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        }
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    // returns how many lights were uploaded, anything past MAX_LIGHTS is dropped
+    pub fn upload(&self, queue: &wgpu::Queue, lights: &[Light]) -> u32 {
+        let raw = lights
+            .iter()
+            .take(Self::MAX_LIGHTS)
+            .map(|light| light.to_raw())
+            .collect::<Vec<_>>();
+        if !raw.is_empty() {
+            queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&raw));
+        }
+        raw.len() as u32
+    }
+}
+
+// a few colored point lights circling center, plus a spot light sweeping over it from above
+pub fn orbiting_demo(center: Point3<f32>, radius: f32, time: f32) -> Vec<Light> {
+    const COLORS: [[f32; 3]; 4] = [
+        [1.0, 0.2, 0.2],
+        [0.2, 1.0, 0.2],
+        [0.2, 0.4, 1.0],
+        [1.0, 0.8, 0.2],
+    ];
+
+    let mut lights = COLORS
+        .iter()
+        .enumerate()
+        .map(|(i, &color)| {
+            let angle = time * 0.5 + i as f32 * std::f32::consts::TAU / COLORS.len() as f32;
+            Light {
+                kind: LightKind::Point,
+                position: center + Vector3::new(angle.cos() * radius, 5.0 + (time + i as f32).sin() * 3.0, angle.sin() * radius),
+                color,
+                intensity: 2.0,
+                range: radius,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let sweep = time * 0.3;
+    lights.push(Light {
+        kind: LightKind::Spot {
+            direction: Vector3::new(sweep.cos() * 0.5, -1.0, sweep.sin() * 0.5),
+            inner_angle: cgmath::Deg(15.0),
+            outer_angle: cgmath::Deg(25.0),
+        },
+        position: center + Vector3::new(0.0, radius, 0.0),
+        color: [1.0, 1.0, 0.9],
+        intensity: 3.0,
+        range: radius * 3.0,
+    });
+    lights
+}
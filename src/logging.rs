@@ -0,0 +1,158 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+// messages from this crate start with its name, everything else comes from a dependency
+const CRATE: &str = env!("CARGO_CRATE_NAME");
+// used when RUST_LOG isn't set. the dependencies (wgpu especially) are chatty below warn
+const DEFAULT_FILTER: &str = "warn,learning_wgpu=info";
+const LOG_DIR: &str = "logs";
+// a file is started over past this size, with the last few kept beside it as name.1.log and so on
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+const KEPT_FILES: u32 = 3;
+// the levels cycle_level goes through, from quiet to loud
+const LEVELS: [LevelFilter; 5] = [
+    LevelFilter::Error,
+    LevelFilter::Warn,
+    LevelFilter::Info,
+    LevelFilter::Debug,
+    LevelFilter::Trace,
+];
+
+// bumped once per frame, so messages can be lined up with what was on screen
+static FRAME: AtomicU64 = AtomicU64::new(0);
+
+// writes to stderr and to a file that's rotated when it grows too big. RUST_LOG limits how much
+// of each dependency gets through, the level set at runtime caps everything
+struct Logger {
+    dependencies: env_logger::filter::Filter,
+    start: Instant,
+    file: Option<Mutex<LogFile>>,
+}
+
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+// call once at startup, the file goes in logs/learning_wgpu.log
+pub fn init() {
+    let filters = std::env::var("RUST_LOG").unwrap_or_else(|_| DEFAULT_FILTER.to_string());
+    let dependencies = env_logger::filter::Builder::new().parse(&filters).build();
+    // the crate starts at whatever level RUST_LOG gives it
+    let level = LEVELS
+        .into_iter()
+        .rev()
+        .find(|level| {
+            level.to_level().is_some_and(|level| dependencies.enabled(&Metadata::builder().target(CRATE).level(level).build()))
+        })
+        .unwrap_or(LevelFilter::Error);
+
+    let path = Path::new(LOG_DIR).join(CRATE).with_extension("log");
+    let file = match LogFile::open(path) {
+        Ok(file) => Some(Mutex::new(file)),
+        Err(e) => {
+            eprintln!("Failed to open the log file, only logging to stderr: {}", e);
+            None
+        }
+    };
+    log::set_boxed_logger(Box::new(Logger { dependencies, start: Instant::now(), file }))
+        .expect("Failed to set the logger");
+    log::set_max_level(level);
+}
+
+pub fn next_frame() {
+    FRAME.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn level() -> LevelFilter {
+    log::max_level()
+}
+
+// one level louder, back to the quietest after the loudest. works with any logger, the
+// browser's included
+pub fn cycle_level() -> LevelFilter {
+    let current = LEVELS.iter().position(|&level| level == log::max_level()).unwrap_or(0);
+    let level = LEVELS[(current + 1) % LEVELS.len()];
+    log::set_max_level(level);
+    level
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let ours = metadata.target().split("::").next() == Some(CRATE);
+        metadata.level() <= log::max_level() && (ours || self.dependencies.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!(
+            "[{:>9.3} #{:<6} {:<5} {}] {}\n",
+            self.start.elapsed().as_secs_f64(),
+            FRAME.load(Ordering::Relaxed),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        eprint!("{}", line);
+        if let Some(file) = &self.file {
+            if let Ok(mut file) = file.lock() {
+                file.append(line.as_bytes());
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            if let Ok(mut file) = file.lock() {
+                let _ = file.file.flush();
+            }
+        }
+    }
+}
+
+impl LogFile {
+    // every run starts a new file, the previous runs' are rotated out of the way
+    fn open(path: PathBuf) -> std::io::Result<Self> {
+        std::fs::create_dir_all(path.parent().unwrap_or_else(|| Path::new(".")))?;
+        rotate(&path);
+        Ok(LogFile { file: File::create(&path)?, path, size: 0 })
+    }
+
+    // a failed write isn't worth a message, it would only be written here again
+    fn append(&mut self, bytes: &[u8]) {
+        if self.size + bytes.len() as u64 > MAX_FILE_SIZE {
+            rotate(&self.path);
+            match File::create(&self.path) {
+                Ok(file) => {
+                    self.file = file;
+                    self.size = 0;
+                }
+                Err(_) => return,
+            }
+        }
+        if self.file.write_all(bytes).is_ok() {
+            self.size += bytes.len() as u64;
+        }
+    }
+}
+
+// name.log becomes name.1.log, name.1.log becomes name.2.log and so on, dropping the oldest
+fn rotate(path: &Path) {
+    let numbered = |n: u32| {
+        let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or(CRATE);
+        path.with_file_name(format!("{}.{}.log", stem, n))
+    };
+    let _ = std::fs::remove_file(numbered(KEPT_FILES));
+    for n in (1..KEPT_FILES).rev() {
+        let _ = std::fs::rename(numbered(n), numbered(n + 1));
+    }
+    let _ = std::fs::rename(path, numbered(1));
+}
@@ -1,133 +1,161 @@
-use winit::{
-    event::*,
-    event_loop::{ControlFlow, EventLoop},
-    window::{WindowBuilder, Fullscreen},
-};
-use log::{info, debug};
+#[cfg(not(target_arch = "wasm32"))]
+use clap::Parser;
 
-mod app;
-mod camera;
-mod graphics;
-mod input;
-
-const EXCLUSIVE_FULLSCREEN: bool = false;
-
-fn main() {
-    run_app();
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Parser)]
+#[clap(about = "Instanced scene renderer built while learning wgpu")]
+struct Args {
+    /// Settings file loaded at startup and written back on exit
+    #[clap(long, default_value = "settings.toml")]
+    settings: std::path::PathBuf,
+    /// Window (or headless render) width in pixels, overrides the settings file
+    #[clap(long)]
+    width: Option<u32>,
+    /// Window (or headless render) height in pixels, overrides the settings file
+    #[clap(long)]
+    height: Option<u32>,
+    /// Window x position, relative to the chosen monitor
+    #[clap(long, default_value_t = 100)]
+    x: i32,
+    /// Window y position, relative to the chosen monitor
+    #[clap(long, default_value_t = 50)]
+    y: i32,
+    /// Start in fullscreen (F11 toggles at runtime)
+    #[clap(long)]
+    fullscreen: bool,
+    /// Fullscreen mode used at startup and by F11, overrides the settings file
+    #[clap(long, value_enum)]
+    fullscreen_mode: Option<FullscreenMode>,
+    /// Monitor index to open on, overrides the settings file (the primary monitor otherwise)
+    #[clap(long)]
+    monitor: Option<usize>,
+    /// Exclusive fullscreen resolution as WIDTHxHEIGHT, overrides the settings file (the monitor's own otherwise)
+    #[clap(long, value_parser = parse_resolution)]
+    fullscreen_resolution: Option<[u32; 2]>,
+    /// Exclusive fullscreen refresh rate in Hz, overrides the settings file (the highest available otherwise)
+    #[clap(long)]
+    refresh_rate: Option<u16>,
+    /// Sync presenting frames to the display, overrides the settings file
+    #[clap(long, conflicts_with = "no-vsync")]
+    vsync: bool,
+    /// Present frames as fast as possible instead of syncing to the display, overrides the settings file
+    #[clap(long)]
+    no_vsync: bool,
+    /// Cap the frame rate (0 for no cap), overrides the settings file
+    #[clap(long)]
+    max_fps: Option<u32>,
+    /// Scene resolution relative to the window's, from 0.5 to 2, overrides the settings file
+    #[clap(long)]
+    render_scale: Option<f32>,
+    /// Comma separated wgpu backends, e.g. vulkan or dx12,gl (otherwise WGPU_BACKEND or the platform default)
+    #[clap(long)]
+    backend: Option<String>,
+    /// Index from the adapter listing logged at startup, or part of the adapter's name
+    #[clap(long)]
+    adapter: Option<String>,
+    /// Scene file to load at startup
+    #[clap(long)]
+    scene: Option<std::path::PathBuf>,
+    /// Render without a window and write the frames to captures/headless
+    #[clap(long)]
+    headless: bool,
+    /// Number of frames to render in headless mode
+    #[clap(long, default_value_t = 60)]
+    frames: u32,
+    /// Render a thumbnail for every scene file in this directory and exit
+    #[clap(long, value_name = "DIR")]
+    thumbnails: Option<std::path::PathBuf>,
 }
 
-fn run_app() {
-    env_logger::init();
-    let event_loop = EventLoop::new();
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, clap::ValueEnum)]
+enum FullscreenMode {
+    Borderless,
+    Exclusive,
+}
 
-    info!("Initializing... Please wait.");
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_resolution(text: &str) -> Result<[u32; 2], String> {
+    let (width, height) = text.split_once('x').ok_or("Expected WIDTHxHEIGHT")?;
+    let parse = |n: &str| n.parse::<u32>().map_err(|_| format!("Expected WIDTHxHEIGHT, {:?} isn't a number", n));
+    Ok([parse(width)?, parse(height)?])
+}
 
-    let window = WindowBuilder::new()
-        .with_inner_size(winit::dpi::PhysicalSize::new(1600, 900))
-        .with_position(winit::dpi::PhysicalPosition::new(100, 50))
-        .with_title("learning_wgpu")
-        .with_visible(false)
-        .build(&event_loop)
-        .expect("Failed to build window");
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    learning_wgpu::init_logging();
+    let args = Args::parse();
 
-    info!("Size of application on stack: {}kb", &(std::mem::size_of::<app::App>() as f64 / 1024.0).to_string()[0..4]);
-    let mut app = app::App::new(&window);
-    let mut last_frame = std::time::Instant::now();
-    let mut is_focused = false;
-    let mut last_fps_update = std::time::Instant::now();
-    let mut frames = 0;
-    info!("Done initializing.");
+    // command line flags win over the settings file for this run only, saved is written back
+    // without them
+    let saved = learning_wgpu::Settings::load(&args.settings);
+    let mut settings = saved.clone().unwrap_or_default();
+    if let Some(width) = args.width {
+        settings.resolution[0] = width;
+    }
+    if let Some(height) = args.height {
+        settings.resolution[1] = height;
+    }
+    if args.fullscreen {
+        settings.fullscreen = true;
+    }
+    if let Some(mode) = args.fullscreen_mode {
+        settings.fullscreen_mode = match mode {
+            FullscreenMode::Borderless => learning_wgpu::FullscreenMode::Borderless,
+            FullscreenMode::Exclusive => learning_wgpu::FullscreenMode::Exclusive,
+        };
+    }
+    if let Some(monitor) = args.monitor {
+        settings.monitor = Some(monitor);
+    }
+    if let Some(resolution) = args.fullscreen_resolution {
+        settings.fullscreen_resolution = Some(resolution);
+    }
+    if let Some(refresh_rate) = args.refresh_rate {
+        settings.refresh_rate = Some(refresh_rate);
+    }
+    if args.vsync {
+        settings.vsync = true;
+    }
+    if args.no_vsync {
+        settings.vsync = false;
+    }
+    if let Some(max_fps) = args.max_fps {
+        settings.max_fps = max_fps;
+    }
+    if let Some(render_scale) = args.render_scale {
+        settings.render_scale = render_scale;
+    }
 
-    window.set_visible(true);
-    event_loop.run(move |event, window_target, control_flow| {
-        match event {
-            Event::WindowEvent {
-                ref event,
-                window_id,
-            } if window_id == window.id() => match event {
-                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-                WindowEvent::KeyboardInput {
-                    input:
-                        KeyboardInput {
-                            state: ElementState::Pressed,
-                            virtual_keycode: Some(key),
-                            ..
-                        },
-                    ..
-                } => {
-                    match key {
-                        VirtualKeyCode::Escape => {
-                            if !is_focused {
-                                *control_flow = ControlFlow::Exit;
-                            } else {
-                                is_focused = false;
-                                window.set_cursor_visible(true);
-                            }
-                        }
-                        VirtualKeyCode::F11 => {
-                            window.set_fullscreen(
-                                if let None = window.fullscreen() {
-                                    if EXCLUSIVE_FULLSCREEN {
-                                        Some(Fullscreen::Exclusive(
-                                            window_target
-                                                .primary_monitor()
-                                                .expect("Failed to get primary monitor")
-                                                .video_modes()
-                                                .next()
-                                                .expect("No fullscreen video modes available")
-                                        ))
-                                    } else {
-                                        Some(Fullscreen::Borderless(None))
-                                    }
-                                } else {
-                                    None
-                                }
-                            );
-                        }
-                        _ => app.input(Some(event), None, &window, is_focused)
-                    }
-                }
-                WindowEvent::MouseInput {
-                    state: ElementState::Pressed,
-                    button: MouseButton::Left,
-                    ..
-                } => {
-                    is_focused = true;
-                    window.set_cursor_visible(false);
-                }
-                WindowEvent::Focused(focused) => {
-                    is_focused = *focused;
-                    window.set_cursor_visible(!is_focused);
-                }
-                _ => app.input(Some(event), None, &window, is_focused)
-            },
-            Event::DeviceEvent { ref event, .. } => {
-                app.input(None, Some(event), &window, is_focused);
-            }
-            Event::RedrawRequested(window_id) if window_id == window.id() => {
-                app.update();
-                match app.render() {
-                    Ok(_) => {}
-                    Err(wgpu::SurfaceError::Lost) => app.resize(app.size),
-                    Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
-                    Err(e) => debug!("SurfaceError: {:?}", e),
-                }
-            }
-            Event::MainEventsCleared => {
-                frames += 1;
-                let now = std::time::Instant::now();
-                if now.duration_since(last_fps_update) >= std::time::Duration::from_secs(1) {
-                    window.set_title(&format!("learing_wgpu | FPS: {}", frames));
-                    frames = 0;
-                    last_fps_update = now;
-                }
+    let options = learning_wgpu::ContextOptions::new(
+        args.backend.as_deref(),
+        args.adapter.as_deref(),
+        settings.vsync,
+    );
+    let size = (settings.resolution[0], settings.resolution[1]);
 
-                let now = std::time::Instant::now();
-                app.delta_time = now.duration_since(last_frame).as_secs_f64();
-                last_frame = now;
-                window.request_redraw();
-            }
-            _ => {}
-        }
-    });
+    let result = if args.headless {
+        learning_wgpu::run_headless(args.frames, size, options, args.scene)
+    } else if let Some(dir) = args.thumbnails {
+        learning_wgpu::generate_thumbnails(&dir, options)
+    } else {
+        let window_options = learning_wgpu::WindowOptions {
+            size,
+            position: (args.x, args.y),
+            fullscreen: settings.fullscreen,
+            fullscreen_mode: settings.fullscreen_mode,
+            monitor: settings.monitor,
+            fullscreen_resolution: settings.fullscreen_resolution.map(|[width, height]| (width, height)),
+            refresh_rate: settings.refresh_rate,
+        };
+        learning_wgpu::run_app(options, window_options, args.scene, settings, saved, args.settings)
+    };
+    if let Err(e) = result {
+        log::error!("{}", e);
+        std::process::exit(1);
+    }
 }
+
+// the browser build starts through the wasm_bindgen entry point in lib.rs instead
+#[cfg(target_arch = "wasm32")]
+fn main() {}
@@ -0,0 +1,111 @@
+use cgmath::{Matrix4, Point3};
+use log::warn;
+use winit::window::Window;
+
+use crate::camera;
+use crate::graphics;
+
+// a second window looking straight down on the scene around the camera. it has its own surface
+// and render targets, everything else is shared with the main window
+pub struct MapWindow {
+    window: Window,
+    surface: wgpu::Surface,
+    config: wgpu::SurfaceConfiguration,
+    depth_texture: (wgpu::TextureView, wgpu::Sampler, wgpu::Texture),
+    msaa_target: Option<wgpu::TextureView>,
+}
+
+impl MapWindow {
+    // world units across the shorter side of the window
+    const EXTENT: f32 = 300.0;
+    // the map shows this far above and below the camera
+    const HEIGHT: f32 = 500.0;
+
+    // the window has to use the main window's format, the pipelines are shared with it
+    pub fn new(
+        window: Window,
+        instance: &wgpu::Instance,
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let surface = unsafe { instance.create_surface(&window) };
+        if !surface.get_supported_formats(adapter).contains(&format) {
+            warn!("The map window's surface doesn't list {:?}, it may not show", format);
+        }
+        let size = window.inner_size();
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        surface.configure(device, &config);
+        MapWindow {
+            window,
+            surface,
+            depth_texture: graphics::create_depth_texture(device, &config, "map_depth_texture"),
+            msaa_target: graphics::create_msaa_target(device, &config, "map_msaa_target"),
+            config,
+        }
+    }
+
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    // drops the surface, so another one can be made for the window
+    pub fn into_window(self) -> Window {
+        self.window
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, size: winit::dpi::PhysicalSize<u32>) {
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
+        self.config.width = size.width;
+        self.config.height = size.height;
+        self.surface.configure(device, &self.config);
+        self.depth_texture = graphics::create_depth_texture(device, &self.config, "map_depth_texture");
+        self.msaa_target = graphics::create_msaa_target(device, &self.config, "map_msaa_target");
+    }
+
+    // orthographic from above with north (-z) up, centered on a point
+    pub fn view_proj(&self, center: Point3<f32>) -> Matrix4<f32> {
+        let aspect = self.config.width as f32 / self.config.height as f32;
+        camera::top_down_view_proj(center, Self::EXTENT, Self::HEIGHT, aspect)
+    }
+
+    pub fn current_texture(&self) -> Result<wgpu::SurfaceTexture, wgpu::SurfaceError> {
+        self.surface.get_current_texture()
+    }
+
+    // the main pass's attachments, into the given frame
+    pub fn begin_pass<'a>(
+        &'a self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        view: &'a wgpu::TextureView,
+        clear_color: wgpu::Color,
+    ) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("map_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: self.msaa_target.as_ref().unwrap_or(view),
+                resolve_target: self.msaa_target.as_ref().map(|_| view),
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear_color),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture.0,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: false,
+                }),
+                stencil_ops: None,
+            }),
+        })
+    }
+}
@@ -0,0 +1,228 @@
+use cgmath::{Matrix4, Point3};
+
+use crate::camera;
+use crate::graphics;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct MinimapUniform {
+    screen: [f32; 4],
+    rect: [f32; 4],
+}
+
+// a small top-down view of the scene, rendered into a texture every frame and shown in the top
+// right corner of the screen
+pub struct Minimap {
+    view_proj: Matrix4<f32>,
+    texture: wgpu::TextureView,
+    msaa_target: Option<wgpu::TextureView>,
+    depth_texture: (wgpu::TextureView, wgpu::Sampler, wgpu::Texture),
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    // where it's shown, in pixels from the top left
+    rect: [f32; 4],
+}
+
+impl Minimap {
+    // pixels per side of the texture, it's shown at the same size
+    const SIZE: u32 = 256;
+    // the view looks down on the scene from this far above the center
+    const HEIGHT: f32 = 500.0;
+    // between the minimap and the edges of the screen
+    const MARGIN: f32 = 10.0;
+
+    // shows extent world units across, around center
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, center: Point3<f32>, extent: f32) -> Self {
+        let shader = graphics::create_templated_shader(device, format, "minimap.wgsl", include_str!("minimap.wgsl"));
+
+        // the targets are made like the main window's, just smaller
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width: Self::SIZE,
+            height: Self::SIZE,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let texture = graphics::create_texture(
+            device,
+            &wgpu::TextureDescriptor {
+                label: Some("minimap_texture"),
+                size: wgpu::Extent3d {
+                    width: Self::SIZE,
+                    height: Self::SIZE,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            },
+        )
+        .create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("minimap_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let uniform_buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some("minimap_uniform_buffer"),
+            size: std::mem::size_of::<MinimapUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("minimap_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("minimap_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&texture),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("minimap_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("minimap_pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: graphics::SAMPLE_COUNT,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        Minimap {
+            view_proj: camera::top_down_view_proj(center, extent, Self::HEIGHT, 1.0),
+            texture,
+            msaa_target: graphics::create_msaa_target(device, &config, "minimap_msaa_target"),
+            depth_texture: graphics::create_depth_texture(device, &config, "minimap_depth_texture"),
+            pipeline,
+            uniform_buffer,
+            bind_group,
+            rect: [0.0; 4],
+        }
+    }
+
+    pub fn view_proj(&self) -> Matrix4<f32> {
+        self.view_proj
+    }
+
+    // keeps it in the top right corner of a screen this size
+    pub fn place(&mut self, queue: &wgpu::Queue, width: u32, height: u32) {
+        let size = Self::SIZE as f32;
+        self.rect = [width as f32 - size - Self::MARGIN, Self::MARGIN, size, size];
+        let uniform = MinimapUniform {
+            screen: [width as f32, height as f32, 0.0, 0.0],
+            rect: self.rect,
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    // where a point shows up on the screen inside the minimap, None when it's off the map
+    pub fn to_screen(&self, point: Point3<f32>) -> Option<(f32, f32)> {
+        let ndc = self.view_proj * point.to_homogeneous();
+        if ndc.x.abs() > 1.0 || ndc.y.abs() > 1.0 {
+            return None;
+        }
+        let [x, y, width, height] = self.rect;
+        Some((x + (ndc.x + 1.0) / 2.0 * width, y + (1.0 - ndc.y) / 2.0 * height))
+    }
+
+    // the pass the minimap's view of the scene is drawn in, with the same attachments as the
+    // main pass
+    pub fn begin_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder, clear_color: wgpu::Color) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("minimap_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: self.msaa_target.as_ref().unwrap_or(&self.texture),
+                resolve_target: self.msaa_target.as_ref().map(|_| &self.texture),
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear_color),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture.0,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: false,
+                }),
+                stencil_ops: None,
+            }),
+        })
+    }
+
+    // puts the texture on the screen, in the overlay pass
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..6, 0..1);
+    }
+}
@@ -0,0 +1,50 @@
+struct MinimapUniform {
+    // xy is the screen size in pixels
+    screen: vec4<f32>,
+    // top left corner and size in pixels
+    rect: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> minimap: MinimapUniform;
+
+@group(0) @binding(1)
+var map_tex: texture_2d<f32>;
+
+@group(0) @binding(2)
+var map_sampler: sampler;
+
+// the corners are 0..1 across the rect, the border reaches past them on every side
+let BORDER: f32 = 2.0;
+let BORDER_COLOR: vec4<f32> = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 0.0), vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 1.0), vec2<f32>(0.0, 1.0),
+    );
+    let corner = corners[index];
+    let pixel = minimap.rect.xy - BORDER + corner * (minimap.rect.zw + 2.0 * BORDER);
+
+    var out: VertexOutput;
+    // pixels run down from the top left, ndc runs up from the middle
+    out.clip_position = vec4<f32>(pixel.x / minimap.screen.x * 2.0 - 1.0, 1.0 - pixel.y / minimap.screen.y * 2.0, 0.0, 1.0);
+    out.uv = (pixel - minimap.rect.xy) / minimap.rect.zw;
+    return out;
+}
+
+// the texture is already in the screen's format, so it's copied over as it is
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(map_tex, map_sampler, in.uv);
+    if any(in.uv < vec2<f32>(0.0)) || any(in.uv > vec2<f32>(1.0)) {
+        return BORDER_COLOR;
+    }
+    return vec4<f32>(color.rgb, 1.0);
+}
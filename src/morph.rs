@@ -0,0 +1,212 @@
+use cgmath::{InnerSpace, Vector3};
+use std::collections::HashMap;
+
+use crate::graphics::{self, Vertex};
+use crate::primitives::{self, Mesh};
+
+// where each vertex goes with one target fully applied, as offsets from the base mesh
+pub struct MorphTarget {
+    pub name: &'static str,
+    pub position_deltas: Vec<[f32; 3]>,
+    pub normal_deltas: Vec<[f32; 3]>,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct MorphUniform {
+    weights: [[f32; 4]; 2],
+    // x is the vertex count, y the target count
+    counts: [u32; 4],
+}
+
+// a mesh that blends between shapes in the vertex shader. the deltas of every target sit in one
+// storage buffer, and only the weights change from frame to frame
+pub struct MorphMesh {
+    vertices: wgpu::Buffer,
+    indices: wgpu::Buffer,
+    num_indices: u32,
+    uniform: MorphUniform,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pub target_names: Vec<&'static str>,
+    pub transforms_buf: wgpu::Buffer,
+}
+
+impl MorphMesh {
+    // matches the weights array in shader.wgsl
+    pub const MAX_TARGETS: usize = 8;
+
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        mesh: &Mesh,
+        targets: &[MorphTarget],
+        model: cgmath::Matrix4<f32>,
+    ) -> Self {
+        assert!(targets.len() <= Self::MAX_TARGETS, "Too many morph targets");
+        // two vec4s per vertex per target, the position delta then the normal delta
+        let deltas: Vec<[f32; 4]> = targets
+            .iter()
+            .flat_map(|target| {
+                target.position_deltas.iter().zip(&target.normal_deltas).flat_map(|(&[px, py, pz], &[nx, ny, nz])| {
+                    [[px, py, pz, 0.0], [nx, ny, nz, 0.0]]
+                })
+            })
+            .collect();
+        let deltas_buffer = graphics::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+            label: Some("morph_deltas"),
+            // bindings can't be empty
+            contents: bytemuck::cast_slice(if deltas.is_empty() { &[[0.0f32; 4]] } else { &deltas[..] }),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let uniform = MorphUniform {
+            weights: [[0.0; 4]; 2],
+            counts: [mesh.vertices.len() as u32, targets.len() as u32, 0, 0],
+        };
+        let uniform_buffer = graphics::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+            label: Some("morph_uniform_buffer"),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("morph_bind_group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: deltas_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        MorphMesh {
+            vertices: graphics::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+                label: Some("vertices_morph"),
+                contents: bytemuck::cast_slice(&mesh.vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+            indices: graphics::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+                label: Some("indices_morph"),
+                contents: bytemuck::cast_slice(&mesh.indices),
+                usage: wgpu::BufferUsages::INDEX,
+            }),
+            num_indices: mesh.indices.len() as u32,
+            uniform,
+            uniform_buffer,
+            bind_group,
+            target_names: targets.iter().map(|target| target.name).collect(),
+            transforms_buf: graphics::create_transforms_buffer(device, "transforms_morph", model),
+        }
+    }
+
+    // one weight per target in the order they were given, 0 is the base shape and 1 the target's
+    pub fn set_weights(&mut self, queue: &wgpu::Queue, weights: &[f32]) {
+        for (i, &weight) in weights.iter().take(self.target_names.len()).enumerate() {
+            self.uniform.weights[i / 4][i % 4] = weight;
+        }
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+    }
+
+    // expects the morph pipeline and the mesh's bind group to be set already
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertices.slice(..));
+        render_pass.set_index_buffer(self.indices.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+    }
+}
+
+impl MorphTarget {
+    // moves every vertex of mesh with displace, which gets the position and normal and returns
+    // the new position. the new normals are recomputed from the displaced triangles
+    pub fn from_fn(
+        name: &'static str,
+        mesh: &Mesh,
+        displace: impl Fn(Vector3<f32>, Vector3<f32>) -> Vector3<f32>,
+    ) -> Self {
+        let positions: Vec<Vector3<f32>> = mesh
+            .vertices
+            .iter()
+            .map(|v| displace(v.position.into(), v.normal.into()))
+            .collect();
+        let normals = smooth_normals(&mesh.vertices, &positions, &mesh.indices);
+        MorphTarget {
+            name,
+            position_deltas: positions.iter().zip(&mesh.vertices).map(|(p, v)| (p - Vector3::from(v.position)).into()).collect(),
+            normal_deltas: normals.iter().zip(&mesh.vertices).map(|(n, v)| (n - Vector3::from(v.normal)).into()).collect(),
+        }
+    }
+}
+
+// area weighted vertex normals. vertices the base mesh has in the same place, like along a
+// uv seam, share one normal so the seam doesn't show in the lighting
+fn smooth_normals(base: &[Vertex], positions: &[Vector3<f32>], indices: &[u32]) -> Vec<Vector3<f32>> {
+    let key = |v: &Vertex| v.position.map(f32::to_bits);
+    let mut sums: HashMap<[u32; 3], Vector3<f32>> = HashMap::new();
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+        let face = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+        for i in [a, b, c] {
+            *sums.entry(key(&base[i])).or_insert(Vector3::new(0.0, 0.0, 0.0)) += face;
+        }
+    }
+    base.iter()
+        .map(|v| {
+            let (sum, normal) = (sums[&key(v)], Vector3::from(v.normal));
+            if sum.magnitude2() == 0.0 {
+                return normal;
+            }
+            // kept on the same side as the base normal, whichever way the triangles wind
+            if sum.dot(normal) < 0.0 { -sum.normalize() } else { sum.normalize() }
+        })
+        .collect()
+}
+
+// a ball that squashes, grows a snout and sprouts bumps
+pub fn blob(radius: f32) -> (Mesh, Vec<MorphTarget>) {
+    let mesh = primitives::uv_sphere(radius, 48, 24);
+    let targets = vec![
+        MorphTarget::from_fn("squash", &mesh, |p, _| Vector3::new(p.x * 1.3, p.y * 0.6, p.z * 1.3)),
+        MorphTarget::from_fn("snout", &mesh, |p, n| p + n * radius * 0.6 * n.z.max(0.0).powi(6)),
+        MorphTarget::from_fn("bumps", &mesh, |p, n| {
+            let k = 6.0 / radius;
+            let bumps = (p.x * k).sin() * (p.y * k).sin() * (p.z * k).sin();
+            p + n * radius * 0.2 * bumps.abs()
+        }),
+    ];
+    (mesh, targets)
+}
+
+// the second bind group of the morph pipeline, after the one every object has
+pub fn build_morph_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("morph_bind_group_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    })
+}
@@ -0,0 +1,82 @@
+// 2d perlin noise, shuffled by a seed so different seeds give unrelated terrain
+pub struct Perlin {
+    perm: [u8; 512],
+}
+
+impl Perlin {
+    pub fn new(seed: u32) -> Self {
+        let mut table = [0u8; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            *entry = i as u8;
+        }
+
+        // fisher-yates with xorshift, zero would get xorshift stuck so it's nudged away
+        let mut state = seed.wrapping_mul(0x9e37_79b9) | 1;
+        for i in (1..table.len()).rev() {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            table.swap(i, state as usize % (i + 1));
+        }
+
+        let mut perm = [0u8; 512];
+        for (i, entry) in perm.iter_mut().enumerate() {
+            *entry = table[i % 256];
+        }
+        Perlin { perm }
+    }
+
+    // roughly -1 to 1, zero at every integer coordinate
+    pub fn get(&self, x: f32, y: f32) -> f32 {
+        let (xf, yf) = (x.floor(), y.floor());
+        let (xi, yi) = ((xf as i32 & 255) as usize, (yf as i32 & 255) as usize);
+        let (x, y) = (x - xf, y - yf);
+
+        let hash = |i: usize, j: usize| self.perm[self.perm[i] as usize + j];
+        let n00 = gradient(hash(xi, yi), x, y);
+        let n10 = gradient(hash(xi + 1, yi), x - 1.0, y);
+        let n01 = gradient(hash(xi, yi + 1), x, y - 1.0);
+        let n11 = gradient(hash(xi + 1, yi + 1), x - 1.0, y - 1.0);
+
+        let (u, v) = (fade(x), fade(y));
+        let nx0 = n00 + (n10 - n00) * u;
+        let nx1 = n01 + (n11 - n01) * u;
+        nx0 + (nx1 - nx0) * v
+    }
+
+    // octaves of noise, each at lacunarity times the frequency and persistence times the
+    // amplitude of the last, rescaled back to roughly -1 to 1
+    pub fn fractal(&self, x: f32, y: f32, octaves: u32, persistence: f32, lacunarity: f32) -> f32 {
+        let mut sum = 0.0;
+        let mut max = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        for octave in 0..octaves {
+            // offset each octave so their zeros at integer coordinates don't line up
+            let offset = octave as f32 * 17.31;
+            sum += self.get(x * frequency + offset, y * frequency + offset) * amplitude;
+            max += amplitude;
+            amplitude *= persistence;
+            frequency *= lacunarity;
+        }
+        if max > 0.0 { sum / max } else { 0.0 }
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+// dot product with one of eight gradient directions picked by the hash
+fn gradient(hash: u8, x: f32, y: f32) -> f32 {
+    match hash & 7 {
+        0 => x + y,
+        1 => x - y,
+        2 => -x + y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
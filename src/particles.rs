@@ -0,0 +1,350 @@
+use cgmath::{InnerSpace, Matrix4, Point3, SquareMatrix, Vector3};
+use std::f32::consts::TAU;
+
+use crate::graphics;
+
+// where particles come from and how they behave while they're alive
+#[derive(Debug, Clone)]
+pub struct Emitter {
+    pub position: Point3<f32>,
+    // particles per second
+    pub rate: f32,
+    pub speed: f32,
+    // half the angle of the cone around +y that particles leave in, in degrees
+    pub spread: f32,
+    // seconds, each particle gets between half of this and all of it
+    pub lifetime: f32,
+    pub start_size: f32,
+    pub end_size: f32,
+    // linear colors with the alpha scaling the brightness, blended over each particle's life
+    pub start_color: [f32; 4],
+    pub end_color: [f32; 4],
+    pub gravity: f32,
+    // the part of a particle left over from earlier frames, so low rates still spawn evenly
+    pending: f32,
+}
+
+impl Emitter {
+    // a burst of sparks thrown upwards that glow orange and fade out as they fall back down
+    pub fn fountain(position: Point3<f32>) -> Self {
+        Emitter {
+            position,
+            rate: 400.0,
+            speed: 25.0,
+            spread: 20.0,
+            lifetime: 3.0,
+            start_size: 0.6,
+            end_size: 1.5,
+            start_color: [1.0, 0.8, 0.3, 1.0],
+            end_color: [0.8, 0.1, 0.0, 0.0],
+            gravity: 15.0,
+            pending: 0.0,
+        }
+    }
+}
+
+struct Particle {
+    position: Point3<f32>,
+    velocity: Vector3<f32>,
+    age: f32,
+    lifetime: f32,
+    // which emitter it came from, for its size, colors and gravity
+    emitter: usize,
+}
+
+// the per instance data of a particle quad
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ParticleRaw {
+    position: [f32; 3],
+    size: f32,
+    color: [f32; 4],
+}
+
+impl ParticleRaw {
+    const ATTRIBS: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<ParticleRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ParticleUniform {
+    view_proj: [[f32; 4]; 4],
+    inv_view_proj: [[f32; 4]; 4],
+    camera_pos: [f32; 4],
+    // the quads are turned to face the camera with this
+    view: [[f32; 4]; 4],
+}
+
+// particles simulated on the cpu and drawn as camera facing quads that add up their light.
+// they're drawn in their own pass after the scene, since they read the scene's depth to fade
+// out where they cut into geometry instead of ending in a hard line
+pub struct ParticleSystem {
+    pub emitters: Vec<Emitter>,
+    particles: Vec<Particle>,
+    rng: u32,
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    // recreated along with the depth texture
+    bind_group: wgpu::BindGroup,
+    uniform_buffer: wgpu::Buffer,
+    instance_buffer: wgpu::Buffer,
+    // particles the buffer has room for, it's recreated larger when a frame needs more
+    capacity: usize,
+    instance_count: u32,
+}
+
+impl ParticleSystem {
+    // new particles aren't spawned past this, so a runaway emitter can't take all the memory
+    const MAX_PARTICLES: usize = 50_000;
+    const INITIAL_CAPACITY: usize = 1024;
+
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, depth_view: &wgpu::TextureView) -> Self {
+        let texture_type = if graphics::SAMPLE_COUNT > 1 {
+            "texture_depth_multisampled_2d"
+        } else {
+            "texture_depth_2d"
+        };
+        let source = include_str!("particles.wgsl")
+            .replace("DEPTH_TEXTURE_TYPE", texture_type)
+            .replace("SAMPLE_COUNT", &graphics::SAMPLE_COUNT.to_string());
+        let shader = graphics::create_templated_shader(device, format, "particles.wgsl", &source);
+
+        let uniform_buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some("particles_uniform_buffer"),
+            size: std::mem::size_of::<ParticleUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particles_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: graphics::SAMPLE_COUNT > 1,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("particles_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("particles_pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[ParticleRaw::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState { color: additive, alpha: additive }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // the depth texture is read by the shader instead, it can't be attached at the same time
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: graphics::SAMPLE_COUNT,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let bind_group = create_bind_group(device, &bind_group_layout, &uniform_buffer, depth_view);
+        ParticleSystem {
+            emitters: Vec::new(),
+            particles: Vec::new(),
+            rng: 0x2545_f491,
+            pipeline,
+            bind_group_layout,
+            bind_group,
+            uniform_buffer,
+            instance_buffer: create_instance_buffer(device, Self::INITIAL_CAPACITY),
+            capacity: Self::INITIAL_CAPACITY,
+            instance_count: 0,
+        }
+    }
+
+    // has to be called whenever the depth texture is recreated, like on resize
+    pub fn set_depth_view(&mut self, device: &wgpu::Device, depth_view: &wgpu::TextureView) {
+        self.bind_group = create_bind_group(device, &self.bind_group_layout, &self.uniform_buffer, depth_view);
+    }
+
+    // drops every emitter and every particle still alive
+    pub fn clear(&mut self) {
+        self.emitters.clear();
+        self.particles.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    // ages and moves every particle, removes the dead ones and spawns new ones from the emitters
+    pub fn update(&mut self, dt: f32) {
+        let emitters = &self.emitters;
+        self.particles.retain_mut(|particle| {
+            particle.age += dt;
+            particle.velocity.y -= emitters[particle.emitter].gravity * dt;
+            particle.position += particle.velocity * dt;
+            particle.age < particle.lifetime
+        });
+
+        for index in 0..self.emitters.len() {
+            let emitter = &mut self.emitters[index];
+            emitter.pending += emitter.rate * dt;
+            let count = emitter.pending as usize;
+            emitter.pending -= count as f32;
+
+            let room = Self::MAX_PARTICLES.saturating_sub(self.particles.len());
+            for _ in 0..count.min(room) {
+                let particle = self.spawn(index);
+                self.particles.push(particle);
+            }
+        }
+    }
+
+    fn spawn(&mut self, emitter: usize) -> Particle {
+        let (r1, r2, r3) = (self.random(), self.random(), self.random());
+        let e = &self.emitters[emitter];
+        // evenly spread over the cap of the sphere inside the cone
+        let cos_theta = 1.0 - r1 * (1.0 - e.spread.to_radians().cos());
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let (sin_phi, cos_phi) = (r2 * TAU).sin_cos();
+        let direction = Vector3::new(sin_theta * cos_phi, cos_theta, sin_theta * sin_phi);
+        Particle {
+            position: e.position,
+            velocity: direction.normalize() * e.speed,
+            age: 0.0,
+            lifetime: e.lifetime * (0.5 + 0.5 * r3),
+            emitter,
+        }
+    }
+
+    // 0 to 1, xorshift is plenty for where sparks fly
+    fn random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 8) as f32 / (1 << 24) as f32
+    }
+
+    pub fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view_proj: Matrix4<f32>,
+        camera_pos: Point3<f32>,
+        view: Matrix4<f32>,
+    ) {
+        let uniform = ParticleUniform {
+            view_proj: view_proj.into(),
+            inv_view_proj: view_proj.invert().unwrap_or_else(Matrix4::identity).into(),
+            camera_pos: [camera_pos.x, camera_pos.y, camera_pos.z, 1.0],
+            view: view.into(),
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+        let instances = self
+            .particles
+            .iter()
+            .map(|particle| {
+                let e = &self.emitters[particle.emitter];
+                let t = particle.age / particle.lifetime;
+                ParticleRaw {
+                    position: particle.position.into(),
+                    size: e.start_size + (e.end_size - e.start_size) * t,
+                    color: std::array::from_fn(|i| e.start_color[i] + (e.end_color[i] - e.start_color[i]) * t),
+                }
+            })
+            .collect::<Vec<_>>();
+        if instances.len() > self.capacity {
+            self.capacity = instances.len().next_power_of_two();
+            self.instance_buffer = create_instance_buffer(device, self.capacity);
+        }
+        if !instances.is_empty() {
+            queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+        }
+        self.instance_count = instances.len() as u32;
+    }
+
+    // in a pass without a depth attachment, after everything else is drawn
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.instance_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        render_pass.draw(0..6, 0..self.instance_count);
+    }
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    depth_view: &wgpu::TextureView,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("particles_bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(depth_view),
+            },
+        ],
+    })
+}
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    graphics::create_buffer(device, &wgpu::BufferDescriptor {
+        label: Some("particles_instances"),
+        size: (std::mem::size_of::<ParticleRaw>() * capacity) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
@@ -0,0 +1,80 @@
+// DEPTH_TEXTURE_TYPE and SAMPLE_COUNT are substituted before compiling (see particles.rs)
+
+// world units over which a particle fades out as it gets close to the geometry behind it
+let SOFTNESS: f32 = 3.0;
+
+struct ParticleUniform {
+    view_proj: mat4x4<f32>,
+    inv_view_proj: mat4x4<f32>,
+    camera_pos: vec4<f32>,
+    view: mat4x4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> particles: ParticleUniform;
+
+@group(0) @binding(1)
+var depth_tex: DEPTH_TEXTURE_TYPE;
+
+struct InstanceInput {
+    // xyz is the center, w half the width of the quad
+    @location(0) position_size: vec4<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // -1 to 1 across the quad
+    @location(0) corner: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) world_pos: vec3<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, instance: InstanceInput) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, -1.0), vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, 1.0), vec2<f32>(-1.0, 1.0),
+    );
+    let corner = corners[index];
+    // the rows of the view rotation are the camera's right and up in world space
+    let right = vec3<f32>(particles.view[0].x, particles.view[1].x, particles.view[2].x);
+    let up = vec3<f32>(particles.view[0].y, particles.view[1].y, particles.view[2].y);
+    let offset = right * corner.x + up * corner.y;
+    let world_pos = instance.position_size.xyz + offset * instance.position_size.w;
+
+    var out: VertexOutput;
+    out.clip_position = particles.view_proj * vec4<f32>(world_pos, 1.0);
+    out.corner = corner;
+    out.color = instance.color;
+    out.world_pos = world_pos;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // the nearest sample, so particles stay hidden right up to the edges of what's in front
+    let coords = vec2<i32>(in.clip_position.xy);
+    var scene_depth = 1.0;
+    for (var i = 0; i < SAMPLE_COUNT; i++) {
+        scene_depth = min(scene_depth, textureLoad(depth_tex, coords, i));
+    }
+    if in.clip_position.z >= scene_depth {
+        discard;
+    }
+
+    // the scene point behind this pixel in world space, so the fade is in world units
+    let size = vec2<f32>(textureDimensions(depth_tex));
+    let ndc = vec2<f32>(in.clip_position.x / size.x * 2.0 - 1.0, 1.0 - in.clip_position.y / size.y * 2.0);
+    let scene_h = particles.inv_view_proj * vec4<f32>(ndc, scene_depth, 1.0);
+    let scene_pos = scene_h.xyz / scene_h.w;
+    let camera = particles.camera_pos.xyz;
+    let soft = clamp((distance(scene_pos, camera) - distance(in.world_pos, camera)) / SOFTNESS, 0.0, 1.0);
+
+    // a round blob, brightest in the middle
+    let falloff = clamp(1.0 - dot(in.corner, in.corner), 0.0, 1.0);
+    var color = in.color.rgb * in.color.a * falloff * falloff * soft;
+    color = apply_output_gamma(color);
+    // added on top of the scene, the alpha is left as it is
+    return vec4<f32>(color, 0.0);
+}
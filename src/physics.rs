@@ -0,0 +1,95 @@
+use cgmath::{Matrix4, Point3, Quaternion, Vector3};
+use rapier3d::prelude::*;
+
+// rigid bodies for some of the scene's objects, stepped at a fixed rate however long frames take.
+// after each update the bodies' positions are read back as the objects' model matrices
+pub struct Physics {
+    gravity: rapier3d::math::Vector<Real>,
+    parameters: IntegrationParameters,
+    pipeline: PhysicsPipeline,
+    islands: IslandManager,
+    broad_phase: BroadPhase,
+    narrow_phase: NarrowPhase,
+    bodies: RigidBodySet,
+    colliders: ColliderSet,
+    impulse_joints: ImpulseJointSet,
+    multibody_joints: MultibodyJointSet,
+    ccd: CCDSolver,
+    // frame time that hasn't been stepped through yet
+    accumulator: f32,
+}
+
+impl Physics {
+    const STEP: f32 = 1.0 / 60.0;
+    // a long stall is dropped rather than caught up on, which would stall the next frame too
+    const MAX_STEPS: u32 = 5;
+    const GRAVITY: f32 = 9.81;
+
+    pub fn new() -> Self {
+        Physics {
+            gravity: vector![0.0, -Self::GRAVITY, 0.0],
+            parameters: IntegrationParameters { dt: Self::STEP, ..Default::default() },
+            pipeline: PhysicsPipeline::new(),
+            islands: IslandManager::new(),
+            broad_phase: BroadPhase::new(),
+            narrow_phase: NarrowPhase::new(),
+            bodies: RigidBodySet::new(),
+            colliders: ColliderSet::new(),
+            impulse_joints: ImpulseJointSet::new(),
+            multibody_joints: MultibodyJointSet::new(),
+            ccd: CCDSolver::new(),
+            accumulator: 0.0,
+        }
+    }
+
+    // a box that never moves, like the floor
+    pub fn add_ground(&mut self, min: Point3<f32>, max: Point3<f32>) {
+        let half = (max - min) / 2.0;
+        let center = min + half;
+        let collider = ColliderBuilder::cuboid(half.x, half.y, half.z)
+            .translation(vector![center.x, center.y, center.z])
+            .build();
+        self.colliders.insert(collider);
+    }
+
+    // a box that falls and tumbles, starting at position turned by rotation
+    pub fn add_box(&mut self, position: Vector3<f32>, rotation: Quaternion<f32>, half_extents: Vector3<f32>) -> RigidBodyHandle {
+        let body = RigidBodyBuilder::dynamic()
+            .position(Isometry::from_parts(
+                Translation::new(position.x, position.y, position.z),
+                Rotation::new_normalize(rapier3d::na::Quaternion::new(rotation.s, rotation.v.x, rotation.v.y, rotation.v.z)),
+            ))
+            .build();
+        let handle = self.bodies.insert(body);
+        let collider = ColliderBuilder::cuboid(half_extents.x, half_extents.y, half_extents.z).build();
+        self.colliders.insert_with_parent(collider, handle, &mut self.bodies);
+        handle
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.accumulator = (self.accumulator + dt).min(Self::STEP * Self::MAX_STEPS as f32);
+        while self.accumulator >= Self::STEP {
+            self.pipeline.step(
+                &self.gravity,
+                &self.parameters,
+                &mut self.islands,
+                &mut self.broad_phase,
+                &mut self.narrow_phase,
+                &mut self.bodies,
+                &mut self.colliders,
+                &mut self.impulse_joints,
+                &mut self.multibody_joints,
+                &mut self.ccd,
+                None,
+                &(),
+                &(),
+            );
+            self.accumulator -= Self::STEP;
+        }
+    }
+
+    pub fn model(&self, handle: RigidBodyHandle) -> Matrix4<f32> {
+        let columns: [[f32; 4]; 4] = self.bodies[handle].position().to_homogeneous().into();
+        Matrix4::from(columns)
+    }
+}
@@ -0,0 +1,202 @@
+use cgmath::{InnerSpace, Matrix4, Point3, SquareMatrix, Vector3, Vector4};
+
+use crate::graphics::{self, SAMPLE_COUNT};
+
+// reads single depth values back from the (possibly multisampled) depth buffer.
+// multisampled depth textures can't be copied to a buffer directly, so a tiny compute
+// pass resolves the requested pixel into a storage buffer first
+pub struct DepthReadback {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pixel_buffer: wgpu::Buffer,
+    result_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+}
+
+impl DepthReadback {
+    const RESULT_SIZE: wgpu::BufferAddress = std::mem::size_of::<f32>() as wgpu::BufferAddress;
+
+    pub fn new(device: &wgpu::Device) -> Self {
+        let texture_type = if SAMPLE_COUNT > 1 {
+            "texture_depth_multisampled_2d"
+        } else {
+            "texture_depth_2d"
+        };
+        let source = include_str!("depth_resolve.wgsl")
+            .replace("DEPTH_TEXTURE_TYPE", texture_type)
+            .replace("SAMPLE_COUNT", &SAMPLE_COUNT.to_string());
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader at depth_resolve.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry { // depth texture
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: SAMPLE_COUNT > 1,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Depth,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // pixel coordinates
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry { // resolved depth
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("depth_resolve_bind_group_layout"),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("depth_resolve_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("depth_resolve_pipeline"),
+            layout: Some(&layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        let pixel_buffer = graphics::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+            label: Some("depth_resolve_pixel"),
+            contents: bytemuck::cast_slice(&[0u32; 4]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let result_buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some("depth_resolve_result"),
+            size: Self::RESULT_SIZE,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let readback_buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some("depth_resolve_readback"),
+            size: Self::RESULT_SIZE,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        DepthReadback {
+            pipeline,
+            bind_group_layout,
+            pixel_buffer,
+            result_buffer,
+            readback_buffer,
+        }
+    }
+
+    // blocks until the gpu has finished, only meant for occasional queries (clicks, autofocus)
+    pub fn read_depth(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        depth_view: &wgpu::TextureView,
+        pixel: (u32, u32),
+    ) -> f32 {
+        queue.write_buffer(&self.pixel_buffer, 0, bytemuck::cast_slice(&[pixel.0, pixel.1, 0, 0]));
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(depth_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.pixel_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.result_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("depth_resolve_bind_group"),
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("depth_resolve_encoder"),
+        });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("depth_resolve_pass"),
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(1, 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&self.result_buffer, 0, &self.readback_buffer, 0, Self::RESULT_SIZE);
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = self.readback_buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| ());
+        device.poll(wgpu::Maintain::Wait);
+        let depth = bytemuck::cast_slice::<u8, f32>(&slice.get_mapped_range())[0];
+        self.readback_buffer.unmap();
+
+        depth
+    }
+}
+
+// turns a pixel and its depth buffer value back into a world space position
+pub fn unproject(
+    view_proj: Matrix4<f32>,
+    pixel: (u32, u32),
+    depth: f32,
+    size: (u32, u32),
+) -> Option<Point3<f32>> {
+    let inv = view_proj.invert()?;
+    let ndc = Vector4::new(
+        (pixel.0 as f32 + 0.5) / size.0 as f32 * 2.0 - 1.0,
+        1.0 - (pixel.1 as f32 + 0.5) / size.1 as f32 * 2.0,
+        depth,
+        1.0,
+    );
+    let world = inv * ndc;
+    Some(Point3::new(world.x / world.w, world.y / world.w, world.z / world.w))
+}
+
+// where the ray through a pixel starts on the near plane, and which way it goes
+pub fn ray(view_proj: Matrix4<f32>, pixel: (u32, u32), size: (u32, u32)) -> Option<(Point3<f32>, Vector3<f32>)> {
+    let near = unproject(view_proj, pixel, 0.0, size)?;
+    let far = unproject(view_proj, pixel, 1.0, size)?;
+    Some((near, (far - near).normalize()))
+}
+
+// where the ray meets the plane through point, None when it runs along it or points away
+pub fn intersect_plane(
+    (origin, direction): (Point3<f32>, Vector3<f32>),
+    point: Point3<f32>,
+    normal: Vector3<f32>,
+) -> Option<Point3<f32>> {
+    let along = direction.dot(normal);
+    if along.abs() < f32::EPSILON {
+        return None;
+    }
+    let t = (point - origin).dot(normal) / along;
+    (t >= 0.0).then(|| origin + direction * t)
+}
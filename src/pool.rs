@@ -0,0 +1,149 @@
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use crate::graphics;
+
+// hands out pieces of a few big buffers instead of a buffer each, so a scene with hundreds of
+// small meshes doesn't need thousands of buffers. a piece goes back to its arena when dropped
+pub struct BufferPool {
+    label: &'static str,
+    usage: wgpu::BufferUsages,
+    alignment: wgpu::BufferAddress,
+    arenas: Vec<Arc<Arena>>,
+}
+
+struct Arena {
+    buffer: wgpu::Buffer,
+    // the unused ranges, sorted and never touching each other
+    free: Mutex<Vec<Range<wgpu::BufferAddress>>>,
+}
+
+// a piece of a pool's buffer, bound and drawn from at its offset
+pub struct PoolSlice {
+    arena: Arc<Arena>,
+    range: Range<wgpu::BufferAddress>,
+    size: wgpu::BufferAddress,
+}
+
+impl BufferPool {
+    // a piece bigger than this gets an arena to itself
+    const ARENA_SIZE: wgpu::BufferAddress = 4 * 1024 * 1024;
+
+    // alignment is what offsets into the buffer have to be a multiple of for its usage
+    pub fn new(label: &'static str, usage: wgpu::BufferUsages, alignment: wgpu::BufferAddress) -> Self {
+        BufferPool {
+            label,
+            usage: usage | wgpu::BufferUsages::COPY_DST,
+            alignment: alignment.max(wgpu::COPY_BUFFER_ALIGNMENT),
+            arenas: Vec::new(),
+        }
+    }
+
+    // the data's size has to be a multiple of 4, like for any buffer write
+    pub fn alloc<T: bytemuck::Pod>(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[T]) -> PoolSlice {
+        let bytes: &[u8] = bytemuck::cast_slice(data);
+        let size = bytes.len() as wgpu::BufferAddress;
+        // empty pieces still take up some room, a binding can't have a size of 0
+        let reserved = size.max(1).div_ceil(self.alignment) * self.alignment;
+        let (arena, start) = self
+            .arenas
+            .iter()
+            .find_map(|arena| arena.take(reserved).map(|start| (arena.clone(), start)))
+            .unwrap_or_else(|| {
+                let arena = self.add_arena(device, reserved);
+                let start = arena.take(reserved).expect("Failed to allocate from a new arena");
+                (arena, start)
+            });
+        queue.write_buffer(&arena.buffer, start, bytes);
+        PoolSlice { arena, range: start..start + reserved, size }
+    }
+
+    fn add_arena(&mut self, device: &wgpu::Device, min_size: wgpu::BufferAddress) -> Arc<Arena> {
+        let size = min_size.max(Self::ARENA_SIZE);
+        let buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some(&format!("{}_{}", self.label, self.arenas.len())),
+            size,
+            usage: self.usage,
+            mapped_at_creation: false,
+        });
+        let arena = Arc::new(Arena { buffer, free: Mutex::new(std::iter::once(0..size).collect()) });
+        self.arenas.push(arena.clone());
+        arena
+    }
+}
+
+impl Arena {
+    // the first free range that fits, sizes are all multiples of the alignment so the start is
+    // already aligned
+    fn take(&self, size: wgpu::BufferAddress) -> Option<wgpu::BufferAddress> {
+        let mut free = self.free.lock().expect("Failed to lock the pool");
+        let i = free.iter().position(|range| range.end - range.start >= size)?;
+        let start = free[i].start;
+        free[i].start += size;
+        if free[i].is_empty() {
+            free.remove(i);
+        }
+        Some(start)
+    }
+
+    fn give_back(&self, range: Range<wgpu::BufferAddress>) {
+        let mut free = self.free.lock().expect("Failed to lock the pool");
+        let i = free.partition_point(|free| free.start < range.start);
+        free.insert(i, range);
+        // merge with the ranges either side
+        if i + 1 < free.len() && free[i].end == free[i + 1].start {
+            free[i].end = free.remove(i + 1).end;
+        }
+        if i > 0 && free[i - 1].end == free[i].start {
+            free[i - 1].end = free.remove(i).end;
+        }
+    }
+}
+
+impl PoolSlice {
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.arena.buffer
+    }
+
+    pub fn offset(&self) -> wgpu::BufferAddress {
+        self.range.start
+    }
+
+    // for set_vertex_buffer and set_index_buffer
+    pub fn slice(&self) -> wgpu::BufferSlice<'_> {
+        self.arena.buffer.slice(self.range.start..self.range.start + self.size)
+    }
+
+    // for a buffer binding in a bind group
+    pub fn binding(&self) -> wgpu::BufferBinding<'_> {
+        wgpu::BufferBinding {
+            buffer: &self.arena.buffer,
+            offset: self.range.start,
+            size: wgpu::BufferSize::new(self.size),
+        }
+    }
+}
+
+impl Drop for PoolSlice {
+    fn drop(&mut self) {
+        self.arena.give_back(self.range.clone());
+    }
+}
+
+// where the objects drawn with the main pipeline keep their meshes and transforms
+pub struct MeshPools {
+    pub vertices: BufferPool,
+    pub indices: BufferPool,
+    pub transforms: BufferPool,
+}
+
+impl MeshPools {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let storage_alignment = device.limits().min_storage_buffer_offset_alignment as wgpu::BufferAddress;
+        MeshPools {
+            vertices: BufferPool::new("vertex_pool", wgpu::BufferUsages::VERTEX, wgpu::VERTEX_STRIDE_ALIGNMENT),
+            indices: BufferPool::new("index_pool", wgpu::BufferUsages::INDEX, 4),
+            transforms: BufferPool::new("transforms_pool", wgpu::BufferUsages::STORAGE, storage_alignment),
+        }
+    }
+}
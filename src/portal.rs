@@ -0,0 +1,276 @@
+use cgmath::{Deg, Matrix4, SquareMatrix, Vector3};
+use serde::{Deserialize, Serialize};
+
+use crate::graphics;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct PortalConfig {
+    // the middle of the opening looked into and of the one looked out of, the other side of the
+    // exit is what shows through the entrance
+    pub entrance: [f32; 3],
+    pub exit: [f32; 3],
+    // degrees around y the openings face, 0 is +z
+    pub entrance_yaw: f32,
+    pub exit_yaw: f32,
+    pub width: f32,
+    pub height: f32,
+    // how many times the view through the portal is repeated when the portal shows up in it,
+    // up to Portal::MAX_LEVELS
+    pub levels: u32,
+}
+
+impl Default for PortalConfig {
+    fn default() -> Self {
+        PortalConfig {
+            entrance: [-30.0, -15.0, 75.0],
+            exit: [-100.0, -15.0, 75.0],
+            entrance_yaw: -90.0,
+            exit_yaw: -75.0,
+            width: 20.0,
+            height: 20.0,
+            levels: 3,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct PortalUniform {
+    view_proj: [[f32; 4]; 4],
+    model: [[f32; 4]; 4],
+    color: [f32; 4],
+}
+
+// a quad that shows the scene from the other side of its exit. the quad is drawn into the
+// stencil, the depth behind it is cleared and the scene is drawn again from the moved camera
+// only where the stencil was set. when the entrance shows up in that view the same happens
+// inside it, one stencil value further each time, until the levels run out
+pub struct Portal {
+    // where each opening is and which way it faces
+    entrance: Matrix4<f32>,
+    exit: Matrix4<f32>,
+    // the entrance scaled to the opening's size, for drawing the quad
+    quad: Matrix4<f32>,
+    // the exit's plane, facing away from where the view through the portal would be blocked
+    clip_plane: [f32; 4],
+    levels: u32,
+    // sets the stencil where the quad is visible
+    mark_pipeline: wgpu::RenderPipeline,
+    // fills the opening with the background and pushes its depth to the far plane
+    open_pipeline: wgpu::RenderPipeline,
+    // the innermost opening, left as a flat surface
+    cap_pipeline: wgpu::RenderPipeline,
+    // puts the outermost quad back in the depth buffer, for what's drawn after it
+    close_pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    uniform_stride: u32,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Portal {
+    pub const MAX_LEVELS: u32 = 4;
+
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, config: &PortalConfig) -> Self {
+        let shader = graphics::create_templated_shader(device, format, "portal.wgsl", include_str!("portal.wgsl"));
+
+        let align = device.limits().min_uniform_buffer_offset_alignment;
+        let uniform_stride = (std::mem::size_of::<PortalUniform>() as u32).div_ceil(align) * align;
+        let uniform_buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some("portal_uniform_buffer"),
+            size: (uniform_stride * (Self::MAX_LEVELS + 1)) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("portal_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<PortalUniform>() as u64),
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("portal_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &uniform_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<PortalUniform>() as u64),
+                }),
+            }],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("portal_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let build_pipeline = |label, vertex_entry, write_color, depth_write, depth_compare, stencil_compare, pass_op| {
+            let face = wgpu::StencilFaceState {
+                compare: stencil_compare,
+                fail_op: wgpu::StencilOperation::Keep,
+                depth_fail_op: wgpu::StencilOperation::Keep,
+                pass_op,
+            };
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: vertex_entry,
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: if write_color { wgpu::ColorWrites::ALL } else { wgpu::ColorWrites::empty() },
+                    })],
+                }),
+                // looking at the back of the entrance shows nothing through it
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: graphics::DEPTH_FORMAT,
+                    depth_write_enabled: depth_write,
+                    depth_compare,
+                    stencil: wgpu::StencilState { front: face, back: face, read_mask: !0, write_mask: !0 },
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: graphics::SAMPLE_COUNT,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
+        };
+        use wgpu::{CompareFunction, StencilOperation};
+        let mark_pipeline = build_pipeline(
+            "portal_mark_pipeline",
+            "vs_main",
+            false,
+            false,
+            CompareFunction::Less,
+            CompareFunction::Equal,
+            StencilOperation::IncrementClamp,
+        );
+        let open_pipeline = build_pipeline(
+            "portal_open_pipeline",
+            "vs_far",
+            true,
+            true,
+            CompareFunction::Always,
+            CompareFunction::Equal,
+            StencilOperation::Keep,
+        );
+        let cap_pipeline = build_pipeline(
+            "portal_cap_pipeline",
+            "vs_main",
+            true,
+            true,
+            CompareFunction::Less,
+            CompareFunction::Equal,
+            StencilOperation::Keep,
+        );
+        let close_pipeline = build_pipeline(
+            "portal_close_pipeline",
+            "vs_main",
+            false,
+            true,
+            CompareFunction::Always,
+            CompareFunction::NotEqual,
+            StencilOperation::Keep,
+        );
+
+        let frame = |position: [f32; 3], yaw: f32| {
+            Matrix4::from_translation(position.into()) * Matrix4::from_angle_y(Deg(yaw))
+        };
+        let entrance = frame(config.entrance, config.entrance_yaw);
+        let exit = frame(config.exit, config.exit_yaw);
+        let normal = (exit * Vector3::unit_z().extend(0.0)).truncate();
+        let exit_position = Vector3::from(config.exit);
+        Portal {
+            entrance,
+            exit,
+            quad: entrance * Matrix4::from_nonuniform_scale(config.width / 2.0, config.height / 2.0, 1.0),
+            clip_plane: [-normal.x, -normal.y, -normal.z, cgmath::dot(normal, exit_position)],
+            levels: config.levels.min(Self::MAX_LEVELS),
+            mark_pipeline,
+            open_pipeline,
+            cap_pipeline,
+            close_pipeline,
+            uniform_buffer,
+            uniform_stride,
+            bind_group,
+        }
+    }
+
+    pub fn levels(&self) -> u32 {
+        self.levels
+    }
+
+    // moves the world so it's seen from the exit, put between the view and the model matrices
+    // once for every level deep the view is
+    pub fn through(&self, level: u32) -> Matrix4<f32> {
+        let step = self.entrance * self.exit.invert().unwrap_or_else(Matrix4::identity);
+        (0..level).fold(Matrix4::identity(), |m, _| m * step)
+    }
+
+    // keeps what's between the moved camera and the exit out of the view through the portal
+    pub fn clip_plane(&self) -> [f32; 4] {
+        self.clip_plane
+    }
+
+    // one uniform for every level's quad, color is the linear background behind the view
+    pub fn update(&self, queue: &wgpu::Queue, view_proj: Matrix4<f32>, color: [f32; 4]) {
+        for level in 0..=self.levels {
+            let uniform = PortalUniform {
+                view_proj: (view_proj * self.through(level)).into(),
+                model: self.quad.into(),
+                color,
+            };
+            let offset = (level * self.uniform_stride) as wgpu::BufferAddress;
+            queue.write_buffer(&self.uniform_buffer, offset, bytemuck::cast_slice(&[uniform]));
+        }
+    }
+
+    // sets the stencil to level + 1 where the entrance is visible at a level
+    pub fn mark<'a>(&'a self, rp: &mut wgpu::RenderPass<'a>, level: u32) {
+        self.draw(rp, &self.mark_pipeline, level, level);
+    }
+
+    // readies what mark set for the scene to be drawn into at the next level
+    pub fn open<'a>(&'a self, rp: &mut wgpu::RenderPass<'a>, level: u32) {
+        self.draw(rp, &self.open_pipeline, level, level + 1);
+    }
+
+    // covers the entrance wherever it shows up in the innermost view
+    pub fn cap<'a>(&'a self, rp: &mut wgpu::RenderPass<'a>) {
+        self.draw(rp, &self.cap_pipeline, self.levels, self.levels);
+    }
+
+    // after the last level, so whatever comes next is hidden behind the entrance as usual
+    pub fn close<'a>(&'a self, rp: &mut wgpu::RenderPass<'a>) {
+        self.draw(rp, &self.close_pipeline, 0, 0);
+    }
+
+    fn draw<'a>(&'a self, rp: &mut wgpu::RenderPass<'a>, pipeline: &'a wgpu::RenderPipeline, level: u32, stencil: u32) {
+        rp.set_pipeline(pipeline);
+        rp.set_stencil_reference(stencil);
+        rp.set_bind_group(0, &self.bind_group, &[level * self.uniform_stride]);
+        rp.draw(0..6, 0..1);
+    }
+}
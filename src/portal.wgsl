@@ -0,0 +1,39 @@
+// one of these per level of views nested inside the portal
+struct PortalUniform {
+    view_proj: mat4x4<f32>,
+    // places the -1..1 quad over the opening
+    model: mat4x4<f32>,
+    // linear, what shows before the view through the portal is drawn over it
+    color: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> portal: PortalUniform;
+
+fn corner(index: u32) -> vec4<f32> {
+    // counter clockwise seen from the front, which faces +z before the model turns it
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, -1.0), vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, 1.0), vec2<f32>(-1.0, 1.0),
+    );
+    return portal.view_proj * portal.model * vec4<f32>(corners[index], 0.0, 1.0);
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    return corner(index);
+}
+
+// the same quad pushed back to the far plane, so it clears the depth behind the opening
+@vertex
+fn vs_far(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let position = corner(index);
+    return vec4<f32>(position.xy, position.w, position.w);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    var color = portal.color.rgb;
+    color = apply_output_gamma(color);
+    return vec4<f32>(color, 1.0);
+}
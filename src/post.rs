@@ -0,0 +1,254 @@
+use crate::graphics;
+
+// the cheap lens effects applied at the very end, each off at 0 and strongest at 1
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Effects {
+    // darkens the frame towards its corners
+    pub vignette: f32,
+    // splits red and blue apart towards the frame's edges
+    pub aberration: f32,
+}
+
+impl Effects {
+    pub fn is_off(&self) -> bool {
+        self.vignette == 0.0 && self.aberration == 0.0
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct EffectsUniform {
+    vignette: f32,
+    aberration: f32,
+    _padding: [f32; 2],
+}
+
+// the size the scene is rendered at for a window of config's size, never less than a pixel
+pub fn scaled_config(config: &wgpu::SurfaceConfiguration, scale: f32) -> wgpu::SurfaceConfiguration {
+    let scaled = |size: u32| ((size as f32 * scale).round() as u32).max(1);
+    wgpu::SurfaceConfiguration {
+        width: scaled(config.width),
+        height: scaled(config.height),
+        ..config.clone()
+    }
+}
+
+// the last steps between the scene and the frame: stretching it over the window when it's rendered
+// at a different resolution, color grading it, then the lens effects. the scene's passes resolve into the texture
+// here instead of the frame, and the overlay is drawn on top afterwards at the window's own
+// resolution so it's neither stretched nor graded
+pub struct PostProcess {
+    // the scene at its scaled size
+    view: wgpu::TextureView,
+    // the frame's size, filled with the stretched scene for the overlay pass to load
+    msaa_target: Option<wgpu::TextureView>,
+    sampler: wgpu::Sampler,
+    effects_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+    // the same with a grading table bound as the second group
+    graded_pipeline: wgpu::RenderPipeline,
+}
+
+impl PostProcess {
+    // the render scale's range, a quarter of the pixels to four times as many
+    pub const MIN_SCALE: f32 = 0.5;
+    pub const MAX_SCALE: f32 = 2.0;
+
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        scene_config: &wgpu::SurfaceConfiguration,
+        grading_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let shader = graphics::create_templated_shader(device, config.format, "post.wgsl", include_str!("post.wgsl"));
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("post_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("post_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let effects_buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some("post_effects_buffer"),
+            size: std::mem::size_of::<EffectsUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let create_pipeline = |label: &str, bind_group_layouts: &[&wgpu::BindGroupLayout], entry_point: &str| {
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(&format!("{}_layout", label)),
+                bind_group_layouts,
+                push_constant_ranges: &[],
+            });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: config.format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                // drawn into the same target the overlay pass draws into
+                multisample: wgpu::MultisampleState {
+                    count: graphics::SAMPLE_COUNT,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
+        };
+        let pipeline = create_pipeline("post_pipeline", &[&bind_group_layout], "fs_main");
+        let graded_pipeline = create_pipeline("post_graded_pipeline", &[&bind_group_layout, grading_layout], "fs_graded");
+
+        let (view, bind_group) = create_scene_target(device, &bind_group_layout, &sampler, &effects_buffer, scene_config);
+        PostProcess {
+            view,
+            msaa_target: graphics::create_msaa_target(device, config, "post_msaa_target"),
+            sampler,
+            effects_buffer,
+            bind_group_layout,
+            bind_group,
+            pipeline,
+            graded_pipeline,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, scene_config: &wgpu::SurfaceConfiguration) {
+        (self.view, self.bind_group) =
+            create_scene_target(device, &self.bind_group_layout, &self.sampler, &self.effects_buffer, scene_config);
+        self.msaa_target = graphics::create_msaa_target(device, config, "post_msaa_target");
+    }
+
+    pub fn set_effects(&self, queue: &wgpu::Queue, effects: &Effects) {
+        let uniform = EffectsUniform {
+            vignette: effects.vignette,
+            aberration: effects.aberration,
+            _padding: [0.0; 2],
+        };
+        queue.write_buffer(&self.effects_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    // where the scene's passes resolve to in place of the frame
+    pub fn scene_view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    // what the overlay pass draws into in place of the main msaa target, which is the scene's size
+    pub fn msaa_target(&self) -> Option<&wgpu::TextureView> {
+        self.msaa_target.as_ref()
+    }
+
+    // stretches the scene over the frame, looking its colors up in grade when there is one, then
+    // applies the effects last set
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, frame: &wgpu::TextureView, grade: Option<&wgpu::BindGroup>) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("post_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: self.msaa_target.as_ref().unwrap_or(frame),
+                resolve_target: self.msaa_target.as_ref().map(|_| frame),
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        match grade {
+            Some(grade) => {
+                render_pass.set_pipeline(&self.graded_pipeline);
+                render_pass.set_bind_group(1, grade, &[]);
+            }
+            None => render_pass.set_pipeline(&self.pipeline),
+        }
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_scene_target(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    effects_buffer: &wgpu::Buffer,
+    scene_config: &wgpu::SurfaceConfiguration,
+) -> (wgpu::TextureView, wgpu::BindGroup) {
+    let view = graphics::create_texture(device, &wgpu::TextureDescriptor {
+        label: Some("post_scene_texture"),
+        size: wgpu::Extent3d {
+            width: scene_config.width,
+            height: scene_config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: scene_config.format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+    })
+    .create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("post_bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: effects_buffer.as_entire_binding(),
+            },
+        ],
+    });
+    (view, bind_group)
+}
@@ -0,0 +1,84 @@
+@group(0) @binding(0)
+var scene_tex: texture_2d<f32>;
+
+@group(0) @binding(1)
+var scene_sampler: sampler;
+
+// each off at 0 and strongest at 1 (see post::Effects)
+struct Effects {
+    vignette: f32,
+    aberration: f32,
+}
+
+@group(0) @binding(2)
+var<uniform> effects: Effects;
+
+// how far red and blue are pulled apart at the frame's corners, in uvs, at full strength
+let MAX_ABERRATION: f32 = 0.015;
+
+// only bound for fs_graded, the color grading lookup table (see grading.rs)
+@group(1) @binding(0)
+var lut_tex: texture_3d<f32>;
+
+@group(1) @binding(1)
+var lut_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// one triangle covering the whole screen, the parts past its edges are clipped
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+// the scene with red sampled further from the center and blue nearer, like a cheap lens. the
+// split grows with the distance from the center, so the middle stays sharp
+fn scene_color(uv: vec2<f32>) -> vec3<f32> {
+    let offset = (uv - 0.5) * effects.aberration * MAX_ABERRATION * 2.0;
+    return vec3<f32>(
+        textureSample(scene_tex, scene_sampler, uv - offset).r,
+        textureSample(scene_tex, scene_sampler, uv).g,
+        textureSample(scene_tex, scene_sampler, uv + offset).b,
+    );
+}
+
+// untouched in the middle, falling off smoothly to as dark as 1 - vignette in the corners
+fn vignette(color: vec3<f32>, uv: vec2<f32>) -> vec3<f32> {
+    let distance = length(uv - 0.5) * 1.41421356;
+    return color * (1.0 - effects.vignette * smoothstep(0.4, 1.0, distance));
+}
+
+// the scene is already in the screen's format, so it's only stretched. halving the size samples
+// exactly between four texels, which averages them
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(vignette(scene_color(in.uv), in.uv), 1.0);
+}
+
+// the stretched scene looked up in the grading table. the table maps srgb to srgb, and an srgb
+// scene texture samples as linear, so it's converted there and back around the lookup. the
+// coordinates are pulled in half a texel from each edge so 0 and 1 land on the first and last
+// entries rather than between them and the clamped border
+@fragment
+fn fs_graded(in: VertexOutput) -> @location(0) vec4<f32> {
+    var color = clamp(scene_color(in.uv), vec3<f32>(0.0), vec3<f32>(1.0));
+    if !MANUAL_GAMMA {
+        color = pow(color, vec3<f32>(1.0 / GAMMA));
+    }
+
+    let size = f32(textureDimensions(lut_tex).x);
+    color = textureSample(lut_tex, lut_sampler, color * (size - 1.0) / size + 0.5 / size).rgb;
+
+    if !MANUAL_GAMMA {
+        color = pow(color, vec3<f32>(GAMMA));
+    }
+    return vec4<f32>(vignette(color, in.uv), 1.0);
+}
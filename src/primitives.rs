@@ -0,0 +1,278 @@
+use cgmath::{InnerSpace, Vector3};
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use crate::graphics::Vertex;
+
+// every primitive is centered on the origin with y up
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    fn new() -> Self {
+        Mesh {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    fn append(&mut self, other: Mesh) {
+        let offset = self.vertices.len() as u32;
+        self.vertices.extend(other.vertices);
+        self.indices.extend(other.indices.into_iter().map(|i| i + offset));
+    }
+}
+
+// a point on the outline that revolve spins around the y axis
+struct ProfilePoint {
+    radius: f32,
+    y: f32,
+    // outward and upward parts of the normal
+    normal: (f32, f32),
+    v: f32,
+}
+
+// sweeps the profile a full turn around the y axis, u goes around and v along the profile.
+// the seam column is doubled so u can run from 0 to 1
+fn revolve(profile: &[ProfilePoint], segments: u32) -> Mesh {
+    let mut mesh = Mesh::new();
+    for point in profile {
+        for i in 0..=segments {
+            let u = i as f32 / segments as f32;
+            let (sin, cos) = (u * TAU).sin_cos();
+            let normal = Vector3::new(point.normal.0 * cos, point.normal.1, point.normal.0 * sin);
+            mesh.vertices.push(Vertex {
+                position: [point.radius * cos, point.y, point.radius * sin],
+                tex_coords: [u, point.v],
+                normal: normal.normalize().into(),
+            });
+        }
+    }
+
+    let columns = segments + 1;
+    for j in 0..profile.len().saturating_sub(1) {
+        // a row on the axis collapses to a point, which would leave one triangle of each quad empty
+        let on_axis = |point: &ProfilePoint| point.radius.abs() < 1e-6;
+        let (on_axis, next_on_axis) = (on_axis(&profile[j]), on_axis(&profile[j + 1]));
+        for i in 0..segments {
+            let a = j as u32 * columns + i;
+            let b = a + columns;
+            if !on_axis {
+                mesh.indices.extend_from_slice(&[a, b, a + 1]);
+            }
+            if !next_on_axis {
+                mesh.indices.extend_from_slice(&[a + 1, b, b + 1]);
+            }
+        }
+    }
+    mesh
+}
+
+// a flat disc at height y facing up or down, textured from its center outwards
+fn cap(radius: f32, y: f32, up: bool, segments: u32) -> Mesh {
+    let ny = if up { 1.0 } else { -1.0 };
+    let profile = [
+        ProfilePoint { radius: 0.0, y, normal: (0.0, ny), v: 0.0 },
+        ProfilePoint { radius, y, normal: (0.0, ny), v: 1.0 },
+    ];
+    revolve(&profile, segments)
+}
+
+// a square in the xz plane, split into subdivisions by subdivisions quads
+pub fn plane(size: f32, subdivisions: u32) -> Mesh {
+    let mut mesh = Mesh::new();
+    let n = subdivisions.max(1);
+    for z in 0..=n {
+        for x in 0..=n {
+            let (u, v) = (x as f32 / n as f32, z as f32 / n as f32);
+            mesh.vertices.push(Vertex {
+                position: [(u - 0.5) * size, 0.0, (v - 0.5) * size],
+                tex_coords: [u, v],
+                normal: [0.0, 1.0, 0.0],
+            });
+        }
+    }
+
+    let row = n + 1;
+    for z in 0..n {
+        for x in 0..n {
+            let a = z * row + x;
+            let b = a + row;
+            mesh.indices.extend_from_slice(&[a, b, a + 1, a + 1, b, b + 1]);
+        }
+    }
+    mesh
+}
+
+// each face has its own corners so its normal stays flat
+pub fn cube(size: f32) -> Mesh {
+    let mut mesh = Mesh::new();
+    let faces = [
+        (Vector3::unit_x(), Vector3::unit_z()),
+        (-Vector3::unit_x(), Vector3::unit_z()),
+        (Vector3::unit_y(), Vector3::unit_x()),
+        (-Vector3::unit_y(), Vector3::unit_x()),
+        (Vector3::unit_z(), Vector3::unit_x()),
+        (-Vector3::unit_z(), Vector3::unit_x()),
+    ];
+    for (normal, u_axis) in faces {
+        // the same winding as plane, facing along the normal
+        let v_axis = u_axis.cross(normal);
+        let base = mesh.vertices.len() as u32;
+        for (u, v) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)] {
+            let position = (normal * 0.5 + u_axis * (u - 0.5) + v_axis * (v - 0.5)) * size;
+            mesh.vertices.push(Vertex {
+                position: position.into(),
+                tex_coords: [u, v],
+                normal: normal.into(),
+            });
+        }
+        mesh.indices.extend_from_slice(&[base, base + 2, base + 1, base + 1, base + 2, base + 3]);
+    }
+    mesh
+}
+
+pub fn cylinder(radius: f32, height: f32, segments: u32) -> Mesh {
+    let half = height / 2.0;
+    let mut mesh = revolve(
+        &[
+            ProfilePoint { radius, y: -half, normal: (1.0, 0.0), v: 1.0 },
+            ProfilePoint { radius, y: half, normal: (1.0, 0.0), v: 0.0 },
+        ],
+        segments,
+    );
+    mesh.append(cap(radius, half, true, segments));
+    mesh.append(cap(radius, -half, false, segments));
+    mesh
+}
+
+// base on y = -height / 2, tip on y = height / 2
+pub fn cone(radius: f32, height: f32, segments: u32) -> Mesh {
+    let half = height / 2.0;
+    // the side leans in by radius over height, so its normal leans up by the same ratio
+    let normal = (height, radius);
+    let mut mesh = revolve(
+        &[
+            ProfilePoint { radius, y: -half, normal, v: 1.0 },
+            ProfilePoint { radius: 0.0, y: half, normal, v: 0.0 },
+        ],
+        segments,
+    );
+    mesh.append(cap(radius, -half, false, segments));
+    mesh
+}
+
+// a cylinder of the given height with a hemisphere on each end
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> Mesh {
+    let half = height / 2.0;
+    let rings = rings.max(1);
+    let total_length = height + PI * radius;
+
+    let mut profile = Vec::new();
+    // bottom hemisphere from the pole up to the equator, then the top one, with the cylinder between
+    for (center, from, to) in [(-half, -FRAC_PI_2, 0.0), (half, 0.0, FRAC_PI_2)] {
+        for i in 0..=rings {
+            let angle = from + (to - from) * i as f32 / rings as f32;
+            let (sin, cos) = angle.sin_cos();
+            let y = center + sin * radius;
+            // measured along the surface from the top pole, so the texture doesn't stretch
+            let arc = if center > 0.0 {
+                (FRAC_PI_2 - angle) * radius
+            } else {
+                height + (FRAC_PI_2 - angle) * radius
+            };
+            profile.push(ProfilePoint {
+                radius: cos * radius,
+                y,
+                normal: (cos, sin),
+                v: arc / total_length,
+            });
+        }
+    }
+    revolve(&profile, segments)
+}
+
+pub fn torus(major_radius: f32, minor_radius: f32, major_segments: u32, minor_segments: u32) -> Mesh {
+    let profile = (0..=minor_segments)
+        .map(|i| {
+            let v = i as f32 / minor_segments as f32;
+            let (sin, cos) = (v * TAU).sin_cos();
+            ProfilePoint {
+                radius: major_radius + cos * minor_radius,
+                y: sin * minor_radius,
+                normal: (cos, sin),
+                v,
+            }
+        })
+        .collect::<Vec<_>>();
+    revolve(&profile, major_segments)
+}
+
+// latitude rings from pole to pole, vertices only repeat along the texture seam and at the poles,
+// where each column needs its own u
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> Mesh {
+    let profile = (0..=rings)
+        .map(|j| {
+            let v = j as f32 / rings as f32;
+            let (sin, cos) = (FRAC_PI_2 - v * PI).sin_cos();
+            ProfilePoint {
+                radius: cos * radius,
+                y: sin * radius,
+                normal: (cos, sin),
+                v,
+            }
+        })
+        .collect::<Vec<_>>();
+    revolve(&profile, segments)
+}
+
+// a subdivided icosahedron, every vertex the same distance from its neighbours. uvs are spherical
+// and wrap around once, so the faces crossing the seam stretch across the whole texture
+pub fn icosphere(radius: f32, subdivisions: u32) -> Mesh {
+    let t = (1.0 + 5f32.sqrt()) / 2.0;
+    let mut points = [
+        [-1.0, t, 0.0], [1.0, t, 0.0], [-1.0, -t, 0.0], [1.0, -t, 0.0],
+        [0.0, -1.0, t], [0.0, 1.0, t], [0.0, -1.0, -t], [0.0, 1.0, -t],
+        [t, 0.0, -1.0], [t, 0.0, 1.0], [-t, 0.0, -1.0], [-t, 0.0, 1.0],
+    ]
+    .map(|p| Vector3::from(p).normalize())
+    .to_vec();
+    let mut faces: Vec<[u32; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        // edges are shared by two faces, so each midpoint is only added once
+        let mut midpoints = HashMap::new();
+        let mut midpoint = |a: u32, b: u32| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                points.push(((points[a as usize] + points[b as usize]) / 2.0).normalize());
+                points.len() as u32 - 1
+            })
+        };
+        faces = faces
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    Mesh {
+        vertices: points
+            .iter()
+            .map(|p| Vertex {
+                position: (p * radius).into(),
+                tex_coords: [0.5 + p.z.atan2(p.x) / TAU, 0.5 - p.y.asin() / PI],
+                normal: (*p).into(),
+            })
+            .collect(),
+        indices: faces.into_iter().flatten().collect(),
+    }
+}
@@ -0,0 +1,127 @@
+// world units per second the drops fall at, each gets up to FALL_SPREAD more
+let FALL_SPEED: f32 = 35.0;
+let FALL_SPREAD: f32 = 10.0;
+// how much of a second's fall each streak is drawn as, the motion blur a camera would show
+let STREAK_TIME: f32 = 0.03;
+// half the width of a streak in world units
+let STREAK_WIDTH: f32 = 0.02;
+// drops are mostly see-through, they only catch a little of the light
+let OPACITY: f32 = 0.35;
+
+struct RainUniform {
+    view_proj: mat4x4<f32>,
+    // xyz is the middle of the box the drops fall through, w half its size
+    camera: vec4<f32>,
+    // x is the time step, y the time
+    params: vec4<f32>,
+    // xz is the wind's push in world units per second
+    wind: vec4<f32>,
+    // the light falling on the drops
+    color: vec4<f32>,
+    // x is the drop count
+    count: vec4<u32>,
+}
+
+struct Drop {
+    // w is 0 until the drop has been placed
+    position: vec4<f32>,
+    velocity: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> rain: RainUniform;
+
+@group(0) @binding(1)
+var<storage, read_write> drops: array<Drop>;
+
+// pcg, good enough to scatter the drops without any visible pattern
+fn hash(v: u32) -> u32 {
+    let state = v * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn random(seed: u32) -> f32 {
+    return f32(hash(seed)) / 4294967295.0;
+}
+
+// somewhere in the box around the camera, at a random height the first time and at the top after
+fn spawn(i: u32, seed: u32, top: bool) -> Drop {
+    let size = rain.camera.w;
+    let x = random(seed) * 2.0 - 1.0;
+    let z = random(seed + 1u) * 2.0 - 1.0;
+    var y = 1.0;
+    if !top {
+        y = random(seed + 2u) * 2.0 - 1.0;
+    }
+    let position = rain.camera.xyz + vec3<f32>(x, y, z) * size;
+    let speed = FALL_SPEED + random(i) * FALL_SPREAD;
+    let velocity = vec3<f32>(rain.wind.x, -speed, rain.wind.z);
+    return Drop(vec4<f32>(position, 1.0), vec4<f32>(velocity, 0.0));
+}
+
+@compute @workgroup_size(256)
+fn cs_update(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= rain.count.x {
+        return;
+    }
+
+    // a new seed each frame, so a drop lands somewhere else every time it falls
+    let seed = i * 3u + hash(bitcast<u32>(rain.params.y));
+    var drop = drops[i];
+    if drop.position.w == 0.0 {
+        drops[i] = spawn(i, seed, false);
+        return;
+    }
+    var position = drop.position.xyz + drop.velocity.xyz * rain.params.x;
+    if position.y < rain.camera.y - rain.camera.w {
+        drops[i] = spawn(i, seed, true);
+        return;
+    }
+    // wrapped around the box as the camera moves, so it's always raining just as hard around it
+    let size = rain.camera.w * 2.0;
+    let offset = position.xz - rain.camera.xz;
+    let wrapped = offset - size * floor(offset / size + 0.5);
+    position = vec3<f32>(rain.camera.x + wrapped.x, position.y, rain.camera.z + wrapped.y);
+    drops[i] = Drop(vec4<f32>(position, 1.0), drop.velocity);
+}
+
+struct InstanceInput {
+    @location(0) position: vec4<f32>,
+    @location(1) velocity: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // -1 to 1 across the streak, y from its tail to its head
+    @location(0) corner: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, instance: InstanceInput) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, -1.0), vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, 1.0), vec2<f32>(-1.0, 1.0),
+    );
+    let corner = corners[index];
+    // stretched along the way the drop falls and turned about that to face the camera
+    let along = instance.velocity.xyz * STREAK_TIME;
+    let to_camera = rain.camera.xyz - instance.position.xyz;
+    let side = normalize(cross(along, to_camera)) * STREAK_WIDTH;
+    let world_pos = instance.position.xyz + along * (corner.y * 0.5 - 0.5) + side * corner.x;
+
+    var out: VertexOutput;
+    out.clip_position = rain.view_proj * vec4<f32>(world_pos, 1.0);
+    out.corner = corner;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // brightest along the middle and towards the head
+    let strength = (1.0 - in.corner.x * in.corner.x) * (in.corner.y * 0.5 + 0.5);
+    var color = rain.color.rgb;
+    color = apply_output_gamma(color);
+    return vec4<f32>(color, strength * OPACITY);
+}
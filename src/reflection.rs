@@ -0,0 +1,141 @@
+use cgmath::{Matrix4, Vector3};
+
+use crate::graphics;
+
+// the scene mirrored in a horizontal plane, rendered each frame into a texture the size of the
+// screen by a camera flipped to the other side of it. the plane samples the texture where it is
+// on screen, since both cameras share the projection
+pub struct PlanarReflection {
+    height: f32,
+    texture: wgpu::TextureView,
+    msaa_target: Option<wgpu::TextureView>,
+    depth_texture: (wgpu::TextureView, wgpu::Sampler, wgpu::Texture),
+    bind_group: wgpu::BindGroup,
+}
+
+impl PlanarReflection {
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        config: &wgpu::SurfaceConfiguration,
+        height: f32,
+    ) -> Self {
+        let texture = graphics::create_texture(
+            device,
+            &wgpu::TextureDescriptor {
+                label: Some("reflection_texture"),
+                size: wgpu::Extent3d {
+                    width: config.width,
+                    height: config.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: config.format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            },
+        )
+        .create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("reflection_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("reflection_bind_group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        PlanarReflection {
+            height,
+            texture,
+            msaa_target: graphics::create_msaa_target(device, config, "reflection_msaa_target"),
+            depth_texture: graphics::create_depth_texture(device, config, "reflection_depth_texture"),
+            bind_group,
+        }
+    }
+
+    // the textures follow the screen's size
+    pub fn resize(&mut self, device: &wgpu::Device, layout: &wgpu::BindGroupLayout, config: &wgpu::SurfaceConfiguration) {
+        *self = PlanarReflection::new(device, layout, config, self.height);
+    }
+
+    // flips the world upside down around the plane, put between the view and the model matrices
+    pub fn mirror(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(Vector3::new(0.0, self.height, 0.0))
+            * Matrix4::from_nonuniform_scale(1.0, -1.0, 1.0)
+            * Matrix4::from_translation(Vector3::new(0.0, -self.height, 0.0))
+    }
+
+    // keeps what's below the plane out of the reflection, it would show up above it
+    pub fn clip_plane(&self) -> [f32; 4] {
+        [0.0, 1.0, 0.0, -self.height]
+    }
+
+    // the pass the mirrored scene is drawn in, before the main one
+    pub fn begin_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder, clear_color: wgpu::Color) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("reflection_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: self.msaa_target.as_ref().unwrap_or(&self.texture),
+                resolve_target: self.msaa_target.as_ref().map(|_| &self.texture),
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear_color),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture.0,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: false,
+                }),
+                stencil_ops: None,
+            }),
+        })
+    }
+
+    // for the reflective pipeline's second group
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+}
+
+// the second bind group of the reflective pipeline, after the one every object has
+pub fn build_reflection_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("reflection_bind_group_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    })
+}
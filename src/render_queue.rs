@@ -0,0 +1,62 @@
+// the pipelines scene objects are drawn with, in the order they're drawn in
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum DrawPipeline {
+    // whichever the pass set, the main one unless a debug view or the portal's stencil replaces it
+    Scene,
+    Chrome,
+    Emissive,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DrawItem<K> {
+    // what's drawn
+    pub key: K,
+    pub pipeline: DrawPipeline,
+    // the pipeline's second bind group, draws sharing one go together
+    pub group: usize,
+    // squared distance from the camera
+    pub depth: f32,
+    pub transparent: bool,
+}
+
+// the scene's draws in the order they're encoded, built again every frame before any pass
+pub struct RenderQueue<K> {
+    opaque: Vec<DrawItem<K>>,
+    transparent: Vec<DrawItem<K>>,
+}
+
+impl<K: Copy> RenderQueue<K> {
+    pub fn new() -> Self {
+        RenderQueue {
+            opaque: Vec::new(),
+            transparent: Vec::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.opaque.clear();
+        self.transparent.clear();
+    }
+
+    pub fn push(&mut self, item: DrawItem<K>) {
+        if item.transparent {
+            self.transparent.push(item);
+        } else {
+            self.opaque.push(item);
+        }
+    }
+
+    // opaque draws are grouped by what they bind, switching pipelines costs the most, and then go
+    // front to back so hidden fragments fail the depth test early. transparent ones come after,
+    // back to front so each blends over what's behind it
+    pub fn sort(&mut self) {
+        self.opaque.sort_by(|a, b| {
+            (a.pipeline, a.group).cmp(&(b.pipeline, b.group)).then(a.depth.total_cmp(&b.depth))
+        });
+        self.transparent.sort_by(|a, b| b.depth.total_cmp(&a.depth));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &DrawItem<K>> {
+        self.opaque.iter().chain(&self.transparent)
+    }
+}
@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::animation::AnimationClip;
+use crate::app::INSTANCED_COLS;
+use crate::app::INSTANCED_ROWS;
+use crate::app::INSTANCE_SPACING;
+use crate::grass::GrassConfig;
+use crate::portal::PortalConfig;
+use crate::terrain::TerrainConfig;
+use crate::tv::TvConfig;
+use crate::voxel::VoxelConfig;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Scene {
+    pub camera: CameraPose,
+    pub clear_color: [f64; 3],
+    pub selected_obj: u32,
+    pub bounds: WorldBounds,
+    // camera path played back with L, or automatically when rendering headlessly
+    pub flythrough: Vec<CameraPose>,
+    // seconds for a full day and night, 0 leaves the scene unlit under a plain clear color
+    pub day_length: f32,
+    // where in the day the scene starts, 0 is midnight and 0.5 noon
+    pub time_of_day: f32,
+    // colored lights orbiting the sphere grid
+    pub light_demo: bool,
+    // heightmap terrain around the scene, in addition to or instead of the floor
+    pub terrain: Option<TerrainConfig>,
+    // endless block world streamed in around the camera
+    pub voxels: Option<VoxelConfig>,
+    // an animated marching cubes blob beside the cube grid
+    pub metaballs: bool,
+    // a row of the generated primitives in front of the cube grid
+    pub primitives_demo: bool,
+    // a fountain of sparks above the sphere grid
+    pub particles_demo: bool,
+    // a million particles simulated in a compute shader, swirling above the cube grid
+    pub gpu_particles: bool,
+    // blades of grass swaying in the wind all over the floor
+    pub grass: Option<GrassConfig>,
+    // the floor mirrors the scene above it
+    pub reflective_floor: bool,
+    // a skinned character walking back and forth along the edge of the floor
+    pub character: bool,
+    // a ball blending between morph targets beside the cube grid
+    pub morph_demo: bool,
+    // an opening beside the cube grid that looks out somewhere else
+    pub portal: Option<PortalConfig>,
+    // a screen in front of the cube grid showing the scene from another camera
+    pub tv: Option<TvConfig>,
+    // crates dropped onto the floor beside the cube grid, needs the physics feature
+    pub physics_demo: bool,
+    // the instanced spheres thrown around and bouncing off the floor and each other
+    pub bouncing_balls: bool,
+    // looped behind the scene, needs the audio feature
+    pub music: MusicConfig,
+    // objects by name giving off a hum that's louder up close, also needs the audio feature
+    pub hums: Vec<String>,
+    // rhai scripts run every frame, reloaded when they change. needs the scripting feature
+    pub scripts: Vec<PathBuf>,
+    // how the instanced spheres are built
+    pub sphere_mesh: SphereMesh,
+    // keyframed transforms by object name (cubes, pyramids, spheres, floor, placed for the
+    // clicked in crates or one of the primitives), in place of the object's own animation
+    pub animations: HashMap<String, AnimationClip>,
+    // also by object name, everything else is textured
+    pub materials: HashMap<String, Material>,
+}
+
+// tracks streamed from disk, the scene is silent without one
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct MusicConfig {
+    pub track: Option<PathBuf>,
+    // played instead while the sun is down, in scenes with days and nights
+    pub night: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SphereMesh {
+    // the original generator, kept for comparison. it has seams, slivers at the poles and
+    // lots of duplicated vertices
+    Pythagoras,
+    #[default]
+    Uv,
+    Ico,
+}
+
+// how an object's surface is shaded
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Material {
+    // its texture, lit by the sun and the lights
+    #[default]
+    Textured,
+    // polished metal reflecting the sky
+    Chrome,
+    // its texture times color, without any lighting. pulse is how many times a second it
+    // dims and brightens again, 0 keeps it steady
+    Emissive {
+        color: [f32; 3],
+        #[serde(default)]
+        pulse: f32,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct CameraPose {
+    pub position: [f32; 3],
+    pub yaw: f32,
+    pub pitch: f32,
+    // only kept in free look mode
+    pub roll: f32,
+}
+
+// the box the camera is kept inside of. with wrap set, leaving through one side in x/z
+// re-enters from the opposite side instead of bouncing off
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct WorldBounds {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+    pub wrap: bool,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Scene {
+            camera: CameraPose::default(),
+            clear_color: [0.0, 0.25, 0.0],
+            selected_obj: 1,
+            bounds: WorldBounds::default(),
+            flythrough: Vec::new(),
+            day_length: 0.0,
+            time_of_day: 0.3,
+            light_demo: false,
+            terrain: None,
+            voxels: None,
+            metaballs: false,
+            primitives_demo: false,
+            particles_demo: false,
+            gpu_particles: false,
+            grass: None,
+            reflective_floor: false,
+            character: false,
+            morph_demo: false,
+            portal: None,
+            tv: None,
+            physics_demo: false,
+            bouncing_balls: false,
+            music: MusicConfig::default(),
+            hums: Vec::new(),
+            scripts: Vec::new(),
+            sphere_mesh: SphereMesh::default(),
+            materials: HashMap::new(),
+            animations: HashMap::new(),
+        }
+    }
+}
+
+impl WorldBounds {
+    const BORDER_SPACE: f32 = 150.0;
+}
+
+impl Default for WorldBounds {
+    fn default() -> Self {
+        WorldBounds {
+            min: [-Self::BORDER_SPACE, -Self::BORDER_SPACE, -Self::BORDER_SPACE],
+            max: [
+                INSTANCED_ROWS as f32 * INSTANCE_SPACING + Self::BORDER_SPACE,
+                100.0,
+                INSTANCED_COLS as f32 * INSTANCE_SPACING + Self::BORDER_SPACE,
+            ],
+            wrap: false,
+        }
+    }
+}
+
+impl Default for CameraPose {
+    fn default() -> Self {
+        CameraPose {
+            position: [0.0, 0.0, 0.0],
+            yaw: 45.0,
+            pitch: 0.0,
+            roll: 0.0,
+        }
+    }
+}
+
+impl Scene {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    // the names of the fields whose values differ from other's. destructuring other means a new
+    // field can't be added without being compared here too
+    pub fn changed_fields(&self, other: &Scene) -> BTreeSet<&'static str> {
+        let mut changed = BTreeSet::new();
+        macro_rules! compare {
+            ($($field:ident),* $(,)?) => {
+                let Scene { $($field),* } = other;
+                $(
+                    if self.$field != *$field {
+                        changed.insert(stringify!($field));
+                    }
+                )*
+            };
+        }
+        compare!(
+            camera,
+            clear_color,
+            selected_obj,
+            bounds,
+            flythrough,
+            day_length,
+            time_of_day,
+            light_demo,
+            terrain,
+            voxels,
+            metaballs,
+            primitives_demo,
+            particles_demo,
+            gpu_particles,
+            grass,
+            reflective_floor,
+            character,
+            morph_demo,
+            portal,
+            tv,
+            physics_demo,
+            bouncing_balls,
+            music,
+            hums,
+            scripts,
+            sphere_mesh,
+            animations,
+            materials,
+        );
+        changed
+    }
+}
@@ -0,0 +1,227 @@
+use cgmath::{Deg, EuclideanSpace, Matrix4, Point3, Quaternion, Rotation3, Vector3};
+use log::{info, warn};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Scope, AST, FLOAT};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use crate::lights::{Light, LightKind};
+use crate::scene::CameraPose;
+
+// where a script put an object, the parts it didn't set are as they'd be for an unmoved object
+#[derive(Debug, Clone, Copy)]
+pub struct ScriptTransform {
+    pub position: Vector3<f32>,
+    // yaw, pitch and roll in degrees, the same as the camera's
+    pub rotation: [f32; 3],
+    pub scale: f32,
+}
+
+impl Default for ScriptTransform {
+    fn default() -> Self {
+        ScriptTransform {
+            position: Vector3::new(0.0, 0.0, 0.0),
+            rotation: [0.0; 3],
+            scale: 1.0,
+        }
+    }
+}
+
+impl ScriptTransform {
+    pub fn matrix(&self) -> Matrix4<f32> {
+        let [yaw, pitch, roll] = self.rotation;
+        let rotation = Quaternion::from_angle_y(Deg(-yaw)) * Quaternion::from_angle_z(Deg(pitch)) * Quaternion::from_angle_x(Deg(roll));
+        Matrix4::from_translation(self.position) * Matrix4::from(rotation) * Matrix4::from_scale(self.scale)
+    }
+}
+
+// what the scripts asked for in one frame, for the app to apply once they've all run
+#[derive(Debug, Default)]
+pub struct ScriptOutput {
+    // replacing the model matrices of the objects with these names
+    pub transforms: BTreeMap<String, ScriptTransform>,
+    pub lights: Vec<Light>,
+    // the camera's new pose, None leaves it where it is
+    pub camera: Option<CameraPose>,
+}
+
+// shared between the functions registered with the engine and the frame running them
+#[derive(Default)]
+struct Frame {
+    camera: CameraPose,
+    output: ScriptOutput,
+}
+
+struct Script {
+    path: PathBuf,
+    // when the file was last changed as of the last time it was compiled, None when it's missing
+    modified: Option<SystemTime>,
+    // whether it's been tried at all, a missing one isn't tried again until it shows up
+    checked: bool,
+    // the last version that compiled, None when none has yet
+    ast: Option<AST>,
+    // set when it fails to run, so the error isn't logged every frame. cleared when it's reloaded
+    failed: bool,
+}
+
+// rhai scripts run once a frame, each one top to bottom with time and dt set. they move objects
+// by name, add lights and move the camera through the functions registered in new. a script is
+// compiled again whenever its file changes
+pub struct Scripts {
+    engine: Engine,
+    scripts: Vec<Script>,
+    frame: Arc<Mutex<Frame>>,
+    // seconds until the files are checked for changes again
+    reload_timer: f32,
+    // names scripts moved that no object has, only warned about once
+    unknown: BTreeSet<String>,
+}
+
+impl Scripts {
+    const RELOAD_INTERVAL: f32 = 0.5;
+    // a script stuck in a loop is stopped after this many steps instead of freezing the app
+    const MAX_OPERATIONS: u64 = 1_000_000;
+
+    pub fn new(paths: &[PathBuf]) -> Self {
+        let frame = Arc::new(Mutex::new(Frame::default()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(Self::MAX_OPERATIONS);
+        engine.on_print(|text| info!("Script: {}", text));
+
+        let transform = |frame: &Arc<Mutex<Frame>>, name: &str, change: &dyn Fn(&mut ScriptTransform)| {
+            let mut frame = lock(frame);
+            change(frame.output.transforms.entry(name.to_string()).or_default());
+        };
+        let f = frame.clone();
+        engine.register_fn("set_position", move |name: &str, position: Array| -> Result<(), Box<EvalAltResult>> {
+            let position = vec3(position)?;
+            transform(&f, name, &|t| t.position = position);
+            Ok(())
+        });
+        let f = frame.clone();
+        engine.register_fn("set_rotation", move |name: &str, yaw: FLOAT, pitch: FLOAT, roll: FLOAT| {
+            transform(&f, name, &|t| t.rotation = [yaw as f32, pitch as f32, roll as f32]);
+        });
+        let f = frame.clone();
+        engine.register_fn("set_scale", move |name: &str, scale: FLOAT| {
+            transform(&f, name, &|t| t.scale = scale as f32);
+        });
+        let f = frame.clone();
+        engine.register_fn(
+            "add_light",
+            move |position: Array, color: Array, intensity: FLOAT, range: FLOAT| -> Result<(), Box<EvalAltResult>> {
+                let light = Light {
+                    kind: LightKind::Point,
+                    position: Point3::from_vec(vec3(position)?),
+                    color: vec3(color)?.into(),
+                    intensity: intensity as f32,
+                    range: range as f32,
+                };
+                lock(&f).output.lights.push(light);
+                Ok(())
+            },
+        );
+        let f = frame.clone();
+        engine.register_fn("camera_position", move || -> Array {
+            lock(&f).camera.position.iter().map(|&x| Dynamic::from_float(x as FLOAT)).collect()
+        });
+        let f = frame.clone();
+        engine.register_fn("set_camera", move |position: Array, yaw: FLOAT, pitch: FLOAT| -> Result<(), Box<EvalAltResult>> {
+            let mut frame = lock(&f);
+            let pose = CameraPose { position: vec3(position)?.into(), yaw: yaw as f32, pitch: pitch as f32, ..frame.camera.clone() };
+            frame.output.camera = Some(pose);
+            Ok(())
+        });
+
+        let mut scripts = Scripts {
+            engine,
+            scripts: paths
+                .iter()
+                .map(|path| Script { path: path.clone(), modified: None, checked: false, ast: None, failed: false })
+                .collect(),
+            frame,
+            reload_timer: 0.0,
+            unknown: BTreeSet::new(),
+        };
+        scripts.reload_changed();
+        scripts
+    }
+
+    // runs every script that compiled, camera is where the camera is before they move it
+    pub fn run(&mut self, camera: CameraPose, time: f32, dt: f32) -> ScriptOutput {
+        self.reload_timer -= dt;
+        if self.reload_timer <= 0.0 {
+            self.reload_timer = Self::RELOAD_INTERVAL;
+            self.reload_changed();
+        }
+
+        *lock(&self.frame) = Frame { camera, output: ScriptOutput::default() };
+        for script in &mut self.scripts {
+            let Some(ast) = &script.ast else {
+                continue;
+            };
+            if script.failed {
+                continue;
+            }
+            let mut scope = Scope::new();
+            scope.push_constant("time", time as FLOAT);
+            scope.push_constant("dt", dt as FLOAT);
+            if let Err(e) = self.engine.run_ast_with_scope(&mut scope, ast) {
+                warn!("{} stopped, it'll run again once it's changed: {}", script.path.display(), e);
+                script.failed = true;
+            }
+        }
+        std::mem::take(&mut lock(&self.frame).output)
+    }
+
+    // for names in ScriptOutput::transforms that aren't an object's
+    pub fn warn_unknown(&mut self, name: &str) {
+        if self.unknown.insert(name.to_string()) {
+            warn!("A script moved {}, but there's no object by that name", name);
+        }
+    }
+
+    // compiles the scripts whose files changed since they were last compiled. one that no longer
+    // compiles keeps running its last good version
+    fn reload_changed(&mut self) {
+        for script in &mut self.scripts {
+            let modified = modified(&script.path);
+            if script.checked && modified == script.modified {
+                continue;
+            }
+            script.checked = true;
+            script.modified = modified;
+            match self.engine.compile_file(script.path.clone()) {
+                Ok(ast) => {
+                    info!("{} {}", if script.ast.is_some() { "Reloaded" } else { "Loaded" }, script.path.display());
+                    script.ast = Some(ast);
+                    script.failed = false;
+                }
+                Err(e) => warn!("Failed to compile {}: {}", script.path.display(), e),
+            }
+        }
+    }
+}
+
+// a script that panicked partway through can't have left the frame in a state worth refusing
+fn lock(frame: &Mutex<Frame>) -> MutexGuard<'_, Frame> {
+    frame.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+// [x, y, z] from a script, which can mix integers and floats
+fn vec3(array: Array) -> Result<Vector3<f32>, Box<EvalAltResult>> {
+    let numbers = array
+        .iter()
+        .map(|value| value.as_float().or_else(|_| value.as_int().map(|i| i as FLOAT)))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| "Expected an array of numbers")?;
+    match numbers.as_slice() {
+        &[x, y, z] => Ok(Vector3::new(x as f32, y as f32, z as f32)),
+        _ => Err(format!("Expected 3 numbers, got {}", numbers.len()).into()),
+    }
+}
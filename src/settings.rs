@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+#[cfg(not(target_arch = "wasm32"))]
+use log::{info, warn};
+
+use crate::camera::Camera;
+use crate::input::KeyBindings;
+use crate::scene::CameraPose;
+use crate::sky::CloudQuality;
+use crate::FullscreenMode;
+
+// user preferences that outlive a single run, loaded at startup and written back on exit
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Settings {
+    pub resolution: [u32; 2],
+    pub fullscreen: bool,
+    pub fullscreen_mode: FullscreenMode,
+    // index into the available monitors, the primary monitor when unset
+    pub monitor: Option<usize>,
+    // the video mode of exclusive fullscreen, picked from the monitor's modes when unset
+    pub fullscreen_resolution: Option<[u32; 2]>,
+    pub refresh_rate: Option<u16>,
+    // degrees of rotation per mouse count
+    pub mouse_sensitivity: f32,
+    // time constants in seconds for easing into look and movement changes, 0 disables
+    pub look_smoothing: f32,
+    pub movement_smoothing: f32,
+    // vertical field of view in degrees
+    pub fov: f32,
+    // the view widening and shaking a little while sprinting
+    pub sprint_effects: bool,
+    // how strongly walking bobs the view, 0 turns it off
+    pub head_bob: f32,
+    // of the sound effects and the music, 0 to 1
+    pub volume: f32,
+    pub music_volume: f32,
+    // world units visible vertically in the orthographic projection
+    pub ortho_extent: f32,
+    pub vsync: bool,
+    // the scene's resolution relative to the window's, 0.5 to 2. the hud stays at the window's
+    pub render_scale: f32,
+    // how finely the sky's clouds are drawn, off for none
+    pub cloud_quality: CloudQuality,
+    // frame rate cap on top of (or instead of) vsync, 0 for none
+    pub max_fps: u32,
+    // frames averaged into each delta time, 1 for none
+    pub delta_smoothing_frames: usize,
+    // the longest a single frame counts as in seconds, so a hitch doesn't jump everything ahead
+    pub max_delta_time: f64,
+    pub keybindings: KeyBindings,
+    // camera poses saved with ctrl + a number key, keyed by that number
+    pub bookmarks: BTreeMap<String, CameraPose>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            resolution: [1600, 900],
+            fullscreen: false,
+            fullscreen_mode: FullscreenMode::Borderless,
+            monitor: None,
+            fullscreen_resolution: None,
+            refresh_rate: None,
+            mouse_sensitivity: Camera::DEFAULT_SENS,
+            look_smoothing: 0.0,
+            movement_smoothing: 0.0,
+            fov: Camera::DEFAULT_FOVY,
+            sprint_effects: true,
+            head_bob: 1.0,
+            volume: 1.0,
+            music_volume: 0.5,
+            ortho_extent: Camera::DEFAULT_ORTHO_EXTENT,
+            vsync: true,
+            render_scale: 1.0,
+            cloud_quality: CloudQuality::Medium,
+            max_fps: 0,
+            delta_smoothing_frames: 8,
+            max_delta_time: 0.1,
+            keybindings: KeyBindings::default(),
+            bookmarks: BTreeMap::new(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Settings {
+    // a missing file isn't fatal, the defaults are used and written out on exit. None for a file
+    // that's there but won't parse, the run uses the defaults then and leaves the file for the
+    // user to fix rather than saving over it
+    pub fn load(path: &Path) -> Option<Self> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => {
+                info!("No settings loaded from {} ({}), using defaults", path.display(), e);
+                return Some(Settings::default());
+            }
+        };
+
+        match toml::from_str(&text) {
+            Ok(settings) => Some(settings),
+            Err(e) => {
+                warn!("Failed to parse settings {}, using defaults and not saving over it: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) {
+        let text = match toml::to_string_pretty(self) {
+            Ok(text) => text,
+            Err(e) => {
+                warn!("Failed to serialize settings: {}", e);
+                return;
+            }
+        };
+
+        match std::fs::write(path, text) {
+            Ok(_) => info!("Saved settings to {}", path.display()),
+            Err(e) => warn!("Failed to save settings {}: {}", path.display(), e),
+        }
+    }
+}
@@ -1,63 +1,352 @@
-struct CameraUniform {
-    view_proj: mat4x4<f32>
-}
+// distance shown as black in the depth view, the camera's far plane
+let DEPTH_VIEW_RANGE: f32 = 1000.0;
 
-struct ModelUniform {
-    model: mat4x4<f32>
+// see CameraUniform in graphics.rs
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    clip_plane: vec4<f32>,
 }
 
 @group(0) @binding(0)
 var<uniform> camera: CameraUniform;
 
+// see InstanceRaw in graphics.rs
+struct Transform {
+    model: mat4x4<f32>,
+    // multiplies the texture's color
+    tint: vec4<f32>,
+}
+
+// the object's model matrix, then one per instance. see graphics::transforms
 @group(0) @binding(1)
-var<uniform> model: ModelUniform;
+var<storage, read> transforms: array<Transform>;
+
+// set before each draw of an object without instances, see DrawConstants in graphics.rs
+struct DrawConstants {
+    model: mat4x4<f32>,
+    // DRAW_OWN_MODEL to use model instead of the first transform
+    flags: u32,
+}
+
+let DRAW_OWN_MODEL: u32 = 1u;
+
+// push constants where the device has them. otherwise private and so always zero, leaving every
+// object on its transforms
+var<DRAW_CONSTANTS_SPACE> draw: DrawConstants;
+
+struct LightUniform {
+    // direction towards the sun
+    sun_dir: vec4<f32>,
+    sun_color: vec4<f32>,
+    ambient: vec4<f32>,
+    camera_pos: vec4<f32>,
+    // x is the number of entries in use in lights, y the view mode
+    light_count: vec4<u32>,
+    // x is how wet the floor is, 0 to 1, and y its height
+    wet_floor: vec4<f32>,
+}
 
 @group(0) @binding(2)
-var<uniform> is_instanced: i32;
+var<uniform> light: LightUniform;
+
+// see LightRaw in lights.rs
+struct Light {
+    // w is 0 for point lights and 1 for spot lights
+    position: vec4<f32>,
+    // w is the range
+    color: vec4<f32>,
+    // w is the cosine of the outer cone angle
+    direction: vec4<f32>,
+    // x is the cosine of the inner cone angle
+    params: vec4<f32>,
+}
+
+@group(0) @binding(3)
+var<storage, read> lights: array<Light>;
 
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
+    // zero when the mesh has none
+    @location(6) normal: vec3<f32>,
 }
 
-struct InstanceInput {
-    @location(2) model_matrix_0: vec4<f32>,
-    @location(3) model_matrix_1: vec4<f32>,
-    @location(4) model_matrix_2: vec4<f32>,
-    @location(5) model_matrix_3: vec4<f32>,
-};
-
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
-    @location(0) tex_coords: vec2<f32>
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) world_pos: vec3<f32>,
+    @location(2) normal: vec3<f32>,
+    // negative on the side of the camera's clip plane that isn't drawn
+    @location(3) clip: f32,
+    // the object's origin, or the instance's for instanced objects
+    @location(4) origin: vec3<f32>,
+    @location(5) tint: vec4<f32>,
 };
 
-@vertex
-fn vs_main(in: VertexInput, instance: InstanceInput) -> VertexOutput {
+// object space to clip space, through the model matrix and then the instance's
+fn transform(position: vec4<f32>, normal: vec4<f32>, tex_coords: vec2<f32>, instance: u32) -> VertexOutput {
     var out: VertexOutput;
-    let m = mat4x4<f32>(
-        instance.model_matrix_0,
-        instance.model_matrix_1,
-        instance.model_matrix_2,
-        instance.model_matrix_3,
-    );
-
-    if is_instanced == 1 {
-        out.clip_position = camera.view_proj * m * model.model * vec4<f32>(in.position, 1.0);
-    } else if is_instanced == 0 {
-        out.clip_position = camera.view_proj * model.model * vec4<f32>(in.position, 1.0);
+    var model = transforms[0].model;
+    if (draw.flags & DRAW_OWN_MODEL) != 0u {
+        model = draw.model;
     }
+    let m = transforms[instance + 1u].model * model;
+
+    let world_pos = m * position;
+    // the cofactor matrix, the inverse transpose up to a scale, so normals stay square to surfaces
+    // that are stretched more one way than another
+    let normal_matrix = mat3x3<f32>(cross(m[1].xyz, m[2].xyz), cross(m[2].xyz, m[0].xyz), cross(m[0].xyz, m[1].xyz));
+    let world_normal = normal_matrix * normal.xyz;
+    out.origin = m[3].xyz;
+    out.clip_position = camera.view_proj * world_pos;
+    out.world_pos = world_pos.xyz;
+    out.normal = world_normal;
+    out.clip = dot(camera.clip_plane, vec4<f32>(world_pos.xyz, 1.0));
 
-    out.tex_coords = in.tex_coords;
+    out.tex_coords = tex_coords;
+    out.tint = transforms[instance + 1u].tint;
     return out;
 }
 
-@group(0) @binding(3)
-var tex_diffuse: texture_2d<f32>;
+@vertex
+fn vs_main(in: VertexInput, @builtin(instance_index) instance: u32) -> VertexOutput {
+    return transform(vec4<f32>(in.position, 1.0), vec4<f32>(in.normal, 0.0), in.tex_coords, instance);
+}
+
+// see SkinnedVertex in graphics.rs
+struct SkinnedVertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(6) normal: vec3<f32>,
+    @location(7) joints: vec4<u32>,
+    @location(8) weights: vec4<f32>,
+}
+
+// each joint's current transform times its inverse bind matrix, written every frame by skinning.rs
+@group(1) @binding(0)
+var<storage, read> bones: array<mat4x4<f32>>;
+
+@vertex
+fn vs_skinned(in: SkinnedVertexInput, @builtin(instance_index) instance: u32) -> VertexOutput {
+    let skin = bones[in.joints.x] * in.weights.x
+        + bones[in.joints.y] * in.weights.y
+        + bones[in.joints.z] * in.weights.z
+        + bones[in.joints.w] * in.weights.w;
+    return transform(skin * vec4<f32>(in.position, 1.0), skin * vec4<f32>(in.normal, 0.0), in.tex_coords, instance);
+}
+
+// see morph.rs
+struct MorphUniform {
+    weights: array<vec4<f32>, 2>,
+    // x is the vertex count, y the target count
+    counts: vec4<u32>,
+}
+
+// for every target, a position delta and a normal delta per vertex
+@group(1) @binding(0)
+var<storage, read> morph_deltas: array<vec4<f32>>;
+
+@group(1) @binding(1)
+var<uniform> morph: MorphUniform;
+
+@vertex
+fn vs_morph(
+    @builtin(vertex_index) index: u32,
+    @builtin(instance_index) instance: u32,
+    in: VertexInput,
+) -> VertexOutput {
+    var position = in.position;
+    var normal = in.normal;
+    for (var i = 0u; i < morph.counts.y; i = i + 1u) {
+        let weight = morph.weights[i / 4u][i % 4u];
+        let delta = (i * morph.counts.x + index) * 2u;
+        position = position + morph_deltas[delta].xyz * weight;
+        normal = normal + morph_deltas[delta + 1u].xyz * weight;
+    }
+    return transform(vec4<f32>(position, 1.0), vec4<f32>(normal, 0.0), in.tex_coords, instance);
+}
+
 @group(0) @binding(4)
-var tex_sampler: sampler; 
+var tex_diffuse: texture_2d<f32>;
+@group(0) @binding(5)
+var tex_sampler: sampler;
+
+// meshes without normals use the flat face normal from the screen space derivatives,
+// turned towards the camera since faces are drawn from both sides
+fn surface_normal(in: VertexOutput) -> vec3<f32> {
+    var normal = normalize(cross(dpdx(in.world_pos), dpdy(in.world_pos)));
+    if dot(normal, light.camera_pos.xyz - in.world_pos) < 0.0 {
+        normal = -normal;
+    }
+    if dot(in.normal, in.normal) > 0.0 {
+        normal = normalize(in.normal);
+    }
+    return normal;
+}
+
+// a soaked floor is darker and picks up sharp highlights of the sun and lights
+let WET_DARKENING: f32 = 0.45;
+let WET_SHININESS: f32 = 96.0;
+let WET_SPECULAR: f32 = 0.9;
+
+// the floor is the only surface drawn flat at its height, nothing else gets wet
+fn floor_wetness(in: VertexOutput, normal: vec3<f32>) -> f32 {
+    if normal.y < 0.99 || abs(in.world_pos.y - light.wet_floor.y) > 0.01 {
+        return 0.0;
+    }
+    return light.wet_floor.x;
+}
+
+fn highlight(normal: vec3<f32>, view_dir: vec3<f32>, to_light: vec3<f32>) -> f32 {
+    return pow(max(dot(normal, normalize(view_dir + to_light)), 0.0), WET_SHININESS) * WET_SPECULAR;
+}
+
+// the lit color before gamma correction
+fn shade(in: VertexOutput) -> vec4<f32> {
+    var color = textureSample(tex_diffuse, tex_sampler, in.tex_coords) * in.tint;
+    let normal = surface_normal(in);
+    // after the derivatives, they need every fragment around this one
+    if in.clip < 0.0 {
+        discard;
+    }
+    let wet = floor_wetness(in, normal);
+    let view_dir = normalize(light.camera_pos.xyz - in.world_pos);
+    var diffuse = max(dot(normal, light.sun_dir.xyz), 0.0) * light.sun_color.rgb;
+    var specular = highlight(normal, view_dir, light.sun_dir.xyz) * light.sun_color.rgb;
+
+    for (var i = 0u; i < light.light_count.x; i = i + 1u) {
+        let l = lights[i];
+        let to_light = l.position.xyz - in.world_pos;
+        let dist = length(to_light);
+        let dir = to_light / dist;
+        let falloff = clamp(1.0 - dist / l.color.w, 0.0, 1.0);
+        var reach = falloff * falloff;
+        if l.position.w == 1.0 {
+            reach = reach * smoothstep(l.direction.w, l.params.x, dot(-dir, l.direction.xyz));
+        }
+        diffuse = diffuse + l.color.rgb * max(dot(normal, dir), 0.0) * reach;
+        specular = specular + l.color.rgb * highlight(normal, view_dir, dir) * reach;
+    }
+    let albedo = color.rgb * mix(1.0, WET_DARKENING, wet);
+    color = vec4<f32>(albedo * (light.ambient.rgb + diffuse) + specular * wet, color.a);
+
+    // cases are ViewMode in graphics.rs, Lit keeps the shaded color
+    switch light.light_count.y {
+        case 1u: {
+            color = vec4<f32>(normal * 0.5 + 0.5, 1.0);
+        }
+        case 2u: {
+            let depth = distance(in.world_pos, light.camera_pos.xyz) / DEPTH_VIEW_RANGE;
+            color = vec4<f32>(vec3<f32>(1.0 - clamp(depth, 0.0, 1.0)), 1.0);
+        }
+        case 3u: {
+            color = vec4<f32>(fract(in.tex_coords), 0.0, 1.0);
+        }
+        case 4u: {
+            // added up by the overdraw pipeline, red saturates after ten layers
+            color = vec4<f32>(0.1, 0.04, 0.02, 1.0);
+        }
+        default: {}
+    }
+    return color;
+}
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(tex_diffuse, tex_sampler, in.tex_coords);
-}
\ No newline at end of file
+    var color = shade(in);
+    color = vec4<f32>(apply_output_gamma(color.rgb), color.a);
+    return color;
+}
+
+// see reflection.rs, the scene drawn from below the floor with the same projection
+@group(1) @binding(0)
+var reflection_tex: texture_2d<f32>;
+@group(1) @binding(1)
+var reflection_sampler: sampler;
+
+// how much is mirrored looking straight down, it goes up to all of it at grazing angles
+let REFLECTANCE: f32 = 0.1;
+
+@fragment
+fn fs_reflective(in: VertexOutput) -> @location(0) vec4<f32> {
+    // the mirrored scene lines up with this one pixel for pixel
+    let uv = in.clip_position.xy / vec2<f32>(textureDimensions(reflection_tex));
+    let reflected = remove_output_gamma(textureSample(reflection_tex, reflection_sampler, uv).rgb);
+    var color = shade(in);
+
+    // schlick's approximation of the fresnel term, against the floor's normal
+    let view_dir = normalize(light.camera_pos.xyz - in.world_pos);
+    let fresnel = REFLECTANCE + (1.0 - REFLECTANCE) * pow(1.0 - abs(view_dir.y), 5.0);
+    color = vec4<f32>(mix(color.rgb, reflected, fresnel), color.a);
+    color = vec4<f32>(apply_output_gamma(color.rgb), color.a);
+    return color;
+}
+
+// the edges drawn over the filled scene by the wireframe overlay
+@fragment
+fn fs_wireframe(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+}
+
+// see sky.rs, the sky baked into a cubemap
+@group(1) @binding(0)
+var environment: texture_cube<f32>;
+@group(1) @binding(1)
+var environment_sampler: sampler;
+
+// how much of the surface's own lit color shows through the reflection
+let CHROME_TINT: f32 = 0.15;
+
+// polished metal, mostly the sky reflected around the view ray
+@fragment
+fn fs_env_mapped(in: VertexOutput) -> @location(0) vec4<f32> {
+    let normal = surface_normal(in);
+    let view_dir = normalize(in.world_pos - light.camera_pos.xyz);
+    let reflected = textureSample(environment, environment_sampler, reflect(view_dir, normal)).rgb;
+    var color = shade(in);
+    color = vec4<f32>(mix(reflected, color.rgb, CHROME_TINT), color.a);
+    color = vec4<f32>(apply_output_gamma(color.rgb), color.a);
+    return color;
+}
+
+// see emissive.rs
+struct EmissiveUniform {
+    // rgb multiplies the texture, w is pulses per second
+    color: vec4<f32>,
+    // x is the time
+    params: vec4<f32>,
+}
+
+@group(1) @binding(0)
+var<uniform> emissive: EmissiveUniform;
+
+// glows in its own texture tinted by the emissive color, the same however it's lit
+@fragment
+fn fs_emissive(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = textureSample(tex_diffuse, tex_sampler, in.tex_coords);
+    if in.clip < 0.0 {
+        discard;
+    }
+    // from half to full brightness and back, out of step between objects and instances
+    let phase = dot(in.origin, vec3<f32>(0.37, 0.11, 0.23));
+    let pulse = 0.75 + 0.25 * sin((emissive.params.x * emissive.color.w + phase) * 6.2831853);
+    var color = vec4<f32>(texel.rgb * emissive.color.rgb * pulse, texel.a);
+    color = vec4<f32>(apply_output_gamma(color.rgb), color.a);
+    return color;
+}
+
+// see tv.rs, the scene filmed from the tv's camera
+@group(1) @binding(0)
+var screen_tex: texture_2d<f32>;
+@group(1) @binding(1)
+var screen_sampler: sampler;
+
+// the picture is already in the output's format, so it's shown as it is
+@fragment
+fn fs_screen(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = textureSample(screen_tex, screen_sampler, in.tex_coords);
+    if in.clip < 0.0 {
+        discard;
+    }
+    return vec4<f32>(texel.rgb, 1.0);
+}
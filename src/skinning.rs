@@ -0,0 +1,290 @@
+use cgmath::{Matrix4, Quaternion, SquareMatrix, Vector3, VectorSpace};
+
+use crate::graphics::{self, SkinnedVertex};
+
+// a node's transform relative to its parent, the part of the hierarchy animations change
+#[derive(Clone, Copy)]
+struct Pose {
+    translation: Vector3<f32>,
+    rotation: Quaternion<f32>,
+    scale: Vector3<f32>,
+}
+
+impl Pose {
+    fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.translation)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+}
+
+enum Keyframes {
+    Translation(Vec<Vector3<f32>>),
+    Rotation(Vec<Quaternion<f32>>),
+    Scale(Vec<Vector3<f32>>),
+}
+
+// one animated property of one node
+struct Channel {
+    node: usize,
+    times: Vec<f32>,
+    keyframes: Keyframes,
+    // holds each keyframe until the next instead of blending between them
+    step: bool,
+}
+
+impl Channel {
+    fn apply(&self, time: f32, pose: &mut Pose) {
+        // the keyframe at or before time and how far it is towards the next one
+        let next = self.times.partition_point(|&t| t <= time);
+        let (i, j, t) = if next == 0 {
+            (0, 0, 0.0)
+        } else if next == self.times.len() || self.step {
+            (next - 1, next - 1, 0.0)
+        } else {
+            let (start, end) = (self.times[next - 1], self.times[next]);
+            (next - 1, next, (time - start) / (end - start))
+        };
+        match &self.keyframes {
+            Keyframes::Translation(values) => pose.translation = values[i].lerp(values[j], t),
+            Keyframes::Rotation(values) => pose.rotation = values[i].slerp(values[j], t),
+            Keyframes::Scale(values) => pose.scale = values[i].lerp(values[j], t),
+        }
+    }
+}
+
+// a skinned mesh loaded from a binary gltf file, playing its first animation on a loop.
+// the bones are posed on the cpu and the vertices follow them in the vertex shader
+pub struct SkinnedModel {
+    vertices: wgpu::Buffer,
+    indices: wgpu::Buffer,
+    num_indices: u32,
+    // every node in the file, as it is when nothing animates it
+    rest_poses: Vec<Pose>,
+    parents: Vec<Option<usize>>,
+    // the nodes with parents before their children
+    order: Vec<usize>,
+    // the nodes acting as the skin's joints, in the order the vertices refer to them
+    joints: Vec<usize>,
+    inverse_bind_matrices: Vec<Matrix4<f32>>,
+    channels: Vec<Channel>,
+    // seconds in one loop of the animation
+    duration: f32,
+    bones_buffer: wgpu::Buffer,
+    bones_bind_group: wgpu::BindGroup,
+    pub transforms_buf: wgpu::Buffer,
+}
+
+impl SkinnedModel {
+    pub fn from_glb(
+        device: &wgpu::Device,
+        bones_layout: &wgpu::BindGroupLayout,
+        bytes: &[u8],
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let gltf = gltf::Gltf::from_slice(bytes)?;
+        // only the buffer embedded in the .glb itself, there's no filesystem on the web
+        let blob = gltf.blob.as_deref();
+        let get_buffer = |buffer: gltf::Buffer| match buffer.source() {
+            gltf::buffer::Source::Bin => blob,
+            gltf::buffer::Source::Uri(_) => None,
+        };
+
+        let (mesh, skin) = gltf
+            .nodes()
+            .find_map(|node| node.mesh().zip(node.skin()))
+            .ok_or("no skinned mesh in the file")?;
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for primitive in mesh.primitives() {
+            let reader = primitive.reader(get_buffer);
+            let base = vertices.len() as u32;
+            let positions = reader.read_positions().ok_or("mesh has no positions")?;
+            let joints = reader.read_joints(0).ok_or("mesh has no joints")?.into_u16();
+            let weights = reader.read_weights(0).ok_or("mesh has no weights")?.into_f32();
+            vertices.extend(positions.zip(joints).zip(weights).map(|((position, joints), weights)| SkinnedVertex {
+                position,
+                joints,
+                weights,
+                ..Default::default()
+            }));
+            let primitive_vertices = &mut vertices[base as usize..];
+            if let Some(normals) = reader.read_normals() {
+                primitive_vertices.iter_mut().zip(normals).for_each(|(v, normal)| v.normal = normal);
+            }
+            if let Some(tex_coords) = reader.read_tex_coords(0) {
+                primitive_vertices.iter_mut().zip(tex_coords.into_f32()).for_each(|(v, uv)| v.tex_coords = uv);
+            }
+            match reader.read_indices() {
+                Some(read) => indices.extend(read.into_u32().map(|i| base + i)),
+                None => indices.extend(base..vertices.len() as u32),
+            }
+        }
+
+        let mut rest_poses = Vec::new();
+        let mut parents = vec![None; gltf.nodes().len()];
+        for node in gltf.nodes() {
+            let (translation, rotation, scale) = node.transform().decomposed();
+            rest_poses.push(Pose {
+                translation: translation.into(),
+                rotation: Quaternion::new(rotation[3], rotation[0], rotation[1], rotation[2]),
+                scale: scale.into(),
+            });
+            for child in node.children() {
+                parents[child.index()] = Some(node.index());
+            }
+        }
+        // breadth first from the roots, so every global transform can be built on its parent's
+        let mut order: Vec<usize> = (0..parents.len()).filter(|&i| parents[i].is_none()).collect();
+        let mut next = 0;
+        while next < order.len() {
+            // a node reached twice isn't part of a tree, and could be reached forever
+            if order.len() > parents.len() {
+                return Err("the nodes don't form a tree".into());
+            }
+            let node = gltf.nodes().nth(order[next]).ok_or("a child node is missing")?;
+            order.extend(node.children().map(|child| child.index()));
+            next += 1;
+        }
+
+        let joints: Vec<usize> = skin.joints().map(|joint| joint.index()).collect();
+        let inverse_bind_matrices = match skin.reader(get_buffer).read_inverse_bind_matrices() {
+            Some(matrices) => matrices.map(Matrix4::from).collect(),
+            None => vec![Matrix4::identity(); joints.len()],
+        };
+
+        let channels: Vec<Channel> = gltf
+            .animations()
+            .next()
+            .map(|animation| {
+                animation
+                    .channels()
+                    .filter_map(|channel| {
+                        let reader = channel.reader(get_buffer);
+                        let times: Vec<f32> = reader.read_inputs()?.collect();
+                        let cubic = channel.sampler().interpolation() == gltf::animation::Interpolation::CubicSpline;
+                        let keyframes = match reader.read_outputs()? {
+                            gltf::animation::util::ReadOutputs::Translations(t) => {
+                                Keyframes::Translation(spline_values(t.map(Vector3::from).collect(), cubic))
+                            }
+                            gltf::animation::util::ReadOutputs::Rotations(r) => Keyframes::Rotation(spline_values(
+                                r.into_f32().map(|[x, y, z, w]| Quaternion::new(w, x, y, z)).collect(),
+                                cubic,
+                            )),
+                            gltf::animation::util::ReadOutputs::Scales(s) => {
+                                Keyframes::Scale(spline_values(s.map(Vector3::from).collect(), cubic))
+                            }
+                            gltf::animation::util::ReadOutputs::MorphTargetWeights(_) => return None,
+                        };
+                        Some(Channel {
+                            node: channel.target().node().index(),
+                            times,
+                            keyframes,
+                            step: channel.sampler().interpolation() == gltf::animation::Interpolation::Step,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let duration = channels.iter().filter_map(|channel| channel.times.last()).fold(0.0f32, |a, &b| a.max(b));
+
+        let bones_buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some("skinned_bones"),
+            size: (std::mem::size_of::<[[f32; 4]; 4]>() * joints.len().max(1)) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bones_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("skinned_bones_bind_group"),
+            layout: bones_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: bones_buffer.as_entire_binding(),
+            }],
+        });
+
+        Ok(SkinnedModel {
+            vertices: graphics::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+                label: Some("vertices_skinned"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+            indices: graphics::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+                label: Some("indices_skinned"),
+                contents: bytemuck::cast_slice(&indices),
+                usage: wgpu::BufferUsages::INDEX,
+            }),
+            num_indices: indices.len() as u32,
+            rest_poses,
+            parents,
+            order,
+            joints,
+            inverse_bind_matrices,
+            channels,
+            duration,
+            bones_buffer,
+            bones_bind_group,
+            transforms_buf: graphics::create_transforms_buffer(device, "transforms_skinned", Matrix4::identity()),
+        })
+    }
+
+    // poses the skeleton as it is time seconds into the animation and uploads the bones
+    pub fn update(&self, queue: &wgpu::Queue, time: f32) {
+        let time = if self.duration > 0.0 { time.rem_euclid(self.duration) } else { 0.0 };
+        let mut poses = self.rest_poses.clone();
+        for channel in &self.channels {
+            channel.apply(time, &mut poses[channel.node]);
+        }
+
+        let mut globals = vec![Matrix4::identity(); poses.len()];
+        for &node in &self.order {
+            let local = poses[node].matrix();
+            globals[node] = match self.parents[node] {
+                Some(parent) => globals[parent] * local,
+                None => local,
+            };
+        }
+        let bones: Vec<[[f32; 4]; 4]> = self
+            .joints
+            .iter()
+            .zip(&self.inverse_bind_matrices)
+            .map(|(&joint, inverse_bind)| (globals[joint] * inverse_bind).into())
+            .collect();
+        queue.write_buffer(&self.bones_buffer, 0, bytemuck::cast_slice(&bones));
+    }
+
+    // expects the skinned pipeline and the model's bind group to be set already
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_bind_group(1, &self.bones_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertices.slice(..));
+        render_pass.set_index_buffer(self.indices.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+    }
+}
+
+// cubic splines store an in and out tangent around every value, only the values are kept
+// and blended linearly like everything else
+fn spline_values<T>(all: Vec<T>, cubic: bool) -> Vec<T> {
+    if cubic {
+        all.into_iter().skip(1).step_by(3).collect()
+    } else {
+        all
+    }
+}
+
+// the second bind group of the skinned pipeline, after the one every object has
+pub fn build_bones_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("bones_bind_group_layout"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    })
+}
@@ -0,0 +1,569 @@
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector2, Vector3};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::graphics::{self, ViewMode};
+
+// uniform for the main shader's lighting
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightUniform {
+    // direction towards the sun
+    sun_dir: [f32; 4],
+    sun_color: [f32; 4],
+    ambient: [f32; 4],
+    // for turning face normals towards the viewer
+    camera_pos: [f32; 4],
+    // x is the number of active lights in the light storage buffer, y the view mode
+    light_count: [u32; 4],
+    // x is how wet the floor is, 0 to 1, and y its height
+    wet_floor: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SkyUniform {
+    inv_view_proj: [[f32; 4]; 4],
+    // see Scattering, w of zenith is its strength
+    perez: [[f32; 4]; 5],
+    zenith: [f32; 4],
+    night_zenith: [f32; 4],
+    night_horizon: [f32; 4],
+    sun_dir: [f32; 4],
+    sun_color: [f32; 4],
+    // w is how much of the night sky, the stars and moon, shows
+    moon_dir: [f32; 4],
+    // x and y are the march's steps through the clouds and towards the sun from each, z how many
+    // octaves of noise shape them. 0 steps leaves the clouds out
+    cloud_steps: [u32; 4],
+    // xz is how far the wind has blown the clouds, w the time in seconds for the stars to twinkle by
+    cloud_offset: [f32; 4],
+}
+
+// how finely the clouds are raymarched, they're the sky pass's most expensive part by far
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CloudQuality {
+    Off,
+    Low,
+    Medium,
+    High,
+}
+
+impl CloudQuality {
+    pub const ALL: [CloudQuality; 4] = [CloudQuality::Off, CloudQuality::Low, CloudQuality::Medium, CloudQuality::High];
+
+    // steps through the layer, steps towards the sun from each and octaves of noise
+    fn steps(self) -> [u32; 3] {
+        match self {
+            CloudQuality::Off => [0, 0, 0],
+            CloudQuality::Low => [16, 2, 3],
+            CloudQuality::Medium => [32, 4, 4],
+            CloudQuality::High => [64, 6, 5],
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CloudQuality::Off => "off",
+            CloudQuality::Low => "low",
+            CloudQuality::Medium => "medium",
+            CloudQuality::High => "high",
+        }
+    }
+}
+
+// the daylight sky from preetham's analytic model of sunlight scattered through the atmosphere.
+// each of the sky's luminance and two chromaticities (xyY) is the zenith's value shaped by the
+// perez distribution, five coefficients that depend on how hazy the air is
+struct Scattering {
+    // A to E, each for Y, x and y
+    perez: [[f32; 3]; 5],
+    // the zenith's Y, x and y, already divided by the distribution at the zenith
+    zenith: [f32; 3],
+    // fades the scattered light out as the sun sets
+    strength: f32,
+}
+
+impl Scattering {
+    // the haziness, 2 is a clear sky and 10 a murky one
+    const TURBIDITY: f32 = 3.0;
+    // scales the model's luminance, in kcd/m², down to where the tone mapping keeps the colors
+    const EXPOSURE: f32 = 0.05;
+
+    // no light, at white's chromaticity so nothing divides by zero
+    fn none() -> Self {
+        Scattering {
+            perez: [[0.0; 3]; 5],
+            zenith: [0.0, 1.0 / 3.0, 1.0 / 3.0],
+            strength: 0.0,
+        }
+    }
+
+    fn new(sun_dir: Vector3<f32>) -> Self {
+        let t = Self::TURBIDITY;
+        // the model only covers a sun above the horizon, below it the light just fades out
+        let theta = sun_dir.y.clamp(0.0, 1.0).acos();
+        let chi = (4.0 / 9.0 - t / 120.0) * (std::f32::consts::PI - 2.0 * theta);
+        let luminance = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192) * Self::EXPOSURE;
+        let cubic = |c: [f32; 4]| ((c[0] * theta + c[1]) * theta + c[2]) * theta + c[3];
+        let x = t * t * cubic([0.00166, -0.00375, 0.00209, 0.0])
+            + t * cubic([-0.02903, 0.06377, -0.03202, 0.00394])
+            + cubic([0.11693, -0.21196, 0.06052, 0.25886]);
+        let y = t * t * cubic([0.00275, -0.00610, 0.00317, 0.0])
+            + t * cubic([-0.04214, 0.08970, -0.04153, 0.00516])
+            + cubic([0.15346, -0.26756, 0.06670, 0.26688]);
+
+        let perez = [
+            [0.1787 * t - 1.4630, -0.0193 * t - 0.2592, -0.0167 * t - 0.2608],
+            [-0.3554 * t + 0.4275, -0.0665 * t + 0.0008, -0.0950 * t + 0.0092],
+            [-0.0227 * t + 5.3251, -0.0004 * t + 0.2125, -0.0079 * t + 0.2102],
+            [0.1206 * t - 2.5771, -0.0641 * t - 0.8989, -0.0441 * t - 1.6537],
+            [-0.0670 * t + 0.3703, -0.0033 * t + 0.0452, -0.0109 * t + 0.0529],
+        ];
+        let at_zenith = distribution(&perez, 1.0, theta);
+        let zenith = [luminance, x, y];
+        Scattering {
+            perez,
+            zenith: [0, 1, 2].map(|i| zenith[i] / at_zenith[i]),
+            strength: smoothstep(-0.1, 0.05, sun_dir.y),
+        }
+    }
+
+    // the same as scattering in sky.wgsl, linear rgb for dir above the horizon
+    fn color(&self, dir: Vector3<f32>, sun_dir: Vector3<f32>) -> [f32; 3] {
+        let gamma = dir.dot(sun_dir).clamp(-1.0, 1.0).acos();
+        let f = distribution(&self.perez, dir.y.max(0.001), gamma);
+        let [luminance, x, y] = [0, 1, 2].map(|i| self.zenith[i] * f[i]);
+        let [r, g, b] = xyy_to_rgb(luminance, x, y);
+        [r, g, b].map(|c| (1.0 - (-c.max(0.0)).exp()) * self.strength)
+    }
+}
+
+// the perez distribution for a direction theta from the zenith and gamma from the sun
+fn distribution(perez: &[[f32; 3]; 5], cos_theta: f32, gamma: f32) -> [f32; 3] {
+    let [a, b, c, d, e] = perez;
+    [0, 1, 2].map(|i| {
+        let near_horizon = 1.0 + a[i] * (b[i] / cos_theta).exp();
+        let around_sun = 1.0 + c[i] * (d[i] * gamma).exp() + e[i] * gamma.cos().powi(2);
+        near_horizon * around_sun
+    })
+}
+
+fn xyy_to_rgb(luminance: f32, x: f32, y: f32) -> [f32; 3] {
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    [
+        3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z,
+        -0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z,
+        0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z,
+    ]
+}
+
+// lighting and sky colors for one moment of the day
+pub struct Sun {
+    dir: Vector3<f32>,
+    color: [f32; 3],
+    ambient: [f32; 3],
+    scattering: Scattering,
+    // opposite the sun across the sky
+    moon_dir: Vector3<f32>,
+    // 0 by day, 1 once the sun is well below the horizon
+    night: f32,
+}
+
+impl Sun {
+    // what's left of the sky once the scattered light is gone
+    const NIGHT_ZENITH: [f32; 3] = [0.005, 0.007, 0.02];
+    const NIGHT_HORIZON: [f32; 3] = [0.02, 0.025, 0.05];
+    const DAY_SUN: [f32; 3] = [1.0, 0.95, 0.85];
+    const SUNSET_SUN: [f32; 3] = [1.0, 0.45, 0.15];
+    const NIGHT_AMBIENT: [f32; 3] = [0.05, 0.06, 0.1];
+    const DAY_AMBIENT: [f32; 3] = [0.35, 0.38, 0.45];
+
+    // no sun and full ambient, which leaves textures exactly as they are
+    pub fn unlit() -> Self {
+        Sun {
+            dir: Vector3::unit_y(),
+            color: [0.0; 3],
+            ambient: [1.0; 3],
+            scattering: Scattering::none(),
+            moon_dir: -Vector3::unit_y(),
+            night: 0.0,
+        }
+    }
+
+    // time_of_day goes from 0 to 1 over a full day, midnight at 0 and noon at 0.5
+    pub fn at(time_of_day: f32) -> Self {
+        let angle = time_of_day * std::f32::consts::TAU - std::f32::consts::FRAC_PI_2;
+        // rises in +x, tilted off the zenith so noon shadows aren't straight down
+        let dir = Vector3::new(-angle.cos(), angle.sin(), 0.4).normalize();
+        let moon_dir = Vector3::new(angle.cos(), -angle.sin(), 0.4).normalize();
+
+        let daylight = smoothstep(-0.2, 0.3, dir.y);
+        // strongest while the sun is near the horizon
+        let sunset = 1.0 - smoothstep(0.0, 0.35, dir.y.abs());
+        let sun_strength = smoothstep(-0.05, 0.15, dir.y);
+
+        let sun_color = mix(Self::DAY_SUN, Self::SUNSET_SUN, sunset);
+
+        Sun {
+            dir,
+            color: sun_color.map(|c| c * sun_strength),
+            ambient: mix(Self::NIGHT_AMBIENT, Self::DAY_AMBIENT, daylight),
+            scattering: Scattering::new(dir),
+            moon_dir,
+            // the stars come out as the sun sets
+            night: 1.0 - smoothstep(-0.2, 0.05, dir.y),
+        }
+    }
+
+    // below the horizon, an unlit scene's sun never is
+    #[cfg_attr(not(feature = "audio"), allow(dead_code))]
+    pub fn is_down(&self) -> bool {
+        self.dir.y < 0.0
+    }
+
+    // black when there's no sun, or it's set
+    pub fn color(&self) -> [f32; 3] {
+        self.color
+    }
+
+    pub fn ambient(&self) -> [f32; 3] {
+        self.ambient
+    }
+
+    // where the sun is in ndc, None while it's behind the camera. as a direction it's infinitely
+    // far away, which only a perspective projection can place
+    pub fn screen_position(&self, view_proj: Matrix4<f32>) -> Option<Vector2<f32>> {
+        let clip = view_proj * self.dir.extend(0.0);
+        (clip.w > f32::EPSILON).then(|| Vector2::new(clip.x / clip.w, clip.y / clip.w))
+    }
+
+    // the same as fs_main in sky.wgsl short of the clouds, stars and moon, dir is normalized
+    fn sky_color(&self, dir: Vector3<f32>) -> [f32; 3] {
+        // below the horizon is the horizon darkened towards the ground
+        let above = Vector3::new(dir.x, dir.y.max(0.0), dir.z).normalize();
+        let night = mix(Self::NIGHT_HORIZON, Self::NIGHT_ZENITH, above.y.sqrt());
+        let scattered = self.scattering.color(above, self.dir);
+        let color = [0, 1, 2].map(|i| night[i] + scattered[i]);
+        let color = mix(color, color.map(|c| c * 0.3), (-dir.y * 4.0).clamp(0.0, 1.0));
+        let sun = dir.dot(self.dir).max(0.0);
+        let glow = sun.powf(800.0) * 8.0 + sun.powf(12.0) * 0.25;
+        [0, 1, 2].map(|i| color[i] + self.color[i] * glow)
+    }
+
+    pub fn light_uniform(
+        &self,
+        camera_pos: cgmath::Point3<f32>,
+        light_count: u32,
+        view_mode: ViewMode,
+        wet_floor: [f32; 2],
+    ) -> LightUniform {
+        let [wetness, floor_y] = wet_floor;
+        LightUniform {
+            sun_dir: self.dir.extend(0.0).into(),
+            sun_color: extend(self.color),
+            ambient: extend(self.ambient),
+            camera_pos: [camera_pos.x, camera_pos.y, camera_pos.z, 1.0],
+            light_count: [light_count, view_mode.index(), 0, 0],
+            wet_floor: [wetness, floor_y, 0.0, 0.0],
+        }
+    }
+}
+
+// the scattered daylight over a dim night gradient, with a sun disc, stars and a moon at night and
+// raymarched clouds, drawn behind the scene. the same sky is baked into a small cubemap for shiny
+// materials to reflect, without the clouds, marching them on the cpu every frame would be far too
+// slow, and without the stars and moon, which are too small to survive the cubemap's resolution
+pub struct SkyRenderer {
+    cloud_quality: CloudQuality,
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    environment: wgpu::Texture,
+    environment_bind_group: wgpu::BindGroup,
+}
+
+impl SkyRenderer {
+    // texels along each side of a cubemap face
+    const ENVIRONMENT_SIZE: u32 = 64;
+    // what unlit scenes reflect, since they have no sky of their own
+    const DEFAULT_TIME_OF_DAY: f32 = 0.4;
+    // world units per second the clouds drift along x and z
+    const WIND: [f32; 2] = [6.0, 2.5];
+
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        environment_layout: &wgpu::BindGroupLayout,
+    ) -> Result<Self, AppError> {
+        let shader = graphics::create_templated_shader(device, format, "sky.wgsl", include_str!("sky.wgsl"));
+
+        let uniform_buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some("sky_uniform_buffer"),
+            size: std::mem::size_of::<SkyUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("sky_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // the moon's surface
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = graphics::build_bind_group(
+            &bind_group_layout,
+            include_bytes!("../res/tex/moon.png"),
+            "sky_bind_group",
+            device,
+            queue,
+            vec![uniform_buffer.as_entire_buffer_binding()],
+        )?;
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("sky_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("sky_pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // shares the scene's depth buffer without touching it, everything else draws over the sky
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: graphics::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: graphics::SAMPLE_COUNT,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let environment = graphics::create_texture(device, &wgpu::TextureDescriptor {
+            label: Some("sky_environment"),
+            size: wgpu::Extent3d {
+                width: Self::ENVIRONMENT_SIZE,
+                height: Self::ENVIRONMENT_SIZE,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+        let environment_view = environment.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let environment_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("sky_environment_sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let environment_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("sky_environment_bind_group"),
+            layout: environment_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&environment_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&environment_sampler),
+                },
+            ],
+        });
+
+        let sky = SkyRenderer {
+            cloud_quality: CloudQuality::Medium,
+            pipeline,
+            uniform_buffer,
+            bind_group,
+            environment,
+            environment_bind_group,
+        };
+        sky.bake_environment(queue, &Sun::at(Self::DEFAULT_TIME_OF_DAY));
+        Ok(sky)
+    }
+
+    pub fn cloud_quality(&self) -> CloudQuality {
+        self.cloud_quality
+    }
+
+    pub fn set_cloud_quality(&mut self, quality: CloudQuality) {
+        self.cloud_quality = quality;
+    }
+
+    // time is in seconds, for how far the wind has moved the clouds
+    pub fn update(&self, queue: &wgpu::Queue, view_proj: Matrix4<f32>, sun: &Sun, time: f32) {
+        let scattering = &sun.scattering;
+        let [luminance, x, y] = scattering.zenith;
+        let [steps, light_steps, octaves] = self.cloud_quality.steps();
+        let uniform = SkyUniform {
+            inv_view_proj: view_proj.invert().unwrap_or_else(Matrix4::identity).into(),
+            perez: scattering.perez.map(extend),
+            zenith: [luminance, x, y, scattering.strength],
+            night_zenith: extend(Sun::NIGHT_ZENITH),
+            night_horizon: extend(Sun::NIGHT_HORIZON),
+            sun_dir: sun.dir.extend(0.0).into(),
+            sun_color: extend(sun.color),
+            moon_dir: sun.moon_dir.extend(sun.night).into(),
+            cloud_steps: [steps, light_steps, octaves, 0],
+            cloud_offset: [Self::WIND[0] * time, 0.0, Self::WIND[1] * time, time],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+        self.bake_environment(queue, sun);
+    }
+
+    // fills the cubemap on the cpu, it's small enough to redo every frame as the sun moves
+    fn bake_environment(&self, queue: &wgpu::Queue, sun: &Sun) {
+        let size = Self::ENVIRONMENT_SIZE;
+        let mut texels = Vec::with_capacity((size * size * 6 * 4) as usize);
+        for face in 0..6 {
+            for y in 0..size {
+                for x in 0..size {
+                    // -1 to 1 across the face, v pointing down
+                    let u = (x as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                    let v = (y as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                    // the faces in the order +x, -x, +y, -y, +z, -z
+                    let dir = match face {
+                        0 => Vector3::new(1.0, -v, -u),
+                        1 => Vector3::new(-1.0, -v, u),
+                        2 => Vector3::new(u, 1.0, v),
+                        3 => Vector3::new(u, -1.0, -v),
+                        4 => Vector3::new(u, -v, 1.0),
+                        _ => Vector3::new(-u, -v, -1.0),
+                    };
+                    let color = sun.sky_color(dir.normalize());
+                    texels.extend(color.map(|c| (linear_to_srgb(c) * 255.0).round() as u8));
+                    texels.push(255);
+                }
+            }
+        }
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.environment,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &texels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(4 * size),
+                rows_per_image: std::num::NonZeroU32::new(size),
+            },
+            wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 6,
+            },
+        );
+    }
+
+    // the sky as a cubemap, for the env mapped pipeline's second group
+    pub fn environment_bind_group(&self) -> &wgpu::BindGroup {
+        &self.environment_bind_group
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn mix(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t)
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+fn extend(c: [f32; 3]) -> [f32; 4] {
+    [c[0], c[1], c[2], 1.0]
+}
+
+// the second bind group of the env mapped pipeline, after the one every object has
+pub fn build_environment_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("environment_bind_group_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::Cube,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    })
+}
@@ -0,0 +1,243 @@
+let PI: f32 = 3.14159265;
+
+// the cloud layer's world heights, the camera can be under it, in it or over it
+let CLOUD_BOTTOM: f32 = 250.0;
+let CLOUD_TOP: f32 = 450.0;
+// clouds further away than this are left out, and fade out on their way there
+let CLOUD_DISTANCE: f32 = 6000.0;
+// the noise's features per world unit
+let CLOUD_SCALE: f32 = 0.004;
+// the part of the sky the clouds cover, 0 to 1
+let CLOUD_COVERAGE: f32 = 0.45;
+// how much light a unit of the densest cloud blocks per world unit
+let CLOUD_EXTINCTION: f32 = 0.04;
+// how far towards the sun the light steps reach
+let CLOUD_LIGHT_DISTANCE: f32 = 200.0;
+// cells per unit of direction the stars are scattered over, one star at most in each
+let STAR_GRID: f32 = 150.0;
+// the part of the cells that have a star
+let STAR_CHANCE: f32 = 0.3;
+// the moon's radius in radians, several times the real one so it reads at this resolution
+let MOON_RADIUS: f32 = 0.04;
+
+struct SkyUniform {
+    inv_view_proj: mat4x4<f32>,
+    // the perez distribution's A to E, each for Y, x and y (see Scattering in sky.rs)
+    perez: array<vec4<f32>, 5>,
+    // the zenith's Y, x and y divided by the distribution there, w fades the scattered light out
+    zenith: vec4<f32>,
+    night_zenith: vec4<f32>,
+    night_horizon: vec4<f32>,
+    // direction towards the sun
+    sun_dir: vec4<f32>,
+    sun_color: vec4<f32>,
+    // w is how much the stars and moon show
+    moon_dir: vec4<f32>,
+    // steps through the layer, steps towards the sun and octaves of noise, see CloudQuality
+    cloud_steps: vec4<u32>,
+    // how far the wind has blown the clouds, w is the time in seconds
+    cloud_offset: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> sky: SkyUniform;
+
+@group(0) @binding(1)
+var moon_tex: texture_2d<f32>;
+
+@group(0) @binding(2)
+var moon_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+};
+
+fn distribution(cos_theta: f32, gamma: f32) -> vec3<f32> {
+    let p = sky.perez;
+    let near_horizon = 1.0 + p[0].xyz * exp(p[1].xyz / cos_theta);
+    let around_sun = 1.0 + p[2].xyz * exp(p[3].xyz * gamma) + p[4].xyz * cos(gamma) * cos(gamma);
+    return near_horizon * around_sun;
+}
+
+// preetham's daylight in linear rgb for dir above the horizon, tone mapped so the bright sky
+// around the sun rolls off instead of clipping
+fn scattering(dir: vec3<f32>) -> vec3<f32> {
+    let gamma = acos(clamp(dot(dir, sky.sun_dir.xyz), -1.0, 1.0));
+    let yxy = sky.zenith.xyz * distribution(max(dir.y, 0.001), gamma);
+    let xyz = vec3<f32>(yxy.y / yxy.z * yxy.x, yxy.x, (1.0 - yxy.y - yxy.z) / yxy.z * yxy.x);
+    let rgb = mat3x3<f32>(
+        vec3<f32>(3.2406, -0.9689, 0.0557),
+        vec3<f32>(-1.5372, 1.8758, -0.2040),
+        vec3<f32>(-0.4986, 0.0415, 1.0570),
+    ) * xyz;
+    return (1.0 - exp(-max(rgb, vec3<f32>(0.0)))) * sky.zenith.w;
+}
+
+fn hash(p: vec3<f32>) -> f32 {
+    return fract(sin(dot(p, vec3<f32>(127.1, 311.7, 74.7))) * 43758.5453);
+}
+
+// smoothly blended random values at the corners of a unit grid, 0 to 1
+fn value_noise(p: vec3<f32>) -> f32 {
+    let i = floor(p);
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+    let bottom = mix(
+        mix(hash(i), hash(i + vec3<f32>(1.0, 0.0, 0.0)), u.x),
+        mix(hash(i + vec3<f32>(0.0, 1.0, 0.0)), hash(i + vec3<f32>(1.0, 1.0, 0.0)), u.x),
+        u.y,
+    );
+    let top = mix(
+        mix(hash(i + vec3<f32>(0.0, 0.0, 1.0)), hash(i + vec3<f32>(1.0, 0.0, 1.0)), u.x),
+        mix(hash(i + vec3<f32>(0.0, 1.0, 1.0)), hash(i + vec3<f32>(1.0, 1.0, 1.0)), u.x),
+        u.y,
+    );
+    return mix(bottom, top, u.z);
+}
+
+// 0 for clear air, thickening towards the middle of the layer and thinning towards its edges
+fn cloud_density(p: vec3<f32>) -> f32 {
+    let height = (p.y - CLOUD_BOTTOM) / (CLOUD_TOP - CLOUD_BOTTOM);
+    let profile = smoothstep(0.0, 0.2, height) * (1.0 - smoothstep(0.6, 1.0, height));
+
+    var q = (p + sky.cloud_offset.xyz) * CLOUD_SCALE;
+    var noise = 0.0;
+    var amplitude = 0.5;
+    for (var i = 0u; i < sky.cloud_steps.z; i++) {
+        noise += value_noise(q) * amplitude;
+        q *= 2.03;
+        amplitude *= 0.5;
+    }
+    return max(noise * profile - (1.0 - CLOUD_COVERAGE), 0.0) * 4.0;
+}
+
+// how much of the sunlight gets through the cloud between p and the sun
+fn cloud_shadow(p: vec3<f32>) -> f32 {
+    let step = CLOUD_LIGHT_DISTANCE / f32(sky.cloud_steps.y);
+    var density = 0.0;
+    for (var i = 0u; i < sky.cloud_steps.y; i++) {
+        density += cloud_density(p + sky.sun_dir.xyz * (f32(i) + 0.5) * step);
+    }
+    return exp(-density * CLOUD_EXTINCTION * step);
+}
+
+// a point of light in some of the cells of a grid over the directions, each twinkling at its own
+// pace. the dimmer stars are far more common than the bright ones
+fn stars(dir: vec3<f32>) -> vec3<f32> {
+    let p = dir * STAR_GRID;
+    let cell = floor(p);
+    let chance = hash(cell + vec3<f32>(17.0, 31.0, 5.0));
+    if chance > STAR_CHANCE {
+        return vec3<f32>(0.0);
+    }
+    let center = vec3<f32>(hash(cell), hash(cell + vec3<f32>(3.0, 0.0, 0.0)), hash(cell + vec3<f32>(0.0, 7.0, 0.0)));
+    let glow = 1.0 - smoothstep(0.0, 0.2, length(fract(p) - (center * 0.6 + 0.2)));
+    let brightness = pow(chance / STAR_CHANCE, 4.0) * 2.0 + 0.1;
+    let twinkle = 0.75 + 0.25 * sin(sky.cloud_offset.w * (2.0 + chance * 10.0) + center.x * 40.0);
+    // a little warmer or bluer than white
+    let tint = mix(vec3<f32>(1.0, 0.85, 0.7), vec3<f32>(0.75, 0.85, 1.0), center.z);
+    return tint * glow * brightness * twinkle;
+}
+
+// the moon's color where dir crosses its disc in rgb, and in w how much of the disc covers it
+fn moon(dir: vec3<f32>) -> vec4<f32> {
+    let to_moon = sky.moon_dir.xyz;
+    let right = normalize(cross(to_moon, vec3<f32>(0.0, 1.0, 0.0)));
+    let up = cross(right, to_moon);
+    // -1 to 1 across the disc, like a billboard facing the camera from infinitely far away
+    let disc = vec2<f32>(dot(dir, right), -dot(dir, up)) / MOON_RADIUS;
+    let r = length(disc);
+    // sampled everywhere, only the disc keeps it
+    let surface = textureSampleLevel(moon_tex, moon_sampler, disc * 0.5 + 0.5, 0.0).rgb;
+    let cover = (1.0 - smoothstep(0.95, 1.0, r)) * step(0.0, dot(dir, to_moon));
+    // darker towards the edge, the way a sphere lit from the front is
+    let limb = mix(0.55, 1.0, sqrt(max(1.0 - r * r, 0.0)));
+    return vec4<f32>(surface * limb * 1.2, cover);
+}
+
+// henyey-greenstein, more light scattered forwards the closer g is to 1
+fn phase(cos_angle: f32, g: f32) -> f32 {
+    let g2 = g * g;
+    return (1.0 - g2) / (4.0 * PI * pow(1.0 + g2 - 2.0 * g * cos_angle, 1.5));
+}
+
+// the light the clouds along the ray scatter towards the camera in rgb, and in w how much of
+// what's behind them still shows through. noise offsets where the steps start per pixel, which
+// trades banding for grain
+fn march_clouds(origin: vec3<f32>, dir: vec3<f32>, noise: f32) -> vec4<f32> {
+    if sky.cloud_steps.x == 0u || abs(dir.y) < 0.001 {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    let to_bottom = (CLOUD_BOTTOM - origin.y) / dir.y;
+    let to_top = (CLOUD_TOP - origin.y) / dir.y;
+    let start = max(min(to_bottom, to_top), 0.0);
+    let end = min(max(to_bottom, to_top), CLOUD_DISTANCE);
+    if end <= start {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+
+    let step = (end - start) / f32(sky.cloud_steps.x);
+    let cos_angle = dot(dir, sky.sun_dir.xyz);
+    // a bright rim towards the sun and a little light thrown back away from it
+    let sun_light = sky.sun_color.rgb * (phase(cos_angle, 0.6) + phase(cos_angle, -0.2)) * 2.0 * PI;
+    let ambient = sky.night_zenith.rgb + scattering(vec3<f32>(0.0, 1.0, 0.0));
+
+    var light = vec3<f32>(0.0);
+    var transmittance = 1.0;
+    for (var i = 0u; i < sky.cloud_steps.x; i++) {
+        let p = origin + dir * (start + (f32(i) + noise) * step);
+        let density = cloud_density(p);
+        if density > 0.0 {
+            let absorbed = 1.0 - exp(-density * CLOUD_EXTINCTION * step);
+            light += transmittance * absorbed * (sun_light * cloud_shadow(p) + ambient);
+            transmittance *= 1.0 - absorbed;
+            if transmittance < 0.01 {
+                break;
+            }
+        }
+    }
+
+    // faded towards the horizon, where the steps get too long to show any shape
+    let fade = 1.0 - smoothstep(CLOUD_DISTANCE * 0.5, CLOUD_DISTANCE, start);
+    return vec4<f32>(light * fade, mix(1.0, transmittance, fade));
+}
+
+// a single triangle covering the whole screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    let ndc = uv * 2.0 - 1.0;
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+    out.ndc = ndc;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // view ray through this pixel, from its near plane point to its far plane point
+    let near = sky.inv_view_proj * vec4<f32>(in.ndc, 0.0, 1.0);
+    let far = sky.inv_view_proj * vec4<f32>(in.ndc, 1.0, 1.0);
+    let dir = normalize(far.xyz / far.w - near.xyz / near.w);
+
+    // below the horizon is the horizon darkened towards the ground
+    let above = normalize(vec3<f32>(dir.x, max(dir.y, 0.0), dir.z));
+    var color = mix(sky.night_horizon.rgb, sky.night_zenith.rgb, sqrt(above.y)) + scattering(above);
+    color = mix(color, color * 0.3, clamp(-dir.y * 4.0, 0.0, 1.0));
+
+    let sun = max(dot(dir, sky.sun_dir.xyz), 0.0);
+    color = color + sky.sun_color.rgb * (pow(sun, 800.0) * 8.0 + pow(sun, 12.0) * 0.25);
+
+    // fading into the haze at the horizon
+    let night = sky.moon_dir.w * smoothstep(0.0, 0.15, dir.y);
+    let moon_disc = moon(dir);
+    color += (stars(dir) * (1.0 - moon_disc.w) + moon_disc.rgb * moon_disc.w) * night;
+
+    let clouds = march_clouds(near.xyz / near.w, dir, hash(vec3<f32>(in.clip_position.xy, 0.0)));
+    color = color * clouds.w + clouds.rgb;
+
+    color = apply_output_gamma(color);
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,172 @@
+use cgmath::{EuclideanSpace, InnerSpace, Matrix, Matrix4, Point3, Vector3, Vector4};
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use crate::graphics::Instance;
+
+// the six planes around what a view/projection matrix sees, pointing inwards. built for wgpu's
+// 0 to 1 depth range
+pub struct Frustum {
+    planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    pub fn new(view_proj: Matrix4<f32>) -> Self {
+        let m = view_proj.transpose();
+        let planes = [m.w + m.x, m.w - m.x, m.w + m.y, m.w - m.y, m.z, m.w - m.z]
+            .map(|plane| plane / plane.truncate().magnitude());
+        Frustum { planes }
+    }
+
+    pub fn intersects_sphere(&self, center: Point3<f32>, radius: f32) -> bool {
+        self.planes.iter().all(|plane| plane.truncate().dot(center.to_vec()) + plane.w >= -radius)
+    }
+
+    // true when some of the box could be inside, it's only ever wrong by letting one through
+    pub fn intersects_box(&self, min: Point3<f32>, max: Point3<f32>) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner furthest along the plane's normal
+            let corner = Vector3::new(
+                if plane.x > 0.0 { max.x } else { min.x },
+                if plane.y > 0.0 { max.y } else { min.y },
+                if plane.z > 0.0 { max.z } else { min.z },
+            );
+            plane.truncate().dot(corner) + plane.w >= 0.0
+        })
+    }
+}
+
+struct Cell {
+    instances: Vec<usize>,
+    // the lowest and highest instance that was ever in the cell
+    min_y: f32,
+    max_y: f32,
+}
+
+// a uniform grid in the ground plane over an instanced object's instances, for finding the ones a
+// camera sees or near a point without going through them all. it's only updated for the
+// instances that moved
+pub struct InstanceGrid {
+    cells: BTreeMap<(i32, i32), Cell>,
+    // where each instance was put and its size
+    positions: Vec<Point3<f32>>,
+    scales: Vec<f32>,
+    max_scale: f32,
+    // from an unscaled instance's origin to the furthest point of the mesh, before the model matrix
+    mesh_radius: f32,
+}
+
+impl InstanceGrid {
+    const CELL_SIZE: f32 = 16.0;
+
+    pub fn new(instances: &[Instance], mesh_radius: f32) -> Self {
+        let mut grid = InstanceGrid {
+            cells: BTreeMap::new(),
+            positions: Vec::with_capacity(instances.len()),
+            scales: Vec::with_capacity(instances.len()),
+            max_scale: 0.0,
+            mesh_radius,
+        };
+        for (i, instance) in instances.iter().enumerate() {
+            grid.positions.push(Point3::from_vec(instance.trans));
+            grid.scales.push(Self::scale_of(instance));
+            grid.insert(i);
+        }
+        grid
+    }
+
+    // for an instance added after the others
+    pub fn push(&mut self, instance: &Instance) {
+        self.positions.push(Point3::from_vec(instance.trans));
+        self.scales.push(Self::scale_of(instance));
+        self.insert(self.positions.len() - 1);
+    }
+
+    // moves the instances in range that have changed position or size into their new cells
+    pub fn refresh(&mut self, instances: &[Instance], range: Range<usize>) {
+        for i in range {
+            let position = Point3::from_vec(instances[i].trans);
+            let scale = Self::scale_of(&instances[i]);
+            if position == self.positions[i] && scale == self.scales[i] {
+                continue;
+            }
+            if let Some(cell) = self.cells.get_mut(&Self::cell_of(self.positions[i])) {
+                cell.instances.retain(|&j| j != i);
+            }
+            self.positions[i] = position;
+            self.scales[i] = scale;
+            self.insert(i);
+        }
+    }
+
+    // how far the mesh can reach from an unscaled instance's origin once the model matrix moved it
+    pub fn reach(&self, model: Matrix4<f32>) -> f32 {
+        let stretch = model.x.truncate().magnitude().max(model.y.truncate().magnitude()).max(model.z.truncate().magnitude());
+        model.w.truncate().magnitude() + stretch * self.mesh_radius
+    }
+
+    // the first count instances the frustum can see, as runs of consecutive instance indices
+    pub fn visible(&self, frustum: &Frustum, model: Matrix4<f32>, count: usize) -> Vec<Range<u32>> {
+        let reach = self.reach(model);
+        let margin = reach * self.max_scale;
+        let mut visible = self
+            .cells
+            .iter()
+            .filter(|(&(x, z), cell)| {
+                let min = Point3::new(x as f32 * Self::CELL_SIZE - margin, cell.min_y - margin, z as f32 * Self::CELL_SIZE - margin);
+                let max = Point3::new((x + 1) as f32 * Self::CELL_SIZE + margin, cell.max_y + margin, (z + 1) as f32 * Self::CELL_SIZE + margin);
+                frustum.intersects_box(min, max)
+            })
+            .flat_map(|(_, cell)| cell.instances.iter().copied())
+            .filter(|&i| i < count && frustum.intersects_sphere(self.positions[i], reach * self.scales[i]))
+            .collect::<Vec<_>>();
+        visible.sort_unstable();
+
+        let mut runs: Vec<Range<u32>> = Vec::new();
+        for i in visible.into_iter().map(|i| i as u32) {
+            match runs.last_mut() {
+                Some(run) if run.end == i => run.end += 1,
+                _ => runs.push(i..i + 1),
+            }
+        }
+        runs
+    }
+
+    // the instances whose origin is within radius of the point, on top of each one's own reach
+    pub fn near(&self, point: Point3<f32>, radius: f32, model: Matrix4<f32>) -> Vec<usize> {
+        let reach = self.reach(model);
+        let margin = radius + reach * self.max_scale;
+        let (min_x, min_z) = Self::cell_of(point - Vector3::new(margin, 0.0, margin));
+        let (max_x, max_z) = Self::cell_of(point + Vector3::new(margin, 0.0, margin));
+        (min_x..=max_x)
+            .flat_map(|x| (min_z..=max_z).map(move |z| (x, z)))
+            .filter_map(|key| self.cells.get(&key))
+            .flat_map(|cell| cell.instances.iter().copied())
+            .filter(|&i| {
+                let limit = radius + reach * self.scales[i];
+                (self.positions[i] - point).magnitude2() <= limit * limit
+            })
+            .collect()
+    }
+
+    fn insert(&mut self, i: usize) {
+        let position = self.positions[i];
+        self.max_scale = self.max_scale.max(self.scales[i]);
+        let cell = self.cells.entry(Self::cell_of(position)).or_insert_with(|| Cell {
+            instances: Vec::new(),
+            min_y: position.y,
+            max_y: position.y,
+        });
+        cell.instances.push(i);
+        cell.min_y = cell.min_y.min(position.y);
+        cell.max_y = cell.max_y.max(position.y);
+    }
+
+    fn cell_of(position: Point3<f32>) -> (i32, i32) {
+        ((position.x / Self::CELL_SIZE).floor() as i32, (position.z / Self::CELL_SIZE).floor() as i32)
+    }
+
+    fn scale_of(instance: &Instance) -> f32 {
+        instance.scale.x.max(instance.scale.y).max(instance.scale.z)
+    }
+}
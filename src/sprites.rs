@@ -0,0 +1,332 @@
+use crate::graphics;
+
+// everything in the sprite atlas, each one a white shape in its own square cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Icon {
+    Crosshair,
+    Light,
+    Grid,
+    // fully opaque, for panels and bars stretched to any size
+    Solid,
+    // the mouse pointer with its tip at CURSOR_TIP, and a slightly larger one drawn behind it
+    // in a darker color so it shows up on light and dark backgrounds alike
+    Cursor,
+    CursorOutline,
+}
+
+impl Icon {
+    const ALL: [Icon; 6] = [Icon::Crosshair, Icon::Light, Icon::Grid, Icon::Solid, Icon::Cursor, Icon::CursorOutline];
+    // where the tip of the pointer is, as a fraction of the icon's size from its top left corner
+    pub const CURSOR_TIP: f32 = 0.075;
+
+    // the part of the atlas the icon covers, pulled in by half a texel so the linear
+    // filter never picks up the icon next to it
+    fn uv_rect(self) -> [f32; 4] {
+        let width = (ICON_SIZE as usize * Icon::ALL.len()) as f32;
+        let left = (self as usize as f32 * ICON_SIZE as f32 + 0.5) / width;
+        let right = left + (ICON_SIZE as f32 - 1.0) / width;
+        let half_texel = 0.5 / ICON_SIZE as f32;
+        [left, half_texel, right, 1.0 - half_texel]
+    }
+
+    // whether the shape covers a point, x and y are -1..1 across the cell
+    fn coverage(self, x: f32, y: f32) -> bool {
+        let r = (x * x + y * y).sqrt();
+        match self {
+            Icon::Crosshair => {
+                let arm = |along: f32, across: f32| across.abs() < 0.1 && (0.3..0.9).contains(&along.abs());
+                arm(x, y) || arm(y, x) || r < 0.12
+            }
+            Icon::Light => {
+                let angle = y.atan2(x);
+                // eight rays, each a narrow wedge
+                let ray = ((angle / std::f32::consts::TAU * 8.0).rem_euclid(1.0) - 0.5).abs() > 0.38;
+                r < 0.4 || (ray && (0.55..0.9).contains(&r))
+            }
+            Icon::Grid => {
+                let line = |t: f32| [-0.85, -0.28, 0.28, 0.85].iter().any(|l| (t - l).abs() < 0.08);
+                x.abs() < 0.93 && y.abs() < 0.93 && (line(x) || line(y))
+            }
+            Icon::Solid => true,
+            Icon::Cursor => in_polygon(&ARROW, x, y),
+            Icon::CursorOutline => {
+                let offsets = (0..8).map(|i| i as f32 / 8.0 * std::f32::consts::TAU);
+                in_polygon(&ARROW, x, y) || offsets.into_iter().any(|a| in_polygon(&ARROW, x + 0.1 * a.cos(), y + 0.1 * a.sin()))
+            }
+        }
+    }
+}
+
+// the pointer's outline in the same -1..1 cell coordinates as coverage, y down. the tip is near
+// the top left corner, leaving room for CursorOutline
+const ARROW: [(f32, f32); 7] = [
+    (-0.85, -0.85),
+    (-0.85, 0.6),
+    (-0.5, 0.27),
+    (-0.25, 0.83),
+    (-0.07, 0.75),
+    (-0.32, 0.2),
+    (0.17, 0.2),
+];
+
+// even-odd rule, counting the edges a ray towards +x crosses
+fn in_polygon(points: &[(f32, f32)], x: f32, y: f32) -> bool {
+    let edges = points.iter().zip(points.iter().cycle().skip(1));
+    edges
+        .filter(|((x1, y1), (x2, y2))| (*y1 > y) != (*y2 > y) && x < (x2 - x1) * (y - y1) / (y2 - y1) + x1)
+        .count()
+        % 2
+        == 1
+}
+
+// pixels per side of each icon in the atlas
+const ICON_SIZE: u32 = 32;
+// samples per pixel side when drawing the icons, for smooth edges
+const SUPERSAMPLES: u32 = 4;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SpriteInstance {
+    rect: [f32; 4],
+    uv_rect: [f32; 4],
+    color: [f32; 4],
+}
+
+impl SpriteInstance {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] = wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x4];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<SpriteInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+// immediate mode 2d sprites on top of the frame, in pixels from the top left corner of the screen.
+// every icon comes from one atlas, so a whole frame of them is a single instanced draw
+pub struct SpriteBatch {
+    sprites: Vec<SpriteInstance>,
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    instance_buffer: wgpu::Buffer,
+    // sprites the buffer has room for, it's recreated larger when a frame needs more
+    capacity: usize,
+    instance_count: u32,
+}
+
+impl SpriteBatch {
+    const INITIAL_CAPACITY: usize = 64;
+
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, format: wgpu::TextureFormat) -> Self {
+        let shader = graphics::create_templated_shader(device, format, "sprites.wgsl", include_str!("sprites.wgsl"));
+
+        let atlas_size = wgpu::Extent3d {
+            width: ICON_SIZE * Icon::ALL.len() as u32,
+            height: ICON_SIZE,
+            depth_or_array_layers: 1,
+        };
+        let atlas = graphics::create_texture(device, &wgpu::TextureDescriptor {
+            label: Some("sprite_atlas"),
+            size: atlas_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &atlas,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &atlas_pixels(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(atlas_size.width * 4),
+                rows_per_image: std::num::NonZeroU32::new(atlas_size.height),
+            },
+            atlas_size,
+        );
+        let atlas_view = atlas.create_view(&wgpu::TextureViewDescriptor::default());
+        // icons get scaled to whatever size they're drawn at
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let uniform_buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some("sprite_uniform_buffer"),
+            size: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("sprite_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("sprite_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&atlas_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("sprite_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("sprite_pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[SpriteInstance::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // drawn in the overlay pass, which has no depth attachment
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: graphics::SAMPLE_COUNT,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        SpriteBatch {
+            sprites: Vec::new(),
+            pipeline,
+            uniform_buffer,
+            bind_group,
+            instance_buffer: create_instance_buffer(device, Self::INITIAL_CAPACITY),
+            capacity: Self::INITIAL_CAPACITY,
+            instance_count: 0,
+        }
+    }
+
+    // x and y are the top left corner in pixels, sprites queued later are drawn on top
+    pub fn draw_sprite(&mut self, icon: Icon, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
+        self.sprites.push(SpriteInstance {
+            rect: [x, y, width, height],
+            uv_rect: icon.uv_rect(),
+            color,
+        });
+    }
+
+    // uploads everything queued this frame and starts the next one empty
+    pub fn flush(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, width: u32, height: u32) {
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[width as f32, height as f32, 0.0, 0.0]));
+        if self.sprites.len() > self.capacity {
+            self.capacity = self.sprites.len().next_power_of_two();
+            self.instance_buffer = create_instance_buffer(device, self.capacity);
+        }
+        if !self.sprites.is_empty() {
+            queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&self.sprites));
+        }
+        self.instance_count = self.sprites.len() as u32;
+        self.sprites.clear();
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.instance_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        render_pass.draw(0..6, 0..self.instance_count);
+    }
+}
+
+// every icon side by side in one row, white with the shape in the alpha channel
+fn atlas_pixels() -> Vec<u8> {
+    let width = (ICON_SIZE as usize) * Icon::ALL.len();
+    let mut pixels = vec![0u8; width * ICON_SIZE as usize * 4];
+    let samples = (SUPERSAMPLES * SUPERSAMPLES) as f32;
+    for (i, icon) in Icon::ALL.into_iter().enumerate() {
+        for row in 0..ICON_SIZE {
+            for column in 0..ICON_SIZE {
+                let mut covered = 0;
+                for sy in 0..SUPERSAMPLES {
+                    for sx in 0..SUPERSAMPLES {
+                        let to_cell = |p: u32, s: u32| ((p * SUPERSAMPLES + s) as f32 + 0.5) / (ICON_SIZE * SUPERSAMPLES) as f32 * 2.0 - 1.0;
+                        covered += icon.coverage(to_cell(column, sx), to_cell(row, sy)) as u32;
+                    }
+                }
+                let texel = (row as usize * width + i * ICON_SIZE as usize + column as usize) * 4;
+                pixels[texel..texel + 4].copy_from_slice(&[255, 255, 255, (covered as f32 / samples * 255.0).round() as u8]);
+            }
+        }
+    }
+    pixels
+}
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    graphics::create_buffer(device, &wgpu::BufferDescriptor {
+        label: Some("sprite_instances"),
+        size: (std::mem::size_of::<SpriteInstance>() * capacity) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
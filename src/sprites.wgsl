@@ -0,0 +1,48 @@
+// xy is the screen size in pixels
+@group(0) @binding(0)
+var<uniform> screen: vec4<f32>;
+
+@group(0) @binding(1)
+var atlas: texture_2d<f32>;
+
+@group(0) @binding(2)
+var atlas_sampler: sampler;
+
+struct SpriteInput {
+    // top left corner and size in pixels
+    @location(0) rect: vec4<f32>,
+    // top left and bottom right corners of the sprite's region of the atlas
+    @location(1) uv_rect: vec4<f32>,
+    @location(2) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, sprite: SpriteInput) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 0.0), vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 1.0), vec2<f32>(0.0, 1.0),
+    );
+    let corner = corners[index];
+    let pixel = sprite.rect.xy + corner * sprite.rect.zw;
+
+    var out: VertexOutput;
+    // pixels run down from the top left, ndc runs up from the middle
+    out.clip_position = vec4<f32>(pixel.x / screen.x * 2.0 - 1.0, 1.0 - pixel.y / screen.y * 2.0, 0.0, 1.0);
+    out.uv = mix(sprite.uv_rect.xy, sprite.uv_rect.zw, corner);
+    out.color = sprite.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // the atlas is white, the sprites get their color from the tint
+    var color = textureSample(atlas, atlas_sampler, in.uv) * in.color;
+    color = vec4<f32>(apply_output_gamma(color.rgb), color.a);
+    return color;
+}
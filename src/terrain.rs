@@ -0,0 +1,230 @@
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector3};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::graphics::{self, Vertex};
+use crate::noise::Perlin;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct TerrainConfig {
+    // grayscale image read from disk, the built in res/terrain/heightmap.png when unset
+    pub heightmap: Option<PathBuf>,
+    // generates the heights instead of reading an image, can be tweaked while running
+    pub noise: Option<NoiseConfig>,
+    // the corner with the lowest x and z, at the height of a black pixel
+    pub origin: [f32; 3],
+    // world units between neighbouring heightmap pixels
+    pub cell_size: f32,
+    // how far a white pixel is above a black one
+    pub height_scale: f32,
+    // world units covered by one repeat of the texture
+    pub texture_size: f32,
+    // hides the flat floor quad so the terrain is the only ground
+    pub replace_floor: bool,
+}
+
+impl Default for TerrainConfig {
+    fn default() -> Self {
+        TerrainConfig {
+            heightmap: None,
+            noise: None,
+            origin: [-250.0, -60.0, -250.0],
+            cell_size: 3.0,
+            height_scale: 60.0,
+            texture_size: 30.0,
+            replace_floor: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct NoiseConfig {
+    pub seed: u32,
+    // heightmap samples along x and z
+    pub size: [usize; 2],
+    pub octaves: u32,
+    // noise features per heightmap sample in the first octave
+    pub frequency: f32,
+    // amplitude of each octave relative to the one before
+    pub persistence: f32,
+    // frequency of each octave relative to the one before
+    pub lacunarity: f32,
+}
+
+impl NoiseConfig {
+    pub const MAX_OCTAVES: u32 = 10;
+}
+
+impl Default for NoiseConfig {
+    fn default() -> Self {
+        NoiseConfig {
+            seed: 1,
+            size: [257, 257],
+            octaves: 5,
+            frequency: 0.01,
+            persistence: 0.5,
+            lacunarity: 2.0,
+        }
+    }
+}
+
+// heights from 0 to 1, one per pixel of the source image
+pub struct Heightmap {
+    width: usize,
+    depth: usize,
+    heights: Vec<f32>,
+}
+
+impl Heightmap {
+    pub fn from_image(bytes: &[u8]) -> Result<Self, image::ImageError> {
+        let img = image::load_from_memory(bytes)?.into_luma16();
+        Ok(Heightmap {
+            width: img.width() as usize,
+            depth: img.height() as usize,
+            heights: img.pixels().map(|p| p.0[0] as f32 / u16::MAX as f32).collect(),
+        })
+    }
+
+    pub fn from_noise(config: &NoiseConfig) -> Self {
+        let perlin = Perlin::new(config.seed);
+        let [width, depth] = config.size;
+        let mut heights = Vec::with_capacity(width * depth);
+        for z in 0..depth {
+            for x in 0..width {
+                let (nx, nz) = (x as f32 * config.frequency, z as f32 * config.frequency);
+                let noise = perlin.fractal(nx, nz, config.octaves, config.persistence, config.lacunarity);
+                heights.push(noise);
+            }
+        }
+
+        // noise rarely gets near its theoretical limits, stretch it so height_scale is the full range
+        let min = heights.iter().copied().fold(f32::MAX, f32::min);
+        let max = heights.iter().copied().fold(f32::MIN, f32::max);
+        let range = (max - min).max(f32::EPSILON);
+        for height in &mut heights {
+            *height = (*height - min) / range;
+        }
+        Heightmap { width, depth, heights }
+    }
+
+    // clamped to the edges, so normals along the border still have neighbours to sample
+    fn height(&self, x: isize, z: isize) -> f32 {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let z = z.clamp(0, self.depth as isize - 1) as usize;
+        self.heights[z * self.width + x]
+    }
+}
+
+struct TerrainChunk {
+    vertices: graphics::CountedBuffer,
+    indices: graphics::CountedBuffer,
+    num_indices: u32,
+}
+
+// a heightmap turned into a grid mesh, split into chunks so no single draw covers the whole terrain
+pub struct Terrain {
+    chunks: Vec<TerrainChunk>,
+    pub transforms_buf: wgpu::Buffer,
+}
+
+impl Terrain {
+    // cells along each side of a chunk
+    const CHUNK_CELLS: usize = 64;
+
+    pub fn new(device: &wgpu::Device, config: &TerrainConfig, heightmap: &Heightmap) -> Self {
+        Terrain {
+            chunks: build_chunks(device, config, heightmap),
+            transforms_buf: graphics::create_transforms_buffer(device, "transforms_terrain", Matrix4::identity()),
+        }
+    }
+
+    // replaces the mesh, the uniforms and so the bind group built around them stay
+    pub fn rebuild(&mut self, device: &wgpu::Device, config: &TerrainConfig, heightmap: &Heightmap) {
+        self.chunks = build_chunks(device, config, heightmap);
+    }
+
+    // expects the main pipeline and the terrain's bind group to be set already
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        for chunk in &self.chunks {
+            render_pass.set_vertex_buffer(0, chunk.vertices.slice(..));
+            render_pass.set_index_buffer(chunk.indices.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..chunk.num_indices, 0, 0..1);
+        }
+    }
+}
+
+fn build_chunks(device: &wgpu::Device, config: &TerrainConfig, heightmap: &Heightmap) -> Vec<TerrainChunk> {
+    let chunk_cells = Terrain::CHUNK_CELLS;
+    let cells_x = heightmap.width.saturating_sub(1);
+    let cells_z = heightmap.depth.saturating_sub(1);
+
+    let mut chunks = Vec::new();
+    for start_z in (0..cells_z).step_by(chunk_cells) {
+        for start_x in (0..cells_x).step_by(chunk_cells) {
+            let end_x = (start_x + chunk_cells).min(cells_x);
+            let end_z = (start_z + chunk_cells).min(cells_z);
+            chunks.push(build_chunk(device, config, heightmap, (start_x, start_z), (end_x, end_z)));
+        }
+    }
+    chunks
+}
+
+// the vertices from start to end inclusive, so neighbouring chunks share their border vertices
+fn build_chunk(
+    device: &wgpu::Device,
+    config: &TerrainConfig,
+    heightmap: &Heightmap,
+    start: (usize, usize),
+    end: (usize, usize),
+) -> TerrainChunk {
+    let [origin_x, origin_y, origin_z] = config.origin;
+    let mut vertices = Vec::new();
+    for z in start.1..=end.1 {
+        for x in start.0..=end.0 {
+            let (xi, zi) = (x as isize, z as isize);
+            let world_x = origin_x + x as f32 * config.cell_size;
+            let world_z = origin_z + z as f32 * config.cell_size;
+
+            // central differences over the full heightmap, so normals match across chunk borders
+            let slope_x = (heightmap.height(xi + 1, zi) - heightmap.height(xi - 1, zi)) * config.height_scale
+                / (2.0 * config.cell_size);
+            let slope_z = (heightmap.height(xi, zi + 1) - heightmap.height(xi, zi - 1)) * config.height_scale
+                / (2.0 * config.cell_size);
+            let normal = Vector3::new(-slope_x, 1.0, -slope_z).normalize();
+
+            vertices.push(Vertex {
+                position: [world_x, origin_y + heightmap.height(xi, zi) * config.height_scale, world_z],
+                tex_coords: [
+                    (world_x - origin_x) / config.texture_size,
+                    (world_z - origin_z) / config.texture_size,
+                ],
+                normal: normal.into(),
+            });
+        }
+    }
+
+    let row = (end.0 - start.0 + 1) as u32;
+    let mut indices = Vec::new();
+    for z in 0..(end.1 - start.1) as u32 {
+        for x in 0..row - 1 {
+            let i = z * row + x;
+            indices.extend_from_slice(&[i, i + row, i + 1, i + 1, i + row, i + row + 1]);
+        }
+    }
+
+    TerrainChunk {
+        vertices: graphics::create_counted_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+            label: Some("vertices_terrain_chunk"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        }),
+        indices: graphics::create_counted_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+            label: Some("indices_terrain_chunk"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        }),
+        num_indices: indices.len() as u32,
+    }
+}
@@ -0,0 +1,54 @@
+// matches GLYPH_WIDTH and GLYPH_HEIGHT in text.rs
+let GLYPH_SIZE: vec2<f32> = vec2<f32>(5.0, 7.0);
+
+// xy is the screen size in pixels
+@group(0) @binding(0)
+var<uniform> screen: vec4<f32>;
+
+@group(0) @binding(1)
+var atlas: texture_2d<f32>;
+
+struct GlyphInput {
+    @location(0) position: vec2<f32>,
+    @location(1) glyph: u32,
+    @location(2) scale: f32,
+    @location(3) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // font pixels from the glyph's top left corner
+    @location(0) local: vec2<f32>,
+    @location(1) @interpolate(flat) glyph: u32,
+    @location(2) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, glyph: GlyphInput) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 0.0), vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 1.0), vec2<f32>(0.0, 1.0),
+    );
+    let local = corners[index] * GLYPH_SIZE;
+    let pixel = glyph.position + local * glyph.scale;
+
+    var out: VertexOutput;
+    // pixels run down from the top left, ndc runs up from the middle
+    out.clip_position = vec4<f32>(pixel.x / screen.x * 2.0 - 1.0, 1.0 - pixel.y / screen.y * 2.0, 0.0, 1.0);
+    out.local = local;
+    out.glyph = glyph.glyph;
+    out.color = glyph.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = vec2<i32>(min(in.local, GLYPH_SIZE - 1.0)) + vec2<i32>(i32(in.glyph) * i32(GLYPH_SIZE.x), 0);
+    if textureLoad(atlas, texel, 0).r < 0.5 {
+        discard;
+    }
+
+    var color = in.color;
+    color = vec4<f32>(apply_output_gamma(color.rgb), color.a);
+    return color;
+}
@@ -0,0 +1,110 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+// std::time::Instant panics in the browser
+#[cfg(target_arch = "wasm32")]
+use instant::Instant;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+pub struct FrameTimer {
+    last_frame: Instant,
+    samples: VecDeque<f64>,
+    smoothing_frames: usize,
+    max_delta: f64,
+    frames: u32,
+    last_fps_update: Instant,
+}
+
+impl FrameTimer {
+    const FPS_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+    // smoothing_frames is the size of the moving average window (1 disables smoothing).
+    // max_delta is the longest frame (in seconds) that gets fed into the average,
+    // so a single hitch (window drag, asset load) can't launch the camera across the map
+    pub fn new(smoothing_frames: usize, max_delta: f64) -> Self {
+        let now = Instant::now();
+        FrameTimer {
+            last_frame: now,
+            samples: VecDeque::with_capacity(smoothing_frames.max(1)),
+            smoothing_frames: smoothing_frames.max(1),
+            max_delta,
+            frames: 0,
+            last_fps_update: now,
+        }
+    }
+
+    // call once per frame, returns the smoothed and clamped delta time in seconds
+    pub fn tick(&mut self) -> f64 {
+        let now = Instant::now();
+        let raw = now.duration_since(self.last_frame).as_secs_f64();
+        self.last_frame = now;
+        self.frames += 1;
+
+        if self.samples.len() == self.smoothing_frames {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(raw.min(self.max_delta));
+
+        self.samples.iter().sum::<f64>() / self.samples.len() as f64
+    }
+
+    // returns the number of frames counted over the last interval, once per interval
+    pub fn fps(&mut self) -> Option<u32> {
+        let now = Instant::now();
+        if now.duration_since(self.last_fps_update) >= Self::FPS_UPDATE_INTERVAL {
+            let frames = self.frames;
+            self.frames = 0;
+            self.last_fps_update = now;
+            Some(frames)
+        } else {
+            None
+        }
+    }
+}
+
+// caps the frame rate by waiting out the rest of each frame, independent of the present mode
+pub struct FrameLimiter {
+    // None when uncapped
+    interval: Option<Duration>,
+    next_frame: Instant,
+}
+
+impl FrameLimiter {
+    // sleeping tends to overshoot, so the last bit of the wait is spent spinning instead
+    #[cfg(not(target_arch = "wasm32"))]
+    const SPIN_TIME: Duration = Duration::from_millis(1);
+
+    // 0 disables the cap
+    pub fn new(max_fps: u32) -> Self {
+        FrameLimiter {
+            interval: (max_fps > 0).then(|| Duration::from_secs_f64(1.0 / max_fps as f64)),
+            next_frame: Instant::now(),
+        }
+    }
+
+    // call once per frame, blocks until the frame's time slot has passed
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn wait(&mut self) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+
+        let now = Instant::now();
+        if self.next_frame > now {
+            let remaining = self.next_frame - now;
+            if remaining > Self::SPIN_TIME {
+                std::thread::sleep(remaining - Self::SPIN_TIME);
+            }
+            while Instant::now() < self.next_frame {
+                std::hint::spin_loop();
+            }
+        }
+        // scheduled from the previous slot so the average rate stays exact, but never in the
+        // past, or a slow frame would be followed by a burst of uncapped ones
+        self.next_frame = (self.next_frame + interval).max(Instant::now());
+    }
+
+    // the browser paces frames itself and blocking its main thread isn't allowed
+    #[cfg(target_arch = "wasm32")]
+    pub fn wait(&mut self) {}
+}
@@ -0,0 +1,207 @@
+use cgmath::{Deg, Matrix4, Point3, Vector3};
+use serde::{Deserialize, Serialize};
+
+use crate::camera::Camera;
+use crate::graphics;
+use crate::primitives;
+use crate::scene::CameraPose;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct TvConfig {
+    // the middle of the screen, and the degrees around y it faces, 0 is +z
+    pub position: [f32; 3],
+    pub yaw: f32,
+    pub width: f32,
+    pub height: f32,
+    // where the picture is filmed from
+    pub camera: CameraPose,
+    pub fovy: f32,
+}
+
+impl Default for TvConfig {
+    fn default() -> Self {
+        TvConfig {
+            position: [70.0, 0.0, -40.0],
+            yaw: 0.0,
+            width: 32.0,
+            height: 18.0,
+            camera: CameraPose {
+                position: [67.5, 60.0, 180.0],
+                yaw: -90.0,
+                pitch: -30.0,
+                roll: 0.0,
+            },
+            fovy: 60.0,
+        }
+    }
+}
+
+// a screen standing in the scene that shows it from another camera. the picture is rendered into
+// a texture every frame before the passes that draw the screen, which then samples it like any
+// other texture. the screen is left out of its own picture, a texture can't be drawn into while
+// it's bound
+pub struct Tv {
+    view_proj: Matrix4<f32>,
+    texture: wgpu::TextureView,
+    msaa_target: Option<wgpu::TextureView>,
+    depth_texture: (wgpu::TextureView, wgpu::Sampler, wgpu::Texture),
+    bind_group: wgpu::BindGroup,
+    vertices: wgpu::Buffer,
+    indices: wgpu::Buffer,
+    num_indices: u32,
+    pub transforms_buf: wgpu::Buffer,
+}
+
+impl Tv {
+    // pixels from the top to the bottom of the picture, the width follows the screen's shape
+    const TEXTURE_HEIGHT: u32 = 360;
+
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        config: &TvConfig,
+    ) -> Self {
+        let aspect = config.width / config.height;
+        let target_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width: (Self::TEXTURE_HEIGHT as f32 * aspect).round().max(1.0) as u32,
+            height: Self::TEXTURE_HEIGHT,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        // drawn into by the tv's pass and sampled by the main one
+        let texture = graphics::create_texture(
+            device,
+            &wgpu::TextureDescriptor {
+                label: Some("tv_texture"),
+                size: wgpu::Extent3d {
+                    width: target_config.width,
+                    height: target_config.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            },
+        )
+        .create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("tv_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("tv_bind_group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let camera_pose = &config.camera;
+        let mut camera = Camera::new(Point3::from(camera_pose.position), camera_pose.yaw, camera_pose.pitch, aspect);
+        camera.set_fovy(config.fovy);
+
+        // the plane lies in xz with v running along +z, standing it up puts v = 0 at the top
+        let mesh = primitives::plane(1.0, 1);
+        let model = Matrix4::from_translation(Vector3::from(config.position))
+            * Matrix4::from_angle_y(Deg(config.yaw))
+            * Matrix4::from_nonuniform_scale(config.width, config.height, 1.0)
+            * Matrix4::from_angle_x(Deg(90.0));
+
+        Tv {
+            view_proj: camera.build_view_proj(),
+            texture,
+            msaa_target: graphics::create_msaa_target(device, &target_config, "tv_msaa_target"),
+            depth_texture: graphics::create_depth_texture(device, &target_config, "tv_depth_texture"),
+            bind_group,
+            vertices: graphics::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+                label: Some("vertices_tv"),
+                contents: bytemuck::cast_slice(&mesh.vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+            indices: graphics::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+                label: Some("indices_tv"),
+                contents: bytemuck::cast_slice(&mesh.indices),
+                usage: wgpu::BufferUsages::INDEX,
+            }),
+            num_indices: mesh.indices.len() as u32,
+            transforms_buf: graphics::create_transforms_buffer(device, "transforms_tv", model),
+        }
+    }
+
+    // the camera the picture is filmed from
+    pub fn view_proj(&self) -> Matrix4<f32> {
+        self.view_proj
+    }
+
+    // the pass the picture is drawn in, before any pass that shows the screen
+    pub fn begin_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder, clear_color: wgpu::Color) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("tv_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: self.msaa_target.as_ref().unwrap_or(&self.texture),
+                resolve_target: self.msaa_target.as_ref().map(|_| &self.texture),
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear_color),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture.0,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: false,
+                }),
+                stencil_ops: None,
+            }),
+        })
+    }
+
+    // the screen with the picture on it, with the screen pipeline and the first group already set
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertices.slice(..));
+        render_pass.set_index_buffer(self.indices.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+    }
+}
+
+// the second bind group of the screen pipeline, after the one every object has
+pub fn build_screen_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("screen_bind_group_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    })
+}
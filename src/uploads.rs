@@ -0,0 +1,69 @@
+use std::sync::Mutex;
+
+// the small writes made while updating a frame, like the camera and model matrices. each one is
+// copied into a staging buffer that's kept mapped, and the copies to where they go are recorded
+// into an encoder of their own, submitted just ahead of the frame. the staging buffers are reused
+// once the gpu is done with them, instead of the queue allocating for every write
+pub struct Uploads {
+    // only ever used through &mut, the mutex is there so the app can be shared with the threads
+    // encoding the frame's passes
+    staged: Mutex<Staged>,
+}
+
+struct Staged {
+    belt: wgpu::util::StagingBelt,
+    encoder: Option<wgpu::CommandEncoder>,
+}
+
+impl Uploads {
+    // bytes per staging buffer, enough for a frame's uniforms with a few hundred animated objects
+    const CHUNK_SIZE: wgpu::BufferAddress = 64 * 1024;
+
+    pub fn new() -> Self {
+        Uploads {
+            staged: Mutex::new(Staged {
+                belt: wgpu::util::StagingBelt::new(Self::CHUNK_SIZE),
+                encoder: None,
+            }),
+        }
+    }
+
+    // target needs COPY_DST, and offset and the data's size have to be multiples of 4
+    pub fn write<T: bytemuck::Pod>(
+        &mut self,
+        device: &wgpu::Device,
+        target: &wgpu::Buffer,
+        offset: wgpu::BufferAddress,
+        data: &[T],
+    ) {
+        let bytes = bytemuck::cast_slice(data);
+        let Some(size) = wgpu::BufferSize::new(bytes.len() as u64) else {
+            return;
+        };
+        let staged = self.staged();
+        let encoder = staged.encoder.get_or_insert_with(|| {
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("uploads_encoder"),
+            })
+        });
+        staged.belt.write_buffer(encoder, target, offset, size, device).copy_from_slice(bytes);
+    }
+
+    // the copies written since the last submit, to go first in the next one. None when there
+    // weren't any
+    pub fn finish(&mut self) -> Option<wgpu::CommandBuffer> {
+        let staged = self.staged();
+        let encoder = staged.encoder.take()?;
+        staged.belt.finish();
+        Some(encoder.finish())
+    }
+
+    // after the submit, so the staging buffers come back once the copies are done
+    pub fn recall(&mut self) {
+        self.staged().belt.recall();
+    }
+
+    fn staged(&mut self) -> &mut Staged {
+        self.staged.get_mut().expect("Failed to lock the staging belt")
+    }
+}
@@ -0,0 +1,272 @@
+use cgmath::{Matrix4, Point3, SquareMatrix};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::graphics::{self, Vertex};
+use crate::noise::Perlin;
+
+pub type BlockId = u8;
+
+pub const AIR: BlockId = 0;
+pub const STONE: BlockId = 1;
+pub const DIRT: BlockId = 2;
+pub const GRASS: BlockId = 3;
+
+// blocks along each side of a chunk
+const CHUNK_SIZE: usize = 16;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct VoxelConfig {
+    pub seed: u32,
+    // world position of the lowest corner of block (0, 0, 0)
+    pub origin: [f32; 3],
+    pub block_size: f32,
+    // chunks loaded in every direction around the camera in x and z
+    pub view_distance: i32,
+    // chunks stacked vertically, the ground never rises above them
+    pub height_chunks: i32,
+    // noise features per block
+    pub frequency: f32,
+    pub octaves: u32,
+    // hides the flat floor quad so the voxels are the only ground
+    pub replace_floor: bool,
+}
+
+impl Default for VoxelConfig {
+    fn default() -> Self {
+        VoxelConfig {
+            seed: 1,
+            origin: [0.0, -60.0, 0.0],
+            block_size: 2.0,
+            view_distance: 6,
+            height_chunks: 2,
+            frequency: 0.02,
+            octaves: 4,
+            replace_floor: false,
+        }
+    }
+}
+
+// the blocks of one chunk, indexed x fastest then z then y
+struct Chunk {
+    blocks: Vec<BlockId>,
+}
+
+impl Chunk {
+    fn generate(generator: &Generator, pos: [i32; 3]) -> Self {
+        let mut blocks = vec![AIR; CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE];
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let world_x = pos[0] * CHUNK_SIZE as i32 + x as i32;
+                let world_z = pos[2] * CHUNK_SIZE as i32 + z as i32;
+                let ground = generator.ground_height(world_x, world_z);
+                for y in 0..CHUNK_SIZE {
+                    let world_y = pos[1] * CHUNK_SIZE as i32 + y as i32;
+                    blocks[index(x, y, z)] = Generator::block(world_y, ground);
+                }
+            }
+        }
+        Chunk { blocks }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.blocks.iter().all(|&block| block == AIR)
+    }
+}
+
+fn index(x: usize, y: usize, z: usize) -> usize {
+    (y * CHUNK_SIZE + z) * CHUNK_SIZE + x
+}
+
+// decides which block is where, so chunks can be generated in any order and
+// blocks across a chunk border can be looked up without that chunk being loaded
+struct Generator {
+    perlin: Perlin,
+    config: VoxelConfig,
+}
+
+impl Generator {
+    const DIRT_DEPTH: i32 = 3;
+
+    fn ground_height(&self, x: i32, z: i32) -> i32 {
+        let config = &self.config;
+        let noise = self.perlin.fractal(x as f32 * config.frequency, z as f32 * config.frequency, config.octaves, 0.5, 2.0);
+        let max_height = config.height_chunks * CHUNK_SIZE as i32 - 1;
+        // perlin noise stays well inside -1 to 1, so spread it out before clamping
+        (((noise * 1.5 + 1.0) * 0.5).clamp(0.0, 1.0) * max_height as f32) as i32
+    }
+
+    fn block(y: i32, ground: i32) -> BlockId {
+        if y > ground {
+            AIR
+        } else if y == ground {
+            GRASS
+        } else if y > ground - Self::DIRT_DEPTH {
+            DIRT
+        } else {
+            STONE
+        }
+    }
+
+    fn block_at(&self, x: i32, y: i32, z: i32) -> BlockId {
+        if y < 0 {
+            // there's no need to draw the bottom of the world
+            return STONE;
+        }
+        Self::block(y, self.ground_height(x, z))
+    }
+}
+
+struct ChunkMesh {
+    vertices: graphics::CountedBuffer,
+    indices: graphics::CountedBuffer,
+    num_indices: u32,
+}
+
+// one face of a block: its normal and corners, as offsets from the block's lowest corner
+struct Face {
+    normal: [i32; 3],
+    corners: [[f32; 3]; 4],
+}
+
+const FACES: [Face; 6] = [
+    Face { normal: [1, 0, 0], corners: [[1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [1.0, 1.0, 1.0], [1.0, 0.0, 1.0]] },
+    Face { normal: [-1, 0, 0], corners: [[0.0, 0.0, 1.0], [0.0, 1.0, 1.0], [0.0, 1.0, 0.0], [0.0, 0.0, 0.0]] },
+    Face { normal: [0, 1, 0], corners: [[0.0, 1.0, 0.0], [0.0, 1.0, 1.0], [1.0, 1.0, 1.0], [1.0, 1.0, 0.0]] },
+    Face { normal: [0, -1, 0], corners: [[0.0, 0.0, 1.0], [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 0.0, 1.0]] },
+    Face { normal: [0, 0, 1], corners: [[1.0, 0.0, 1.0], [1.0, 1.0, 1.0], [0.0, 1.0, 1.0], [0.0, 0.0, 1.0]] },
+    Face { normal: [0, 0, -1], corners: [[0.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0, 0.0], [1.0, 0.0, 0.0]] },
+];
+const FACE_TEX_COORDS: [[f32; 2]; 4] = [[0.0, 1.0], [0.0, 0.0], [1.0, 0.0], [1.0, 1.0]];
+
+// naive meshing, a quad for every block face that touches air
+fn build_mesh(device: &wgpu::Device, generator: &Generator, chunk: &Chunk, pos: [i32; 3]) -> Option<ChunkMesh> {
+    let base = pos.map(|p| p * CHUNK_SIZE as i32);
+    let block_at = |x: i32, y: i32, z: i32| {
+        let inside = |v: i32| (0..CHUNK_SIZE as i32).contains(&v);
+        if inside(x) && inside(y) && inside(z) {
+            chunk.blocks[index(x as usize, y as usize, z as usize)]
+        } else {
+            generator.block_at(base[0] + x, base[1] + y, base[2] + z)
+        }
+    };
+
+    let [origin_x, origin_y, origin_z] = generator.config.origin;
+    let size = generator.config.block_size;
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for y in 0..CHUNK_SIZE as i32 {
+        for z in 0..CHUNK_SIZE as i32 {
+            for x in 0..CHUNK_SIZE as i32 {
+                if block_at(x, y, z) == AIR {
+                    continue;
+                }
+                for face in &FACES {
+                    let [nx, ny, nz] = face.normal;
+                    if block_at(x + nx, y + ny, z + nz) != AIR {
+                        continue;
+                    }
+
+                    let first = vertices.len() as u32;
+                    for (corner, tex_coords) in face.corners.iter().zip(FACE_TEX_COORDS) {
+                        vertices.push(Vertex {
+                            position: [
+                                origin_x + (base[0] + x) as f32 * size + corner[0] * size,
+                                origin_y + (base[1] + y) as f32 * size + corner[1] * size,
+                                origin_z + (base[2] + z) as f32 * size + corner[2] * size,
+                            ],
+                            tex_coords,
+                            normal: face.normal.map(|n| n as f32),
+                        });
+                    }
+                    indices.extend_from_slice(&[first, first + 1, first + 2, first, first + 2, first + 3]);
+                }
+            }
+        }
+    }
+
+    if indices.is_empty() {
+        return None;
+    }
+    Some(ChunkMesh {
+        vertices: graphics::create_counted_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+            label: Some("vertices_voxel_chunk"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        }),
+        indices: graphics::create_counted_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+            label: Some("indices_voxel_chunk"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        }),
+        num_indices: indices.len() as u32,
+    })
+}
+
+// chunks of blocks generated and meshed as the camera comes near and dropped once it leaves
+pub struct VoxelWorld {
+    generator: Generator,
+    // None for chunks with nothing to draw, so they aren't generated again
+    chunks: HashMap<[i32; 3], Option<ChunkMesh>>,
+    pub transforms_buf: wgpu::Buffer,
+}
+
+impl VoxelWorld {
+    // meshing is the slow part, so only a few chunks are loaded per frame to avoid hitches
+    const LOADS_PER_FRAME: usize = 4;
+
+    pub fn new(device: &wgpu::Device, config: &VoxelConfig) -> Self {
+        VoxelWorld {
+            generator: Generator {
+                perlin: Perlin::new(config.seed),
+                config: config.clone(),
+            },
+            chunks: HashMap::new(),
+            transforms_buf: graphics::create_transforms_buffer(device, "transforms_voxels", Matrix4::identity()),
+        }
+    }
+
+    // loads the closest missing chunks around camera_pos and unloads those out of range
+    pub fn update(&mut self, device: &wgpu::Device, camera_pos: Point3<f32>) {
+        let config = &self.generator.config;
+        let chunk_world_size = CHUNK_SIZE as f32 * config.block_size;
+        let center_x = ((camera_pos.x - config.origin[0]) / chunk_world_size).floor() as i32;
+        let center_z = ((camera_pos.z - config.origin[2]) / chunk_world_size).floor() as i32;
+        let distance = config.view_distance;
+        let in_range = |pos: &[i32; 3]| (pos[0] - center_x).abs() <= distance && (pos[2] - center_z).abs() <= distance;
+
+        self.chunks.retain(|pos, _| in_range(pos));
+
+        let mut missing = Vec::new();
+        for x in center_x - distance..=center_x + distance {
+            for z in center_z - distance..=center_z + distance {
+                for y in 0..config.height_chunks {
+                    if !self.chunks.contains_key(&[x, y, z]) {
+                        missing.push([x, y, z]);
+                    }
+                }
+            }
+        }
+        missing.sort_by_key(|pos| (pos[0] - center_x).pow(2) + (pos[2] - center_z).pow(2));
+
+        for pos in missing.into_iter().take(Self::LOADS_PER_FRAME) {
+            let chunk = Chunk::generate(&self.generator, pos);
+            let mesh = if chunk.is_empty() {
+                None
+            } else {
+                build_mesh(device, &self.generator, &chunk, pos)
+            };
+            self.chunks.insert(pos, mesh);
+        }
+    }
+
+    // expects the main pipeline and the world's bind group to be set already
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        for mesh in self.chunks.values().flatten() {
+            render_pass.set_vertex_buffer(0, mesh.vertices.slice(..));
+            render_pass.set_index_buffer(mesh.indices.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..mesh.num_indices, 0, 0..1);
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+// notices a file being written by polling when it was last modified, no more often than INTERVAL
+pub struct FileWatcher {
+    path: PathBuf,
+    // as of the last check, None while the file is missing
+    modified: Option<SystemTime>,
+    last_check: Instant,
+}
+
+impl FileWatcher {
+    const INTERVAL: Duration = Duration::from_millis(500);
+
+    pub fn new(path: PathBuf) -> Self {
+        let modified = modified(&path);
+        FileWatcher { path, modified, last_check: Instant::now() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // true once for each time the file is written. a file that's deleted isn't a change, it's
+    // likely an editor replacing it
+    pub fn changed(&mut self) -> bool {
+        if self.last_check.elapsed() < Self::INTERVAL {
+            return false;
+        }
+        self.last_check = Instant::now();
+        let modified = modified(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -0,0 +1,318 @@
+use cgmath::{Matrix4, Point3};
+
+use crate::graphics;
+use crate::sky::Sun;
+
+// what the sky is doing, switched from the console
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weather {
+    Clear,
+    Rain,
+}
+
+impl Weather {
+    pub const ALL: [Weather; 2] = [Weather::Clear, Weather::Rain];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Weather::Clear => "clear",
+            Weather::Rain => "rain",
+        }
+    }
+}
+
+// the weather asked for and how far things have got towards it. the rain starts and stops over a
+// few seconds, the ground soaks up while it falls and takes a good while longer to dry again
+pub struct WeatherState {
+    weather: Weather,
+    // how hard it's raining, 0 to 1
+    rain: f32,
+    // how wet the ground is, 0 to 1
+    wetness: f32,
+}
+
+impl WeatherState {
+    // seconds for the rain to start or stop
+    const RAIN_FADE: f32 = 4.0;
+    // seconds of full rain to soak the ground, and for it to dry from soaked
+    const SOAK_TIME: f32 = 15.0;
+    const DRY_TIME: f32 = 60.0;
+
+    pub fn new() -> Self {
+        WeatherState {
+            weather: Weather::Clear,
+            rain: 0.0,
+            wetness: 0.0,
+        }
+    }
+
+    pub fn set_weather(&mut self, weather: Weather) {
+        self.weather = weather;
+    }
+
+    pub fn rain(&self) -> f32 {
+        self.rain
+    }
+
+    pub fn wetness(&self) -> f32 {
+        self.wetness
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let raining = self.weather == Weather::Rain;
+        let target = if raining { 1.0 } else { 0.0 };
+        self.rain = approach(self.rain, target, dt / Self::RAIN_FADE);
+        self.wetness = if self.rain > 0.0 && raining {
+            approach(self.wetness, 1.0, dt * self.rain / Self::SOAK_TIME)
+        } else {
+            approach(self.wetness, 0.0, dt / Self::DRY_TIME)
+        };
+    }
+}
+
+fn approach(value: f32, target: f32, step: f32) -> f32 {
+    if value < target {
+        (value + step).min(target)
+    } else {
+        (value - step).max(target)
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct RainDrop {
+    position: [f32; 4],
+    velocity: [f32; 4],
+}
+
+impl RainDrop {
+    const ATTRIBS: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<RainDrop>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct RainUniform {
+    view_proj: [[f32; 4]; 4],
+    camera: [f32; 4],
+    params: [f32; 4],
+    wind: [f32; 4],
+    color: [f32; 4],
+    count: [u32; 4],
+}
+
+// streaks of rain falling through a box that follows the camera, simulated in a compute pass. the
+// buffer starts out zeroed, which the shader takes as drops still to be scattered through the box,
+// and each drop falling out of the bottom starts again from the top. the scene's depth hides the
+// ones below the ground. only the first part of the buffer is drawn, as much of it as the rain's
+// intensity calls for
+pub struct Rain {
+    uniform_buffer: wgpu::Buffer,
+    buffer: wgpu::Buffer,
+    compute_bind_group: wgpu::BindGroup,
+    render_bind_group: wgpu::BindGroup,
+    update_pipeline: wgpu::ComputePipeline,
+    render_pipeline: wgpu::RenderPipeline,
+    // seconds since the rain was created, seeding where the drops start again
+    time: f32,
+    // the drops drawn this frame
+    active: u32,
+}
+
+impl Rain {
+    const COUNT: u32 = 40_000;
+    const WORKGROUP_SIZE: u32 = 256;
+    // half the size of the box around the camera the rain falls through
+    const BOX_SIZE: f32 = 40.0;
+    // world units per second the drops are blown along x and z
+    const WIND: [f32; 2] = [3.0, 1.5];
+    // how much of the sun's light the drops catch on top of the ambient
+    const SUN_SHARE: f32 = 0.4;
+
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = graphics::create_templated_shader(device, format, "rain.wgsl", include_str!("rain.wgsl"));
+
+        let uniform_buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some("rain_uniform_buffer"),
+            size: std::mem::size_of::<RainUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let buffer = graphics::create_buffer(device, &wgpu::BufferDescriptor {
+            label: Some("rain_buffer"),
+            size: (std::mem::size_of::<RainDrop>() as u32 * Self::COUNT) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+            mapped_at_creation: false,
+        });
+
+        let uniform_entry = wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT | wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let compute_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("rain_compute_bind_group_layout"),
+            entries: &[
+                uniform_entry,
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        // the drops are read as a vertex buffer for drawing, they can't be bound for writing as well
+        let render_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("rain_render_bind_group_layout"),
+            entries: &[uniform_entry],
+        });
+        let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("rain_compute_bind_group"),
+            layout: &compute_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let render_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("rain_render_bind_group"),
+            layout: &render_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let compute_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("rain_compute_pipeline_layout"),
+            bind_group_layouts: &[&compute_layout],
+            push_constant_ranges: &[],
+        });
+        let update_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("rain_update_pipeline"),
+            layout: Some(&compute_pipeline_layout),
+            module: &shader,
+            entry_point: "cs_update",
+        });
+        let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("rain_render_pipeline_layout"),
+            bind_group_layouts: &[&render_layout],
+            push_constant_ranges: &[],
+        });
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("rain_render_pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[RainDrop::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::COLOR,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // hidden behind the scene but left out of the depth buffer, they're see-through
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: graphics::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: graphics::SAMPLE_COUNT,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        Rain {
+            uniform_buffer,
+            buffer,
+            compute_bind_group,
+            render_bind_group,
+            update_pipeline,
+            render_pipeline,
+            time: 0.0,
+            active: 0,
+        }
+    }
+
+    // intensity is 0 to 1, the part of the drops drawn
+    pub fn update(
+        &mut self,
+        queue: &wgpu::Queue,
+        dt: f32,
+        view_proj: Matrix4<f32>,
+        camera: Point3<f32>,
+        sun: &Sun,
+        intensity: f32,
+    ) {
+        self.time += dt;
+        self.active = (Self::COUNT as f32 * intensity.clamp(0.0, 1.0)) as u32;
+        let [wind_x, wind_z] = Self::WIND;
+        // what the drops catch from the sun and the sky
+        let (sun_color, ambient) = (sun.color(), sun.ambient());
+        let [r, g, b] = [0, 1, 2].map(|i| ambient[i] + sun_color[i] * Self::SUN_SHARE);
+        let uniform = RainUniform {
+            view_proj: view_proj.into(),
+            camera: [camera.x, camera.y, camera.z, Self::BOX_SIZE],
+            params: [dt, self.time, 0.0, 0.0],
+            wind: [wind_x, 0.0, wind_z, 0.0],
+            color: [r, g, b, 1.0],
+            count: [Self::COUNT, 0, 0, 0],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    // records this frame's step, before the pass that draws the rain
+    pub fn simulate(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("rain_pass"),
+        });
+        compute_pass.set_pipeline(&self.update_pipeline);
+        compute_pass.set_bind_group(0, &self.compute_bind_group, &[]);
+        compute_pass.dispatch_workgroups(Self::COUNT.div_ceil(Self::WORKGROUP_SIZE), 1, 1);
+    }
+
+    // after everything opaque, so the scene hides the drops behind it
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.active == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.render_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.buffer.slice(..));
+        render_pass.draw(0..6, 0..self.active);
+    }
+}